@@ -0,0 +1,144 @@
+use crate::{AudioConfig, AudioOutput, Oscillator};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{BufferSize, FromSample, SampleFormat, SizedSample, Stream, StreamConfig, StreamError};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// 设备丢失后重新打开的间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 通过 cpal 输出声音，不依赖 SDL。
+/// 设备被拔出后定时重新打开，指定的设备不存在时使用默认设备
+pub struct CpalOutput {
+    config: AudioConfig,
+    stream: Option<Stream>, // 无法打开任何设备时为 None，不输出声音
+    gate: Arc<AtomicBool>,
+    lost: Arc<AtomicBool>, // 输出流是否因设备断开而停止
+    preferred: bool,       // 是否打开了指定的设备
+    retry: Instant,        // 下一次尝试重新打开的时间
+}
+
+impl CpalOutput {
+    /// 打开配置中的输出设备，不存在时使用默认设备，都无法打开时不输出声音
+    pub fn open(config: &AudioConfig) -> Self {
+        let mut output = Self {
+            config: config.clone(),
+            stream: None,
+            gate: Arc::new(AtomicBool::new(false)),
+            lost: Arc::new(AtomicBool::new(false)),
+            preferred: false,
+            retry: Instant::now(),
+        };
+        output.reopen(true);
+        output
+    }
+
+    /// 可用的输出设备名
+    pub fn devices() -> Vec<String> {
+        cpal::default_host()
+            .output_devices()
+            .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+            .unwrap_or_default()
+    }
+
+    /// 重新打开输出流，verbose 为 false 时不重复报告打开失败
+    fn reopen(&mut self, verbose: bool) {
+        self.stream = None;
+        self.lost.store(false, Ordering::Relaxed);
+        self.retry = Instant::now() + RETRY_INTERVAL;
+
+        let host = cpal::default_host();
+        let name = self.config.device.as_deref();
+        let named = name.and_then(|name| {
+            host.output_devices()
+                .ok()?
+                .find(|d| d.name().is_ok_and(|n| n == name))
+        });
+        if let (Some(name), None) = (name, &named) {
+            if verbose {
+                println!("Couldn't find audio device {:?}", name);
+            }
+        }
+        self.preferred = name.is_none() || named.is_some();
+        let Some(device) = named.or_else(|| host.default_output_device()) else {
+            if verbose {
+                println!("Couldn't open audio output: no audio output device");
+            }
+            return;
+        };
+        match self.open_stream(&device) {
+            Ok(stream) => self.stream = Some(stream),
+            Err(e) if verbose => println!("Couldn't open audio output: {}", e),
+            Err(_) => (),
+        }
+    }
+
+    fn open_stream(&self, device: &cpal::Device) -> Result<Stream, String> {
+        let supported = device.default_output_config().map_err(|e| e.to_string())?;
+        let format = supported.sample_format();
+        let mut stream_config: StreamConfig = supported.into();
+        if let Some(size) = self.config.buffer_size {
+            stream_config.buffer_size = BufferSize::Fixed(size as u32);
+        }
+        let stream = match format {
+            SampleFormat::F32 => self.build::<f32>(device, &stream_config),
+            SampleFormat::I16 => self.build::<i16>(device, &stream_config),
+            SampleFormat::U16 => self.build::<u16>(device, &stream_config),
+            format => return Err(format!("unsupported sample format {}", format)),
+        }?;
+        stream.play().map_err(|e| e.to_string())?;
+        Ok(stream)
+    }
+
+    /// 创建输出流，单声道的采样复制到每个声道
+    fn build<T: SizedSample + FromSample<f32>>(
+        &self,
+        device: &cpal::Device,
+        stream_config: &StreamConfig,
+    ) -> Result<Stream, String> {
+        let channels = stream_config.channels.max(1) as usize;
+        let mut osc = Oscillator::new(&self.config, stream_config.sample_rate.0);
+        let mut samples = Vec::new();
+        let gate = self.gate.clone();
+        let lost = self.lost.clone();
+        device
+            .build_output_stream(
+                stream_config,
+                move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+                    osc.set_gate(gate.load(Ordering::Relaxed));
+                    samples.resize(data.len() / channels, 0.0);
+                    osc.fill(&mut samples);
+                    for (frame, sample) in data.chunks_mut(channels).zip(&samples) {
+                        frame.fill(T::from_sample(*sample));
+                    }
+                },
+                move |e| match e {
+                    StreamError::DeviceNotAvailable => lost.store(true, Ordering::Relaxed),
+                    e => eprintln!("Audio error: {}", e),
+                },
+                None,
+            )
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl AudioOutput for CpalOutput {
+    fn set_tone(&mut self, on: bool) {
+        self.gate.store(on, Ordering::Relaxed);
+        // 设备断开、没有设备或指定的设备尚未出现时，每隔一段时间尝试重新打开
+        let lost = self.lost.load(Ordering::Relaxed) || self.stream.is_none();
+        if (lost || !self.preferred) && Instant::now() >= self.retry {
+            if lost {
+                self.reopen(false);
+            } else if Self::devices()
+                .iter()
+                .any(|n| Some(n) == self.config.device.as_ref())
+            {
+                self.reopen(true);
+            } else {
+                self.retry = Instant::now() + RETRY_INTERVAL;
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+#[cfg(feature = "cpal")]
+mod cpal_output;
+
+#[cfg(feature = "cpal")]
+pub use cpal_output::CpalOutput;
+
+use std::f32::consts::PI;
+use std::str::FromStr;
+
+/// 蜂鸣器起音/释音时间 (秒)，用于消除开关声音时的爆音
+const ENVELOPE_TIME: f32 = 0.005;
+
+/// 蜂鸣器波形
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Waveform {
+    #[default]
+    Square,
+    Triangle,
+    Sine,
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            "sine" => Ok(Waveform::Sine),
+            _ => Err(format!("unknown waveform: {}", s)),
+        }
+    }
+}
+
+/// 音频输出配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioConfig {
+    pub waveform: Waveform,
+    /// 音调频率 (Hz)
+    pub frequency: f32,
+    /// 音量 0.0 ~ 1.0
+    pub volume: f32,
+    /// 音频缓冲区采样数，越小延迟越低，None 表示使用后端的默认值
+    pub buffer_size: Option<u16>,
+    /// 输出设备名，None 或设备不存在时使用默认设备
+    pub device: Option<String>,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::Square,
+            frequency: 440.0,
+            volume: 0.25,
+            buffer_size: None,
+            device: None,
+        }
+    }
+}
+
+/// 音频输出。前端每帧根据声音定时器打开或关闭蜂鸣器，采样由后端的音频线程生成
+pub trait AudioOutput {
+    fn set_tone(&mut self, on: bool);
+}
+
+/// 不输出声音，用于没有音频设备的情况
+#[derive(Debug, Default)]
+pub struct Silent;
+
+impl AudioOutput for Silent {
+    fn set_tone(&mut self, _on: bool) {}
+}
+
+/// 蜂鸣器振荡器，由音频回调拉取单声道采样
+#[derive(Debug, Clone)]
+pub struct Oscillator {
+    waveform: Waveform,
+    phase_inc: f32,
+    phase: f32,
+    volume: f32,
+    gate: bool,     // 声音是否打开
+    level: f32,     // 当前包络电平 0.0 ~ 1.0
+    level_inc: f32, // 每个采样的包络变化量
+}
+
+impl Oscillator {
+    /// 按输出设备的采样率创建振荡器
+    pub fn new(config: &AudioConfig, sample_rate: u32) -> Self {
+        let sample_rate = sample_rate.max(1) as f32;
+        Self {
+            waveform: config.waveform,
+            phase_inc: config.frequency / sample_rate,
+            phase: 0.0,
+            volume: config.volume.clamp(0.0, 1.0),
+            gate: false,
+            level: 0.0,
+            level_inc: 1.0 / (ENVELOPE_TIME * sample_rate),
+        }
+    }
+
+    /// 设置音量 0.0 ~ 1.0，正在发声时立即生效
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn set_gate(&mut self, on: bool) {
+        self.gate = on;
+    }
+
+    pub fn gate(&self) -> bool {
+        self.gate
+    }
+
+    /// 生成下一段采样
+    pub fn fill(&mut self, out: &mut [f32]) {
+        for x in out.iter_mut() {
+            let sample = match self.waveform {
+                Waveform::Square => {
+                    if self.phase <= 0.5 {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+                Waveform::Sine => (2.0 * PI * self.phase).sin(),
+            };
+            // 线性起音/释音，避免波形被突然截断
+            self.level = if self.gate {
+                (self.level + self.level_inc).min(1.0)
+            } else {
+                (self.level - self.level_inc).max(0.0)
+            };
+            *x = sample * self.volume * self.level;
+            self.phase = if self.level > 0.0 {
+                (self.phase + self.phase_inc) % 1.0
+            } else {
+                0.0
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oscillator() {
+        let config = AudioConfig {
+            volume: 0.5,
+            ..AudioConfig::default()
+        };
+        let mut osc = Oscillator::new(&config, 44100);
+        let mut out = [1.0; 64];
+        osc.fill(&mut out);
+        assert!(out.iter().all(|x| *x == 0.0));
+
+        // 起音结束后达到设定的音量
+        osc.set_gate(true);
+        let mut out = vec![0.0; 44100 / 100];
+        osc.fill(&mut out);
+        assert_eq!(out.last().map(|x| x.abs()), Some(0.5));
+        osc.set_volume(2.0);
+        osc.fill(&mut out);
+        assert_eq!(out.last().map(|x| x.abs()), Some(1.0));
+
+        // 释音结束后恢复静音
+        osc.set_gate(false);
+        osc.fill(&mut out);
+        assert_eq!(out.last(), Some(&0.0));
+        assert!("noise".parse::<Waveform>().is_err());
+    }
+}
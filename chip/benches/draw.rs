@@ -0,0 +1,30 @@
+use chip::{Chip, ENTRY_ADDR};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// 反复绘制 15 行的精灵，每次移动位置，包括跨越屏幕边缘回绕的情况
+const DRAW_LOOP: [u8; 10] = [
+    0xD0, 0x1F, // 200: DRW V0, V1, 15
+    0x70, 0x03, // 202: ADD V0, 3
+    0x71, 0x01, // 204: ADD V1, 1
+    0xD0, 0x18, // 206: DRW V0, V1, 8
+    0x12, 0x00, // 208: JP 0x200
+];
+
+fn draw(c: &mut Criterion) {
+    let mut chip = Chip::new(0);
+    chip.load_rom(ENTRY_ADDR, &DRAW_LOOP).unwrap();
+    // 精灵数据使用内存开头的字体
+    c.bench_function("dxyn", |b| {
+        b.iter(|| {
+            for _ in 0..5 {
+                chip.step().unwrap();
+            }
+            black_box(chip.framebuffer_rows());
+        })
+    });
+    // 前端每帧解包一次画面
+    c.bench_function("framebuffer", |b| b.iter(|| black_box(chip.framebuffer())));
+}
+
+criterion_group!(benches, draw);
+criterion_main!(benches);
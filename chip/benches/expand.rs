@@ -0,0 +1,57 @@
+use chip::expand::expand;
+use chip::{Chip, DISP_HEIGHT, DISP_WIDTH, ENTRY_ADDR};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const FG: u32 = 0xFFFF_FFFF;
+const BG: u32 = 0xFF00_0000;
+
+/// 画满半屏的字符，让点亮和熄灭的像素交错
+fn screen() -> Chip {
+    let mut rom = Vec::new();
+    for i in 0..64u8 {
+        let (x, y) = (i % 16 * 4, i / 16 * 8);
+        rom.extend([0x60, x, 0x61, y, 0xA0, i % 16 * 5, 0xD0, 0x15]);
+    }
+    let mut chip = Chip::new(0);
+    chip.load_rom(ENTRY_ADDR, &rom).unwrap();
+    for _ in 0..rom.len() / 2 {
+        chip.step().unwrap();
+    }
+    chip
+}
+
+/// 逐像素从 framebuffer() 展开，作为比较的基准
+fn expand_pixels(chip: &Chip, scale: usize, out: &mut [u32]) {
+    let stride = DISP_WIDTH * scale;
+    for (i, &on) in chip.framebuffer().iter().enumerate() {
+        let (x, y) = (i % DISP_WIDTH * scale, i / DISP_WIDTH * scale);
+        for row in out[y * stride..].chunks_mut(stride).take(scale) {
+            row[x..x + scale].fill(if on { FG } else { BG });
+        }
+    }
+}
+
+fn bench(c: &mut Criterion) {
+    let chip = screen();
+    let mut group = c.benchmark_group("expand");
+    for scale in [1, 8, 16] {
+        let stride = DISP_WIDTH * scale;
+        let mut out = vec![0; stride * DISP_HEIGHT * scale];
+        group.bench_with_input(BenchmarkId::new("rows", scale), &scale, |b, &scale| {
+            b.iter(|| {
+                expand(chip.framebuffer_rows(), scale, FG, BG, &mut out, stride);
+                black_box(&out);
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("pixels", scale), &scale, |b, &scale| {
+            b.iter(|| {
+                expand_pixels(&chip, scale, &mut out);
+                black_box(&out);
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -0,0 +1,244 @@
+use crate::{Instruction, ENTRY_ADDR};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// 需要扩展指令集的操作码所属的平台
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Extension {
+    SuperChip,
+    XoChip,
+    /// 本模拟器的调试输出指令 0FX0 和 0F01，需要 --debug-print
+    DebugPrint,
+    /// ROM 单元测试的失败指令 0F02，只在 `chip8 unit-test` 中使用
+    UnitTest,
+}
+
+impl Extension {
+    /// 判断操作码是否属于某个扩展指令集
+    pub fn of(opcode: u16) -> Option<Self> {
+        let x = (opcode & 0x0F00) >> 8;
+        match (opcode >> 12, opcode & 0xFF) {
+            (0, nn) if x == 0 && (nn & 0xF0 == 0xC0 || (0xFB..=0xFF).contains(&nn)) => {
+                Some(Extension::SuperChip)
+            }
+            (0, nn) if x == 0 && nn & 0xF0 == 0xD0 => Some(Extension::XoChip),
+            (0, nn) if x == 0xF && (nn & 0xF == 0 || nn == 0x01) => Some(Extension::DebugPrint),
+            (0, 0x02) if x == 0xF => Some(Extension::UnitTest),
+            (0x5, nn) if nn & 0xF == 2 || nn & 0xF == 3 => Some(Extension::XoChip),
+            (0xD, nn) if nn & 0xF == 0 => Some(Extension::SuperChip),
+            (0xF, 0x30 | 0x75 | 0x85) => Some(Extension::SuperChip),
+            (0xF, 0x00 | 0x02) if x == 0 => Some(Extension::XoChip),
+            (0xF, 0x01 | 0x3A) => Some(Extension::XoChip),
+            _ => None,
+        }
+    }
+}
+
+/// ROM 的静态分析结果，地址都是装载到 ENTRY_ADDR 之后的地址
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Analysis {
+    /// ROM 大小
+    pub size: usize,
+    /// 从入口可以到达的指令地址
+    pub code: BTreeSet<u16>,
+    /// 代码之外的字节数 (数据或者无法到达的代码)
+    pub data_bytes: usize,
+    /// `LD I, addr` 指向代码之外的地址，通常是精灵数据
+    pub sprites: BTreeSet<u16>,
+    /// 每种助记符出现的次数
+    pub mnemonics: BTreeMap<String, usize>,
+    /// 使用了扩展指令的地址和操作码
+    pub extensions: Vec<(u16, u16, Extension)>,
+    /// 无法识别的操作码，执行到这里会出错
+    pub illegal: Vec<(u16, u16)>,
+    /// 会写入代码区域的指令地址
+    pub self_modifying: Vec<u16>,
+    /// `JP V0, addr` 的地址，跳转目标无法静态确定
+    pub indirect_jumps: Vec<u16>,
+    /// 最大子程序调用深度，存在递归时为 None
+    pub max_call_depth: Option<usize>,
+}
+
+impl Analysis {
+    /// 运行 ROM 所需的扩展指令集
+    pub fn requires(&self) -> BTreeSet<Extension> {
+        self.extensions.iter().map(|(_, _, ext)| *ext).collect()
+    }
+}
+
+/// 从入口开始沿控制流遍历 ROM，统计可以到达的代码和其中的指令
+///
+/// 遍历时只跟踪 `LD I, addr` 设置的 I，用来找出精灵和写入代码的指令，
+/// 因此结果是近似的：通过 `JP V0` 或修改代码跳转到的部分不会被统计
+pub fn analyze(rom: &[u8]) -> Analysis {
+    let start = ENTRY_ADDR as usize;
+    let end = start + rom.len();
+    let fetch = |addr: u16| -> Option<u16> {
+        let addr = addr as usize;
+        (addr >= start && addr + 1 < end)
+            .then(|| u16::from_be_bytes([rom[addr - start], rom[addr + 1 - start]]))
+    };
+
+    let mut analysis = Analysis {
+        size: rom.len(),
+        ..Analysis::default()
+    };
+    let mut calls: HashMap<u16, BTreeSet<u16>> = HashMap::new();
+    let mut writes = Vec::new(); // (指令地址, 写入的地址范围)
+    let mut functions = vec![ENTRY_ADDR];
+    let mut seen_functions = BTreeSet::from([ENTRY_ADDR]);
+
+    while let Some(entry) = functions.pop() {
+        let callees = calls.entry(entry).or_default();
+        let mut pending = vec![(entry, None::<u16>)];
+        while let Some((pc, i)) = pending.pop() {
+            let Some(opcode) = fetch(pc) else { continue };
+            if !analysis.code.insert(pc) {
+                continue;
+            }
+            let ins = Instruction::decode(opcode);
+            let next = pc.wrapping_add(2);
+            let mnemonic = ins.to_string();
+            let mnemonic = mnemonic.split(' ').next().unwrap();
+            *analysis.mnemonics.entry(mnemonic.to_string()).or_default() += 1;
+
+            match ins {
+                Instruction::Ret => (),
+                Instruction::Jp(nnn) => pending.push((nnn.get(), i)),
+                Instruction::JpV0(_) => analysis.indirect_jumps.push(pc),
+                Instruction::Call(nnn) => {
+                    let nnn = nnn.get();
+                    callees.insert(nnn);
+                    if seen_functions.insert(nnn) {
+                        functions.push(nnn);
+                    }
+                    // 子程序可能修改了 I
+                    pending.push((next, None));
+                }
+                Instruction::SeImm(..)
+                | Instruction::SneImm(..)
+                | Instruction::SeReg(..)
+                | Instruction::SneReg(..)
+                | Instruction::Skp(_)
+                | Instruction::Sknp(_) => {
+                    pending.push((next, i));
+                    pending.push((next.wrapping_add(2), i));
+                }
+                Instruction::LdI(nnn) => pending.push((next, Some(nnn.get()))),
+                Instruction::AddI(_) => pending.push((next, None)),
+                Instruction::StoreRegs(x) => {
+                    if let Some(i) = i {
+                        writes.push((pc, i, i + x.get() as u16));
+                    }
+                    pending.push((next, i));
+                }
+                Instruction::LdBcd(_) => {
+                    if let Some(i) = i {
+                        writes.push((pc, i, i + 2));
+                    }
+                    pending.push((next, i));
+                }
+                Instruction::Unknown(op) => match Extension::of(op) {
+                    Some(ext) => {
+                        analysis.extensions.push((pc, op, ext));
+                        // XO-CHIP 的 F000 NNNN 占 4 个字节
+                        let len = if op == 0xF000 { 4 } else { 2 };
+                        pending.push((pc.wrapping_add(len), i));
+                    }
+                    None => analysis.illegal.push((pc, op)),
+                },
+                _ => {
+                    if let (Instruction::Drw(_, _, 0), Some(ext)) = (ins, Extension::of(opcode)) {
+                        analysis.extensions.push((pc, opcode, ext));
+                    }
+                    pending.push((next, i));
+                }
+            }
+        }
+    }
+
+    let is_code =
+        |addr: u16| analysis.code.contains(&addr) || analysis.code.contains(&addr.wrapping_sub(1));
+    let code_bytes = (start..end).filter(|addr| is_code(*addr as u16)).count();
+    analysis.data_bytes = rom.len() - code_bytes;
+    analysis.self_modifying = writes
+        .iter()
+        .filter(|(_, from, to)| (*from..=*to).any(is_code))
+        .map(|(pc, _, _)| *pc)
+        .collect();
+    analysis.sprites = analysis
+        .code
+        .iter()
+        .filter_map(|pc| match Instruction::decode(fetch(*pc).unwrap()) {
+            Instruction::LdI(nnn) if !is_code(nnn.get()) => Some(nnn.get()),
+            _ => None,
+        })
+        .collect();
+    analysis.extensions.sort();
+    analysis.illegal.sort();
+    analysis.self_modifying.sort();
+    analysis.self_modifying.dedup();
+    analysis.indirect_jumps.sort();
+    analysis.max_call_depth = call_depth(ENTRY_ADDR, &calls, &mut Vec::new(), &mut HashMap::new());
+    analysis
+}
+
+/// 计算从 entry 开始的最大调用深度，path 为当前的调用链，depths 缓存已经算出的深度
+fn call_depth(
+    entry: u16,
+    calls: &HashMap<u16, BTreeSet<u16>>,
+    path: &mut Vec<u16>,
+    depths: &mut HashMap<u16, usize>,
+) -> Option<usize> {
+    if let Some(depth) = depths.get(&entry) {
+        return Some(*depth);
+    }
+    if path.contains(&entry) {
+        return None;
+    }
+    path.push(entry);
+    let mut depth = 0;
+    for callee in calls.get(&entry).into_iter().flatten() {
+        depth = depth.max(call_depth(*callee, calls, path, depths)? + 1);
+    }
+    path.pop();
+    depths.insert(entry, depth);
+    Some(depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let rom = [
+            0xA2, 0x0E, // 200: LD I, 0x20E
+            0x22, 0x08, // 202: CALL 0x208
+            0x12, 0x04, // 204: JP 0x204
+            0x00, 0xFF, // 206: (无法到达)
+            0xA2, 0x02, // 208: LD I, 0x202
+            0xF0, 0x55, // 20A: LD [I], V0
+            0x00, 0xEE, // 20C: RET
+            0xF0, 0x90, // 20E: 精灵数据
+        ];
+        let analysis = analyze(&rom);
+        assert_eq!(
+            analysis.code,
+            BTreeSet::from([0x200, 0x202, 0x204, 0x208, 0x20A, 0x20C])
+        );
+        assert_eq!(analysis.data_bytes, 4);
+        assert_eq!(analysis.sprites, BTreeSet::from([0x20E]));
+        assert_eq!(analysis.self_modifying, [0x20A]);
+        assert_eq!(analysis.max_call_depth, Some(1));
+        assert_eq!(analysis.mnemonics["LD"], 3);
+        assert!(analysis.requires().is_empty());
+
+        // 递归调用和扩展指令
+        let analysis = analyze(&[0x00, 0xFF, 0x22, 0x00]);
+        assert_eq!(analysis.max_call_depth, None);
+        assert_eq!(analysis.requires(), BTreeSet::from([Extension::SuperChip]));
+        // DBG V0 不是空指令
+        let analysis = analyze(&[0x0F, 0x00]);
+        assert_eq!(analysis.requires(), BTreeSet::from([Extension::DebugPrint]));
+    }
+}
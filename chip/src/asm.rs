@@ -0,0 +1,691 @@
+use crate::{Addr, Instruction, Reg, ENTRY_ADDR};
+use std::collections::HashMap;
+use std::fmt;
+
+/// 汇编错误，行号和列号从 1 开始
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    /// 出错的被包含文件，主文件中的错误为 None
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+/// 汇编结果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Assembly {
+    /// 从 ENTRY_ADDR 开始装载的程序
+    pub code: Vec<u8>,
+    /// 标签及其地址，按出现顺序排列
+    pub labels: Vec<(String, u16)>,
+    /// 主文件中每条指令的地址和所在行号，用于生成源代码映射
+    pub lines: Vec<(u16, usize)>,
+}
+
+/// 读取 INCLUDE 的文件，参数为包含它的文件 (主文件为 None) 和 INCLUDE 中写的路径，
+/// 返回用于报错的文件名和文件内容
+pub type Loader<'a> = dyn FnMut(Option<&str>, &str) -> Result<(String, String), String> + 'a;
+
+/// 最多嵌套包含的层数
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// 一个操作数及其在行内的列号
+struct Operand {
+    text: String,
+    column: usize,
+}
+
+/// 一行中的指令或数据
+struct Statement {
+    file: usize, // Parser::files 的下标，0 为主文件
+    line: usize,
+    column: usize,
+    mnemonic: String,
+    operands: Vec<Operand>,
+    size: usize, // 占用的字节数
+}
+
+/// 汇编源代码
+///
+/// 语法与反汇编输出相同 (Cowgod 助记符)，大小写不敏感，';' 之后为注释。
+/// 以 ':' 结尾的名字定义标签，可以用在任何需要地址或立即数的地方。
+/// `DB` 和 `DW` 分别写入字节和字，例如 `DB 0xF0, 0x90, 0b11110000`。
+/// `DBG Vx` 和 `DBG [I]` 生成调试输出指令 0FX0 和 0F01，`FAIL` 生成 ROM 测试的失败指令。
+/// 其余伪指令见 `assemble_with`，这里不能使用 `INCLUDE`
+pub fn assemble(source: &str) -> Result<Assembly, AsmError> {
+    assemble_with(source, &[], &mut |_, path| {
+        Err(format!("can't include {:?} without a loader", path))
+    })
+}
+
+/// 汇编可以包含其他文件的源代码，defines 为预先定义的常量 (例如命令行的 -D)
+///
+/// 除了 `assemble` 的语法，还支持以下伪指令：
+/// - `INCLUDE "file"`：在此处汇编另一个文件，标签和常量在所有文件间共享
+/// - `NAME EQU value`：定义常量，可以用在任何需要立即数的地方
+/// - `ALIGN n`：填充 0 直到地址是 n 的倍数
+/// - `IF value` / `IFDEF NAME` / `IFNDEF NAME` ... `ELSE` ... `ENDIF`：条件汇编，可以嵌套
+///
+/// EQU、ALIGN 和 IF 的值只能引用之前定义的常量和标签
+pub fn assemble_with(
+    source: &str,
+    defines: &[(String, u16)],
+    load: &mut Loader,
+) -> Result<Assembly, AsmError> {
+    // 第一遍：解析语句并计算标签地址
+    let mut parser = Parser {
+        load,
+        statements: Vec::new(),
+        labels: Vec::new(),
+        constants: defines.to_vec(),
+        addr: ENTRY_ADDR,
+        conditions: Vec::new(),
+        files: vec![String::new()],
+        including: vec![0],
+    };
+    parser.file(0, source)?;
+    if let Some(condition) = parser.conditions.first() {
+        return Err(parser.locate(
+            condition.file,
+            error(
+                condition.line,
+                condition.column,
+                "missing ENDIF".to_string(),
+            ),
+        ));
+    }
+
+    // 第二遍：生成代码
+    let symbols: HashMap<&str, u16> = parser
+        .constants
+        .iter()
+        .chain(&parser.labels)
+        .map(|(k, v)| (k.as_str(), *v))
+        .collect();
+    let mut code = Vec::new();
+    let mut lines = Vec::new();
+    for statement in &parser.statements {
+        let ctx = Context {
+            statement,
+            file: (statement.file != 0).then(|| parser.files[statement.file].as_str()),
+            symbols: &symbols,
+        };
+        match statement.mnemonic.as_str() {
+            "DB" => {
+                for op in &statement.operands {
+                    code.push(ctx.value(op, 0xFF)? as u8);
+                }
+            }
+            "DW" => {
+                for op in &statement.operands {
+                    code.extend_from_slice(&ctx.value(op, 0xFFFF)?.to_be_bytes());
+                }
+            }
+            "ALIGN" => code.resize(code.len() + statement.size, 0),
+            _ => {
+                if statement.file == 0 {
+                    lines.push((ENTRY_ADDR + code.len() as u16, statement.line));
+                }
+                code.extend_from_slice(&ctx.instruction()?.encode().to_be_bytes());
+            }
+        }
+    }
+
+    Ok(Assembly {
+        code,
+        labels: parser.labels,
+        lines,
+    })
+}
+
+/// 一层 IF 的状态
+struct Condition {
+    file: usize,
+    line: usize,
+    column: usize,
+    outer: bool, // 外层是否生效
+    taken: bool, // 已经有分支生效
+    active: bool,
+    in_else: bool,
+}
+
+/// 第一遍：逐行解析，处理伪指令和包含的文件
+struct Parser<'l, 'a> {
+    load: &'l mut Loader<'a>,
+    statements: Vec<Statement>,
+    labels: Vec<(String, u16)>,
+    constants: Vec<(String, u16)>,
+    addr: u16,
+    conditions: Vec<Condition>,
+    files: Vec<String>,    // 文件名，主文件为空
+    including: Vec<usize>, // 正在解析的文件链，用于发现循环包含
+}
+
+impl Parser<'_, '_> {
+    fn file(&mut self, file: usize, text: &str) -> Result<(), AsmError> {
+        for (n, line) in text.lines().enumerate() {
+            self.line(file, n + 1, line)
+                .map_err(|e| self.locate(file, e))?;
+        }
+        Ok(())
+    }
+
+    /// 为被包含文件中的错误加上文件名，已经带有文件名的错误来自更深的包含
+    fn locate(&self, file: usize, mut e: AsmError) -> AsmError {
+        if file != 0 && e.file.is_none() {
+            e.file = Some(self.files[file].clone());
+        }
+        e
+    }
+
+    fn active(&self) -> bool {
+        self.conditions.last().is_none_or(|c| c.active)
+    }
+
+    fn defined(&self, name: &str) -> bool {
+        self.labels
+            .iter()
+            .chain(&self.constants)
+            .any(|(n, _)| n == name)
+    }
+
+    /// 解析数字或之前定义的常量和标签
+    fn resolve(&self, text: &str, line_no: usize, column: usize) -> Result<u32, AsmError> {
+        if let Some(value) = parse_number(text) {
+            return Ok(value);
+        }
+        if !is_name(text) {
+            return Err(error(line_no, column, format!("invalid number {:?}", text)));
+        }
+        self.constants
+            .iter()
+            .chain(&self.labels)
+            .find(|(name, _)| name == text)
+            .map(|(_, value)| *value as u32)
+            .ok_or_else(|| error(line_no, column, format!("unknown name {:?}", text)))
+    }
+
+    fn line(&mut self, file: usize, line_no: usize, line: &str) -> Result<(), AsmError> {
+        let code = line.split(';').next().unwrap();
+        let trimmed = code.trim();
+        let (word, args) = trimmed
+            .split_once(char::is_whitespace)
+            .unwrap_or((trimmed, ""));
+        let args = args.trim();
+        let column = column_of(line, word);
+        // 条件汇编的伪指令在不生效的分支中也要处理，才能匹配嵌套
+        match word.to_ascii_uppercase().as_str() {
+            "IF" | "IFDEF" | "IFNDEF" => {
+                let outer = self.active();
+                let taken = outer
+                    && match word.to_ascii_uppercase().as_str() {
+                        "IF" => self.resolve(args, line_no, column_of(line, args))? != 0,
+                        "IFDEF" => self.defined(args),
+                        _ => !self.defined(args),
+                    };
+                self.conditions.push(Condition {
+                    file,
+                    line: line_no,
+                    column,
+                    outer,
+                    taken,
+                    active: taken,
+                    in_else: false,
+                });
+                return Ok(());
+            }
+            "ELSE" => {
+                let condition = match self.conditions.last_mut() {
+                    Some(c) if !c.in_else => c,
+                    _ => return Err(error(line_no, column, "unexpected ELSE".to_string())),
+                };
+                condition.in_else = true;
+                condition.active = condition.outer && !condition.taken;
+                return Ok(());
+            }
+            "ENDIF" => {
+                if self.conditions.pop().is_none() {
+                    return Err(error(line_no, column, "unexpected ENDIF".to_string()));
+                }
+                return Ok(());
+            }
+            _ if !self.active() => return Ok(()),
+            _ => (),
+        }
+
+        if args
+            .split_whitespace()
+            .next()
+            .is_some_and(|w| w.eq_ignore_ascii_case("EQU"))
+        {
+            if !is_name(word) {
+                return Err(error(line_no, column, format!("invalid name {:?}", word)));
+            }
+            if self.defined(word) {
+                return Err(error(line_no, column, format!("duplicate name {:?}", word)));
+            }
+            let text = args[3..].trim();
+            let value = self.resolve(text, line_no, column_of(line, text))?;
+            let value = u16::try_from(value).map_err(|_| {
+                error(
+                    line_no,
+                    column_of(line, text),
+                    format!("value {} out of range", text),
+                )
+            })?;
+            self.constants.push((word.to_string(), value));
+            return Ok(());
+        }
+
+        let mut rest = code.trim_start();
+        while let Some((label, after)) = rest.split_once(':') {
+            let label = label.trim();
+            if label.is_empty() || label.contains(char::is_whitespace) {
+                break;
+            }
+            let column = column_of(line, label);
+            if !is_name(label) {
+                return Err(error(line_no, column, format!("invalid label {:?}", label)));
+            }
+            if self.defined(label) {
+                return Err(error(
+                    line_no,
+                    column,
+                    format!("duplicate label {:?}", label),
+                ));
+            }
+            self.labels.push((label.to_string(), self.addr));
+            rest = after.trim_start();
+        }
+        if rest.trim().is_empty() {
+            return Ok(());
+        }
+
+        let (mnemonic, args) = rest
+            .trim_end()
+            .split_once(char::is_whitespace)
+            .unwrap_or((rest.trim_end(), ""));
+        let column = column_of(line, mnemonic);
+        let mnemonic = mnemonic.to_ascii_uppercase();
+        if mnemonic == "INCLUDE" {
+            return self.include(file, line_no, column, args.trim());
+        }
+        let mut operands = Vec::new();
+        if !args.trim().is_empty() {
+            for arg in args.split(',') {
+                let text = arg.trim();
+                let column = column_of(line, arg) + (arg.len() - arg.trim_start().len());
+                if text.is_empty() {
+                    return Err(error(line_no, column, "missing operand".to_string()));
+                }
+                operands.push(Operand {
+                    text: text.to_string(),
+                    column,
+                });
+            }
+        }
+        let size = match mnemonic.as_str() {
+            "DB" => operands.len(),
+            "DW" => operands.len() * 2,
+            "ALIGN" => {
+                let [op] = operands.as_slice() else {
+                    return Err(error(line_no, column, "expected ALIGN n".to_string()));
+                };
+                match self.resolve(&op.text, line_no, op.column)? {
+                    0 => return Err(error(line_no, op.column, "can't align to 0".to_string())),
+                    n => (n - self.addr as u32 % n) as usize % n as usize,
+                }
+            }
+            _ => 2,
+        };
+        self.addr = (self.addr as usize + size)
+            .try_into()
+            .ok()
+            .filter(|addr| *addr as usize <= crate::MEM_SIZE)
+            .ok_or_else(|| error(line_no, column, "program too large".to_string()))?;
+        self.statements.push(Statement {
+            file,
+            line: line_no,
+            column,
+            mnemonic,
+            operands,
+            size,
+        });
+        Ok(())
+    }
+
+    fn include(
+        &mut self,
+        file: usize,
+        line_no: usize,
+        column: usize,
+        path: &str,
+    ) -> Result<(), AsmError> {
+        let path = path
+            .strip_prefix('"')
+            .and_then(|p| p.strip_suffix('"'))
+            .unwrap_or(path);
+        if path.is_empty() {
+            return Err(error(
+                line_no,
+                column,
+                "expected INCLUDE \"file\"".to_string(),
+            ));
+        }
+        if self.including.len() > MAX_INCLUDE_DEPTH {
+            return Err(error(
+                line_no,
+                column,
+                "includes nested too deeply".to_string(),
+            ));
+        }
+        let from = (file != 0).then(|| self.files[file].as_str());
+        let (name, text) = (self.load)(from, path)
+            .map_err(|e| error(line_no, column, format!("can't include {:?}: {}", path, e)))?;
+        if self.including.iter().any(|f| self.files[*f] == name) {
+            return Err(error(
+                line_no,
+                column,
+                format!("{:?} includes itself", name),
+            ));
+        }
+        self.files.push(name);
+        let index = self.files.len() - 1;
+        self.including.push(index);
+        self.file(index, &text)?;
+        self.including.pop();
+        Ok(())
+    }
+}
+
+/// 第二遍中编码一条语句所需的信息
+struct Context<'a> {
+    statement: &'a Statement,
+    file: Option<&'a str>, // 被包含文件的文件名
+    symbols: &'a HashMap<&'a str, u16>,
+}
+
+impl Context<'_> {
+    fn error(&self, column: usize, message: String) -> AsmError {
+        AsmError {
+            file: self.file.map(str::to_string),
+            ..error(self.statement.line, column, message)
+        }
+    }
+
+    fn instruction(&self) -> Result<Instruction, AsmError> {
+        use Instruction::*;
+
+        let ops = &self.statement.operands;
+        let kinds: Vec<Kind> = ops.iter().map(|op| Kind::of(&op.text)).collect();
+        let reg = |n: usize| self.register(&ops[n]);
+        let byte = |n: usize| self.value(&ops[n], 0xFF).map(|v| v as u8);
+        let addr = |n: usize| self.value(&ops[n], 0xFFF).map(Addr::from_bits);
+
+        let ins = match (self.statement.mnemonic.as_str(), kinds.as_slice()) {
+            ("NOP", []) => Nop,
+            ("CLS", []) => Cls,
+            ("RET", []) => Ret,
+            ("JP", [Kind::Value]) => Jp(addr(0)?),
+            ("JP", [Kind::Reg(0), Kind::Value]) => JpV0(addr(1)?),
+            ("CALL", [Kind::Value]) => Call(addr(0)?),
+            ("SE", [Kind::Reg(_), Kind::Value]) => SeImm(reg(0)?, byte(1)?),
+            ("SE", [Kind::Reg(_), Kind::Reg(_)]) => SeReg(reg(0)?, reg(1)?),
+            ("SNE", [Kind::Reg(_), Kind::Value]) => SneImm(reg(0)?, byte(1)?),
+            ("SNE", [Kind::Reg(_), Kind::Reg(_)]) => SneReg(reg(0)?, reg(1)?),
+            ("LD", [Kind::Reg(_), Kind::Value]) => LdImm(reg(0)?, byte(1)?),
+            ("LD", [Kind::Reg(_), Kind::Reg(_)]) => LdReg(reg(0)?, reg(1)?),
+            ("LD", [Kind::Key("I"), Kind::Value]) => LdI(addr(1)?),
+            ("LD", [Kind::Reg(_), Kind::Key("DT")]) => LdVxDt(reg(0)?),
+            ("LD", [Kind::Reg(_), Kind::Key("K")]) => LdKey(reg(0)?),
+            ("LD", [Kind::Key("DT"), Kind::Reg(_)]) => LdDtVx(reg(1)?),
+            ("LD", [Kind::Key("ST"), Kind::Reg(_)]) => LdStVx(reg(1)?),
+            ("LD", [Kind::Key("F"), Kind::Reg(_)]) => LdFont(reg(1)?),
+            ("LD", [Kind::Key("B"), Kind::Reg(_)]) => LdBcd(reg(1)?),
+            ("LD", [Kind::Key("[I]"), Kind::Reg(_)]) => StoreRegs(reg(1)?),
+            ("LD", [Kind::Reg(_), Kind::Key("[I]")]) => LoadRegs(reg(0)?),
+            ("ADD", [Kind::Reg(_), Kind::Value]) => AddImm(reg(0)?, byte(1)?),
+            ("ADD", [Kind::Reg(_), Kind::Reg(_)]) => Add(reg(0)?, reg(1)?),
+            ("ADD", [Kind::Key("I"), Kind::Reg(_)]) => AddI(reg(1)?),
+            ("OR", [Kind::Reg(_), Kind::Reg(_)]) => Or(reg(0)?, reg(1)?),
+            ("AND", [Kind::Reg(_), Kind::Reg(_)]) => And(reg(0)?, reg(1)?),
+            ("XOR", [Kind::Reg(_), Kind::Reg(_)]) => Xor(reg(0)?, reg(1)?),
+            ("SUB", [Kind::Reg(_), Kind::Reg(_)]) => Sub(reg(0)?, reg(1)?),
+            ("SUBN", [Kind::Reg(_), Kind::Reg(_)]) => Subn(reg(0)?, reg(1)?),
+            // 省略 Vy 时取 Vy = Vx，这样不论移位是否使用 Vy 结果都相同
+            ("SHR", [Kind::Reg(_)]) => Shr(reg(0)?, reg(0)?),
+            ("SHR", [Kind::Reg(_), Kind::Reg(_)]) => Shr(reg(0)?, reg(1)?),
+            ("SHL", [Kind::Reg(_)]) => Shl(reg(0)?, reg(0)?),
+            ("SHL", [Kind::Reg(_), Kind::Reg(_)]) => Shl(reg(0)?, reg(1)?),
+            ("RND", [Kind::Reg(_), Kind::Value]) => Rnd(reg(0)?, byte(1)?),
+            ("DRW", [Kind::Reg(_), Kind::Reg(_), Kind::Value]) => {
+                Drw(reg(0)?, reg(1)?, self.value(&ops[2], 0xF)? as u8)
+            }
+            ("SKP", [Kind::Reg(_)]) => Skp(reg(0)?),
+            ("SKNP", [Kind::Reg(_)]) => Sknp(reg(0)?),
+            // 调试输出指令，需要在解释器中启用
+            ("DBG", [Kind::Reg(_)]) => Unknown(0x0F00 | (reg(0)?.get() as u16) << 4),
+            ("DBG", [Kind::Key("[I]")]) => Unknown(0x0F01),
+            // ROM 单元测试的失败指令
+            ("FAIL", []) => Unknown(crate::romtest::FAIL_OPCODE),
+            (
+                "NOP" | "CLS" | "RET" | "JP" | "CALL" | "SE" | "SNE" | "LD" | "ADD" | "OR" | "AND"
+                | "XOR" | "SUB" | "SUBN" | "SHR" | "SHL" | "RND" | "DRW" | "SKP" | "SKNP" | "DBG"
+                | "FAIL",
+                _,
+            ) => {
+                return Err(self.error(
+                    self.statement.column,
+                    format!("invalid operands for {}", self.statement.mnemonic),
+                ))
+            }
+            (mnemonic, _) => {
+                return Err(self.error(
+                    self.statement.column,
+                    format!("unknown instruction {:?}", mnemonic),
+                ))
+            }
+        };
+        Ok(ins)
+    }
+
+    fn register(&self, op: &Operand) -> Result<Reg, AsmError> {
+        match Kind::of(&op.text) {
+            Kind::Reg(x) => Ok(Reg::from_nibble(x)),
+            _ => Err(self.error(op.column, format!("expected register, found {:?}", op.text))),
+        }
+    }
+
+    /// 解析数字、常量或标签，并检查是否超出范围
+    fn value(&self, op: &Operand, max: u16) -> Result<u16, AsmError> {
+        let value = match parse_number(&op.text) {
+            Some(value) => value,
+            None if is_name(&op.text) => match self.symbols.get(op.text.as_str()) {
+                Some(addr) => *addr as u32,
+                None => return Err(self.error(op.column, format!("unknown label {:?}", op.text))),
+            },
+            None => return Err(self.error(op.column, format!("invalid number {:?}", op.text))),
+        };
+        if value > max as u32 {
+            return Err(self.error(
+                op.column,
+                format!("value {} out of range (max 0x{:X})", op.text, max),
+            ));
+        }
+        Ok(value as u16)
+    }
+}
+
+/// 操作数的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Reg(u8),
+    Key(&'static str),
+    Value,
+}
+
+impl Kind {
+    fn of(text: &str) -> Self {
+        let upper = text.to_ascii_uppercase();
+        if let Some(x) = upper.strip_prefix('V') {
+            if x.len() == 1 {
+                if let Ok(x) = u8::from_str_radix(x, 16) {
+                    return Kind::Reg(x);
+                }
+            }
+        }
+        match ["I", "DT", "ST", "K", "F", "B", "[I]"]
+            .into_iter()
+            .find(|key| *key == upper)
+        {
+            Some(key) => Kind::Key(key),
+            None => Kind::Value,
+        }
+    }
+}
+
+/// 解析十进制、0x 开头的十六进制或 0b 开头的二进制数
+fn parse_number(text: &str) -> Option<u32> {
+    let lower = text.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u32::from_str_radix(bin, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+fn is_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// 子串在行内的列号，子串必须是从 line 中切出来的
+fn column_of(line: &str, part: &str) -> usize {
+    part.as_ptr() as usize - line.as_ptr() as usize + 1
+}
+
+fn error(line: usize, column: usize, message: String) -> AsmError {
+    AsmError {
+        file: None,
+        line,
+        column,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assemble() {
+        let source = "
+            ; 画一个字符然后停在原地
+            start:  LD V0, 0x0A
+                    ld f, v0
+                    DRW V1, V2, 5
+            loop:   JP loop
+            data:   DB 0xF0, 0b10010000, 144
+                    DW start
+        ";
+        let asm = assemble(source).unwrap();
+        assert_eq!(
+            asm.code,
+            [0x60, 0x0A, 0xF0, 0x29, 0xD1, 0x25, 0x12, 0x06, 0xF0, 0x90, 0x90, 0x02, 0x00]
+        );
+        assert_eq!(
+            asm.labels,
+            [
+                ("start".to_string(), 0x200),
+                ("loop".to_string(), 0x206),
+                ("data".to_string(), 0x208)
+            ]
+        );
+        assert_eq!(asm.lines, [(0x200, 3), (0x202, 4), (0x204, 5), (0x206, 6)]);
+
+        // 反汇编的输出可以重新汇编
+        assert_eq!(
+            assemble("DBG VA\nDBG [I]").unwrap().code,
+            [0x0F, 0xA0, 0x0F, 0x01]
+        );
+        for op in [0x8AA6, 0xF265, 0xB123, 0xE09E, 0xF855] {
+            let text = Instruction::decode(op).to_string();
+            assert_eq!(assemble(&text).unwrap().code, op.to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn test_assemble_error() {
+        let err = assemble("CLS\n  LD V0, 0x100").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 10));
+        let err = assemble("  JP nowhere").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 6));
+        let err = assemble("MOV V0, V1").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 1));
+        assert!(assemble("a: CLS\na: RET").is_err());
+        assert!(assemble("SHL I").is_err());
+    }
+
+    #[test]
+    fn test_assemble_with() {
+        let files = [
+            ("sprites.asm", "SIZE EQU 5\nALIGN 4\nball: DB 0xF0, 0xF0"),
+            ("bad.asm", "JP nowhere"),
+            ("self.asm", "INCLUDE \"self.asm\""),
+        ];
+        let mut load = |_: Option<&str>, path: &str| {
+            files
+                .iter()
+                .find(|(name, _)| *name == path)
+                .map(|(name, text)| (name.to_string(), text.to_string()))
+                .ok_or_else(|| "not found".to_string())
+        };
+        let source = "
+            CLS
+            INCLUDE \"sprites.asm\"
+            IFDEF DEBUG
+                LD V0, SIZE
+            ELSE
+                IF 0
+                    RET
+                ENDIF
+                LD V1, SIZE
+            ENDIF
+            LD I, ball
+        ";
+        let asm = assemble_with(source, &[], &mut load).unwrap();
+        assert_eq!(
+            asm.code,
+            [0x00, 0xE0, 0, 0, 0xF0, 0xF0, 0x61, 0x05, 0xA2, 0x04]
+        );
+        assert_eq!(asm.labels, [("ball".to_string(), 0x204)]);
+        assert_eq!(asm.lines, [(0x200, 2), (0x206, 10), (0x208, 12)]);
+
+        let debug = assemble_with(source, &[("DEBUG".to_string(), 1)], &mut load).unwrap();
+        assert_eq!(debug.code[6..8], [0x60, 0x05]);
+
+        let err = assemble_with("CLS\nINCLUDE \"bad.asm\"", &[], &mut load).unwrap_err();
+        assert_eq!(err.to_string(), "bad.asm:1:4: unknown label \"nowhere\"");
+        assert!(assemble_with("INCLUDE \"self.asm\"", &[], &mut load).is_err());
+        assert!(assemble_with("INCLUDE \"missing.asm\"", &[], &mut load).is_err());
+        assert!(assemble("IF 1\nCLS").is_err());
+        assert!(assemble("ENDIF").is_err());
+    }
+}
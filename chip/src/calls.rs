@@ -0,0 +1,225 @@
+use crate::{Chip, ENTRY_ADDR};
+use std::collections::BTreeMap;
+
+/// 调用树中的一个节点，表示从某条调用链进入的一个子程序
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallNode {
+    /// 子程序地址，根节点为 ENTRY_ADDR
+    pub addr: u16,
+    /// 从这条调用链调用的次数
+    pub calls: u64,
+    /// 在子程序自身中执行的指令数，不包括其调用的子程序
+    pub instructions: u64,
+    /// 在子程序自身中每个地址上的指令执行的次数
+    pub addresses: BTreeMap<u16, u64>,
+    /// 子节点的下标
+    pub children: Vec<usize>,
+}
+
+/// 按子程序汇总的统计
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subroutine {
+    pub addr: u16,
+    pub calls: u64,
+    /// 子程序自身执行的指令数
+    pub instructions: u64,
+    /// 包括其调用的子程序在内执行的指令数
+    pub total: u64,
+}
+
+/// 根据执行的 2NNN 和 00EE 记录动态调用树
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallTree {
+    nodes: Vec<CallNode>,
+    path: Vec<usize>, // 当前的调用链，第一个是根节点
+}
+
+impl Default for CallTree {
+    fn default() -> Self {
+        Self {
+            nodes: vec![CallNode {
+                addr: ENTRY_ADDR,
+                calls: 1,
+                instructions: 0,
+                addresses: BTreeMap::new(),
+                children: Vec::new(),
+            }],
+            path: vec![0],
+        }
+    }
+}
+
+impl CallTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在每条指令成功执行后调用，记录这条指令
+    pub fn record(&mut self, chip: &Chip) {
+        let current = *self.path.last().unwrap();
+        self.nodes[current].instructions += 1;
+        *self.nodes[current]
+            .addresses
+            .entry(chip.opcode_addr())
+            .or_insert(0) += 1;
+        match chip.opcode() {
+            op if op & 0xF000 == 0x2000 => {
+                let addr = chip.pc();
+                let child = self.nodes[current]
+                    .children
+                    .iter()
+                    .copied()
+                    .find(|child| self.nodes[*child].addr == addr);
+                let child = child.unwrap_or_else(|| {
+                    self.nodes.push(CallNode {
+                        addr,
+                        calls: 0,
+                        instructions: 0,
+                        addresses: BTreeMap::new(),
+                        children: Vec::new(),
+                    });
+                    let child = self.nodes.len() - 1;
+                    self.nodes[current].children.push(child);
+                    child
+                });
+                self.nodes[child].calls += 1;
+                self.path.push(child);
+            }
+            0x00EE if self.path.len() > 1 => {
+                self.path.pop();
+            }
+            _ => (),
+        }
+    }
+
+    /// 所有节点，下标 0 为根节点
+    pub fn nodes(&self) -> &[CallNode] {
+        &self.nodes
+    }
+
+    /// 节点及其所有子节点执行的指令数
+    pub fn total(&self, index: usize) -> u64 {
+        let node = &self.nodes[index];
+        node.instructions + node.children.iter().map(|c| self.total(*c)).sum::<u64>()
+    }
+
+    /// 每个地址上的指令执行的次数，按次数从多到少排列
+    pub fn hot_addresses(&self) -> Vec<(u16, u64)> {
+        let mut counts = BTreeMap::new();
+        for node in &self.nodes {
+            for (addr, count) in &node.addresses {
+                *counts.entry(*addr).or_insert(0) += count;
+            }
+        }
+        let mut hot: Vec<(u16, u64)> = counts.into_iter().collect();
+        hot.sort_by_key(|(addr, count)| (std::cmp::Reverse(*count), *addr));
+        hot
+    }
+
+    /// 生成火焰图工具使用的折叠栈格式，每行为 `帧;帧;... 次数`。
+    /// 调用链上的每个子程序是一帧，最后一帧是执行指令的地址，frame 给出子程序帧的名字
+    pub fn folded(&self, frame: impl Fn(u16) -> String) -> String {
+        let mut out = String::new();
+        self.fold(0, &mut Vec::new(), &frame, &mut out);
+        out
+    }
+
+    fn fold(
+        &self,
+        index: usize,
+        stack: &mut Vec<String>,
+        frame: &impl Fn(u16) -> String,
+        out: &mut String,
+    ) {
+        let node = &self.nodes[index];
+        stack.push(frame(node.addr));
+        let prefix = stack.join(";");
+        for (addr, count) in &node.addresses {
+            out.push_str(&format!("{};{:03X} {}\n", prefix, addr, count));
+        }
+        for child in &node.children {
+            self.fold(*child, stack, frame, out);
+        }
+        stack.pop();
+    }
+
+    /// 按子程序汇总，按包括子调用在内的指令数从多到少排列。
+    /// 递归调用时只计算最外层的总数，避免重复计算
+    pub fn flat(&self) -> Vec<Subroutine> {
+        let mut subroutines = BTreeMap::new();
+        self.collect(0, &mut Vec::new(), &mut subroutines);
+        let mut flat: Vec<Subroutine> = subroutines.into_values().collect();
+        flat.sort_by_key(|s| (std::cmp::Reverse(s.total), s.addr));
+        flat
+    }
+
+    fn collect(&self, index: usize, path: &mut Vec<u16>, out: &mut BTreeMap<u16, Subroutine>) {
+        let node = &self.nodes[index];
+        let entry = out.entry(node.addr).or_insert(Subroutine {
+            addr: node.addr,
+            calls: 0,
+            instructions: 0,
+            total: 0,
+        });
+        entry.calls += node.calls;
+        entry.instructions += node.instructions;
+        if !path.contains(&node.addr) {
+            entry.total += self.total(index);
+        }
+        path.push(node.addr);
+        for child in &node.children {
+            self.collect(*child, path, out);
+        }
+        path.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_call_tree() {
+        let mut chip = Chip::new(0);
+        // 200: CALL 206; 202: CALL 206; 204: JP 204; 206: CALL 20A; 208: RET; 20A: RET
+        chip.load_rom(
+            0x200,
+            &[
+                0x22, 0x06, 0x22, 0x06, 0x12, 0x04, 0x22, 0x0A, 0x00, 0xEE, 0x00, 0xEE,
+            ],
+        )
+        .unwrap();
+        let mut tree = CallTree::new();
+        for _ in 0..10 {
+            chip.step().unwrap();
+            tree.record(&chip);
+        }
+
+        let nodes = tree.nodes();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(
+            (nodes[1].addr, nodes[1].calls, nodes[1].instructions),
+            (0x206, 2, 4)
+        );
+        assert_eq!(
+            (nodes[2].addr, nodes[2].calls, nodes[2].instructions),
+            (0x20A, 2, 2)
+        );
+        assert_eq!(tree.total(0), 10);
+        assert_eq!(nodes[0].addresses[&0x204], 2);
+        assert_eq!(tree.hot_addresses()[0], (0x204, 2));
+        let folded = tree.folded(|addr| format!("sub_{:03X}", addr));
+        assert!(folded.contains("sub_200;204 2\n"));
+        assert!(folded.contains("sub_200;sub_206;sub_20A;20A 2\n"));
+        let flat = tree.flat();
+        assert_eq!(
+            flat[1],
+            Subroutine {
+                addr: 0x206,
+                calls: 2,
+                instructions: 4,
+                total: 6
+            }
+        );
+    }
+}
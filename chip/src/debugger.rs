@@ -0,0 +1,390 @@
+use crate::expr::Expr;
+use crate::{Chip, Exception, DISP_HEIGHT, DISP_WIDTH};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+/// 调试运行停止的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    /// 执行到断点，PC 指向断点处尚未执行的指令
+    Breakpoint(u16),
+    /// 将要执行匹配操作码断点的指令，PC 指向这条指令
+    Opcode { addr: u16, opcode: u16 },
+    /// 断点的条件无法计算，例如读取超出内存，PC 指向断点处
+    Condition { addr: u16, error: String },
+    /// 绘图指令改变了监视区域内的像素，PC 指向这条指令之后
+    ScreenChange {
+        addr: u16,
+        opcode: u16,
+        region: ScreenRegion,
+    },
+    /// 执行出错
+    Exception(Exception),
+    /// 达到指定的指令数
+    Limit,
+}
+
+/// 操作码断点的模式，例如 `DXYN` 匹配所有绘图指令，`FX0A` 匹配所有等待按键的指令
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OpcodePattern {
+    pub mask: u16,
+    pub value: u16,
+}
+
+impl OpcodePattern {
+    pub fn matches(&self, opcode: u16) -> bool {
+        opcode & self.mask == self.value
+    }
+}
+
+impl FromStr for OpcodePattern {
+    type Err = String;
+
+    /// 4 个字符，十六进制数字需要匹配，其他字符 (如 X、Y、N、K、?) 匹配任意值
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.chars().count() != 4 {
+            return Err(format!("opcode pattern {:?} must have 4 digits", s));
+        }
+        let mut pattern = Self { mask: 0, value: 0 };
+        for c in s.chars() {
+            pattern.mask <<= 4;
+            pattern.value <<= 4;
+            if let Some(digit) = c.to_digit(16) {
+                pattern.mask |= 0xF;
+                pattern.value |= digit as u16;
+            }
+        }
+        Ok(pattern)
+    }
+}
+
+impl fmt::Display for OpcodePattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for shift in [12, 8, 4, 0] {
+            if (self.mask >> shift) & 0xF == 0 {
+                write!(f, "?")?;
+            } else {
+                write!(f, "{:X}", (self.value >> shift) & 0xF)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 画面上的矩形区域，用于监视画面变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScreenRegion {
+    pub x: u8,
+    pub y: u8,
+    pub width: u8,
+    pub height: u8,
+}
+
+impl ScreenRegion {
+    /// 区域在一行中的像素掩码，最高位是最左边的像素
+    fn mask(&self) -> u64 {
+        let right = (self.x + self.width) as u32;
+        (u64::MAX >> self.x) & !u64::MAX.checked_shr(right).unwrap_or(0)
+    }
+
+    /// 两个画面在区域内是否不同
+    fn changed(&self, before: &[u64; DISP_HEIGHT], after: &[u64; DISP_HEIGHT]) -> bool {
+        let mask = self.mask();
+        let rows = self.y as usize..(self.y + self.height) as usize;
+        before[rows.clone()]
+            .iter()
+            .zip(&after[rows])
+            .any(|(a, b)| (a ^ b) & mask != 0)
+    }
+}
+
+impl FromStr for ScreenRegion {
+    type Err = String;
+
+    /// `x,y,宽,高` 形式的十进制数，区域不能超出画面
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u8>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .filter(|v| v.len() == 4 && v[2] > 0 && v[3] > 0)
+            .ok_or_else(|| format!("invalid screen region {:?} (expected x,y,width,height)", s))?;
+        let region = Self {
+            x: values[0],
+            y: values[1],
+            width: values[2],
+            height: values[3],
+        };
+        if region.x as usize + region.width as usize > DISP_WIDTH
+            || region.y as usize + region.height as usize > DISP_HEIGHT
+        {
+            return Err(format!(
+                "screen region {} is outside the {}x{} screen",
+                region, DISP_WIDTH, DISP_HEIGHT
+            ));
+        }
+        Ok(region)
+    }
+}
+
+impl fmt::Display for ScreenRegion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+/// 调试器：单步执行、断点，并按每帧的指令数递减定时器
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    opcode_breakpoints: BTreeSet<OpcodePattern>,
+    conditions: BTreeMap<u16, Expr>, // 条件断点，条件成立时才停止
+    screen_watches: BTreeSet<ScreenRegion>,
+    cheats: BTreeMap<u16, u8>, // 每条指令执行后写回的内存值
+    per_frame: u32,            // 每帧执行的指令数
+    counter: u32,              // 本帧已执行的指令数
+}
+
+impl Debugger {
+    /// 创建调试器，per_frame 为每帧 (1/60 秒) 执行的指令数
+    pub fn new(per_frame: u32) -> Self {
+        Self {
+            breakpoints: BTreeSet::new(),
+            opcode_breakpoints: BTreeSet::new(),
+            conditions: BTreeMap::new(),
+            screen_watches: BTreeSet::new(),
+            cheats: BTreeMap::new(),
+            per_frame: per_frame.max(1),
+            counter: 0,
+        }
+    }
+
+    /// 添加断点，断点已存在时返回 false
+    pub fn add_breakpoint(&mut self, addr: u16) -> bool {
+        self.breakpoints.insert(addr)
+    }
+
+    /// 删除断点及其条件，断点不存在时返回 false
+    pub fn remove_breakpoint(&mut self, addr: u16) -> bool {
+        self.conditions.remove(&addr);
+        self.breakpoints.remove(&addr)
+    }
+
+    /// 设置断点的条件，None 表示无条件停止
+    pub fn set_condition(&mut self, addr: u16, condition: Option<Expr>) {
+        match condition {
+            Some(condition) => self.conditions.insert(addr, condition),
+            None => self.conditions.remove(&addr),
+        };
+    }
+
+    pub fn condition(&self, addr: u16) -> Option<&Expr> {
+        self.conditions.get(&addr)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = u16> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// 添加操作码断点，断点已存在时返回 false
+    pub fn add_opcode_breakpoint(&mut self, pattern: OpcodePattern) -> bool {
+        self.opcode_breakpoints.insert(pattern)
+    }
+
+    /// 删除操作码断点，断点不存在时返回 false
+    pub fn remove_opcode_breakpoint(&mut self, pattern: OpcodePattern) -> bool {
+        self.opcode_breakpoints.remove(&pattern)
+    }
+
+    pub fn opcode_breakpoints(&self) -> impl Iterator<Item = OpcodePattern> + '_ {
+        self.opcode_breakpoints.iter().copied()
+    }
+
+    /// 监视画面区域，区域内的像素改变时停止，已在监视时返回 false
+    pub fn add_screen_watch(&mut self, region: ScreenRegion) -> bool {
+        self.screen_watches.insert(region)
+    }
+
+    /// 取消监视画面区域，没有监视时返回 false
+    pub fn remove_screen_watch(&mut self, region: ScreenRegion) -> bool {
+        self.screen_watches.remove(&region)
+    }
+
+    pub fn screen_watches(&self) -> impl Iterator<Item = ScreenRegion> + '_ {
+        self.screen_watches.iter().copied()
+    }
+
+    /// 把地址处的内存固定为 value，例如保持生命数不变。立即写入，之后每条指令执行后写回
+    pub fn add_cheat(&mut self, chip: &mut Chip, addr: u16, value: u8) -> Result<(), Exception> {
+        chip.load_region(addr, &[value])?;
+        self.cheats.insert(addr, value);
+        Ok(())
+    }
+
+    /// 不再固定地址处的内存，没有固定时返回 false
+    pub fn remove_cheat(&mut self, addr: u16) -> bool {
+        self.cheats.remove(&addr).is_some()
+    }
+
+    pub fn cheats(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+        self.cheats.iter().map(|(&addr, &value)| (addr, value))
+    }
+
+    /// 执行一条指令，每执行 per_frame 条指令定时器递减一次
+    pub fn step(&mut self, chip: &mut Chip) -> Result<(), Exception> {
+        chip.step()?;
+        for (&addr, &value) in &self.cheats {
+            chip.load_region(addr, &[value])?;
+        }
+        self.counter += 1;
+        if self.counter >= self.per_frame {
+            self.counter = 0;
+            chip.tick_timers();
+        }
+        Ok(())
+    }
+
+    /// 连续执行直到遇到断点、监视的画面区域改变、出错或执行了 limit 条指令。
+    /// 当前 PC 处的断点会被跳过，这样可以从断点处继续运行
+    pub fn run(&mut self, chip: &mut Chip, limit: u64) -> Stop {
+        for n in 0..limit {
+            let pc = chip.pc();
+            if n > 0 && self.breakpoints.contains(&pc) {
+                match self.conditions.get(&pc).map(|c| c.test(chip)) {
+                    None | Some(Ok(true)) => return Stop::Breakpoint(pc),
+                    Some(Ok(false)) => (),
+                    Some(Err(error)) => return Stop::Condition { addr: pc, error },
+                }
+            }
+            if let Some(opcode) = chip.opcode_at(pc).filter(|op| n > 0 && self.matches(*op)) {
+                return Stop::Opcode { addr: pc, opcode };
+            }
+            let before = *chip.framebuffer_rows();
+            if let Err(e) = self.step(chip) {
+                return Stop::Exception(e);
+            }
+            if chip.last_draw().is_some() {
+                let after = chip.framebuffer_rows();
+                if let Some(region) = self
+                    .screen_watches
+                    .iter()
+                    .find(|r| r.changed(&before, after))
+                {
+                    return Stop::ScreenChange {
+                        addr: chip.opcode_addr(),
+                        opcode: chip.opcode(),
+                        region: *region,
+                    };
+                }
+            }
+        }
+        Stop::Limit
+    }
+
+    fn matches(&self, opcode: u16) -> bool {
+        self.opcode_breakpoints.iter().any(|p| p.matches(opcode))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breakpoint() {
+        let mut chip = Chip::new(0);
+        // 200: LD V0, 1; 202: ADD V0, 1; 204: JP 0x202
+        chip.load_rom(0x200, &[0x60, 0x01, 0x70, 0x01, 0x12, 0x02])
+            .unwrap();
+        let mut debugger = Debugger::new(10);
+        debugger.add_breakpoint(0x204);
+
+        assert_eq!(debugger.run(&mut chip, 100), Stop::Breakpoint(0x204));
+        assert_eq!(chip.registers()[0], 2);
+        assert_eq!(debugger.run(&mut chip, 100), Stop::Breakpoint(0x204));
+        assert_eq!(chip.registers()[0], 3);
+
+        debugger.remove_breakpoint(0x204);
+        assert_eq!(debugger.run(&mut chip, 10), Stop::Limit);
+
+        // 任何 7XNN 指令
+        let pattern: OpcodePattern = "7XNN".parse().unwrap();
+        assert_eq!(pattern.to_string(), "7???");
+        debugger.add_opcode_breakpoint(pattern);
+        assert_eq!(
+            debugger.run(&mut chip, 10),
+            Stop::Opcode {
+                addr: 0x202,
+                opcode: 0x7001
+            }
+        );
+        assert!("DXY".parse::<OpcodePattern>().is_err());
+
+        // V0 每次循环加 1，只在 V0 为 20 时停止
+        debugger.remove_opcode_breakpoint(pattern);
+        debugger.add_breakpoint(0x204);
+        debugger.set_condition(0x204, Some("v0 == 20".parse().unwrap()));
+        assert_eq!(debugger.run(&mut chip, 100), Stop::Breakpoint(0x204));
+        assert_eq!(chip.registers()[0], 20);
+        debugger.set_condition(0x204, Some("mem[0x1000]".parse().unwrap()));
+        assert!(matches!(
+            debugger.run(&mut chip, 100),
+            Stop::Condition { addr: 0x204, .. }
+        ));
+    }
+
+    #[test]
+    fn test_screen_watch() {
+        let mut chip = Chip::new(0);
+        // 200: LD V0, 8; 202: DRW V0, V0, 5 (画在 (8, 8)); 204: DRW V1, V1, 5 (画在 (0, 0)); 206: JP 0x204
+        chip.load_rom(0x200, &[0x60, 0x08, 0xD0, 0x05, 0xD1, 0x15, 0x12, 0x04])
+            .unwrap();
+        let mut debugger = Debugger::new(10);
+        let region: ScreenRegion = "2,1,3,3".parse().unwrap();
+        assert!(debugger.add_screen_watch(region));
+        assert_eq!(
+            debugger.run(&mut chip, 100),
+            Stop::ScreenChange {
+                addr: 0x204,
+                opcode: 0xD115,
+                region
+            }
+        );
+        // 再次绘制擦除了区域内的像素
+        assert!(matches!(
+            debugger.run(&mut chip, 100),
+            Stop::ScreenChange { addr: 0x204, .. }
+        ));
+        assert!(debugger.remove_screen_watch(region));
+        assert_eq!(debugger.run(&mut chip, 100), Stop::Limit);
+
+        assert_eq!(
+            ScreenRegion::from_str("0,0,64,32").unwrap().mask(),
+            u64::MAX
+        );
+        assert!("60,0,5,1".parse::<ScreenRegion>().is_err());
+        assert!("0,0,0,1".parse::<ScreenRegion>().is_err());
+    }
+
+    #[test]
+    fn test_cheat() {
+        let mut chip = Chip::new(0);
+        // 200: LD I, 0x300; 202: LD V0, [I]; 204: ADD V0, 1; 206: LD [I], V0; 208: JP 0x202
+        chip.load_rom(
+            0x200,
+            &[0xA3, 0x00, 0xF0, 0x65, 0x70, 0x01, 0xF0, 0x55, 0x12, 0x02],
+        )
+        .unwrap();
+        let mut debugger = Debugger::new(10);
+        debugger.add_cheat(&mut chip, 0x300, 7).unwrap();
+        assert_eq!(debugger.run(&mut chip, 20), Stop::Limit);
+        assert_eq!(chip.memory()[0x300], 7);
+        assert_eq!(chip.registers()[0], 8);
+
+        assert!(debugger.remove_cheat(0x300));
+        assert!(!debugger.remove_cheat(0x300));
+        assert!(debugger.add_cheat(&mut chip, 0x1000, 0).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::{Chip, DrawEvent, Exception, Instruction};
+
+/// 一条指令造成的一项状态变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// 寄存器 Vx 从 old 变为 new
+    Register {
+        index: u8,
+        old: u8,
+        new: u8,
+    },
+    /// 索引寄存器 I
+    Index {
+        old: u16,
+        new: u16,
+    },
+    /// 压入栈的返回地址
+    StackPush(u16),
+    /// 从栈弹出的返回地址
+    StackPop(u16),
+    DelayTimer {
+        old: u8,
+        new: u8,
+    },
+    SoundTimer {
+        old: u8,
+        new: u8,
+    },
+    /// 内存中的一个字节
+    Memory {
+        addr: u16,
+        old: u8,
+        new: u8,
+    },
+    /// 绘图
+    Draw(DrawEvent),
+}
+
+/// 执行一条指令的记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepEvent {
+    /// 指令地址
+    pub pc: u16,
+    pub opcode: u16,
+    pub instruction: Instruction,
+    /// 执行后的 PC
+    pub next_pc: u16,
+    pub changes: Vec<Change>,
+}
+
+impl Chip {
+    /// 执行一条指令，并返回该指令造成的状态变化
+    pub fn step_with_changes(&mut self) -> Result<StepEvent, Exception> {
+        let pc = self.pc;
+        let v = self.v;
+        let i = self.i;
+        let stack = self.stack().to_vec();
+        let (dt, st) = (self.dt, self.st);
+        let mem = self.mem;
+
+        self.step()?;
+
+        let mut changes = Vec::new();
+        for (index, (old, new)) in v.iter().zip(self.v.iter()).enumerate() {
+            if old != new {
+                changes.push(Change::Register {
+                    index: index as u8,
+                    old: *old,
+                    new: *new,
+                });
+            }
+        }
+        if i != self.i {
+            changes.push(Change::Index {
+                old: i,
+                new: self.i,
+            });
+        }
+        let depth = self.stack().len();
+        if depth > stack.len() {
+            changes.push(Change::StackPush(self.stack()[depth - 1]));
+        } else if depth < stack.len() {
+            changes.push(Change::StackPop(stack[stack.len() - 1]));
+        }
+        if dt != self.dt {
+            changes.push(Change::DelayTimer {
+                old: dt,
+                new: self.dt,
+            });
+        }
+        if st != self.st {
+            changes.push(Change::SoundTimer {
+                old: st,
+                new: self.st,
+            });
+        }
+        for (addr, (old, new)) in mem.iter().zip(self.mem.iter()).enumerate() {
+            if old != new {
+                changes.push(Change::Memory {
+                    addr: addr as u16,
+                    old: *old,
+                    new: *new,
+                });
+            }
+        }
+        if let Some(draw) = self.last_draw() {
+            changes.push(Change::Draw(draw));
+        }
+
+        Ok(StepEvent {
+            pc,
+            opcode: self.op,
+            instruction: Instruction::decode(self.op),
+            next_pc: self.pc,
+            changes,
+        })
+    }
+
+    /// 逐条执行指令并依次产生执行记录，出错后产生该错误然后结束
+    pub fn steps(&mut self) -> Steps<'_> {
+        Steps {
+            chip: self,
+            done: false,
+        }
+    }
+}
+
+/// `Chip::steps` 返回的迭代器
+pub struct Steps<'a> {
+    chip: &'a mut Chip,
+    done: bool,
+}
+
+impl Iterator for Steps<'_> {
+    type Item = Result<StepEvent, Exception>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.chip.step_with_changes();
+        self.done = result.is_err();
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Addr;
+
+    #[test]
+    fn test_step_changes() {
+        let mut chip = Chip::new(0);
+        // LD V0, 0x7B; LD I, 0x300; LD B, V0; CALL 0x20A; (空); RET
+        chip.load_rom(
+            0x200,
+            &[
+                0x60, 0x7B, 0xA3, 0x00, 0xF0, 0x33, 0x22, 0x0A, 0x00, 0x00, 0x00, 0xEE,
+            ],
+        )
+        .unwrap();
+        let events: Vec<_> = chip.steps().take(5).map(Result::unwrap).collect();
+
+        assert_eq!(
+            events[0].changes,
+            [Change::Register {
+                index: 0,
+                old: 0,
+                new: 0x7B
+            }]
+        );
+        assert_eq!(
+            events[1].instruction,
+            Instruction::LdI(Addr::from_bits(0x300))
+        );
+        assert_eq!(
+            events[2].changes,
+            [
+                Change::Memory {
+                    addr: 0x300,
+                    old: 0,
+                    new: 1
+                },
+                Change::Memory {
+                    addr: 0x301,
+                    old: 0,
+                    new: 2
+                },
+                Change::Memory {
+                    addr: 0x302,
+                    old: 0,
+                    new: 3
+                },
+            ]
+        );
+        assert_eq!(events[3].changes, [Change::StackPush(0x208)]);
+        assert_eq!(events[3].next_pc, 0x20A);
+        assert_eq!(events[4].changes, [Change::StackPop(0x208)]);
+    }
+}
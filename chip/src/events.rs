@@ -0,0 +1,177 @@
+use crate::Chip;
+
+/// 定时器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timer {
+    Delay,
+    Sound,
+}
+
+/// 便于可视化讲解的高层事件，比逐条指令的记录更容易对应到 CHIP-8 的概念
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// 2NNN 把返回地址压栈，depth 为压栈后的深度
+    Push { ret: u16, target: u16, depth: usize },
+    /// 00EE 从栈中弹出返回地址，depth 为出栈后的深度
+    Pop { ret: u16, depth: usize },
+    /// FX15 或 FX18 装载定时器
+    TimerLoad { timer: Timer, value: u8 },
+    /// 定时器递减到 0
+    TimerExpire { timer: Timer },
+    /// FX0A 开始等待按键
+    KeyWaitBegin { addr: u16, reg: u8 },
+    /// FX0A 结束等待，value 为之后 VX 的值
+    KeyWaitEnd { addr: u16, reg: u8, value: u8 },
+    /// DXYN 绘制精灵，collision 为 VF 的结果
+    Draw {
+        x: u8,
+        y: u8,
+        height: u8,
+        addr: u16,
+        collision: bool,
+    },
+    /// 00E0 清屏
+    Clear,
+}
+
+/// 观察每条指令和每次定时器递减，产生高层事件
+#[derive(Debug, Clone, Default)]
+pub struct EventTracker {
+    waiting: Option<u16>, // 正在等待按键的 FX0A 地址
+    timers: (u8, u8),     // 上一次观察时的定时器，用于发现递减到 0
+}
+
+impl EventTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在每条指令成功执行后调用，返回这条指令产生的事件
+    pub fn step(&mut self, chip: &Chip) -> Vec<Event> {
+        let mut events = Vec::new();
+        let op = chip.opcode();
+        let addr = chip.opcode_addr();
+        let x = (op >> 8 & 0xF) as u8;
+        let depth = chip.stack().len();
+        match op & 0xF0FF {
+            _ if op & 0xF000 == 0x2000 => events.push(Event::Push {
+                ret: addr + 2,
+                target: chip.pc(),
+                depth,
+            }),
+            _ if op & 0xF000 == 0xD000 => {
+                if let Some(draw) = chip.last_draw() {
+                    events.push(Event::Draw {
+                        x: draw.x,
+                        y: draw.y,
+                        height: draw.height,
+                        addr: chip.i(),
+                        collision: draw.collision,
+                    });
+                }
+            }
+            0x00E0 => events.push(Event::Clear),
+            0x00EE => events.push(Event::Pop {
+                ret: chip.pc(),
+                depth,
+            }),
+            0xF015 => events.push(Event::TimerLoad {
+                timer: Timer::Delay,
+                value: chip.delay_timer(),
+            }),
+            0xF018 => events.push(Event::TimerLoad {
+                timer: Timer::Sound,
+                value: chip.sound_timer(),
+            }),
+            0xF00A => {
+                // 等待时 PC 停在 FX0A 上，反复执行同一条指令
+                if self.waiting != Some(addr) {
+                    events.push(Event::KeyWaitBegin { addr, reg: x });
+                }
+                if chip.pc() == addr {
+                    self.waiting = Some(addr);
+                } else {
+                    self.waiting = None;
+                    events.push(Event::KeyWaitEnd {
+                        addr,
+                        reg: x,
+                        value: chip.registers()[x as usize],
+                    });
+                }
+            }
+            _ => (),
+        }
+        self.timers = (chip.delay_timer(), chip.sound_timer());
+        events
+    }
+
+    /// 在定时器递减后调用，返回递减到 0 的定时器
+    pub fn tick(&mut self, chip: &Chip) -> Vec<Event> {
+        let mut events = Vec::new();
+        let (dt, st) = self.timers;
+        if dt > 0 && chip.delay_timer() == 0 {
+            events.push(Event::TimerExpire {
+                timer: Timer::Delay,
+            });
+        }
+        if st > 0 && chip.sound_timer() == 0 {
+            events.push(Event::TimerExpire {
+                timer: Timer::Sound,
+            });
+        }
+        self.timers = (chip.delay_timer(), chip.sound_timer());
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let mut chip = Chip::new(0);
+        // 200: CALL 206; 202: LD DT, V0; 204: JP 204; 206: LD V0, 1; 208: CLS; 20A: RET
+        chip.load_rom(
+            0x200,
+            &[
+                0x22, 0x06, 0xF0, 0x15, 0x12, 0x04, 0x60, 0x01, 0x00, 0xE0, 0x00, 0xEE,
+            ],
+        )
+        .unwrap();
+        let mut tracker = EventTracker::new();
+        let mut events = Vec::new();
+        for _ in 0..6 {
+            chip.step().unwrap();
+            events.extend(tracker.step(&chip));
+        }
+        assert_eq!(
+            events,
+            [
+                Event::Push {
+                    ret: 0x202,
+                    target: 0x206,
+                    depth: 1
+                },
+                Event::Clear,
+                Event::Pop {
+                    ret: 0x202,
+                    depth: 0
+                },
+                Event::TimerLoad {
+                    timer: Timer::Delay,
+                    value: 1
+                },
+            ]
+        );
+        chip.tick_timers();
+        assert_eq!(
+            tracker.tick(&chip),
+            [Event::TimerExpire {
+                timer: Timer::Delay
+            }]
+        );
+        chip.tick_timers();
+        assert!(tracker.tick(&chip).is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+use crate::DISP_WIDTH;
+
+/// 把按行打包的画面 (见 `Chip::framebuffer_rows`) 展开为 32 位像素，每个 CHIP-8 像素放大为
+/// scale x scale 个像素，点亮的像素为 fg，其余为 bg。像素格式由调用者决定，例如纹理是 RGBA
+/// 字节时用 `u32::from_ne_bytes([r, g, b, a])`。out 每行 stride 个像素，画面写在开头
+pub fn expand(rows: &[u64], scale: usize, fg: u32, bg: u32, out: &mut [u32], stride: usize) {
+    let width = DISP_WIDTH * scale;
+    assert!(scale > 0 && stride >= width, "invalid scale or stride");
+    for (y, &row) in rows.iter().enumerate() {
+        let start = y * scale * stride;
+        expand_row(row, scale, fg, bg, &mut out[start..start + width]);
+        // 放大后的其余各行与第一行相同，直接复制
+        for i in 1..scale {
+            out.copy_within(start..start + width, start + i * stride);
+        }
+    }
+}
+
+fn expand_row(row: u64, scale: usize, fg: u32, bg: u32, line: &mut [u32]) {
+    if scale == 1 {
+        expand_line(row, fg, bg, line);
+    } else {
+        for (x, pixels) in line.chunks_exact_mut(scale).enumerate() {
+            pixels.fill(if row << x >> 63 != 0 { fg } else { bg });
+        }
+    }
+}
+
+/// 不放大时逐个像素用掩码选择颜色，不用分支
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+fn expand_line(row: u64, fg: u32, bg: u32, line: &mut [u32]) {
+    for (x, pixel) in line.iter_mut().enumerate() {
+        let mask = ((row << x >> 63) as u32).wrapping_neg();
+        *pixel = fg & mask | bg & !mask;
+    }
+}
+
+/// 不放大时每次处理 4 个像素。SSE2 是 x86_64 的基本指令集，不需要在运行时检测
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+fn expand_line(row: u64, fg: u32, bg: u32, line: &mut [u32]) {
+    use std::arch::x86_64::*;
+
+    // SAFETY: x86_64 总是支持 SSE2；每次写入的都是正好 4 个 u32 的切片，storeu 不要求对齐
+    unsafe {
+        let fg4 = _mm_set1_epi32(fg as i32);
+        let bg4 = _mm_set1_epi32(bg as i32);
+        // 把 4 位广播到各通道，与各通道对应的位比较得到掩码，再选择颜色
+        let bits = _mm_set_epi32(1, 2, 4, 8);
+        for (i, pixels) in line.chunks_exact_mut(4).enumerate() {
+            let nibble = (row >> (60 - 4 * i)) as i32 & 0xF;
+            let mask = _mm_cmpeq_epi32(_mm_and_si128(_mm_set1_epi32(nibble), bits), bits);
+            let color = _mm_or_si128(_mm_and_si128(mask, fg4), _mm_andnot_si128(mask, bg4));
+            _mm_storeu_si128(pixels.as_mut_ptr().cast(), color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chip, DISP_HEIGHT, ENTRY_ADDR};
+
+    #[test]
+    fn test_expand() {
+        // 在回绕的位置画几个字符，与逐像素的帧缓冲比较
+        let rom = [0x60, 0x3E, 0x61, 0x1E, 0xD0, 0x15, 0xA0, 0x0A, 0xD1, 0x05];
+        let mut chip = Chip::new(0);
+        chip.load_rom(ENTRY_ADDR, &rom).unwrap();
+        for _ in 0..5 {
+            chip.step().unwrap();
+        }
+        let pixels = chip.framebuffer();
+        for scale in [1, 3, 8] {
+            let stride = DISP_WIDTH * scale + 5;
+            let mut out = vec![0; stride * DISP_HEIGHT * scale];
+            expand(chip.framebuffer_rows(), scale, 1, 2, &mut out, stride);
+            for (y, line) in out.chunks(stride).enumerate() {
+                for (x, &color) in line[..DISP_WIDTH * scale].iter().enumerate() {
+                    let on = pixels[y / scale * DISP_WIDTH + x / scale];
+                    assert_eq!(
+                        color,
+                        if on { 1 } else { 2 },
+                        "scale {} ({}, {})",
+                        scale,
+                        x,
+                        y
+                    );
+                }
+                // 每行末尾多出的像素不变
+                assert!(line[DISP_WIDTH * scale..].iter().all(|&c| c == 0));
+            }
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use crate::Chip;
+use std::fmt;
+use std::str::FromStr;
+
+/// 表达式中可以读取的虚拟机状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Var {
+    V(u8),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Xor,
+    Or,
+    LogicAnd,
+    LogicOr,
+}
+
+impl BinOp {
+    /// 运算符和优先级，数字越大结合越紧，与 C 相同。两个字符的运算符在前，优先匹配
+    const TABLE: [(&'static str, BinOp, u8); 18] = [
+        ("||", BinOp::LogicOr, 1),
+        ("&&", BinOp::LogicAnd, 2),
+        ("==", BinOp::Eq, 6),
+        ("!=", BinOp::Ne, 6),
+        ("<=", BinOp::Le, 7),
+        (">=", BinOp::Ge, 7),
+        ("<<", BinOp::Shl, 8),
+        (">>", BinOp::Shr, 8),
+        ("|", BinOp::Or, 3),
+        ("^", BinOp::Xor, 4),
+        ("&", BinOp::And, 5),
+        ("<", BinOp::Lt, 7),
+        (">", BinOp::Gt, 7),
+        ("+", BinOp::Add, 9),
+        ("-", BinOp::Sub, 9),
+        ("*", BinOp::Mul, 10),
+        ("/", BinOp::Div, 10),
+        ("%", BinOp::Rem, 10),
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Node {
+    Num(i64),
+    Var(Var),
+    /// mem[addr]，读取一个字节
+    Mem(Box<Node>),
+    Neg(Box<Node>),
+    Not(Box<Node>),
+    BitNot(Box<Node>),
+    Binary(BinOp, Box<Node>, Box<Node>),
+}
+
+/// 监视表达式，用于监视窗口和条件断点，例如 `v3 + i`、`mem[i+1]`、`dt != 0`
+///
+/// 可以使用 v0 ~ vf、i、pc、sp、dt、st 和 mem[...]，数字默认为十进制，0x 开头为十六进制。
+/// 运算符与 C 相同，比较和逻辑运算的结果为 0 或 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr {
+    text: String,
+    root: Node,
+}
+
+impl Expr {
+    /// 计算表达式的值，读取超出内存或除以 0 时返回错误
+    pub fn eval(&self, chip: &Chip) -> Result<i64, String> {
+        eval(&self.root, chip)
+    }
+
+    /// 作为条件时是否成立，即值不为 0
+    pub fn test(&self, chip: &Chip) -> Result<bool, String> {
+        self.eval(chip).map(|value| value != 0)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { text: s, pos: 0 };
+        let root = parser.expr(0)?;
+        parser.skip_space();
+        if parser.pos < s.len() {
+            return Err(format!("unexpected {:?} in expression", &s[parser.pos..]));
+        }
+        Ok(Self {
+            text: s.trim().to_string(),
+            root,
+        })
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn eval(node: &Node, chip: &Chip) -> Result<i64, String> {
+    let value = match node {
+        Node::Num(n) => *n,
+        Node::Var(var) => match var {
+            Var::V(x) => chip.registers()[*x as usize] as i64,
+            Var::I => chip.i() as i64,
+            Var::Pc => chip.pc() as i64,
+            Var::Sp => chip.stack().len() as i64,
+            Var::Dt => chip.delay_timer() as i64,
+            Var::St => chip.sound_timer() as i64,
+        },
+        Node::Mem(addr) => {
+            let addr = eval(addr, chip)?;
+            let byte = usize::try_from(addr)
+                .ok()
+                .and_then(|addr| chip.memory().get(addr));
+            *byte.ok_or(format!("address {:#X} is outside memory", addr))? as i64
+        }
+        Node::Neg(a) => eval(a, chip)?.wrapping_neg(),
+        Node::Not(a) => (eval(a, chip)? == 0) as i64,
+        Node::BitNot(a) => !eval(a, chip)?,
+        // 逻辑运算短路求值
+        Node::Binary(BinOp::LogicAnd, a, b) => (eval(a, chip)? != 0 && eval(b, chip)? != 0) as i64,
+        Node::Binary(BinOp::LogicOr, a, b) => (eval(a, chip)? != 0 || eval(b, chip)? != 0) as i64,
+        Node::Binary(op, a, b) => {
+            let (a, b) = (eval(a, chip)?, eval(b, chip)?);
+            match op {
+                BinOp::Mul => a.wrapping_mul(b),
+                BinOp::Div => a.checked_div(b).ok_or("division by zero")?,
+                BinOp::Rem => a.checked_rem(b).ok_or("division by zero")?,
+                BinOp::Add => a.wrapping_add(b),
+                BinOp::Sub => a.wrapping_sub(b),
+                BinOp::Shl => a.wrapping_shl(b as u32),
+                BinOp::Shr => a.wrapping_shr(b as u32),
+                BinOp::Lt => (a < b) as i64,
+                BinOp::Le => (a <= b) as i64,
+                BinOp::Gt => (a > b) as i64,
+                BinOp::Ge => (a >= b) as i64,
+                BinOp::Eq => (a == b) as i64,
+                BinOp::Ne => (a != b) as i64,
+                BinOp::And => a & b,
+                BinOp::Xor => a ^ b,
+                BinOp::Or => a | b,
+                BinOp::LogicAnd | BinOp::LogicOr => unreachable!(),
+            }
+        }
+    };
+    Ok(value)
+}
+
+/// 按优先级递归下降的解析器
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_space(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// 跳过指定的符号，成功时返回 true
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_space();
+        let found = self.rest().starts_with(token);
+        if found {
+            self.pos += token.len();
+        }
+        found
+    }
+
+    /// 解析优先级不低于 min 的二元运算
+    fn expr(&mut self, min: u8) -> Result<Node, String> {
+        let mut left = self.unary()?;
+        loop {
+            self.skip_space();
+            let rest = self.rest();
+            let Some((token, op, prec)) = BinOp::TABLE
+                .iter()
+                .find(|(token, _, _)| rest.starts_with(token))
+                .filter(|(_, _, prec)| *prec >= min)
+            else {
+                return Ok(left);
+            };
+            self.pos += token.len();
+            let right = self.expr(prec + 1)?;
+            left = Node::Binary(*op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat("-") {
+            return Ok(Node::Neg(Box::new(self.unary()?)));
+        }
+        // "!=" 是二元运算符，不会出现在操作数的开头
+        if self.eat("!") {
+            return Ok(Node::Not(Box::new(self.unary()?)));
+        }
+        if self.eat("~") {
+            return Ok(Node::BitNot(Box::new(self.unary()?)));
+        }
+        if self.eat("(") {
+            let node = self.expr(0)?;
+            if !self.eat(")") {
+                return Err("missing ')' in expression".to_string());
+            }
+            return Ok(node);
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        self.skip_space();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err(match rest.chars().next() {
+                Some(c) => format!("unexpected {:?} in expression", c),
+                None => "unexpected end of expression".to_string(),
+            });
+        }
+        let word = rest[..len].to_ascii_lowercase();
+        self.pos += len;
+
+        if word.starts_with(|c: char| c.is_ascii_digit()) {
+            let number = match word.strip_prefix("0x") {
+                Some(hex) => i64::from_str_radix(hex, 16),
+                None => word.parse(),
+            };
+            return number
+                .map(Node::Num)
+                .map_err(|_| format!("invalid number {:?}", &rest[..len]));
+        }
+        let var = match word.as_str() {
+            "i" => Var::I,
+            "pc" => Var::Pc,
+            "sp" => Var::Sp,
+            "dt" => Var::Dt,
+            "st" => Var::St,
+            "mem" => {
+                if !self.eat("[") {
+                    return Err("expected '[' after mem".to_string());
+                }
+                let addr = self.expr(0)?;
+                if !self.eat("]") {
+                    return Err("missing ']' in expression".to_string());
+                }
+                return Ok(Node::Mem(Box::new(addr)));
+            }
+            _ => {
+                let x = word
+                    .strip_prefix('v')
+                    .filter(|x| x.len() == 1)
+                    .and_then(|x| u8::from_str_radix(x, 16).ok())
+                    .ok_or(format!("unknown name {:?} in expression", &rest[..len]))?;
+                Var::V(x)
+            }
+        };
+        Ok(Node::Var(var))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Addr, Reg};
+
+    #[test]
+    fn test_expr() {
+        let mut chip = Chip::new(0);
+        chip.set_register(Reg::from_nibble(3), 2);
+        chip.set_i(Addr::from_bits(0x300));
+        chip.load_region(0x301, &[0x42]).unwrap();
+        let eval = |s: &str| s.parse::<Expr>().unwrap().eval(&chip);
+
+        assert_eq!(eval("v3 + i"), Ok(0x302));
+        assert_eq!(eval("mem[i+1]"), Ok(0x42));
+        assert_eq!(eval("dt != 0"), Ok(0));
+        assert_eq!(eval("1 + 2 * 3 == 7 && !(v3 < 2)"), Ok(1));
+        assert_eq!(eval("-V3 + 0x10 >> 1"), Ok(7));
+        assert_eq!(eval("0 && 1 || 2"), Ok(1));
+        assert_eq!(eval("1 << 2 < 5"), Ok(1));
+        assert!(eval("1 / (v3 - 2)").is_err());
+        assert!(eval("mem[0x1000]").is_err());
+        assert!("v3 +".parse::<Expr>().is_err());
+        assert!("vx".parse::<Expr>().is_err());
+        assert!("(1".parse::<Expr>().is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::Chip;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// 创建单生产者单消费者的无锁按键队列，最多缓存 capacity 个事件。
+/// UI 线程用 KeySender 发送按键，模拟线程每帧用 KeyReceiver 取出并更新虚拟机键盘，
+/// 两边不用共享 `&mut Chip`
+pub fn channel(capacity: usize) -> (KeySender, KeyReceiver) {
+    assert!(capacity > 0, "capacity must be positive");
+    let ring = Arc::new(Ring {
+        slots: (0..capacity).map(|_| AtomicU8::new(0)).collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+    (KeySender { ring: ring.clone() }, KeyReceiver { ring })
+}
+
+/// 环形缓冲区，head 和 tail 只增不减，对容量取模得到位置
+struct Ring {
+    slots: Box<[AtomicU8]>, // 每个事件为 按键 | 按下 << 4
+    head: AtomicUsize,      // 下一个读取的位置，只由消费者写入
+    tail: AtomicUsize,      // 下一个写入的位置，只由生产者写入
+}
+
+/// 按键队列的发送端，只能有一个，可以移到其他线程
+pub struct KeySender {
+    ring: Arc<Ring>,
+}
+
+impl KeySender {
+    /// 发送按键事件，队列已满时返回 Err 并交回事件
+    pub fn send(&mut self, key: u8, pressed: bool) -> Result<(), (u8, bool)> {
+        let ring = &self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        if tail - ring.head.load(Ordering::Acquire) == ring.slots.len() {
+            return Err((key, pressed));
+        }
+        let event = key & 0xF | (pressed as u8) << 4;
+        ring.slots[tail % ring.slots.len()].store(event, Ordering::Relaxed);
+        ring.tail.store(tail + 1, Ordering::Release);
+        Ok(())
+    }
+
+    /// 接收端是否已经被丢弃
+    pub fn is_disconnected(&self) -> bool {
+        Arc::strong_count(&self.ring) == 1
+    }
+}
+
+/// 按键队列的接收端，只能有一个，可以移到其他线程
+pub struct KeyReceiver {
+    ring: Arc<Ring>,
+}
+
+impl KeyReceiver {
+    /// 取出一个按键事件，队列为空时返回 None
+    pub fn try_recv(&mut self) -> Option<(u8, bool)> {
+        let ring = &self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        if head == ring.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        let event = ring.slots[head % ring.slots.len()].load(Ordering::Relaxed);
+        ring.head.store(head + 1, Ordering::Release);
+        Some((event & 0xF, event & 0x10 != 0))
+    }
+
+    /// 取出所有按键事件
+    pub fn try_iter(&mut self) -> impl Iterator<Item = (u8, bool)> + '_ {
+        std::iter::from_fn(|| self.try_recv())
+    }
+
+    /// 把所有按键事件应用到虚拟机键盘，返回事件数
+    pub fn apply(&mut self, chip: &mut Chip) -> usize {
+        let mut count = 0;
+        while let Some((key, pressed)) = self.try_recv() {
+            chip.set_keypad(key, pressed);
+            count += 1;
+        }
+        count
+    }
+
+    /// 发送端是否已经被丢弃，丢弃后队列中剩下的事件仍然可以取出
+    pub fn is_disconnected(&self) -> bool {
+        Arc::strong_count(&self.ring) == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_channel() {
+        let (mut tx, mut rx) = channel(2);
+        tx.send(1, true).unwrap();
+        tx.send(2, false).unwrap();
+        assert_eq!(tx.send(3, true), Err((3, true)));
+        let mut chip = Chip::new(0);
+        assert_eq!(rx.apply(&mut chip), 2);
+        assert!(chip.keypad()[1]);
+
+        // 在另一个线程中发送，顺序不变，不丢失事件
+        let sender = thread::spawn(move || {
+            for i in 0..10_000u32 {
+                let event = ((i % 16) as u8, i.is_multiple_of(3));
+                while tx.send(event.0, event.1).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+        let mut received = 0u32;
+        while received < 10_000 {
+            match rx.try_recv() {
+                Some(event) => {
+                    assert_eq!(event, ((received % 16) as u8, received.is_multiple_of(3)));
+                    received += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+        sender.join().unwrap();
+        assert!(rx.is_disconnected());
+        assert_eq!(rx.try_recv(), None);
+    }
+}
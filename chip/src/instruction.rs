@@ -0,0 +1,352 @@
+use crate::{Addr, Reg, Variant};
+use core::fmt;
+
+/// 解码后的 CHIP-8 指令，解码规则与解释器保持一致
+///
+/// 寄存器用 `Reg` 表示，地址用 12 位的 `Addr` 表示
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    /// 0000: 空指令
+    Nop,
+    /// 00E0: 清屏
+    Cls,
+    /// 00EE: 子程序返回
+    Ret,
+    /// 1NNN: 跳转到 NNN
+    Jp(Addr),
+    /// 2NNN: 调用 NNN 处的子程序
+    Call(Addr),
+    /// 3XNN: Vx == NN 时跳过下一条指令
+    SeImm(Reg, u8),
+    /// 4XNN: Vx != NN 时跳过下一条指令
+    SneImm(Reg, u8),
+    /// 5XY0: Vx == Vy 时跳过下一条指令
+    SeReg(Reg, Reg),
+    /// 6XNN: Vx = NN
+    LdImm(Reg, u8),
+    /// 7XNN: Vx += NN
+    AddImm(Reg, u8),
+    /// 8XY0: Vx = Vy
+    LdReg(Reg, Reg),
+    /// 8XY1: Vx |= Vy
+    Or(Reg, Reg),
+    /// 8XY2: Vx &= Vy
+    And(Reg, Reg),
+    /// 8XY3: Vx ^= Vy
+    Xor(Reg, Reg),
+    /// 8XY4: Vx += Vy, VF = 进位
+    Add(Reg, Reg),
+    /// 8XY5: Vx -= Vy, VF = 无借位
+    Sub(Reg, Reg),
+    /// 8XY6: Vx >>= 1, VF = 移出的位
+    Shr(Reg, Reg),
+    /// 8XY7: Vx = Vy - Vx, VF = 无借位
+    Subn(Reg, Reg),
+    /// 8XYE: Vx <<= 1, VF = 移出的位
+    Shl(Reg, Reg),
+    /// 9XY0: Vx != Vy 时跳过下一条指令
+    SneReg(Reg, Reg),
+    /// ANNN: I = NNN
+    LdI(Addr),
+    /// BNNN: 跳转到 V0 + NNN
+    JpV0(Addr),
+    /// CXNN: Vx = 随机数 & NN
+    Rnd(Reg, u8),
+    /// DXYN: 在 (Vx, Vy) 处绘制 N 行精灵
+    Drw(Reg, Reg, u8),
+    /// EX9E: Vx 对应的按键按下时跳过下一条指令
+    Skp(Reg),
+    /// EXA1: Vx 对应的按键没有按下时跳过下一条指令
+    Sknp(Reg),
+    /// FX07: Vx = DT
+    LdVxDt(Reg),
+    /// FX0A: 等待按键
+    LdKey(Reg),
+    /// FX15: DT = Vx
+    LdDtVx(Reg),
+    /// FX18: ST = Vx
+    LdStVx(Reg),
+    /// FX1E: I += Vx
+    AddI(Reg),
+    /// FX29: I = Vx 对应字符的字体地址
+    LdFont(Reg),
+    /// FX33: 将 Vx 的 BCD 码存放到 I, I+1, I+2
+    LdBcd(Reg),
+    /// FX55: 将 V0 ~ Vx 存放到 I 开始的内存
+    StoreRegs(Reg),
+    /// FX65: 从 I 开始的内存读取 V0 ~ Vx
+    LoadRegs(Reg),
+    /// CHIP-8E 00ED: 停机
+    Stop,
+    /// CHIP-8E 0151: 等待 DT 减到 0
+    WaitDt,
+    /// CHIP-8E 0188: 跳过下一条指令
+    SkipNext,
+    /// CHIP-8E 5XY1: Vx > Vy 时跳过下一条指令
+    SgtReg(Reg, Reg),
+    /// CHIP-8E 5XY2: 将 Vx ~ Vy 存放到 I 开始的内存，I 增加写入的字节数
+    StoreRange(Reg, Reg),
+    /// CHIP-8E 5XY3: 从 I 开始的内存读取 Vx ~ Vy，I 增加读取的字节数
+    LoadRange(Reg, Reg),
+    /// CHIP-8E BBNN: 从下一条指令向前跳转 NN 字节
+    JrBack(u8),
+    /// CHIP-8E BFNN: 从下一条指令向后跳转 NN 字节
+    JrFwd(u8),
+    /// CHIP-8E FX1B: 跳过 Vx 个字节
+    SkipBytes(Reg),
+    /// CHIP-8E FX4F: DT = Vx，然后等待 DT 减到 0
+    Delay(Reg),
+    /// 无法识别的指令
+    Unknown(u16),
+}
+
+impl Instruction {
+    /// 解码一条指令
+    pub fn decode(opcode: u16) -> Self {
+        let d = (opcode & 0xF000) >> 12;
+        let x = Reg::from_nibble((opcode >> 8) as u8);
+        let y = Reg::from_nibble((opcode >> 4) as u8);
+        let n = (opcode & 0x000F) as u8;
+        let nn = (opcode & 0x00FF) as u8;
+        let nnn = Addr::from_bits(opcode);
+
+        match d {
+            0 => match nn {
+                // 0F00 是调试输出指令 DBG V0，与解释器一致不作为空指令
+                0 if opcode != 0x0F00 => Instruction::Nop,
+                0xE0 => Instruction::Cls,
+                0xEE => Instruction::Ret,
+                _ => Instruction::Unknown(opcode),
+            },
+            1 => Instruction::Jp(nnn),
+            2 => Instruction::Call(nnn),
+            3 => Instruction::SeImm(x, nn),
+            4 => Instruction::SneImm(x, nn),
+            5 => Instruction::SeReg(x, y),
+            6 => Instruction::LdImm(x, nn),
+            7 => Instruction::AddImm(x, nn),
+            8 => match n {
+                0 => Instruction::LdReg(x, y),
+                1 => Instruction::Or(x, y),
+                2 => Instruction::And(x, y),
+                3 => Instruction::Xor(x, y),
+                4 => Instruction::Add(x, y),
+                5 => Instruction::Sub(x, y),
+                6 => Instruction::Shr(x, y),
+                7 => Instruction::Subn(x, y),
+                0xE => Instruction::Shl(x, y),
+                _ => Instruction::Unknown(opcode),
+            },
+            9 => Instruction::SneReg(x, y),
+            0xA => Instruction::LdI(nnn),
+            0xB => Instruction::JpV0(nnn),
+            0xC => Instruction::Rnd(x, nn),
+            0xD => Instruction::Drw(x, y, n),
+            0xE => match nn {
+                0x9E => Instruction::Skp(x),
+                0xA1 => Instruction::Sknp(x),
+                _ => Instruction::Unknown(opcode),
+            },
+            0xF => match nn {
+                0x07 => Instruction::LdVxDt(x),
+                0x0A => Instruction::LdKey(x),
+                0x15 => Instruction::LdDtVx(x),
+                0x18 => Instruction::LdStVx(x),
+                0x1E => Instruction::AddI(x),
+                0x29 => Instruction::LdFont(x),
+                0x33 => Instruction::LdBcd(x),
+                0x55 => Instruction::StoreRegs(x),
+                0x65 => Instruction::LoadRegs(x),
+                _ => Instruction::Unknown(opcode),
+            },
+            _ => Instruction::Unknown(opcode),
+        }
+    }
+
+    /// 按指定的变体解码一条指令，变体的扩展指令优先于基本指令
+    pub fn decode_variant(opcode: u16, variant: Variant) -> Self {
+        let x = Reg::from_nibble((opcode >> 8) as u8);
+        let y = Reg::from_nibble((opcode >> 4) as u8);
+        let nn = (opcode & 0x00FF) as u8;
+        match variant {
+            Variant::Chip8 => Self::decode(opcode),
+            Variant::Chip8E => match opcode & 0xF00F {
+                _ if opcode == 0x00ED => Instruction::Stop,
+                _ if opcode == 0x00F2 => Instruction::Nop,
+                _ if opcode == 0x0151 => Instruction::WaitDt,
+                _ if opcode == 0x0188 => Instruction::SkipNext,
+                0x5001 => Instruction::SgtReg(x, y),
+                0x5002 => Instruction::StoreRange(x, y),
+                0x5003 => Instruction::LoadRange(x, y),
+                _ if opcode & 0xFF00 == 0xBB00 => Instruction::JrBack(nn),
+                _ if opcode & 0xFF00 == 0xBF00 => Instruction::JrFwd(nn),
+                _ if opcode & 0xF0FF == 0xF01B => Instruction::SkipBytes(x),
+                _ if opcode & 0xF0FF == 0xF04F => Instruction::Delay(x),
+                _ => Self::decode(opcode),
+            },
+        }
+    }
+
+    /// 编码为操作码，是 decode 的逆操作
+    pub fn encode(&self) -> u16 {
+        let xy = |d: u16, x: Reg, y: Reg, n: u16| {
+            d << 12 | (x.get() as u16) << 8 | (y.get() as u16) << 4 | n
+        };
+        let xnn = |d: u16, x: Reg, nn: u8| d << 12 | (x.get() as u16) << 8 | nn as u16;
+        let fx = |x: Reg, nn: u16| 0xF000 | (x.get() as u16) << 8 | nn;
+        match *self {
+            Instruction::Nop => 0x0000,
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::Jp(nnn) => 0x1000 | nnn.get(),
+            Instruction::Call(nnn) => 0x2000 | nnn.get(),
+            Instruction::SeImm(x, nn) => xnn(3, x, nn),
+            Instruction::SneImm(x, nn) => xnn(4, x, nn),
+            Instruction::SeReg(x, y) => xy(5, x, y, 0),
+            Instruction::LdImm(x, nn) => xnn(6, x, nn),
+            Instruction::AddImm(x, nn) => xnn(7, x, nn),
+            Instruction::LdReg(x, y) => xy(8, x, y, 0),
+            Instruction::Or(x, y) => xy(8, x, y, 1),
+            Instruction::And(x, y) => xy(8, x, y, 2),
+            Instruction::Xor(x, y) => xy(8, x, y, 3),
+            Instruction::Add(x, y) => xy(8, x, y, 4),
+            Instruction::Sub(x, y) => xy(8, x, y, 5),
+            Instruction::Shr(x, y) => xy(8, x, y, 6),
+            Instruction::Subn(x, y) => xy(8, x, y, 7),
+            Instruction::Shl(x, y) => xy(8, x, y, 0xE),
+            Instruction::SneReg(x, y) => xy(9, x, y, 0),
+            Instruction::LdI(nnn) => 0xA000 | nnn.get(),
+            Instruction::JpV0(nnn) => 0xB000 | nnn.get(),
+            Instruction::Rnd(x, nn) => xnn(0xC, x, nn),
+            Instruction::Drw(x, y, n) => xy(0xD, x, y, n as u16 & 0xF),
+            Instruction::Skp(x) => xnn(0xE, x, 0x9E),
+            Instruction::Sknp(x) => xnn(0xE, x, 0xA1),
+            Instruction::LdVxDt(x) => fx(x, 0x07),
+            Instruction::LdKey(x) => fx(x, 0x0A),
+            Instruction::LdDtVx(x) => fx(x, 0x15),
+            Instruction::LdStVx(x) => fx(x, 0x18),
+            Instruction::AddI(x) => fx(x, 0x1E),
+            Instruction::LdFont(x) => fx(x, 0x29),
+            Instruction::LdBcd(x) => fx(x, 0x33),
+            Instruction::StoreRegs(x) => fx(x, 0x55),
+            Instruction::LoadRegs(x) => fx(x, 0x65),
+            Instruction::Stop => 0x00ED,
+            Instruction::WaitDt => 0x0151,
+            Instruction::SkipNext => 0x0188,
+            Instruction::SgtReg(x, y) => xy(5, x, y, 1),
+            Instruction::StoreRange(x, y) => xy(5, x, y, 2),
+            Instruction::LoadRange(x, y) => xy(5, x, y, 3),
+            Instruction::JrBack(nn) => 0xBB00 | nn as u16,
+            Instruction::JrFwd(nn) => 0xBF00 | nn as u16,
+            Instruction::SkipBytes(x) => fx(x, 0x1B),
+            Instruction::Delay(x) => fx(x, 0x4F),
+            Instruction::Unknown(op) => op,
+        }
+    }
+}
+
+/// 按照 Cowgod 技术手册中的助记符反汇编
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Instruction::Nop => write!(f, "NOP"),
+            Instruction::Cls => write!(f, "CLS"),
+            Instruction::Ret => write!(f, "RET"),
+            Instruction::Jp(nnn) => write!(f, "JP 0x{:03X}", nnn),
+            Instruction::Call(nnn) => write!(f, "CALL 0x{:03X}", nnn),
+            Instruction::SeImm(x, nn) => write!(f, "SE V{:X}, 0x{:02X}", x, nn),
+            Instruction::SneImm(x, nn) => write!(f, "SNE V{:X}, 0x{:02X}", x, nn),
+            Instruction::SeReg(x, y) => write!(f, "SE V{:X}, V{:X}", x, y),
+            Instruction::LdImm(x, nn) => write!(f, "LD V{:X}, 0x{:02X}", x, nn),
+            Instruction::AddImm(x, nn) => write!(f, "ADD V{:X}, 0x{:02X}", x, nn),
+            Instruction::LdReg(x, y) => write!(f, "LD V{:X}, V{:X}", x, y),
+            Instruction::Or(x, y) => write!(f, "OR V{:X}, V{:X}", x, y),
+            Instruction::And(x, y) => write!(f, "AND V{:X}, V{:X}", x, y),
+            Instruction::Xor(x, y) => write!(f, "XOR V{:X}, V{:X}", x, y),
+            Instruction::Add(x, y) => write!(f, "ADD V{:X}, V{:X}", x, y),
+            Instruction::Sub(x, y) => write!(f, "SUB V{:X}, V{:X}", x, y),
+            Instruction::Shr(x, _) => write!(f, "SHR V{:X}", x),
+            Instruction::Subn(x, y) => write!(f, "SUBN V{:X}, V{:X}", x, y),
+            Instruction::Shl(x, _) => write!(f, "SHL V{:X}", x),
+            Instruction::SneReg(x, y) => write!(f, "SNE V{:X}, V{:X}", x, y),
+            Instruction::LdI(nnn) => write!(f, "LD I, 0x{:03X}", nnn),
+            Instruction::JpV0(nnn) => write!(f, "JP V0, 0x{:03X}", nnn),
+            Instruction::Rnd(x, nn) => write!(f, "RND V{:X}, 0x{:02X}", x, nn),
+            Instruction::Drw(x, y, n) => write!(f, "DRW V{:X}, V{:X}, {}", x, y, n),
+            Instruction::Skp(x) => write!(f, "SKP V{:X}", x),
+            Instruction::Sknp(x) => write!(f, "SKNP V{:X}", x),
+            Instruction::LdVxDt(x) => write!(f, "LD V{:X}, DT", x),
+            Instruction::LdKey(x) => write!(f, "LD V{:X}, K", x),
+            Instruction::LdDtVx(x) => write!(f, "LD DT, V{:X}", x),
+            Instruction::LdStVx(x) => write!(f, "LD ST, V{:X}", x),
+            Instruction::AddI(x) => write!(f, "ADD I, V{:X}", x),
+            Instruction::LdFont(x) => write!(f, "LD F, V{:X}", x),
+            Instruction::LdBcd(x) => write!(f, "LD B, V{:X}", x),
+            Instruction::StoreRegs(x) => write!(f, "LD [I], V{:X}", x),
+            Instruction::LoadRegs(x) => write!(f, "LD V{:X}, [I]", x),
+            Instruction::Stop => write!(f, "STOP"),
+            Instruction::WaitDt => write!(f, "WAIT DT"),
+            Instruction::SkipNext => write!(f, "SKIP"),
+            Instruction::SgtReg(x, y) => write!(f, "SGT V{:X}, V{:X}", x, y),
+            Instruction::StoreRange(x, y) => write!(f, "LD [I], V{:X}-V{:X}", x, y),
+            Instruction::LoadRange(x, y) => write!(f, "LD V{:X}-V{:X}, [I]", x, y),
+            Instruction::JrBack(nn) => write!(f, "JR -0x{:02X}", nn),
+            Instruction::JrFwd(nn) => write!(f, "JR +0x{:02X}", nn),
+            Instruction::SkipBytes(x) => write!(f, "SKIP V{:X}", x),
+            Instruction::Delay(x) => write!(f, "DELAY V{:X}", x),
+            Instruction::Unknown(op) => write!(f, "DW 0x{:04X}", op),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        let cases = [
+            (0x00E0, "CLS"),
+            (0x1228, "JP 0x228"),
+            (0x6A02, "LD VA, 0x02"),
+            (0x8AB4, "ADD VA, VB"),
+            (0xD015, "DRW V0, V1, 5"),
+            (0xF265, "LD V2, [I]"),
+            (0x8008, "DW 0x8008"),
+        ];
+        for (op, text) in cases {
+            assert_eq!(Instruction::decode(op).to_string(), text);
+        }
+        for op in 0..=u16::MAX {
+            let ins = Instruction::decode(op);
+            assert_eq!(Instruction::decode(ins.encode()), ins);
+        }
+    }
+
+    #[test]
+    fn test_chip8e() {
+        let cases = [
+            (0x00ED, "STOP"),
+            (0x5121, "SGT V1, V2"),
+            (0x5252, "LD [I], V2-V5"),
+            (0xBB04, "JR -0x04"),
+            (0xB204, "JP V0, 0x204"),
+            (0xF34F, "DELAY V3"),
+            (0x5120, "SE V1, V2"),
+        ];
+        for (op, text) in cases {
+            assert_eq!(
+                Instruction::decode_variant(op, Variant::Chip8E).to_string(),
+                text
+            );
+        }
+        assert_eq!(Instruction::decode(0x00ED), Instruction::Unknown(0x00ED));
+        for op in 0..=u16::MAX {
+            let ins = Instruction::decode_variant(op, Variant::Chip8E);
+            assert_eq!(
+                Instruction::decode_variant(ins.encode(), Variant::Chip8E),
+                ins
+            );
+        }
+    }
+}
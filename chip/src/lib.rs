@@ -1,6 +1,42 @@
+pub mod analysis;
+pub mod asm;
+pub mod calls;
+pub mod debugger;
+pub mod delta;
+pub mod events;
+pub mod expand;
+pub mod expr;
+pub mod input;
+mod instruction;
+pub mod memmap;
+mod operand;
+pub mod program;
+pub mod romtest;
+pub mod search;
+pub mod source_map;
+#[cfg(test)]
+mod spec;
+pub mod sprite;
+mod state;
+pub mod steplog;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod symbols;
+pub mod vip;
+pub mod xo_audio;
+
+pub use instruction::Instruction;
+pub use operand::{Addr, Reg};
+pub use state::CpuState;
+
 use core::fmt;
+use core::ops::Range;
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
 
 /// CHIP-8 虚拟机内存的前 512 字节通常是由解释器自身占用的，最后 256 字节被保留用于显示刷新
 /// 因此这里程序入口地址为 512
@@ -12,11 +48,19 @@ pub const DISP_WIDTH: usize = 64;
 pub const DISP_HEIGHT: usize = 32;
 
 /// CHIP-8 虚拟机有 4KiB 的内存空间
-const MEM_SIZE: usize = 4096;
+pub const MEM_SIZE: usize = 4096;
 /// CHIP-8 虚拟机的栈大小是 16 x 16-bit
-const STACK_SIZE: usize = 16;
+pub const STACK_SIZE: usize = 16;
+/// 一些非标准的解释器允许更深的调用，可以配置的最大栈大小
+pub const MAX_STACK_SIZE: usize = 255;
 /// CHIP-8 虚拟机的有 16 个 8-bit 寄存器
 const REG_NUM: usize = 16;
+/// 保留的最近执行的指令数，见 `Chip::history`
+pub const HISTORY_SIZE: usize = 32;
+/// 调试输出最多缓存的条数，没有及时取走时丢弃之后的输出
+const MAX_DEBUG_OUTPUT: usize = 1024;
+/// 0F01 最多输出的字符数
+const MAX_DEBUG_STRING: usize = 256;
 
 /// 字体 0 ~ F, 共 16 个字符
 const CHARS_SIZE: usize = 5 * 16;
@@ -40,7 +84,86 @@ const CHARS: [u8; CHARS_SIZE] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
-#[derive(Debug)]
+/// 一次绘图 (DXYN 或 00E0) 影响的区域，坐标已经按屏幕大小取模。
+/// 精灵超出屏幕右边或下边时会绕回另一边绘制，区域也随之绕回
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DrawEvent {
+    pub x: u8,
+    pub y: u8,
+    pub width: u8,
+    pub height: u8,
+    /// 是否有像素被擦除 (VF = 1)
+    pub collision: bool,
+}
+
+/// 一条指令写入了执行过的代码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeWrite {
+    /// 写入内存的指令地址
+    pub pc: u16,
+    /// 被改写的第一个代码地址
+    pub addr: u16,
+}
+
+/// 一帧的执行统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    /// 执行的指令数
+    pub instructions: u32,
+    /// DXYN 和 00E0 的次数
+    pub draws: u32,
+    /// 发生碰撞 (VF = 1) 的 DXYN 次数
+    pub collisions: u32,
+    /// 这一帧中栈的最大深度
+    pub stack_peak: usize,
+    /// 读取键盘的指令 (EX9E、EXA1、FX0A) 的执行次数
+    pub keys_sampled: u32,
+    /// 这一帧中是否发出过声音
+    pub sound: bool,
+}
+
+impl FrameStats {
+    /// 统计刚执行完的一条指令，自行单步执行的前端可以用它累积一帧的统计
+    pub fn record_step(&mut self, chip: &Chip) {
+        self.instructions += 1;
+        if let Some(draw) = chip.last_draw() {
+            self.draws += 1;
+            self.collisions += draw.collision as u32;
+        }
+        self.stack_peak = self.stack_peak.max(chip.stack().len());
+        if matches!(chip.opcode() & 0xF0FF, 0xE09E | 0xE0A1 | 0xF00A) {
+            self.keys_sampled += 1;
+        }
+        self.sound |= chip.tone();
+    }
+
+    /// 合并另一段执行的统计
+    pub fn merge(&mut self, other: &FrameStats) {
+        self.instructions += other.instructions;
+        self.draws += other.draws;
+        self.collisions += other.collisions;
+        self.stack_peak = self.stack_peak.max(other.stack_peak);
+        self.keys_sampled += other.keys_sampled;
+        self.sound |= other.sound;
+    }
+}
+
+/// 累计的运行计数，供长时间运行的部署采集，见 `Chip::metrics`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// 执行的指令数，包括宽松模式下跳过的非法指令
+    pub instructions: u64,
+    /// 帧数，即定时器递减的次数
+    pub frames: u64,
+    /// 绘图指令 (DXYN 和 00E0) 的次数
+    pub draws: u64,
+    /// 声音从停止到响起的次数
+    pub sound_activations: u64,
+    /// 宽松模式下跳过的非法指令数
+    pub illegal_skipped: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Exception {
     OutOfMemory(u16),
     StackOverflow(u8),
@@ -50,102 +173,292 @@ pub enum Exception {
     Halt(i32),
 }
 
+impl fmt::Display for Exception {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Exception::OutOfMemory(addr) => write!(f, "out of memory at 0x{:04X}", addr),
+            Exception::StackOverflow(sp) => write!(f, "stack overflow (SP: {})", sp),
+            Exception::StackUnderflow(sp) => write!(f, "stack underflow (SP: {})", sp),
+            Exception::IllegalOpcode(op) => write!(f, "illegal opcode 0x{:04X}", op),
+            Exception::IllegalAddress(addr) => write!(f, "illegal address 0x{:04X}", addr),
+            Exception::Halt(code) => write!(f, "halted with code {}", code),
+        }
+    }
+}
+
+impl std::error::Error for Exception {}
+
+/// 从文件或读取器装载 ROM 时的错误
+#[derive(Debug)]
+pub enum RomError {
+    /// 读取失败
+    Io(io::Error),
+    /// ROM 太大，装不进内存
+    Exception(Exception),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::Io(e) => write!(f, "couldn't read rom: {}", e),
+            RomError::Exception(e) => write!(f, "couldn't load rom: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RomError::Io(e) => Some(e),
+            RomError::Exception(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for RomError {
+    fn from(e: io::Error) -> Self {
+        RomError::Io(e)
+    }
+}
+
+impl From<Exception> for RomError {
+    fn from(e: Exception) -> Self {
+        RomError::Exception(e)
+    }
+}
+
+/// 解释器变体，决定除基本指令之外还支持哪些指令
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Variant {
+    /// 原始的 CHIP-8
+    #[default]
+    Chip8,
+    /// CHIP-8E：增加停机、等待定时器、相对跳转、寄存器范围读写等指令，
+    /// 不支持读写 I/O 端口的 FX03、FXE3、FXE7
+    Chip8E,
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Variant::Chip8 => write!(f, "chip8"),
+            Variant::Chip8E => write!(f, "chip8e"),
+        }
+    }
+}
+
+impl std::str::FromStr for Variant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "chip8" => Ok(Variant::Chip8),
+            "chip8e" => Ok(Variant::Chip8E),
+            _ => Err(format!(
+                "unknown variant {:?} (expected chip8 or chip8e)",
+                s
+            )),
+        }
+    }
+}
+
 pub struct Chip {
     mem: [u8; MEM_SIZE],
     v: [u8; REG_NUM], // 寄存器组
     i: u16,           // 索引寄存器
     pc: u16,          // 程序计数器
-    stack: [u16; STACK_SIZE],
-    sp: u8,                               // 栈指针
-    dt: u8,                               // 延迟定时器
-    st: u8,                               // 声音定时器
-    keypad: [bool; 16],                   // 键盘
-    fb: [bool; DISP_WIDTH * DISP_HEIGHT], // 显示帧缓冲，这里用一个布尔值来表示一个像素，方便后续操作
-    rng: SmallRng,                        // 随机数生成器
+    stack: [u16; MAX_STACK_SIZE],
+    sp: u8,                              // 栈指针
+    stack_limit: u8,                     // 栈大小，超过时报告栈溢出
+    stack_peak: u8,                      // 复位以来栈的最大深度
+    dt: u8,                              // 延迟定时器
+    st: u8,                              // 声音定时器
+    keypad: [bool; 16],                  // 键盘
+    fb: [u64; DISP_HEIGHT],              // 显示帧缓冲，每行打包为一个 u64，最高位是最左边的像素
+    seed: u64,                           // 随机数种子
+    rng: Option<SmallRng>,               // 随机数生成器，第一次使用时才用 seed 初始化
+    op: u16,                             // 最近执行的指令
+    op_addr: u16,                        // 最近执行的指令的地址
+    draw: Option<DrawEvent>,             // 最近执行的指令的绘图区域
+    permissive: bool,                    // 宽松模式，跳过非法指令而不是报错
+    executed: [bool; MEM_SIZE],          // 作为指令执行过的字节
+    code_write: Option<CodeWrite>,       // 最近执行的指令对代码的改写
+    code_writes: u64,                    // 复位以来改写代码的次数
+    code_write_sites: BTreeSet<u16>,     // 改写过代码的指令地址
+    debug_print: bool,                   // 是否执行调试输出指令 0FX0 和 0F01
+    debug_output: Vec<(u16, String)>,    // 尚未取走的调试输出及其指令地址
+    variant: Variant,                    // 解释器变体
+    delaying: bool,                      // CHIP-8E 的 FX4F 已经设置 DT，正在等待
+    metrics: Metrics,                    // 累计计数，复位时保留
+    history: [(u16, u16); HISTORY_SIZE], // 最近执行的指令的地址和操作码，环形缓冲区
+    history_len: usize,                  // 复位以来执行的指令数，超过容量后只用来定位
 }
 
+// 虚拟机可以移到模拟线程中运行，也可以在线程间共享只读访问
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Chip>();
+};
+
 impl fmt::Display for Chip {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(
-            f,
-            "PC: {:04X} SP: {:02X} I: {:04X} KEY: {}",
-            self.pc,
-            self.sp,
-            self.i,
-            self.keypad
-                .iter()
-                .enumerate()
-                .find(|(i, &x)| x)
-                .map_or("None".to_string(), |(i, _)| i.to_string())
-        )?;
-        writeln!(
-            f,
-            "DT: {:02X} ST: {:02X} Stack: {:?}",
-            self.dt,
-            self.st,
-            &self.stack[..self.sp as usize]
-        )?;
-        writeln!(
-            f,
-            "V0: {:02X} V1: {:02X} V2: {:02X} V3: {:02X}",
-            self.v[0], self.v[1], self.v[2], self.v[3]
-        )?;
-        writeln!(
-            f,
-            "V4: {:02X} V5: {:02X} V6: {:02X} V7: {:02X}",
-            self.v[4], self.v[5], self.v[6], self.v[7]
-        )?;
-        writeln!(
-            f,
-            "V8: {:02X} V9: {:02X} Va: {:02X} Vb: {:02X}",
-            self.v[8], self.v[9], self.v[10], self.v[11]
-        )?;
-        write!(
-            f,
-            "Vc: {:02X} Vd: {:02X} Ve: {:02X} Vf: {:02X}",
-            self.v[12], self.v[13], self.v[14], self.v[15]
-        )
+        fmt::Display::fmt(&self.state(), f)
     }
 }
 
 impl Chip {
-    pub fn new(seed: u64) -> Self {
+    /// 创建虚拟机。这是 const fn，可以用来初始化 static，随机数生成器在第一次执行 CXNN 时才初始化
+    pub const fn new(seed: u64) -> Self {
         let mut mem = [0; MEM_SIZE];
-        mem[..CHARS_SIZE].copy_from_slice(&CHARS);
+        let mut i = 0;
+        while i < CHARS_SIZE {
+            mem[i] = CHARS[i];
+            i += 1;
+        }
         Self {
             mem,
             v: [0; REG_NUM],
             i: 0,
             pc: ENTRY_ADDR,
-            stack: [0; STACK_SIZE],
+            stack: [0; MAX_STACK_SIZE],
             sp: 0,
+            stack_limit: STACK_SIZE as u8,
+            stack_peak: 0,
             dt: 0,
             st: 0,
             keypad: [false; 16],
-            fb: [false; DISP_WIDTH * DISP_HEIGHT],
-            rng: SmallRng::seed_from_u64(seed),
+            fb: [0; DISP_HEIGHT],
+            seed,
+            rng: None,
+            op: 0,
+            op_addr: ENTRY_ADDR,
+            draw: None,
+            permissive: false,
+            executed: [false; MEM_SIZE],
+            code_write: None,
+            code_writes: 0,
+            code_write_sites: BTreeSet::new(),
+            debug_print: false,
+            debug_output: Vec::new(),
+            variant: Variant::Chip8,
+            delaying: false,
+            metrics: Metrics {
+                instructions: 0,
+                frames: 0,
+                draws: 0,
+                sound_activations: 0,
+                illegal_skipped: 0,
+            },
+            history: [(0, 0); HISTORY_SIZE],
+            history_len: 0,
         }
     }
 
-    /// 模拟系统时钟滴答，自动取指执行
+    /// 模拟系统时钟滴答，定时器递减并自动取指执行
     pub fn tick(&mut self) -> Result<(), Exception> {
+        self.tick_timers();
+        self.step()
+    }
+
+    /// 延迟定时器和声音定时器递减，正常情况下应以 60Hz 的频率调用
+    pub fn tick_timers(&mut self) {
+        self.metrics.frames += 1;
         if self.dt > 0 {
             self.dt -= 1;
         }
         if self.st > 0 {
             self.st -= 1;
         }
+    }
 
-        if self.pc >= MEM_SIZE as u16 {
+    /// 取指并执行一条指令，不影响定时器
+    pub fn step(&mut self) -> Result<(), Exception> {
+        // 与 set_pc 一致，0xFFF 处放不下一条完整的指令
+        if self.pc as usize + 1 >= MEM_SIZE {
             return Err(Exception::OutOfMemory(self.pc));
         }
         let op = self.fetch();
+        self.op = op;
+        self.op_addr = self.pc;
+        self.draw = None;
+        self.code_write = None;
+        self.executed[self.pc as usize] = true;
+        self.executed[self.pc as usize + 1] = true;
+        self.history[self.history_len % HISTORY_SIZE] = (self.pc, op);
+        self.history_len += 1;
         self.pc += 2;
-        self.execute(op)?;
-
+        let sounding = self.st > 0;
+        match self.execute(op) {
+            Ok(()) => (),
+            Err(Exception::IllegalOpcode(_)) if self.permissive => {
+                self.metrics.illegal_skipped += 1;
+            }
+            Err(e) => return Err(e),
+        }
+        self.metrics.instructions += 1;
+        self.metrics.draws += self.draw.is_some() as u64;
+        self.metrics.sound_activations += (!sounding && self.st > 0) as u64;
         Ok(())
     }
 
+    /// 最近执行的至多 HISTORY_SIZE 条指令的地址和操作码，从旧到新，
+    /// 包括出错的那一条，用于出错后回溯
+    pub fn history(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        let count = self.history_len.min(HISTORY_SIZE);
+        (self.history_len - count..self.history_len).map(|n| self.history[n % HISTORY_SIZE])
+    }
+
+    /// 累计的运行计数。复位虚拟机不会清零，需要时调用 `reset_metrics`
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// 计数清零
+    pub fn reset_metrics(&mut self) {
+        self.metrics = Metrics::default();
+    }
+
+    /// 运行一帧：执行指定数量的指令，然后定时器递减一次
+    pub fn run_frame(&mut self, instructions: u32) -> Result<FrameStats, Exception> {
+        let mut stats = FrameStats {
+            stack_peak: self.sp as usize,
+            sound: self.tone(),
+            ..FrameStats::default()
+        };
+        for _ in 0..instructions {
+            self.step()?;
+            stats.record_step(self);
+        }
+        self.tick_timers();
+        Ok(stats)
+    }
+
+    /// 设置宽松模式，打开后非法指令会被当作空指令跳过
+    pub fn set_permissive(&mut self, permissive: bool) {
+        self.permissive = permissive;
+    }
+
+    /// 启用非标准的调试输出指令，复位后保持不变：
+    /// 0FX0 输出 VX，0F01 输出 I 处以 0 结尾的字符串。
+    /// 没有启用时它们与其他 0NNN 一样是非法指令
+    pub fn set_debug_print(&mut self, enabled: bool) {
+        self.debug_print = enabled;
+    }
+
+    /// 设置解释器变体，复位时保持不变
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// 取走调试输出指令产生的文本，每项为 (指令地址, 文本)
+    pub fn take_debug_output(&mut self) -> Vec<(u16, String)> {
+        std::mem::take(&mut self.debug_output)
+    }
+
     /// 设置虚拟机键盘状态
     pub fn set_keypad(&mut self, key: u8, pressed: bool) {
         if key < 16 {
@@ -159,17 +472,69 @@ impl Chip {
         }
     }
 
+    /// 获取虚拟机键盘状态
+    pub fn keypad(&self) -> &[bool; 16] {
+        &self.keypad
+    }
+
     /// 装载程序
     pub fn load_rom(&mut self, offset: u16, bin: &[u8]) -> Result<(), Exception> {
         if bin.len() > (MEM_SIZE - offset as usize) {
             return Err(Exception::OutOfMemory(bin.len() as u16));
         }
-        self.mem[offset as usize..offset as usize + bin.len()].copy_from_slice(&bin);
+        self.mem[offset as usize..offset as usize + bin.len()].copy_from_slice(bin);
         Ok(())
     }
 
-    /// 获取显示帧缓冲
-    pub fn framebuffer(&self) -> &[bool] {
+    /// 从文件装载程序到 ENTRY_ADDR
+    pub fn load_rom_file(&mut self, path: impl AsRef<Path>) -> Result<(), RomError> {
+        self.load_rom_from(File::open(path)?)
+    }
+
+    /// 读取全部数据并装载到 ENTRY_ADDR，最多读取到内存装满再多一个字节，
+    /// 因此过大的输入不会被全部读入
+    pub fn load_rom_from(&mut self, reader: impl Read) -> Result<(), RomError> {
+        let limit = MEM_SIZE - ENTRY_ADDR as usize;
+        let mut bin = Vec::new();
+        reader.take(limit as u64 + 1).read_to_end(&mut bin)?;
+        self.load_rom(ENTRY_ADDR, &bin)?;
+        Ok(())
+    }
+
+    /// 读取一段内存，范围超出内存时返回 IllegalAddress
+    pub fn dump_region(&self, range: Range<u16>) -> Result<Vec<u8>, Exception> {
+        if range.start > range.end {
+            return Err(Exception::IllegalAddress(range.start));
+        }
+        if range.end as usize > MEM_SIZE {
+            return Err(Exception::IllegalAddress(range.start.max(MEM_SIZE as u16)));
+        }
+        Ok(self.mem[range.start as usize..range.end as usize].to_vec())
+    }
+
+    /// 把数据写入从 addr 开始的内存，超出内存时返回 IllegalAddress 并且不写入任何数据
+    pub fn load_region(&mut self, addr: u16, data: &[u8]) -> Result<(), Exception> {
+        let start = addr as usize;
+        if start + data.len() > MEM_SIZE {
+            return Err(Exception::IllegalAddress(addr.max(MEM_SIZE as u16)));
+        }
+        self.mem[start..start + data.len()].copy_from_slice(data);
+        Ok(())
+    }
+
+    /// 获取显示帧缓冲，每个像素一个布尔值，按行排列
+    pub fn framebuffer(&self) -> [bool; DISP_WIDTH * DISP_HEIGHT] {
+        let mut pixels = [false; DISP_WIDTH * DISP_HEIGHT];
+        for (line, row) in pixels.chunks_exact_mut(DISP_WIDTH).zip(&self.fb) {
+            for (col, pixel) in line.iter_mut().enumerate() {
+                *pixel = row & (1 << (63 - col)) != 0;
+            }
+        }
+        pixels
+    }
+
+    /// 获取按行打包的帧缓冲，每行一个 u64，最高位是最左边的像素
+    pub fn framebuffer_rows(&self) -> &[u64; DISP_HEIGHT] {
         &self.fb
     }
 
@@ -178,19 +543,172 @@ impl Chip {
         self.st != 0
     }
 
-    /// 虚拟机复位
+    /// 获取程序计数器
+    pub fn pc(&self) -> u16 {
+        self.pc
+    }
+
+    /// 获取索引寄存器
+    pub fn i(&self) -> u16 {
+        self.i
+    }
+
+    /// 获取 V0 ~ VF 寄存器组，可以用 `Reg` 或 0 ~ 15 取值
+    pub fn registers(&self) -> &[u8; REG_NUM] {
+        &self.v
+    }
+
+    /// 修改寄存器
+    pub fn set_register(&mut self, reg: Reg, value: u8) {
+        self.v[reg] = value;
+    }
+
+    /// 修改索引寄存器
+    pub fn set_i(&mut self, addr: Addr) {
+        self.i = addr.get();
+    }
+
+    /// 修改程序计数器，地址处放不下一条完整的指令 (0xFFF) 时返回 IllegalAddress
+    pub fn set_pc(&mut self, addr: Addr) -> Result<(), Exception> {
+        if addr.get() as usize + 1 >= MEM_SIZE {
+            return Err(Exception::IllegalAddress(addr.get()));
+        }
+        self.pc = addr.get();
+        Ok(())
+    }
+
+    pub fn set_delay_timer(&mut self, value: u8) {
+        self.dt = value;
+    }
+
+    pub fn set_sound_timer(&mut self, value: u8) {
+        self.st = value;
+    }
+
+    /// 获取延迟定时器
+    pub fn delay_timer(&self) -> u8 {
+        self.dt
+    }
+
+    /// 获取声音定时器
+    pub fn sound_timer(&self) -> u8 {
+        self.st
+    }
+
+    /// 获取栈中已压入的返回地址
+    pub fn stack(&self) -> &[u16] {
+        &self.stack[..self.sp as usize]
+    }
+
+    /// 一次获取寄存器、栈、定时器和键盘的快照
+    pub fn state(&self) -> CpuState {
+        CpuState {
+            pc: self.pc,
+            i: self.i,
+            v: self.v,
+            sp: self.sp,
+            stack: self.stack().to_vec(),
+            dt: self.dt,
+            st: self.st,
+            keypad: self.keypad,
+        }
+    }
+
+    /// 设置栈大小 (默认为 STACK_SIZE)，最大为 MAX_STACK_SIZE，复位后保持不变
+    pub fn set_stack_limit(&mut self, limit: usize) {
+        self.stack_limit = limit.clamp(1, MAX_STACK_SIZE) as u8;
+    }
+
+    /// 获取复位以来栈的最大深度，即最大调用深度
+    pub fn stack_peak(&self) -> usize {
+        self.stack_peak as usize
+    }
+
+    /// 获取虚拟机内存
+    pub fn memory(&self) -> &[u8] {
+        &self.mem
+    }
+
+    /// 读取指定地址处的操作码，超出内存时返回 None
+    pub fn opcode_at(&self, addr: u16) -> Option<u16> {
+        let addr = addr as usize;
+        let bytes = self.mem.get(addr..addr + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// 获取最近执行的指令的绘图区域，该指令不是 DXYN 或 00E0 时返回 None
+    pub fn last_draw(&self) -> Option<DrawEvent> {
+        self.draw
+    }
+
+    /// 获取最近执行的指令对已执行代码的改写，没有改写时返回 None
+    pub fn last_code_write(&self) -> Option<CodeWrite> {
+        self.code_write
+    }
+
+    /// 复位以来改写已执行代码的次数
+    pub fn code_writes(&self) -> u64 {
+        self.code_writes
+    }
+
+    /// 改写过已执行代码的指令地址
+    pub fn code_write_sites(&self) -> impl Iterator<Item = u16> + '_ {
+        self.code_write_sites.iter().copied()
+    }
+
+    /// 获取最近执行的指令
+    pub fn opcode(&self) -> u16 {
+        self.op
+    }
+
+    /// 获取最近执行的指令的地址，跳转和调用后与 PC 不同
+    pub fn opcode_addr(&self) -> u16 {
+        self.op_addr
+    }
+
+    /// 创建虚拟机并把 ROM 装载到 ENTRY_ADDR
+    pub fn with_rom(seed: u64, rom: &[u8]) -> Result<Self, Exception> {
+        let mut chip = Self::new(seed);
+        chip.load_rom(ENTRY_ADDR, rom)?;
+        Ok(chip)
+    }
+
+    /// 复位并重新装载 ROM。reset 会清空包括 ROM 在内的整个内存，重新开始游戏时用这个方法。
+    /// ROM 放不进内存时返回 OutOfMemory，虚拟机保持不变
+    pub fn reset_with_rom(&mut self, seed: u64, rom: &[u8]) -> Result<(), Exception> {
+        if rom.len() > MEM_SIZE - ENTRY_ADDR as usize {
+            return Err(Exception::OutOfMemory(rom.len() as u16));
+        }
+        self.reset(seed);
+        self.load_rom(ENTRY_ADDR, rom)
+    }
+
+    /// 虚拟机复位，清空内存 (包括 ROM)，见 reset_with_rom
     pub fn reset(&mut self, seed: u64) {
         self.pc = ENTRY_ADDR;
         self.sp = 0;
+        self.stack_peak = 0;
         self.i = 0;
         self.dt = 0;
         self.st = 0;
         self.keypad.fill(false);
-        self.fb.fill(false);
+        self.fb.fill(0);
         self.v.fill(0);
         self.mem.fill(0);
+        self.mem[..CHARS_SIZE].copy_from_slice(&CHARS);
         self.stack.fill(0);
-        self.rng = SmallRng::seed_from_u64(seed);
+        self.seed = seed;
+        self.rng = None;
+        self.op = 0;
+        self.op_addr = ENTRY_ADDR;
+        self.draw = None;
+        self.executed.fill(false);
+        self.code_write = None;
+        self.code_writes = 0;
+        self.code_write_sites.clear();
+        self.debug_output.clear();
+        self.delaying = false;
+        self.history_len = 0;
     }
 
     // 取指令
@@ -213,8 +731,22 @@ impl Chip {
 
         // println!("op:{opcode:04X}, d:{d:01X}, x:{x:01X}, y:{y:01X}, n:{n}, nn:{nn}, nnn:{nnn:03X}");
 
+        if self.variant == Variant::Chip8E {
+            if let Some(result) = self.execute_chip8e(opcode) {
+                return result;
+            }
+        }
+
         match d {
             0 => match nn {
+                // 0FX0 和 0F01 只在启用调试输出时执行，否则是非法指令。
+                // 0F00 (输出 V0) 也不作为空指令，使用它的 ROM 不会在没有启用时静默运行
+                _ if x == 0xF && (n == 0 || nn == 0x01) => {
+                    if !self.debug_print {
+                        return Err(Exception::IllegalOpcode(opcode));
+                    }
+                    self.print_debug(nn)?
+                }
                 // NOP
                 0 => (),
                 0xE0 => self.disp_clr(),
@@ -243,9 +775,10 @@ impl Chip {
                     self.load_reg(x, val);
                     self.load_reg(0xFu8, if borrow { 0 } else { 1 });
                 }
+                // 与其他运算一样最后写入 VF，X 为 F 时 VF 是标志
                 6 => {
-                    self.load_reg(0xFu8, vx & 0x01);
                     self.load_reg(x, vx >> 1);
+                    self.load_reg(0xFu8, vx & 0x01);
                 }
                 7 => {
                     let (val, borrow) = vy.overflowing_sub(vx);
@@ -253,8 +786,8 @@ impl Chip {
                     self.load_reg(0xFu8, if borrow { 0 } else { 1 });
                 }
                 0xE => {
-                    self.load_reg(0xFu8, if vx & 0x80 == 0 { 0 } else { 1 });
                     self.load_reg(x, vx << 1);
+                    self.load_reg(0xFu8, if vx & 0x80 == 0 { 0 } else { 1 });
                 }
                 _ => return Err(Exception::IllegalOpcode(opcode)),
             },
@@ -262,29 +795,19 @@ impl Chip {
             0xA => self.load_i(nnn),
             0xB => self.jump(self.v[0] as u16 + nnn)?,
             0xC => {
-                let r = self.rng.gen::<u8>() % nn;
+                let seed = self.seed;
+                let rng = self
+                    .rng
+                    .get_or_insert_with(|| SmallRng::seed_from_u64(seed));
+                let r = rng.gen::<u8>() & nn;
                 self.load_reg(x, r);
             }
-            0xD => self.draw_sprite(x, y, n),
+            0xD => self.draw_sprite(x, y, n)?,
             0xE => match nn {
-                0x9E => {
-                    // 如果 Vx 对应的按键按下，则跳过下一条指令
-                    let keypad = self.keypad.iter().enumerate().find(|(_, &k)| k);
-                    if let Some((i, _)) = keypad {
-                        if vx == i as u8 {
-                            self.pc += 2;
-                        }
-                    }
-                }
-                0xA1 => {
-                    // 如果 Vx 对应的按键没有按下，则跳过下一条指令
-                    let keypad = self.keypad.iter().enumerate().find(|(_, &k)| k);
-                    if let Some((i, _)) = keypad {
-                        if vx != i as u8 {
-                            self.pc += 2;
-                        }
-                    }
-                }
+                // 如果 Vx 对应的按键按下，则跳过下一条指令，只使用 Vx 的低 4 位
+                0x9E => self.skip_if_eq(self.keypad[vx as usize & 0xF] as u8, 1),
+                // 如果 Vx 对应的按键没有按下，则跳过下一条指令
+                0xA1 => self.skip_if_ne(self.keypad[vx as usize & 0xF] as u8, 1),
                 _ => return Err(Exception::IllegalOpcode(opcode)),
             },
             0xF => match nn {
@@ -296,9 +819,10 @@ impl Chip {
                 0x18 => {
                     self.st = vx;
                 }
-                0x1E => self.load_i(self.i + vx as u16),
-                0x29 => self.load_i(5 * vx as u16),
-                0x33 => self.store_reg_bcd(x),
+                0x1E => self.load_i(self.i.wrapping_add(vx as u16)),
+                // 字体只有 0 ~ F，只使用 Vx 的低 4 位
+                0x29 => self.load_i(5 * (vx & 0xF) as u16),
+                0x33 => self.store_reg_bcd(x)?,
                 0x55 => self.store_regs(x)?,
                 0x65 => self.load_regs(x)?,
                 _ => return Err(Exception::IllegalOpcode(opcode)),
@@ -308,8 +832,94 @@ impl Chip {
         Ok(())
     }
 
+    /// 执行 CHIP-8E 的扩展指令，不是扩展指令时返回 None
+    fn execute_chip8e(&mut self, opcode: u16) -> Option<Result<(), Exception>> {
+        let result = match Instruction::decode_variant(opcode, Variant::Chip8E) {
+            Instruction::Stop => Err(Exception::Halt(0)),
+            Instruction::Nop => Ok(()),
+            Instruction::WaitDt => {
+                if self.dt != 0 {
+                    self.pc -= 2;
+                }
+                Ok(())
+            }
+            Instruction::SkipNext => self.jump(self.pc + 2),
+            Instruction::SgtReg(x, y) => {
+                if self.v[x] > self.v[y] {
+                    self.pc += 2;
+                }
+                Ok(())
+            }
+            Instruction::StoreRange(x, y) => self.store_range(x, y),
+            Instruction::LoadRange(x, y) => self.load_range(x, y),
+            Instruction::JrBack(nn) => self.jump(self.pc.wrapping_sub(nn as u16)),
+            Instruction::JrFwd(nn) => self.jump(self.pc + nn as u16),
+            Instruction::SkipBytes(x) => self.jump(self.pc + self.v[x] as u16),
+            Instruction::Delay(x) => {
+                // 第一次执行时设置 DT，然后停在这条指令上直到 DT 减到 0
+                if !self.delaying {
+                    self.dt = self.v[x];
+                    self.delaying = true;
+                }
+                if self.dt == 0 {
+                    self.delaying = false;
+                } else {
+                    self.pc -= 2;
+                }
+                Ok(())
+            }
+            _ => return None,
+        };
+        Some(result)
+    }
+
+    /// Vx 到 Vy 的寄存器编号，x 大于 y 时按相反的顺序
+    fn reg_range(x: Reg, y: Reg) -> Vec<usize> {
+        if x <= y {
+            (x.index()..=y.index()).collect()
+        } else {
+            (y.index()..=x.index()).rev().collect()
+        }
+    }
+
+    fn store_range(&mut self, x: Reg, y: Reg) -> Result<(), Exception> {
+        let regs = Self::reg_range(x, y);
+        let start = self.i as usize;
+        if start + regs.len() > MEM_SIZE {
+            return Err(Exception::IllegalAddress(self.i));
+        }
+        for (offset, r) in regs.iter().enumerate() {
+            self.mem[start + offset] = self.v[*r];
+        }
+        self.check_code_write(start..start + regs.len());
+        self.i += regs.len() as u16;
+
+        Ok(())
+    }
+
+    fn load_range(&mut self, x: Reg, y: Reg) -> Result<(), Exception> {
+        let regs = Self::reg_range(x, y);
+        let start = self.i as usize;
+        if start + regs.len() > MEM_SIZE {
+            return Err(Exception::IllegalAddress(self.i));
+        }
+        for (offset, r) in regs.iter().enumerate() {
+            self.v[*r] = self.mem[start + offset];
+        }
+        self.i += regs.len() as u16;
+
+        Ok(())
+    }
+
     fn disp_clr(&mut self) {
-        self.fb.fill(false);
+        self.fb.fill(0);
+        self.draw = Some(DrawEvent {
+            x: 0,
+            y: 0,
+            width: DISP_WIDTH as u8,
+            height: DISP_HEIGHT as u8,
+            collision: false,
+        });
     }
 
     fn ret(&mut self) -> Result<(), Exception> {
@@ -333,12 +943,13 @@ impl Chip {
     }
 
     fn call(&mut self, addr: u16) -> Result<(), Exception> {
-        if self.sp >= STACK_SIZE as u8 {
+        if self.sp >= self.stack_limit {
             return Err(Exception::StackOverflow(self.sp));
         }
         // 压栈
         self.stack[self.sp as usize] = self.pc;
         self.sp += 1;
+        self.stack_peak = self.stack_peak.max(self.sp);
 
         if addr > 0xFFF {
             return Err(Exception::IllegalAddress(addr));
@@ -369,41 +980,75 @@ impl Chip {
         self.i = val;
     }
 
-    fn draw_sprite(&mut self, x: u8, y: u8, n: u8) {
-        let x = self.v[x as usize] as usize;
-        let y = self.v[y as usize] as usize;
+    fn draw_sprite(&mut self, x: u8, y: u8, n: u8) -> Result<(), Exception> {
+        let x = self.v[x as usize] as usize % DISP_WIDTH;
+        let y = self.v[y as usize] as usize % DISP_HEIGHT;
         let n = n as usize;
+        let Some(sprites) = self.mem.get(self.i as usize..self.i as usize + n) else {
+            return Err(Exception::IllegalAddress(self.i));
+        };
         let mut flipped = false;
-        for i in 0..n {
-            let sprite = self.mem[self.i as usize + i];
-            for j in 0..8 {
-                // 判断是否反转像素颜色
-                if sprite & (0x80 >> j) != 0 {
-                    let idx = (x + j) % DISP_WIDTH + ((y + i) % DISP_HEIGHT) * DISP_WIDTH;
-                    // 如果之前的像素是白色，则反转就是黑色，设置 flip 标志
-                    flipped |= self.fb[idx];
-                    // 反转当前像素
-                    self.fb[idx] ^= true;
-                }
+        for (i, &sprite) in sprites.iter().enumerate() {
+            if sprite == 0 {
+                continue;
             }
+            // 把精灵字节移到第 x 列，屏幕正好 64 列，超出右边的部分循环移位到左边
+            let mask = ((sprite as u64) << 56).rotate_right(x as u32);
+            let row = (y + i) % DISP_HEIGHT;
+            // 与已点亮的像素重叠时会擦除它们，设置 flip 标志
+            flipped |= self.fb[row] & mask != 0;
+            self.fb[row] ^= mask;
         }
         self.v[0xF] = if flipped { 1 } else { 0 };
+        self.draw = Some(DrawEvent {
+            x: x as u8,
+            y: y as u8,
+            width: 8,
+            height: n as u8,
+            collision: flipped,
+        });
+
+        Ok(())
+    }
+
+    fn print_debug(&mut self, nn: u8) -> Result<(), Exception> {
+        let text = if nn == 0x01 {
+            // FX1E 可以让 I 超出内存，此时和其他读内存的指令一样报错
+            let Some(mem) = self.mem.get(self.i as usize..).filter(|m| !m.is_empty()) else {
+                return Err(Exception::IllegalAddress(self.i));
+            };
+            // 不可打印的字符显示为 '.'
+            mem.iter()
+                .take(MAX_DEBUG_STRING)
+                .take_while(|b| **b != 0)
+                .map(|b| match b {
+                    0x20..=0x7E => *b as char,
+                    _ => '.',
+                })
+                .collect()
+        } else {
+            let x = nn >> 4;
+            let vx = self.v[x as usize];
+            format!("V{:X} = 0x{:02X} ({})", x, vx, vx)
+        };
+        if self.debug_output.len() < MAX_DEBUG_OUTPUT {
+            self.debug_output.push((self.op_addr, text));
+        }
+        Ok(())
     }
 
     fn wait_for_key(&mut self, x: u8) {
-        // println!("Wait for key: {}", self.v[x as usize]);
-        let keypad = self
-            .keypad
-            .iter()
-            .enumerate()
-            .find(|(i, &k)| self.v[x as usize] == *i as u8 && k);
-
-        if keypad.is_some() {
-            self.pc += 2;
+        // 没有按键按下时 PC 停在这条指令上，按下后把编号最小的按键写入 Vx
+        match self.keypad.iter().position(|&k| k) {
+            Some(key) => self.load_reg(x, key as u8),
+            None => self.pc -= 2,
         }
     }
 
-    fn store_reg_bcd(&mut self, x: u8) {
+    fn store_reg_bcd(&mut self, x: u8) -> Result<(), Exception> {
+        if self.i as usize + 3 > MEM_SIZE {
+            return Err(Exception::IllegalAddress(self.i));
+        }
         let mut bcd = [0u8; 3];
         let num = self.v[x as usize];
         let (div, num) = (num / 100, num % 100);
@@ -412,25 +1057,43 @@ impl Chip {
         bcd[1] = div;
         bcd[2] = num;
         self.mem[self.i as usize..self.i as usize + 3].copy_from_slice(&bcd);
+        self.check_code_write(self.i as usize..self.i as usize + 3);
+
+        Ok(())
     }
 
     fn store_regs(&mut self, x: u8) -> Result<(), Exception> {
         let mut offset = self.i as usize;
-        for i in 0..x as usize {
+        for i in 0..=x as usize {
             if offset < MEM_SIZE {
                 self.mem[offset] = self.v[i];
                 offset += 1;
             } else {
+                self.check_code_write(self.i as usize..offset);
                 return Err(Exception::IllegalAddress(offset as u16));
             }
         }
+        self.check_code_write(self.i as usize..offset);
 
         Ok(())
     }
 
+    // 检查写入的内存范围是否包含执行过的代码
+    fn check_code_write(&mut self, range: Range<usize>) {
+        if let Some(addr) = range.into_iter().find(|addr| self.executed[*addr]) {
+            let pc = self.op_addr;
+            self.code_write = Some(CodeWrite {
+                pc,
+                addr: addr as u16,
+            });
+            self.code_writes += 1;
+            self.code_write_sites.insert(pc);
+        }
+    }
+
     fn load_regs(&mut self, x: u8) -> Result<(), Exception> {
         let mut offset = self.i as usize;
-        for i in 0..x as usize {
+        for i in 0..=x as usize {
             if offset < MEM_SIZE {
                 self.v[i] = self.mem[offset];
                 offset += 1;
@@ -458,27 +1121,309 @@ mod tests {
         assert_eq!(cpu.mem[offset..offset + 8], [1u8, 2, 3, 4, 5, 6, 7, 8]);
     }
 
+    #[test]
+    fn test_load_rom_from() {
+        let mut cpu = Chip::new(0);
+        cpu.load_rom_from(&[0x12u8, 0x34][..]).unwrap();
+        assert_eq!(cpu.opcode_at(ENTRY_ADDR), Some(0x1234));
+
+        let big = vec![0u8; MEM_SIZE];
+        assert!(matches!(
+            cpu.load_rom_from(&big[..]),
+            Err(RomError::Exception(Exception::OutOfMemory(_)))
+        ));
+        assert!(matches!(
+            cpu.load_rom_file("no/such/rom.ch8"),
+            Err(RomError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_with_rom() {
+        let rom = [0x60, 0x05];
+        let mut cpu = Chip::with_rom(0, &rom).unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.registers()[0], 5);
+        cpu.reset_with_rom(0, &rom).unwrap();
+        assert_eq!(cpu.registers()[0], 0);
+        assert_eq!(cpu.opcode_at(ENTRY_ADDR), Some(0x6005));
+        assert!(Chip::with_rom(0, &[0; MEM_SIZE]).is_err());
+        // 放不进内存的 ROM 不会清空正在运行的游戏
+        cpu.step().unwrap();
+        assert!(cpu.reset_with_rom(0, &[0; MEM_SIZE]).is_err());
+        assert_eq!(cpu.registers()[0], 5);
+        assert_eq!(cpu.opcode_at(ENTRY_ADDR), Some(0x6005));
+    }
+
+    #[test]
+    fn test_const_new() {
+        // 编译期创建的虚拟机与运行时创建的相同，随机数也相同
+        const CHIP: Chip = Chip::new(7);
+        let rom = [0xC0, 0xFF, 0xC1, 0xFF]; // RND V0, 0xFF; RND V1, 0xFF
+        let mut a = CHIP;
+        let mut b = Chip::new(7);
+        for cpu in [&mut a, &mut b] {
+            cpu.load_rom(ENTRY_ADDR, &rom).unwrap();
+            cpu.step().unwrap();
+            cpu.step().unwrap();
+        }
+        assert_eq!(a.registers()[..2], b.registers()[..2]);
+        assert_eq!(a.memory()[..CHARS_SIZE], CHARS);
+    }
+
+    #[test]
+    fn test_region() {
+        let mut cpu = Chip::new(0);
+        cpu.load_region(0xFFE, &[1, 2]).unwrap();
+        assert_eq!(cpu.dump_region(0xFFD..0x1000).unwrap(), [0, 1, 2]);
+        assert_eq!(
+            cpu.load_region(0xFFE, &[1, 2, 3]),
+            Err(Exception::IllegalAddress(0x1000))
+        );
+        assert_eq!(
+            cpu.dump_region(0x1000..0x1001),
+            Err(Exception::IllegalAddress(0x1000))
+        );
+
+        cpu.set_register(Reg::VF, 7);
+        assert_eq!(cpu.registers()[Reg::VF], 7);
+        assert_eq!(cpu.registers()[0xF], 7);
+        assert!(Reg::try_from(0x10).is_err());
+        cpu.set_i(Addr::from_bits(0xFFF));
+        assert_eq!(cpu.i(), 0xFFF);
+        assert!(Addr::try_from(0x1000).is_err());
+        assert!(cpu.set_pc(Addr::from_bits(0xFFF)).is_err());
+        assert_eq!(cpu.pc(), ENTRY_ADDR);
+    }
+
     #[test]
     fn test_reg_op() {
+        let rom = program::Program::new()
+            .ld_v(0, 0x0F) // V0 = 15
+            .ld_vv(1, 0) // V1 = V0 => V1 = 15
+            .add_v(0, 10) // V0 += 10 => V0 = 25
+            .or(0, 1) // V0 |= V1 => V0 = 31
+            .build();
+        let mut cpu = Chip::with_rom(0, &rom).unwrap();
+
+        cpu.tick().unwrap();
+        assert_eq!(cpu.v[0], 15);
+        cpu.tick().unwrap();
+        assert_eq!(cpu.v[1], 15);
+        cpu.tick().unwrap();
+        assert_eq!(cpu.v[0], 25);
+        cpu.tick().unwrap();
+        assert_eq!(cpu.v[0], 31);
+    }
+
+    #[test]
+    fn test_metrics() {
         let mut cpu = Chip::new(0);
         cpu.load_rom(
             ENTRY_ADDR,
             &[
-                0x60, 0x0F, // V0 = 15
-                0x81, 0x00, // V1 = V0 => V1 = 15
-                0x70, 0x0A, // V0 += 10 => V0 = 25
-                0x80, 0x11, // V0 |= V1 => V0 = 31
+                0x60, 0x02, // V0 = 2
+                0xF0, 0x18, // ST = 2，声音响起
+                0xD0, 0x05, // 绘图
+                0x80, 0x08, // 非法指令，宽松模式下跳过
+                0xF0, 0x18, // 声音还在响，不计数
+                0x00, 0xE0, // 清屏
             ],
         )
         .unwrap();
+        cpu.set_permissive(true);
+        cpu.run_frame(6).unwrap();
+        let metrics = Metrics {
+            instructions: 6,
+            frames: 1,
+            draws: 2,
+            sound_activations: 1,
+            illegal_skipped: 1,
+        };
+        assert_eq!(*cpu.metrics(), metrics);
 
+        // 复位后保留，清零后重新计数
+        cpu.reset(0);
+        assert_eq!(*cpu.metrics(), metrics);
+        cpu.reset_metrics();
+        cpu.tick_timers();
+        assert_eq!(cpu.metrics().frames, 1);
+        assert_eq!(cpu.metrics().instructions, 0);
+    }
+
+    #[test]
+    fn test_history() {
+        let mut cpu = Chip::new(0);
+        // 循环 40 次后执行非法指令
+        let rom = [0x60, 0x28, 0x70, 0xFF, 0x30, 0x00, 0x12, 0x02, 0xFF, 0xFF];
+        cpu.load_rom(ENTRY_ADDR, &rom).unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.history().collect::<Vec<_>>(), [(0x200, 0x6028)]);
+        while cpu.step().is_ok() {}
+        let history: Vec<_> = cpu.history().collect();
+        assert_eq!(history.len(), HISTORY_SIZE);
+        assert_eq!(history[HISTORY_SIZE - 1], (0x208, 0xFFFF));
+        assert_eq!(history[HISTORY_SIZE - 2], (0x204, 0x3000));
+        cpu.reset(0);
+        assert_eq!(cpu.history().count(), 0);
+    }
+
+    #[test]
+    fn test_permissive() {
+        let mut cpu = Chip::new(0);
+        cpu.load_rom(
+            ENTRY_ADDR,
+            &[
+                0x80, 0x08, // 非法指令
+                0x60, 0x01, // V0 = 1
+            ],
+        )
+        .unwrap();
+
+        assert!(matches!(cpu.tick(), Err(Exception::IllegalOpcode(0x8008))));
+
+        cpu.reset(0);
+        cpu.load_rom(ENTRY_ADDR, &[0x80, 0x08, 0x60, 0x01]).unwrap();
+        cpu.set_permissive(true);
         cpu.tick().unwrap();
-        assert_eq!(cpu.v[0], 15);
-        cpu.tick().unwrap();
-        assert_eq!(cpu.v[1], 15);
-        cpu.tick().unwrap();
-        assert_eq!(cpu.v[0], 25);
         cpu.tick().unwrap();
-        assert_eq!(cpu.v[0], 31);
+        assert_eq!(cpu.v[0], 1);
+    }
+
+    #[test]
+    fn test_debug_print() {
+        let rom = [
+            0x6A, 0x2A, // VA = 0x2A
+            0x0F, 0xA0, // 输出 VA
+            0xA2, 0x08, // I = 0x208
+            0x0F, 0x01, // 输出 I 处的字符串
+            b'h', b'i', 0x07, 0x00,
+        ];
+        let mut cpu = Chip::new(0);
+        cpu.load_rom(ENTRY_ADDR, &rom).unwrap();
+        cpu.step().unwrap();
+        assert!(matches!(cpu.step(), Err(Exception::IllegalOpcode(0x0FA0))));
+
+        cpu.reset(0);
+        cpu.load_rom(ENTRY_ADDR, &rom).unwrap();
+        cpu.set_debug_print(true);
+        for _ in 0..4 {
+            cpu.step().unwrap();
+        }
+        assert_eq!(
+            cpu.take_debug_output(),
+            [
+                (0x202, "VA = 0x2A (42)".to_string()),
+                (0x206, "hi.".to_string())
+            ]
+        );
+        assert!(cpu.take_debug_output().is_empty());
+    }
+
+    #[test]
+    fn test_code_write() {
+        let mut cpu = Chip::new(0);
+        // LD I, 0x200; LD B, V3 (改写 200-202); LD I, 0x300; LD B, V3
+        cpu.load_rom(
+            ENTRY_ADDR,
+            &[0xA2, 0x00, 0xF3, 0x33, 0xA3, 0x00, 0xF3, 0x33],
+        )
+        .unwrap();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(
+            cpu.last_code_write(),
+            Some(CodeWrite {
+                pc: 0x202,
+                addr: 0x200
+            })
+        );
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.last_code_write(), None);
+        assert_eq!(cpu.code_writes(), 1);
+        assert_eq!(cpu.code_write_sites().collect::<Vec<_>>(), [0x202]);
+    }
+
+    #[test]
+    fn test_last_draw() {
+        let mut cpu = Chip::new(0);
+        // V0 = 62, V1 = 31, 在右下角绘制字体 0 的 2 行两次，然后清屏
+        cpu.load_rom(
+            ENTRY_ADDR,
+            &[0x60, 0x3E, 0x61, 0x1F, 0xD0, 0x12, 0xD0, 0x12, 0x00, 0xE0],
+        )
+        .unwrap();
+        cpu.step().unwrap();
+        assert_eq!(cpu.last_draw(), None);
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        let draw = DrawEvent {
+            x: 62,
+            y: 31,
+            width: 8,
+            height: 2,
+            collision: false,
+        };
+        assert_eq!(cpu.last_draw(), Some(draw));
+        cpu.step().unwrap();
+        assert_eq!(
+            cpu.last_draw(),
+            Some(DrawEvent {
+                collision: true,
+                ..draw
+            })
+        );
+        cpu.step().unwrap();
+        assert_eq!(cpu.last_draw().map(|d| (d.width, d.height)), Some((64, 32)));
+    }
+
+    #[test]
+    fn test_frame_stats() {
+        let mut cpu = Chip::new(0);
+        // CALL 0x206; JP 0x202; (空); DRW V0, V0, 5; DRW V0, V0, 5; LD ST, V1 (V1 = 0);
+        // SKP V0; RET
+        cpu.load_rom(
+            ENTRY_ADDR,
+            &[
+                0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0xD0, 0x05, 0xD0, 0x05, 0xF1, 0x18, 0xE0, 0x9E,
+                0x00, 0xEE,
+            ],
+        )
+        .unwrap();
+        let stats = cpu.run_frame(7).unwrap();
+        assert_eq!(
+            stats,
+            FrameStats {
+                instructions: 7,
+                draws: 2,
+                collisions: 1,
+                stack_peak: 1,
+                keys_sampled: 1,
+                sound: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_stack_limit() {
+        // 200: CALL 0x200，无限递归
+        let mut cpu = Chip::new(0);
+        cpu.load_rom(ENTRY_ADDR, &[0x22, 0x00]).unwrap();
+        for _ in 0..STACK_SIZE {
+            cpu.step().unwrap();
+        }
+        assert_eq!(cpu.step(), Err(Exception::StackOverflow(16)));
+        assert_eq!(cpu.stack_peak(), STACK_SIZE);
+
+        cpu.set_stack_limit(32);
+        cpu.reset(0);
+        cpu.load_rom(ENTRY_ADDR, &[0x22, 0x00]).unwrap();
+        assert_eq!(cpu.stack_peak(), 0);
+        for _ in 0..32 {
+            cpu.step().unwrap();
+        }
+        assert_eq!(cpu.step(), Err(Exception::StackOverflow(32)));
+        assert_eq!(cpu.stack_peak(), 32);
     }
 }
@@ -0,0 +1,161 @@
+use crate::analysis::Analysis;
+use crate::{Chip, ENTRY_ADDR, MEM_SIZE};
+use std::fmt;
+
+/// 内存区域的用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Kind {
+    /// 执行过或静态分析可以到达的指令
+    Code,
+    /// 被 FX55 或 FX33 写入，通常是变量
+    Scratch,
+    /// 被 DXYN 读取的精灵
+    Sprite,
+    /// 被 FX65 读取的数据表
+    Data,
+    /// ROM 中没有被执行或访问过的字节
+    Untouched,
+    /// ROM 之后没有被访问过的内存
+    Free,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Kind::Code => "code",
+            Kind::Scratch => "scratch",
+            Kind::Sprite => "sprite",
+            Kind::Data => "data",
+            Kind::Untouched => "untouched",
+            Kind::Free => "free",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// 一段连续的同类内存，end 不包含在内
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub start: u16,
+    pub end: u16,
+    pub kind: Kind,
+}
+
+const EXECUTED: u8 = 1;
+const SPRITE: u8 = 2;
+const READ: u8 = 4;
+const WRITTEN: u8 = 8;
+
+/// 运行时记录每个字节被如何访问
+#[derive(Debug, Clone)]
+pub struct Accesses {
+    flags: Vec<u8>,
+}
+
+impl Default for Accesses {
+    fn default() -> Self {
+        Self {
+            flags: vec![0; MEM_SIZE],
+        }
+    }
+}
+
+impl Accesses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在每条指令成功执行后调用，根据指令和 I 记录访问的内存
+    pub fn record(&mut self, chip: &Chip) {
+        let op = chip.opcode();
+        let addr = chip.opcode_addr() as usize;
+        let i = chip.i() as usize;
+        let x = (op >> 8 & 0xF) as usize;
+        self.mark(addr..addr + 2, EXECUTED);
+        match op & 0xF0FF {
+            _ if op & 0xF000 == 0xD000 => self.mark(i..i + (op & 0xF) as usize, SPRITE),
+            0xF055 => self.mark(i..i + x + 1, WRITTEN),
+            0xF033 => self.mark(i..i + 3, WRITTEN),
+            0xF065 => self.mark(i..i + x + 1, READ),
+            _ => (),
+        }
+    }
+
+    fn mark(&mut self, range: std::ops::Range<usize>, flag: u8) {
+        let end = range.end.min(MEM_SIZE);
+        for byte in &mut self.flags[range.start.min(end)..end] {
+            *byte |= flag;
+        }
+    }
+}
+
+/// 结合静态分析和运行时的访问，把 ENTRY_ADDR 之后的内存划分为区域
+pub fn memory_map(rom_len: usize, analysis: &Analysis, accesses: &Accesses) -> Vec<Region> {
+    let rom_end = ENTRY_ADDR as usize + rom_len;
+    let mut regions: Vec<Region> = Vec::new();
+    for addr in ENTRY_ADDR as usize..MEM_SIZE {
+        let flags = accesses.flags[addr];
+        let static_code = analysis.code.contains(&(addr as u16))
+            || analysis.code.contains(&(addr as u16).wrapping_sub(1));
+        let kind = if flags & EXECUTED != 0 || static_code {
+            Kind::Code
+        } else if flags & WRITTEN != 0 {
+            Kind::Scratch
+        } else if flags & SPRITE != 0 {
+            Kind::Sprite
+        } else if flags & READ != 0 {
+            Kind::Data
+        } else if addr < rom_end {
+            Kind::Untouched
+        } else {
+            Kind::Free
+        };
+        match regions.last_mut() {
+            Some(region) if region.kind == kind => region.end += 1,
+            _ => regions.push(Region {
+                start: addr as u16,
+                end: addr as u16 + 1,
+                kind,
+            }),
+        }
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis;
+
+    #[test]
+    fn test_memory_map() {
+        let rom = [
+            0xA2, 0x0A, // 200: LD I, 0x20A
+            0xD0, 0x04, // 202: DRW V0, V0, 4
+            0xA2, 0x10, // 204: LD I, 0x210
+            0xF3, 0x33, // 206: LD B, V3
+            0x12, 0x08, // 208: JP 0x208
+            0xFF, 0x81, 0x81, 0xFF, // 20A: 精灵
+            0x00, 0x00, // 20E: 未使用
+        ];
+        let mut chip = Chip::new(0);
+        chip.load_rom(ENTRY_ADDR, &rom).unwrap();
+        let mut accesses = Accesses::new();
+        for _ in 0..6 {
+            chip.step().unwrap();
+            accesses.record(&chip);
+        }
+        let map = memory_map(rom.len(), &analysis::analyze(&rom), &accesses);
+        let kinds: Vec<(u16, u16, Kind)> = map.iter().map(|r| (r.start, r.end, r.kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                (0x200, 0x20A, Kind::Code),
+                (0x20A, 0x20E, Kind::Sprite),
+                (0x20E, 0x210, Kind::Untouched),
+                (0x210, 0x213, Kind::Scratch),
+                (0x213, MEM_SIZE as u16, Kind::Free),
+            ]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use crate::Exception;
+use core::fmt;
+use core::ops::{Index, IndexMut};
+
+/// V0 ~ VF 寄存器的编号，只能是 0x0 ~ 0xF
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Reg(u8);
+
+impl Reg {
+    pub const V0: Reg = Reg(0x0);
+    pub const VF: Reg = Reg(0xF);
+
+    /// x 超出 0x0 ~ 0xF 时返回 None
+    pub const fn new(x: u8) -> Option<Self> {
+        if x < 16 {
+            Some(Reg(x))
+        } else {
+            None
+        }
+    }
+
+    /// 取 x 的低 4 位，用于从操作码中解码
+    pub const fn from_nibble(x: u8) -> Self {
+        Reg(x & 0xF)
+    }
+
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// V0 ~ VF
+    pub fn all() -> impl Iterator<Item = Reg> {
+        (0..16).map(Reg)
+    }
+}
+
+impl TryFrom<u8> for Reg {
+    type Error = Exception;
+
+    fn try_from(x: u8) -> Result<Self, Self::Error> {
+        Self::new(x).ok_or(Exception::IllegalAddress(x as u16))
+    }
+}
+
+impl From<Reg> for u8 {
+    fn from(reg: Reg) -> Self {
+        reg.0
+    }
+}
+
+/// 寄存器编号的十六进制数字，`V{:X}` 输出 `VA` 这样的寄存器名
+impl fmt::UpperHex for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "V{:X}", self.0)
+    }
+}
+
+/// 寄存器组按寄存器编号取值，见 `Chip::registers`
+impl Index<Reg> for [u8; 16] {
+    type Output = u8;
+
+    fn index(&self, reg: Reg) -> &u8 {
+        &self[reg.index()]
+    }
+}
+
+impl IndexMut<Reg> for [u8; 16] {
+    fn index_mut(&mut self, reg: Reg) -> &mut u8 {
+        &mut self[reg.index()]
+    }
+}
+
+/// 指令中的 12 位地址，只能是 0x000 ~ 0xFFF
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Addr(u16);
+
+impl Addr {
+    /// addr 超出 12 位时返回 None
+    pub const fn new(addr: u16) -> Option<Self> {
+        if addr <= 0xFFF {
+            Some(Addr(addr))
+        } else {
+            None
+        }
+    }
+
+    /// 取 addr 的低 12 位，用于从操作码中解码
+    pub const fn from_bits(addr: u16) -> Self {
+        Addr(addr & 0xFFF)
+    }
+
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+}
+
+impl TryFrom<u16> for Addr {
+    type Error = Exception;
+
+    fn try_from(addr: u16) -> Result<Self, Self::Error> {
+        Self::new(addr).ok_or(Exception::IllegalAddress(addr))
+    }
+}
+
+impl From<Addr> for u16 {
+    fn from(addr: Addr) -> Self {
+        addr.0
+    }
+}
+
+impl fmt::UpperHex for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::UpperHex::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:03X}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_operands() {
+        assert_eq!(Reg::new(0xF), Some(Reg::VF));
+        assert_eq!(Reg::new(0x10), None);
+        assert!(Reg::try_from(0x10).is_err());
+        assert_eq!(Reg::from_nibble(0x1A).to_string(), "VA");
+        assert_eq!(format!("V{:X}", Reg::from_nibble(0xB)), "VB");
+        assert_eq!(Reg::all().count(), 16);
+
+        let mut regs = [0u8; 16];
+        regs[Reg::VF] = 1;
+        assert_eq!(regs[15], 1);
+
+        assert_eq!(Addr::new(0xFFF).map(Addr::get), Some(0xFFF));
+        assert_eq!(Addr::new(0x1000), None);
+        assert_eq!(Addr::from_bits(0x1234), Addr::new(0x234).unwrap());
+        assert_eq!(Addr::from_bits(0x2A).to_string(), "0x02A");
+    }
+}
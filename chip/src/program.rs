@@ -0,0 +1,242 @@
+use crate::{Addr, Instruction, Reg, ENTRY_ADDR};
+
+/// 链式构造 ROM 字节，用于编写可读的测试和示例，例如
+/// `Program::new().ld_v(0, 0x0F).add_v(0, 10).jump(0x200).build()`
+///
+/// 寄存器编号超出 0 ~ 15 或地址超出 12 位时 panic，测试中写错的程序应当立即失败
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program {
+    bytes: Vec<u8>,
+}
+
+fn reg(x: u8) -> Reg {
+    Reg::new(x).unwrap_or_else(|| panic!("register V{} out of range", x))
+}
+
+fn addr(nnn: u16) -> Addr {
+    Addr::new(nnn).unwrap_or_else(|| panic!("address {:#X} out of 12 bits", nnn))
+}
+
+impl Program {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 下一条指令加载到 ENTRY_ADDR 后的地址，用于计算跳转目标
+    pub fn here(&self) -> u16 {
+        ENTRY_ADDR + self.bytes.len() as u16
+    }
+
+    /// 生成 ROM 字节
+    pub fn build(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// 追加任意指令，包括下面没有单独方法的扩展指令
+    pub fn op(mut self, ins: Instruction) -> Self {
+        self.bytes.extend_from_slice(&ins.encode().to_be_bytes());
+        self
+    }
+
+    /// 追加原始数据，例如精灵
+    pub fn data(mut self, bytes: &[u8]) -> Self {
+        self.bytes.extend_from_slice(bytes);
+        self
+    }
+
+    /// 00E0
+    pub fn cls(self) -> Self {
+        self.op(Instruction::Cls)
+    }
+
+    /// 00EE
+    pub fn ret(self) -> Self {
+        self.op(Instruction::Ret)
+    }
+
+    /// 1NNN
+    pub fn jump(self, nnn: u16) -> Self {
+        self.op(Instruction::Jp(addr(nnn)))
+    }
+
+    /// 2NNN
+    pub fn call(self, nnn: u16) -> Self {
+        self.op(Instruction::Call(addr(nnn)))
+    }
+
+    /// 3XNN: Vx == NN 时跳过下一条指令
+    pub fn se_v(self, x: u8, nn: u8) -> Self {
+        self.op(Instruction::SeImm(reg(x), nn))
+    }
+
+    /// 4XNN: Vx != NN 时跳过下一条指令
+    pub fn sne_v(self, x: u8, nn: u8) -> Self {
+        self.op(Instruction::SneImm(reg(x), nn))
+    }
+
+    /// 5XY0: Vx == Vy 时跳过下一条指令
+    pub fn se_vv(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::SeReg(reg(x), reg(y)))
+    }
+
+    /// 6XNN: Vx = NN
+    pub fn ld_v(self, x: u8, nn: u8) -> Self {
+        self.op(Instruction::LdImm(reg(x), nn))
+    }
+
+    /// 7XNN: Vx += NN
+    pub fn add_v(self, x: u8, nn: u8) -> Self {
+        self.op(Instruction::AddImm(reg(x), nn))
+    }
+
+    /// 8XY0: Vx = Vy
+    pub fn ld_vv(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::LdReg(reg(x), reg(y)))
+    }
+
+    /// 8XY1: Vx |= Vy
+    pub fn or(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::Or(reg(x), reg(y)))
+    }
+
+    /// 8XY2: Vx &= Vy
+    pub fn and(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::And(reg(x), reg(y)))
+    }
+
+    /// 8XY3: Vx ^= Vy
+    pub fn xor(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::Xor(reg(x), reg(y)))
+    }
+
+    /// 8XY4: Vx += Vy，VF 为进位
+    pub fn add_vv(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::Add(reg(x), reg(y)))
+    }
+
+    /// 8XY5: Vx -= Vy，VF 为非借位
+    pub fn sub(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::Sub(reg(x), reg(y)))
+    }
+
+    /// 8XY6
+    pub fn shr(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::Shr(reg(x), reg(y)))
+    }
+
+    /// 8XY7: Vx = Vy - Vx
+    pub fn subn(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::Subn(reg(x), reg(y)))
+    }
+
+    /// 8XYE
+    pub fn shl(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::Shl(reg(x), reg(y)))
+    }
+
+    /// 9XY0: Vx != Vy 时跳过下一条指令
+    pub fn sne_vv(self, x: u8, y: u8) -> Self {
+        self.op(Instruction::SneReg(reg(x), reg(y)))
+    }
+
+    /// ANNN: I = NNN
+    pub fn ld_i(self, nnn: u16) -> Self {
+        self.op(Instruction::LdI(addr(nnn)))
+    }
+
+    /// BNNN: 跳转到 V0 + NNN
+    pub fn jump_v0(self, nnn: u16) -> Self {
+        self.op(Instruction::JpV0(addr(nnn)))
+    }
+
+    /// CXNN: Vx = 随机数 & NN
+    pub fn rnd(self, x: u8, nn: u8) -> Self {
+        self.op(Instruction::Rnd(reg(x), nn))
+    }
+
+    /// DXYN: 在 (Vx, Vy) 处绘制 N 行精灵
+    pub fn drw(self, x: u8, y: u8, n: u8) -> Self {
+        assert!(n < 16, "sprite height {} out of range", n);
+        self.op(Instruction::Drw(reg(x), reg(y), n))
+    }
+
+    /// EX9E: 按键 Vx 按下时跳过下一条指令
+    pub fn skp(self, x: u8) -> Self {
+        self.op(Instruction::Skp(reg(x)))
+    }
+
+    /// EXA1: 按键 Vx 未按下时跳过下一条指令
+    pub fn sknp(self, x: u8) -> Self {
+        self.op(Instruction::Sknp(reg(x)))
+    }
+
+    /// FX07: Vx = DT
+    pub fn ld_v_dt(self, x: u8) -> Self {
+        self.op(Instruction::LdVxDt(reg(x)))
+    }
+
+    /// FX0A: 等待按键，Vx = 按键
+    pub fn ld_key(self, x: u8) -> Self {
+        self.op(Instruction::LdKey(reg(x)))
+    }
+
+    /// FX15: DT = Vx
+    pub fn ld_dt(self, x: u8) -> Self {
+        self.op(Instruction::LdDtVx(reg(x)))
+    }
+
+    /// FX18: ST = Vx
+    pub fn ld_st(self, x: u8) -> Self {
+        self.op(Instruction::LdStVx(reg(x)))
+    }
+
+    /// FX1E: I += Vx
+    pub fn add_i(self, x: u8) -> Self {
+        self.op(Instruction::AddI(reg(x)))
+    }
+
+    /// FX29: I = 数字 Vx 的字体地址
+    pub fn ld_font(self, x: u8) -> Self {
+        self.op(Instruction::LdFont(reg(x)))
+    }
+
+    /// FX33: 在 I 处保存 Vx 的十进制各位
+    pub fn ld_bcd(self, x: u8) -> Self {
+        self.op(Instruction::LdBcd(reg(x)))
+    }
+
+    /// FX55: 把 V0 ~ Vx 保存到 I 处
+    pub fn store(self, x: u8) -> Self {
+        self.op(Instruction::StoreRegs(reg(x)))
+    }
+
+    /// FX65: 从 I 处读取 V0 ~ Vx
+    pub fn load(self, x: u8) -> Self {
+        self.op(Instruction::LoadRegs(reg(x)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program() {
+        let start = Program::new().here();
+        assert_eq!(start, ENTRY_ADDR);
+        let rom = Program::new()
+            .ld_v(0, 0x0F)
+            .ld_vv(1, 0)
+            .add_v(0, 10)
+            .or(0, 1)
+            .drw(0, 1, 5)
+            .jump(start)
+            .data(&[0xF0])
+            .build();
+        assert_eq!(
+            rom,
+            [0x60, 0x0F, 0x81, 0x00, 0x70, 0x0A, 0x80, 0x11, 0xD0, 0x15, 0x12, 0x00, 0xF0]
+        );
+        assert_eq!(Program::new().cls().here(), ENTRY_ADDR + 2);
+    }
+}
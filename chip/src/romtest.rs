@@ -0,0 +1,178 @@
+use crate::symbols::Symbols;
+use crate::{Addr, Chip, Exception, ENTRY_ADDR};
+use std::fmt;
+
+/// 测试失败指令：ROM 执行到这里时测试失败，通常放在 SE/SNE 之后作为断言
+pub const FAIL_OPCODE: u16 = 0x0F02;
+/// 名字以此开头的标签是测试入口
+pub const TEST_PREFIX: &str = "test_";
+/// 每个测试默认最多执行的指令数
+pub const DEFAULT_MAX_STEPS: u64 = 1_000_000;
+
+/// 调用测试的跳板放在解释器区域中字体之后，测试返回时 PC 回到 TRAMPOLINE + 2
+const TRAMPOLINE: u16 = 0x1F0;
+/// 每隔多少条指令递减一次定时器，相当于每秒 700 条指令
+const STEPS_PER_TICK: u64 = 700 / 60;
+
+/// 一个测试的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// 测试子程序正常返回
+    Pass,
+    /// 执行到 addr 处的失败指令
+    Fail { addr: u16 },
+    /// 执行出错
+    Error(Exception),
+    /// 超过指令数限制还没有返回
+    Timeout,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Outcome::Pass => write!(f, "ok"),
+            Outcome::Fail { addr } => write!(f, "FAILED at 0x{:03X}", addr),
+            Outcome::Error(e) => write!(f, "ERROR: {}", e),
+            Outcome::Timeout => write!(f, "TIMEOUT"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    pub name: String,
+    pub addr: u16,
+    pub outcome: Outcome,
+    /// 执行的指令数
+    pub steps: u64,
+    /// 测试中调试输出指令 (0FX0、0F01) 打印的内容
+    pub output: Vec<(u16, String)>,
+}
+
+/// 按地址顺序列出符号中的测试入口
+pub fn entries(symbols: &Symbols) -> Vec<(String, u16)> {
+    let mut entries: Vec<(String, u16)> = symbols
+        .iter()
+        .filter(|(_, name)| name.starts_with(TEST_PREFIX))
+        .map(|(addr, name)| (name.to_string(), addr))
+        .collect();
+    entries.sort_by_key(|(_, addr)| *addr);
+    entries
+}
+
+/// 运行 ROM 中的一个测试：在新的虚拟机中装载 ROM，像子程序一样调用入口，
+/// 直到它返回、执行到失败指令、出错或超过 max_steps 条指令
+pub fn run_test(rom: &[u8], name: &str, addr: u16, max_steps: u64) -> TestResult {
+    let mut chip = Chip::new(0);
+    chip.set_debug_print(true);
+    let mut result = TestResult {
+        name: name.to_string(),
+        addr,
+        outcome: Outcome::Timeout,
+        steps: 0,
+        output: Vec::new(),
+    };
+    let call = (0x2000 | addr & 0xFFF).to_be_bytes();
+    let setup = chip
+        .load_rom(ENTRY_ADDR, rom)
+        .and_then(|()| chip.load_region(TRAMPOLINE, &call))
+        .and_then(|()| chip.set_pc(Addr::from_bits(TRAMPOLINE)));
+    if let Err(e) = setup {
+        result.outcome = Outcome::Error(e);
+        return result;
+    }
+
+    while result.steps < max_steps {
+        if chip.pc() == TRAMPOLINE + 2 {
+            result.outcome = Outcome::Pass;
+            break;
+        }
+        if chip.opcode_at(chip.pc()) == Some(FAIL_OPCODE) {
+            result.outcome = Outcome::Fail { addr: chip.pc() };
+            break;
+        }
+        if let Err(e) = chip.step() {
+            result.outcome = Outcome::Error(e);
+            break;
+        }
+        result.steps += 1;
+        if result.steps.is_multiple_of(STEPS_PER_TICK) {
+            chip.tick_timers();
+        }
+    }
+    result.output = chip.take_debug_output();
+    result
+}
+
+/// 运行 ROM 中的所有测试
+pub fn run(rom: &[u8], symbols: &Symbols, max_steps: u64) -> Vec<TestResult> {
+    entries(symbols)
+        .iter()
+        .map(|(name, addr)| run_test(rom, name, *addr, max_steps))
+        .collect()
+}
+
+/// 运行 ROM 中的所有测试，有测试没有通过或没有找到测试时 panic，
+/// 用于在 `cargo test` 中测试自制 ROM，symbols 为 `chip8-asm --sym` 生成的符号文件内容
+pub fn assert_passes(rom: &[u8], symbols: &str) {
+    let symbols = Symbols::parse(symbols).unwrap_or_else(|e| panic!("invalid symbols: {}", e));
+    let results = run(rom, &symbols, DEFAULT_MAX_STEPS);
+    assert!(!results.is_empty(), "no {}* labels found", TEST_PREFIX);
+    let failures: Vec<String> = results
+        .iter()
+        .filter(|r| r.outcome != Outcome::Pass)
+        .map(|r| format!("{}: {}", r.name, r.outcome))
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} of {} rom tests failed:\n{}",
+        failures.len(),
+        results.len(),
+        failures.join("\n")
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm;
+
+    #[test]
+    fn test_run() {
+        let source = "
+            start:      JP start
+            test_add:   LD V0, 2
+                        ADD V0, 3
+                        DBG V0
+                        SE V0, 5
+                        FAIL
+                        RET
+            test_wrong: LD V0, 1
+                        SE V0, 2
+                        FAIL
+                        RET
+            test_loop:  JP test_loop
+            test_error: DW 0x8008
+        ";
+        let assembly = asm::assemble(source).unwrap();
+        let symbols: Symbols = assembly.labels.iter().map(|(n, a)| (n, *a)).collect();
+        let results = run(&assembly.code, &symbols, 1000);
+        let outcomes: Vec<(&str, &Outcome)> = results
+            .iter()
+            .map(|r| (r.name.as_str(), &r.outcome))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                ("test_add", &Outcome::Pass),
+                ("test_wrong", &Outcome::Fail { addr: 0x212 }),
+                ("test_loop", &Outcome::Timeout),
+                (
+                    "test_error",
+                    &Outcome::Error(Exception::IllegalOpcode(0x8008))
+                ),
+            ]
+        );
+        assert_eq!(results[0].output, [(0x206, "V0 = 0x05 (5)".to_string())]);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::Chip;
+use std::fmt;
+use std::str::FromStr;
+
+/// 内存搜索的条件，与上一次搜索时的快照比较
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    /// 当前值等于给定值
+    Equal(u8),
+}
+
+impl Filter {
+    fn matches(&self, old: u8, new: u8) -> bool {
+        match self {
+            Filter::Changed => new != old,
+            Filter::Unchanged => new == old,
+            Filter::Increased => new > old,
+            Filter::Decreased => new < old,
+            Filter::Equal(value) => new == *value,
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    /// changed、unchanged、increased、decreased 或十进制数 (0x 前缀为十六进制)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "changed" => Ok(Filter::Changed),
+            "unchanged" => Ok(Filter::Unchanged),
+            "increased" => Ok(Filter::Increased),
+            "decreased" => Ok(Filter::Decreased),
+            value => {
+                let parsed = match value.strip_prefix("0x") {
+                    Some(hex) => u8::from_str_radix(hex, 16),
+                    None => value.parse(),
+                };
+                parsed.map(Filter::Equal).map_err(|_| {
+                    format!(
+                        "invalid search filter {:?} (expected changed, unchanged, increased, decreased or a byte)",
+                        s
+                    )
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Filter::Changed => write!(f, "changed"),
+            Filter::Unchanged => write!(f, "unchanged"),
+            Filter::Increased => write!(f, "increased"),
+            Filter::Decreased => write!(f, "decreased"),
+            Filter::Equal(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// 内存搜索：先保存内存快照，之后每次按条件筛选候选地址并更新快照，
+/// 用于找出保存生命数、分数等数值的地址
+#[derive(Debug, Clone)]
+pub struct MemorySearch {
+    snapshot: Vec<u8>,
+    candidates: Vec<u16>,
+}
+
+impl MemorySearch {
+    /// 开始新的搜索，所有地址都是候选
+    pub fn new(chip: &Chip) -> Self {
+        let snapshot = chip.memory().to_vec();
+        let candidates = (0..snapshot.len() as u16).collect();
+        Self {
+            snapshot,
+            candidates,
+        }
+    }
+
+    /// 只保留满足条件的地址，返回剩余的候选数
+    pub fn filter(&mut self, chip: &Chip, filter: Filter) -> usize {
+        let mem = chip.memory();
+        self.candidates
+            .retain(|&addr| filter.matches(self.snapshot[addr as usize], mem[addr as usize]));
+        self.snapshot.copy_from_slice(mem);
+        self.candidates.len()
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        &self.candidates
+    }
+
+    /// 上一次搜索时地址处的值
+    pub fn value(&self, addr: u16) -> u8 {
+        self.snapshot[addr as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_search() {
+        let mut chip = Chip::new(0);
+        // 200: ADD V0, 1; 202: LD I, 0x300; 204: LD [I], V0; 206: JP 0x200
+        chip.load_rom(0x200, &[0x70, 0x01, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x00])
+            .unwrap();
+        let mut search = MemorySearch::new(&chip);
+        assert_eq!(search.candidates().len(), chip.memory().len());
+
+        for _ in 0..4 {
+            chip.step().unwrap();
+        }
+        search.filter(&chip, Filter::Increased);
+        assert_eq!(search.candidates(), [0x300]);
+        assert_eq!(search.value(0x300), 1);
+        assert_eq!(search.filter(&chip, Filter::Unchanged), 1);
+        assert_eq!(search.filter(&chip, Filter::Equal(2)), 0);
+
+        assert_eq!("0x1F".parse::<Filter>(), Ok(Filter::Equal(0x1F)));
+        assert_eq!("Changed".parse::<Filter>(), Ok(Filter::Changed));
+        assert!("300".parse::<Filter>().is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// 源代码映射，记录每条指令的地址对应的源代码行号
+///
+/// 文件格式为可选的 `file <源文件>` 一行，之后每行一个 `<地址> <行号>`，
+/// 地址为十六进制，可以带 0x 前缀，`;` 之后为注释
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// 源文件路径，相对于映射文件所在目录
+    pub file: Option<String>,
+    lines: BTreeMap<u16, usize>,
+}
+
+impl SourceMap {
+    pub fn new(file: Option<&str>) -> Self {
+        Self {
+            file: file.map(str::to_string),
+            lines: BTreeMap::new(),
+        }
+    }
+
+    /// 解析映射文件的内容
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut map = Self::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split(';').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(file) = line.strip_prefix("file ") {
+                map.file = Some(file.trim().to_string());
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let (Some(addr), Some(line_no), None) = (words.next(), words.next(), words.next())
+            else {
+                return Err(format!("line {}: expected `<address> <line>`", n + 1));
+            };
+            let hex = addr
+                .strip_prefix("0x")
+                .or_else(|| addr.strip_prefix("0X"))
+                .unwrap_or(addr);
+            let addr = u16::from_str_radix(hex, 16)
+                .map_err(|_| format!("line {}: invalid address {:?}", n + 1, addr))?;
+            let line_no = line_no
+                .parse()
+                .map_err(|_| format!("line {}: invalid line number {:?}", n + 1, line_no))?;
+            map.insert(addr, line_no);
+        }
+        Ok(map)
+    }
+
+    pub fn insert(&mut self, addr: u16, line: usize) {
+        self.lines.insert(addr, line);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// 地址上的指令对应的行号
+    pub fn line(&self, addr: u16) -> Option<usize> {
+        self.lines.get(&addr).copied()
+    }
+
+    /// 某一行或者其后第一条指令的地址
+    pub fn address(&self, line: usize) -> Option<u16> {
+        self.lines
+            .iter()
+            .filter(|(_, l)| **l >= line)
+            .min_by_key(|(addr, l)| (**l, **addr))
+            .map(|(addr, _)| *addr)
+    }
+}
+
+impl fmt::Display for SourceMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file {
+            writeln!(f, "file {}", file)?;
+        }
+        for (addr, line) in &self.lines {
+            writeln!(f, "0x{:03X} {}", addr, line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_map() {
+        let mut map = SourceMap::new(Some("game.asm"));
+        map.insert(0x200, 3);
+        map.insert(0x202, 4);
+        map.insert(0x204, 7);
+        assert_eq!(map.line(0x202), Some(4));
+        assert_eq!(map.address(5), Some(0x204));
+        assert_eq!(map.address(8), None);
+        assert_eq!(SourceMap::parse(&map.to_string()).unwrap(), map);
+        assert!(SourceMap::parse("0x200 x").is_err());
+    }
+}
@@ -0,0 +1,288 @@
+use crate::{Chip, Exception, Variant, ENTRY_ADDR, STACK_SIZE};
+
+/// 用例中的一项虚拟机状态，只列出用例关心的部分
+#[derive(Debug, Clone, Copy)]
+enum S {
+    V(usize, u8),
+    I(u16),
+    /// 执行后的 PC，没有列出时期望为下一条指令 0x202
+    Pc(u16),
+    Sp(u8),
+    Stack(usize, u16),
+    Dt(u8),
+    St(u8),
+    /// 按下的键，只用于初始状态
+    Key(usize),
+    Mem(u16, u8),
+    /// 帧缓冲的一行，最高位是最左边的像素
+    Row(usize, u64),
+    /// 启用调试输出指令，只用于初始状态
+    DebugPrint,
+}
+
+use S::*;
+
+/// (名称, 指令, 初始状态, 执行后期望的状态)，指令放在 0x200
+type Case = (&'static str, u16, &'static [S], &'static [S]);
+
+/// (名称, 指令, 初始状态, 期望的错误)，这条指令成功时再执行一步，用于检查跳转到的地址
+type ErrorCase = (&'static str, u16, &'static [S], Exception);
+
+#[rustfmt::skip]
+const CASES: &[Case] = &[
+    // 00E0, 00EE, 0NNN
+    ("00E0 clears every row", 0x00E0, &[Row(0, !0), Row(31, 1)], &[Row(0, 0), Row(31, 0)]),
+    ("00EE pops the return address", 0x00EE, &[Sp(1), Stack(0, 0x345)], &[Pc(0x345), Sp(0)]),
+    ("0000 is a no-op", 0x0000, &[V(0, 7)], &[V(0, 7)]),
+    // 1NNN, 2NNN, BNNN
+    ("1NNN jumps", 0x1ABC, &[], &[Pc(0xABC), Sp(0)]),
+    ("1NNN to itself", 0x1200, &[], &[Pc(0x200)]),
+    ("2NNN pushes the next address", 0x2ABC, &[], &[Pc(0xABC), Sp(1), Stack(0, 0x202)]),
+    ("2NNN nests", 0x2300, &[Sp(2), Stack(1, 0x456)], &[Pc(0x300), Sp(3), Stack(1, 0x456), Stack(2, 0x202)]),
+    ("BNNN adds V0", 0xB300, &[V(0, 0x10)], &[Pc(0x310)]),
+    ("BNNN ignores VX", 0xB300, &[V(3, 0x10)], &[Pc(0x300)]),
+    // 3XNN, 4XNN, 5XY0, 9XY0
+    ("3XNN skips when equal", 0x3342, &[V(3, 0x42)], &[Pc(0x204)]),
+    ("3XNN doesn't skip when not equal", 0x3343, &[V(3, 0x42)], &[]),
+    ("4XNN skips when not equal", 0x4343, &[V(3, 0x42)], &[Pc(0x204)]),
+    ("4XNN doesn't skip when equal", 0x4342, &[V(3, 0x42)], &[]),
+    ("5XY0 skips when equal", 0x5120, &[V(1, 9), V(2, 9)], &[Pc(0x204)]),
+    ("5XY0 doesn't skip when not equal", 0x5120, &[V(1, 9), V(2, 8)], &[]),
+    ("5XY0 with X == Y always skips", 0x5110, &[V(1, 9)], &[Pc(0x204)]),
+    ("9XY0 skips when not equal", 0x9120, &[V(1, 9), V(2, 8)], &[Pc(0x204)]),
+    ("9XY0 doesn't skip when equal", 0x9120, &[V(1, 9), V(2, 9)], &[]),
+    // 6XNN, 7XNN
+    ("6XNN loads", 0x6AFF, &[], &[V(0xA, 0xFF)]),
+    ("6XNN loads VF", 0x6F01, &[V(0xF, 5)], &[V(0xF, 1)]),
+    ("7XNN adds", 0x7005, &[V(0, 0x10)], &[V(0, 0x15)]),
+    ("7XNN wraps without touching VF", 0x7001, &[V(0, 0xFF), V(0xF, 0x55)], &[V(0, 0), V(0xF, 0x55)]),
+    ("7XNN on VF", 0x7F01, &[V(0xF, 0xFF)], &[V(0xF, 0)]),
+    // 8XY0 ~ 8XY3
+    ("8XY0 copies", 0x8120, &[V(1, 1), V(2, 2)], &[V(1, 2), V(2, 2)]),
+    ("8XY1 ors", 0x8121, &[V(1, 0x0C), V(2, 0x0A), V(0xF, 7)], &[V(1, 0x0E), V(0xF, 7)]),
+    ("8XY2 ands", 0x8122, &[V(1, 0x0C), V(2, 0x0A), V(0xF, 7)], &[V(1, 0x08), V(0xF, 7)]),
+    ("8XY3 xors", 0x8123, &[V(1, 0x0C), V(2, 0x0A), V(0xF, 7)], &[V(1, 0x06), V(0xF, 7)]),
+    ("8XY3 with X == Y clears", 0x8113, &[V(1, 0x5A)], &[V(1, 0)]),
+    // 8XY4
+    ("8XY4 adds without carry", 0x8124, &[V(1, 0x10), V(2, 0x20), V(0xF, 1)], &[V(1, 0x30), V(0xF, 0)]),
+    ("8XY4 carries", 0x8124, &[V(1, 0xFF), V(2, 1)], &[V(1, 0), V(0xF, 1)]),
+    ("8XY4 255 + 255", 0x8124, &[V(1, 0xFF), V(2, 0xFF)], &[V(1, 0xFE), V(0xF, 1)]),
+    ("8XY4 with X == Y doubles", 0x8114, &[V(1, 0x80)], &[V(1, 0), V(0xF, 1)]),
+    ("8XY4 with X == F keeps the carry", 0x8F14, &[V(0xF, 0xFF), V(1, 1)], &[V(0xF, 1)]),
+    ("8XY4 with X == F keeps no carry", 0x8F14, &[V(0xF, 1), V(1, 2)], &[V(0xF, 0)]),
+    ("8XY4 with Y == F uses the old VF", 0x81F4, &[V(1, 1), V(0xF, 0xFF)], &[V(1, 0), V(0xF, 1)]),
+    // 8XY5, 8XY7
+    ("8XY5 subtracts", 0x8125, &[V(1, 5), V(2, 3)], &[V(1, 2), V(0xF, 1)]),
+    ("8XY5 borrows", 0x8125, &[V(1, 3), V(2, 5)], &[V(1, 0xFE), V(0xF, 0)]),
+    ("8XY5 equal doesn't borrow", 0x8125, &[V(1, 5), V(2, 5)], &[V(1, 0), V(0xF, 1)]),
+    ("8XY5 with X == F keeps the flag", 0x8F15, &[V(0xF, 0x10), V(1, 1)], &[V(0xF, 1)]),
+    ("8XY7 subtracts reversed", 0x8127, &[V(1, 3), V(2, 5)], &[V(1, 2), V(0xF, 1)]),
+    ("8XY7 borrows", 0x8127, &[V(1, 5), V(2, 3)], &[V(1, 0xFE), V(0xF, 0)]),
+    ("8XY7 with X == F keeps the flag", 0x8F17, &[V(0xF, 1), V(1, 0x10)], &[V(0xF, 1)]),
+    // 8XY6, 8XYE 移位 VX，忽略 VY
+    ("8XY6 shifts VX right", 0x8126, &[V(1, 0x05), V(2, 0xF0)], &[V(1, 0x02), V(0xF, 1)]),
+    ("8XY6 without carry", 0x8126, &[V(1, 0x04), V(0xF, 1)], &[V(1, 0x02), V(0xF, 0)]),
+    ("8XY6 with X == F keeps the flag", 0x8F06, &[V(0xF, 0x03)], &[V(0xF, 1)]),
+    ("8XYE shifts VX left", 0x812E, &[V(1, 0x81), V(2, 0x01)], &[V(1, 0x02), V(0xF, 1)]),
+    ("8XYE without carry", 0x812E, &[V(1, 0x41), V(0xF, 1)], &[V(1, 0x82), V(0xF, 0)]),
+    ("8XYE with X == F keeps the flag", 0x8F0E, &[V(0xF, 0x80)], &[V(0xF, 1)]),
+    // ANNN, FX1E, FX29
+    ("ANNN loads I", 0xA123, &[], &[I(0x123)]),
+    ("FX1E adds to I", 0xF01E, &[I(0x100), V(0, 0x10)], &[I(0x110)]),
+    ("FX1E past 0xFFF without touching VF", 0xF01E, &[I(0xFFF), V(0, 1), V(0xF, 0)], &[I(0x1000), V(0xF, 0)]),
+    ("FX29 points at the digit", 0xF029, &[V(0, 0xA)], &[I(0x32)]),
+    ("FX29 uses the low nibble of VX", 0xF029, &[V(0, 0xFA)], &[I(0x32)]),
+    // CXNN
+    ("CX00 is always 0", 0xC000, &[V(0, 0xFF)], &[V(0, 0)]),
+    ("CXNN masks the random byte with seed 0", 0xC0F0, &[], &[V(0, 0xC0)]),
+    // DXYN
+    ("DXYN draws", 0xD015, &[I(0)], &[Row(0, 0xF0 << 56), Row(1, 0x90 << 56), Row(4, 0xF0 << 56), V(0xF, 0)]),
+    ("DXYN collides", 0xD011, &[I(0), Row(0, 0x80 << 56)], &[Row(0, 0x70 << 56), V(0xF, 1)]),
+    ("DXYN clears VF without collision", 0xD011, &[I(0), V(0xF, 1)], &[V(0xF, 0)]),
+    ("DXYN XORs next to lit pixels", 0xD011, &[I(0), Row(0, 0x0F << 56)], &[Row(0, 0xFF << 56), V(0xF, 0)]),
+    ("DXYN wraps horizontally", 0xD011, &[I(0), V(0, 62)], &[Row(0, 0xC000_0000_0000_0003)]),
+    ("DXYN wraps vertically", 0xD013, &[I(0), V(1, 30)], &[Row(30, 0xF0 << 56), Row(31, 0x90 << 56), Row(0, 0x90 << 56)]),
+    ("DXYN takes coordinates modulo the screen", 0xD011, &[I(0), V(0, 72), V(1, 33)], &[Row(1, 0xF0 << 48)]),
+    ("DXYN with N = 0 draws nothing", 0xD010, &[I(0), V(0xF, 1)], &[Row(0, 0), V(0xF, 0)]),
+    ("DXYN at the end of memory", 0xD011, &[I(0xFFF), Mem(0xFFF, 0xFF)], &[Row(0, 0xFF << 56)]),
+    ("DXYN with VF as the coordinate", 0xDFF1, &[I(0), V(0xF, 8)], &[Row(8, 0xF0 << 48), V(0xF, 0)]),
+    // EX9E, EXA1
+    ("EX9E skips when the key is down", 0xE09E, &[V(0, 5), Key(5)], &[Pc(0x204)]),
+    ("EX9E with other keys down", 0xE09E, &[V(0, 5), Key(3), Key(5)], &[Pc(0x204)]),
+    ("EX9E doesn't skip when the key is up", 0xE09E, &[V(0, 4), Key(5)], &[]),
+    ("EX9E uses the low nibble of VX", 0xE09E, &[V(0, 0x15), Key(5)], &[Pc(0x204)]),
+    ("EXA1 skips when no key is down", 0xE0A1, &[V(0, 5)], &[Pc(0x204)]),
+    ("EXA1 skips when another key is down", 0xE0A1, &[V(0, 5), Key(4)], &[Pc(0x204)]),
+    ("EXA1 doesn't skip when the key is down", 0xE0A1, &[V(0, 5), Key(4), Key(5)], &[]),
+    // FX07, FX0A, FX15, FX18
+    ("FX07 reads the delay timer", 0xF307, &[Dt(0x20)], &[V(3, 0x20), Dt(0x20)]),
+    ("FX0A waits without a key", 0xF20A, &[V(2, 9)], &[Pc(0x200), V(2, 9)]),
+    ("FX0A stores the key", 0xF20A, &[Key(7)], &[V(2, 7)]),
+    ("FX0A stores the lowest key", 0xF20A, &[Key(0xC), Key(3)], &[V(2, 3)]),
+    ("FX15 sets the delay timer", 0xF415, &[V(4, 0x33)], &[Dt(0x33)]),
+    ("FX18 sets the sound timer", 0xF418, &[V(4, 0x33)], &[St(0x33)]),
+    // FX33
+    ("FX33 stores BCD", 0xF033, &[V(0, 123), I(0x300)], &[Mem(0x300, 1), Mem(0x301, 2), Mem(0x302, 3), I(0x300)]),
+    ("FX33 of 0", 0xF033, &[V(0, 0), I(0x300), Mem(0x300, 9)], &[Mem(0x300, 0), Mem(0x301, 0), Mem(0x302, 0)]),
+    ("FX33 of 255", 0xF033, &[V(0, 255), I(0x300)], &[Mem(0x300, 2), Mem(0x301, 5), Mem(0x302, 5)]),
+    ("FX33 at the end of memory", 0xF033, &[V(0, 42), I(0xFFD)], &[Mem(0xFFD, 0), Mem(0xFFE, 4), Mem(0xFFF, 2)]),
+    // FX55, FX65 包括 VX，不改变 I
+    ("FX55 stores V0 to VX", 0xF255, &[V(0, 1), V(1, 2), V(2, 3), V(3, 4), I(0x300)], &[Mem(0x300, 1), Mem(0x301, 2), Mem(0x302, 3), Mem(0x303, 0), I(0x300)]),
+    ("F055 stores V0", 0xF055, &[V(0, 7), V(1, 8), I(0x300)], &[Mem(0x300, 7), Mem(0x301, 0)]),
+    ("FX65 loads V0 to VX", 0xF265, &[Mem(0x300, 1), Mem(0x301, 2), Mem(0x302, 3), Mem(0x303, 4), I(0x300)], &[V(0, 1), V(1, 2), V(2, 3), V(3, 0), I(0x300)]),
+    ("FF65 loads VF", 0xFF65, &[Mem(0x30F, 0xAB), I(0x300)], &[V(0xF, 0xAB)]),
+];
+
+#[rustfmt::skip]
+const ERRORS: &[ErrorCase] = &[
+    ("00EE with an empty stack", 0x00EE, &[], Exception::StackUnderflow(0)),
+    ("2NNN with a full stack", 0x2300, &[Sp(STACK_SIZE as u8)], Exception::StackOverflow(STACK_SIZE as u8)),
+    ("BNNN past 0xFFF", 0xBFFF, &[V(0, 1)], Exception::IllegalAddress(0x1000)),
+    ("JP 0xFFF then step", 0x1FFF, &[], Exception::OutOfMemory(0xFFF)),
+    ("00FF is illegal", 0x00FF, &[], Exception::IllegalOpcode(0x00FF)),
+    ("8XY8 is illegal", 0x8128, &[], Exception::IllegalOpcode(0x8128)),
+    ("EX00 is illegal", 0xE000, &[], Exception::IllegalOpcode(0xE000)),
+    ("FX00 is illegal", 0xF000, &[], Exception::IllegalOpcode(0xF000)),
+    ("DXYN past the end of memory", 0xD012, &[I(0xFFF)], Exception::IllegalAddress(0xFFF)),
+    ("FX33 past the end of memory", 0xF033, &[I(0xFFE)], Exception::IllegalAddress(0xFFE)),
+    ("FX55 past the end of memory", 0xF155, &[I(0xFFF)], Exception::IllegalAddress(0x1000)),
+    ("FX65 past the end of memory", 0xF165, &[I(0xFFF)], Exception::IllegalAddress(0x1000)),
+    ("0F00 without debug print is illegal", 0x0F00, &[], Exception::IllegalOpcode(0x0F00)),
+    ("0F01 with I past 0xFFF", 0x0F01, &[DebugPrint, I(0x10FE)], Exception::IllegalAddress(0x10FE)),
+];
+
+/// CHIP-8E 的扩展指令，以及被它们取代的基本指令
+#[rustfmt::skip]
+const CHIP8E_CASES: &[Case] = &[
+    ("00F2 is a no-op", 0x00F2, &[V(0, 7)], &[V(0, 7)]),
+    ("0151 waits for DT", 0x0151, &[Dt(3)], &[Pc(0x200), Dt(3)]),
+    ("0151 continues at DT = 0", 0x0151, &[], &[]),
+    ("0188 skips", 0x0188, &[], &[Pc(0x204)]),
+    ("5XY0 still skips when equal", 0x5120, &[V(1, 9), V(2, 9)], &[Pc(0x204)]),
+    ("5XY1 skips when greater", 0x5121, &[V(1, 9), V(2, 8)], &[Pc(0x204)]),
+    ("5XY1 doesn't skip when equal", 0x5121, &[V(1, 9), V(2, 9)], &[]),
+    ("5XY2 stores VX to VY", 0x5132, &[V(1, 1), V(2, 2), V(3, 3), I(0x300)], &[Mem(0x300, 1), Mem(0x301, 2), Mem(0x302, 3), I(0x303)]),
+    ("5XY2 stores in reverse", 0x5312, &[V(1, 1), V(2, 2), V(3, 3), I(0x300)], &[Mem(0x300, 3), Mem(0x301, 2), Mem(0x302, 1), I(0x303)]),
+    ("5XY3 loads VX to VY", 0x5243, &[Mem(0x300, 7), Mem(0x301, 8), Mem(0x302, 9), I(0x300)], &[V(2, 7), V(3, 8), V(4, 9), I(0x303)]),
+    ("BBNN jumps back", 0xBB06, &[], &[Pc(0x1FC)]),
+    ("BFNN jumps forward", 0xBF06, &[], &[Pc(0x208)]),
+    ("BNNN still adds V0", 0xB300, &[V(0, 0x10)], &[Pc(0x310)]),
+    ("FX1B skips VX bytes", 0xF01B, &[V(0, 6)], &[Pc(0x208)]),
+    ("FX4F sets DT and waits", 0xF04F, &[V(0, 3)], &[Pc(0x200), Dt(3)]),
+    ("FX4F with 0 continues", 0xF04F, &[V(0, 0)], &[Dt(0)]),
+];
+
+#[rustfmt::skip]
+const CHIP8E_ERRORS: &[ErrorCase] = &[
+    ("00ED stops", 0x00ED, &[], Exception::Halt(0)),
+    ("BBNN before address 0", 0xBB20, &[Pc(0x010)], Exception::IllegalAddress(0xFFF2)),
+    ("5XY2 past the end of memory", 0x5012, &[I(0xFFF)], Exception::IllegalAddress(0xFFF)),
+    ("FX03 port output is unsupported", 0xF003, &[], Exception::IllegalOpcode(0xF003)),
+];
+
+/// 设置初始状态，在 PC 处 (默认 0x200) 放入指令后执行一步
+fn run(variant: Variant, op: u16, before: &[S]) -> (Chip, Result<(), Exception>) {
+    let mut chip = Chip::new(0);
+    chip.set_variant(variant);
+    for state in before {
+        match *state {
+            V(x, value) => chip.v[x] = value,
+            I(addr) => chip.i = addr,
+            Pc(addr) => chip.pc = addr,
+            Sp(sp) => chip.sp = sp,
+            Stack(n, addr) => chip.stack[n] = addr,
+            Dt(value) => chip.dt = value,
+            St(value) => chip.st = value,
+            Key(key) => chip.keypad[key] = true,
+            Mem(addr, value) => chip.mem[addr as usize] = value,
+            Row(y, row) => chip.fb[y] = row,
+            DebugPrint => chip.debug_print = true,
+        }
+    }
+    chip.load_region(chip.pc, &op.to_be_bytes()).unwrap();
+    let result = chip.step();
+    (chip, result)
+}
+
+/// 运行一组用例，返回失败的用例
+fn check(variant: Variant, cases: &[Case], errors: &[ErrorCase]) -> Vec<String> {
+    let mut failures = Vec::new();
+    for &(name, op, before, after) in cases {
+        let (chip, result) = run(variant, op, before);
+        if let Err(e) = result {
+            failures.push(format!("{} ({:04X}): {}", name, op, e));
+            continue;
+        }
+        let mut expected: Vec<S> = after.to_vec();
+        if !after.iter().any(|s| matches!(s, Pc(_))) {
+            expected.push(Pc(ENTRY_ADDR + 2));
+        }
+        for state in expected {
+            let actual = match state {
+                V(x, _) => V(x, chip.v[x]),
+                I(_) => I(chip.i),
+                Pc(_) => Pc(chip.pc),
+                Sp(_) => Sp(chip.sp),
+                Stack(n, _) => Stack(n, chip.stack[n]),
+                Dt(_) => Dt(chip.dt),
+                St(_) => St(chip.st),
+                Key(key) => panic!("{}: Key({}) is only for the initial state", name, key),
+                DebugPrint => panic!("{}: DebugPrint is only for the initial state", name),
+                Mem(addr, _) => Mem(addr, chip.mem[addr as usize]),
+                Row(y, _) => Row(y, chip.fb[y]),
+            };
+            if format!("{:?}", actual) != format!("{:?}", state) {
+                failures.push(format!(
+                    "{} ({:04X}): expected {:X?}, got {:X?}",
+                    name, op, state, actual
+                ));
+            }
+        }
+    }
+    for &(name, op, before, ref error) in errors {
+        let (mut chip, result) = run(variant, op, before);
+        let result = result.and_then(|()| chip.step());
+        if result.as_ref() != Err(error) {
+            failures.push(format!(
+                "{} ({:04X}): expected {:?}, got {:?}",
+                name, op, error, result
+            ));
+        }
+    }
+    failures
+}
+
+#[test]
+fn test_spec() {
+    let failures = check(Variant::Chip8, CASES, ERRORS);
+    assert!(
+        failures.is_empty(),
+        "{} spec cases failed:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
+
+/// CXNN 是随机数与 NN 按位与：C0F0 只产生 0x10 的倍数，C0FF 可以产生 0xFF
+#[test]
+fn test_cxnn_mask() {
+    let rnd = |seed, op: u16| {
+        let mut chip = Chip::new(seed);
+        chip.load_region(ENTRY_ADDR, &op.to_be_bytes()).unwrap();
+        chip.step().unwrap();
+        chip.v[0]
+    };
+    assert!((0..512).all(|seed| rnd(seed, 0xC0F0) & 0x0F == 0));
+    assert!((0..512).any(|seed| rnd(seed, 0xC0FF) == 0xFF));
+}
+
+#[test]
+fn test_chip8e() {
+    let failures = check(Variant::Chip8E, CHIP8E_CASES, CHIP8E_ERRORS);
+    assert!(
+        failures.is_empty(),
+        "{} CHIP-8E cases failed:\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,118 @@
+use std::fmt::Write;
+
+/// DXYN 一次最多绘制的行数
+pub const MAX_ROWS: usize = 15;
+
+/// 把单色图片切成宽 8 像素、高 rows 行的精灵，按从左到右、从上到下的顺序排列
+///
+/// pixels 按行排列，点亮的像素为 true。宽度必须是 8 的倍数，高度必须是 rows 的倍数
+pub fn slice(
+    pixels: &[bool],
+    width: usize,
+    height: usize,
+    rows: usize,
+) -> Result<Vec<Vec<u8>>, String> {
+    if pixels.len() != width * height {
+        return Err(format!(
+            "expected {}x{} pixels, found {}",
+            width,
+            height,
+            pixels.len()
+        ));
+    }
+    if width == 0 || !width.is_multiple_of(8) {
+        return Err(format!("width {} is not a multiple of 8", width));
+    }
+    if !(1..=MAX_ROWS).contains(&rows) {
+        return Err(format!(
+            "sprite height {} is not between 1 and {}",
+            rows, MAX_ROWS
+        ));
+    }
+    if height == 0 || !height.is_multiple_of(rows) {
+        return Err(format!("height {} is not a multiple of {}", height, rows));
+    }
+
+    let mut sprites = Vec::new();
+    for top in (0..height).step_by(rows) {
+        for left in (0..width).step_by(8) {
+            let sprite = (top..top + rows)
+                .map(|y| {
+                    (0..8).fold(0u8, |byte, x| {
+                        byte << 1 | pixels[y * width + left + x] as u8
+                    })
+                })
+                .collect();
+            sprites.push(sprite);
+        }
+    }
+    Ok(sprites)
+}
+
+/// 多个精灵时在名字后加上序号
+fn names(name: &str, count: usize) -> Vec<String> {
+    match count {
+        1 => vec![name.to_string()],
+        _ => (0..count).map(|n| format!("{}_{}", name, n)).collect(),
+    }
+}
+
+/// 输出汇编器的 `DB` 行，每行一个字节，用二进制显示图案
+pub fn to_asm(name: &str, sprites: &[Vec<u8>]) -> String {
+    let mut out = String::new();
+    for (name, sprite) in names(name, sprites.len()).iter().zip(sprites) {
+        let _ = writeln!(out, "{}:", name);
+        for byte in sprite {
+            let _ = writeln!(out, "    DB 0b{:08b}", byte);
+        }
+    }
+    out
+}
+
+/// 输出 Rust 常量数组，名字转为大写
+pub fn to_rust(name: &str, sprites: &[Vec<u8>]) -> String {
+    let mut out = String::new();
+    for (name, sprite) in names(name, sprites.len()).iter().zip(sprites) {
+        let _ = writeln!(
+            out,
+            "pub const {}: [u8; {}] = [",
+            name.to_ascii_uppercase(),
+            sprite.len()
+        );
+        for byte in sprite {
+            let _ = writeln!(out, "    0b{:08b},", byte);
+        }
+        let _ = writeln!(out, "];");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice() {
+        // 16x2 的图片：左半边是一条横线，右半边是一个点
+        let mut pixels = vec![false; 32];
+        pixels[..8].fill(true);
+        pixels[16 + 15] = true;
+        let sprites = slice(&pixels, 16, 2, 1).unwrap();
+        assert_eq!(sprites, [vec![0xFF], vec![0x00], vec![0x00], vec![0x01]]);
+        assert_eq!(
+            slice(&pixels, 16, 2, 2).unwrap(),
+            [vec![0xFF, 0x00], vec![0x00, 0x01]]
+        );
+        assert!(slice(&pixels[..24], 12, 2, 1).is_err());
+        assert!(slice(&pixels, 16, 2, 3).is_err());
+
+        let asm = to_asm("dot", &[vec![0x80]]);
+        assert_eq!(asm, "dot:\n    DB 0b10000000\n");
+        crate::asm::assemble(&asm).unwrap();
+        assert_eq!(
+            to_rust("dot", &[vec![0x80], vec![0x01]]),
+            "pub const DOT_0: [u8; 1] = [\n    0b10000000,\n];\n\
+             pub const DOT_1: [u8; 1] = [\n    0b00000001,\n];\n"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use core::fmt;
+
+/// 虚拟机寄存器、栈、定时器和键盘的快照，供界面每帧读取一次，
+/// 打开 `serde` 特性后可以序列化
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuState {
+    pub pc: u16,
+    pub i: u16,
+    pub v: [u8; 16],
+    pub sp: u8,
+    /// 栈中的返回地址，从栈底开始
+    pub stack: Vec<u16>,
+    pub dt: u8,
+    pub st: u8,
+    pub keypad: [bool; 16],
+}
+
+impl fmt::Display for CpuState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "PC: {:04X} SP: {:02X} I: {:04X} KEY: {}",
+            self.pc,
+            self.sp,
+            self.i,
+            self.keypad
+                .iter()
+                .enumerate()
+                .find(|(_, &x)| x)
+                .map_or("None".to_string(), |(i, _)| i.to_string())
+        )?;
+        writeln!(
+            f,
+            "DT: {:02X} ST: {:02X} Stack: {:?}",
+            self.dt, self.st, self.stack
+        )?;
+        writeln!(
+            f,
+            "V0: {:02X} V1: {:02X} V2: {:02X} V3: {:02X}",
+            self.v[0], self.v[1], self.v[2], self.v[3]
+        )?;
+        writeln!(
+            f,
+            "V4: {:02X} V5: {:02X} V6: {:02X} V7: {:02X}",
+            self.v[4], self.v[5], self.v[6], self.v[7]
+        )?;
+        writeln!(
+            f,
+            "V8: {:02X} V9: {:02X} Va: {:02X} Vb: {:02X}",
+            self.v[8], self.v[9], self.v[10], self.v[11]
+        )?;
+        write!(
+            f,
+            "Vc: {:02X} Vd: {:02X} Ve: {:02X} Vf: {:02X}",
+            self.v[12], self.v[13], self.v[14], self.v[15]
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Chip;
+
+    #[test]
+    fn test_cpu_state() {
+        // 200: LD V3, 0x2A; 202: CALL 0x206; 206: LD I, 0x300
+        let mut chip =
+            Chip::with_rom(0, &[0x63, 0x2A, 0x22, 0x06, 0x00, 0x00, 0xA3, 0x00]).unwrap();
+        for _ in 0..3 {
+            chip.step().unwrap();
+        }
+        chip.set_keypad(5, true);
+        let state = chip.state();
+        assert_eq!((state.pc, state.i, state.v[3]), (0x208, 0x300, 0x2A));
+        assert_eq!(state.stack, [0x204]);
+        assert_eq!(state.sp, 1);
+        assert_eq!(state.to_string(), chip.to_string());
+        assert!(state
+            .to_string()
+            .starts_with("PC: 0208 SP: 01 I: 0300 KEY: 5\n"));
+    }
+}
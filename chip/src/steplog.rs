@@ -0,0 +1,229 @@
+use crate::Chip;
+use std::fmt;
+use std::str::FromStr;
+
+/// 执行一条指令前的虚拟机状态，是逐步日志中的一行
+///
+/// 格式为空格分隔的 `名字=十六进制值`：
+/// `PC=200 OP=6A02 V0=00 ... VF=00 I=000 SP=0 DT=00 ST=00`。
+/// 解析时名字不区分大小写，也接受 `:` 分隔、`0x` 或 `$` 前缀、`V=` 后接 32 位十六进制数，
+/// 不认识的字段被忽略。其他模拟器的日志缺少的字段在比较时跳过
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Step {
+    pub pc: Option<u16>,
+    pub op: Option<u16>,
+    pub v: [Option<u8>; 16],
+    pub i: Option<u16>,
+    pub sp: Option<u8>,
+    pub dt: Option<u8>,
+    pub st: Option<u8>,
+}
+
+impl Step {
+    /// 记录虚拟机执行下一条指令前的状态
+    pub fn capture(chip: &Chip) -> Self {
+        let mut v = [None; 16];
+        for (v, r) in v.iter_mut().zip(chip.registers()) {
+            *v = Some(*r);
+        }
+        Self {
+            pc: Some(chip.pc()),
+            op: chip.opcode_at(chip.pc()),
+            v,
+            i: Some(chip.i()),
+            sp: Some(chip.stack().len() as u8),
+            dt: Some(chip.delay_timer()),
+            st: Some(chip.sound_timer()),
+        }
+    }
+
+    /// 是否没有任何字段，例如日志中的标题行
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 两边都有且值不同的字段名
+    pub fn differences(&self, other: &Step) -> Vec<String> {
+        fn differ<T: PartialEq>(a: Option<T>, b: Option<T>) -> bool {
+            matches!((a, b), (Some(a), Some(b)) if a != b)
+        }
+        let mut fields = Vec::new();
+        if differ(self.pc, other.pc) {
+            fields.push("PC".to_string());
+        }
+        if differ(self.op, other.op) {
+            fields.push("OP".to_string());
+        }
+        for x in 0..16 {
+            if differ(self.v[x], other.v[x]) {
+                fields.push(format!("V{:X}", x));
+            }
+        }
+        if differ(self.i, other.i) {
+            fields.push("I".to_string());
+        }
+        for (name, a, b) in [
+            ("SP", self.sp, other.sp),
+            ("DT", self.dt, other.dt),
+            ("ST", self.st, other.st),
+        ] {
+            if differ(a, b) {
+                fields.push(name.to_string());
+            }
+        }
+        fields
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut fields = Vec::new();
+        if let Some(pc) = self.pc {
+            fields.push(format!("PC={:03X}", pc));
+        }
+        if let Some(op) = self.op {
+            fields.push(format!("OP={:04X}", op));
+        }
+        for (x, v) in self.v.iter().enumerate() {
+            if let Some(v) = v {
+                fields.push(format!("V{:X}={:02X}", x, v));
+            }
+        }
+        if let Some(i) = self.i {
+            fields.push(format!("I={:03X}", i));
+        }
+        if let Some(sp) = self.sp {
+            fields.push(format!("SP={:X}", sp));
+        }
+        if let Some(dt) = self.dt {
+            fields.push(format!("DT={:02X}", dt));
+        }
+        if let Some(st) = self.st {
+            fields.push(format!("ST={:02X}", st));
+        }
+        write!(f, "{}", fields.join(" "))
+    }
+}
+
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut step = Step::default();
+        for field in s.split_whitespace() {
+            let Some((name, value)) = field.split_once(['=', ':']) else {
+                continue;
+            };
+            let name = name.to_ascii_uppercase();
+            let digits = value
+                .strip_prefix("0x")
+                .or_else(|| value.strip_prefix("0X"))
+                .or_else(|| value.strip_prefix('$'))
+                .unwrap_or(value)
+                .trim_end_matches(',');
+            let invalid = || format!("invalid value {:?} for {}", value, name);
+            let word = || u16::from_str_radix(digits, 16).map_err(|_| invalid());
+            let byte = || u8::from_str_radix(digits, 16).map_err(|_| invalid());
+            match name.as_str() {
+                "PC" => step.pc = Some(word()?),
+                "OP" | "OPCODE" => step.op = Some(word()?),
+                "I" => step.i = Some(word()?),
+                "SP" => step.sp = Some(byte()?),
+                "DT" => step.dt = Some(byte()?),
+                "ST" => step.st = Some(byte()?),
+                "V" if digits.len() == 32 => {
+                    for (x, v) in step.v.iter_mut().enumerate() {
+                        let pair = digits.get(x * 2..x * 2 + 2).ok_or_else(invalid)?;
+                        *v = Some(u8::from_str_radix(pair, 16).map_err(|_| invalid())?);
+                    }
+                }
+                _ => {
+                    let x = name
+                        .strip_prefix('V')
+                        .filter(|x| x.len() == 1)
+                        .and_then(|x| u8::from_str_radix(x, 16).ok());
+                    if let Some(x) = x {
+                        step.v[x as usize] = Some(byte()?);
+                    }
+                }
+            }
+        }
+        Ok(step)
+    }
+}
+
+/// 两份日志第一次不同的地方
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// 第 n 步 (从 0 开始) 的状态不同，以及不同的字段名
+    Step { step: usize, fields: Vec<String> },
+    /// 一份日志在第 n 步结束，另一份还有后续
+    Length { step: usize },
+}
+
+/// 逐步比较两份日志，返回第一次不同的地方，完全一致时返回 None
+pub fn compare(a: &[Step], b: &[Step]) -> Option<Divergence> {
+    for (step, (a, b)) in a.iter().zip(b).enumerate() {
+        let fields = a.differences(b);
+        if !fields.is_empty() {
+            return Some(Divergence::Step { step, fields });
+        }
+    }
+    (a.len() != b.len()).then(|| Divergence::Length {
+        step: a.len().min(b.len()),
+    })
+}
+
+/// 解析一份日志，跳过空行和没有可识别字段的行
+pub fn parse(text: &str) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let step: Step = line.parse().map_err(|e| format!("line {}: {}", n + 1, e))?;
+        if !step.is_empty() {
+            steps.push(step);
+        }
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let mut chip = Chip::new(0);
+        // 200: LD V0, 1; 202: ADD V0, 1
+        chip.load_rom(0x200, &[0x60, 0x01, 0x70, 0x01]).unwrap();
+        let mut ours = vec![Step::capture(&chip)];
+        chip.step().unwrap();
+        ours.push(Step::capture(&chip));
+        chip.step().unwrap();
+        ours.push(Step::capture(&chip));
+
+        let text: String = ours.iter().map(|s| format!("{}\n", s)).collect();
+        assert_eq!(parse(&text).unwrap(), ours);
+        assert!(text.starts_with("PC=200 OP=6001 V0=00"));
+
+        // 另一个模拟器的日志格式，只有部分字段，第 3 步 V0 不同
+        let theirs = parse(
+            "# header\npc:0x0200 v:00000000000000000000000000000000\n\
+                            pc:0x0202 v0:$01\npc:0x0204 v0:$03\n",
+        )
+        .unwrap();
+        assert_eq!(theirs.len(), 3);
+        assert_eq!(compare(&ours, &ours), None);
+        assert_eq!(
+            compare(&ours, &theirs),
+            Some(Divergence::Step {
+                step: 2,
+                fields: vec!["V0".to_string()]
+            })
+        );
+        assert_eq!(
+            compare(&ours[..2], &theirs),
+            Some(Divergence::Length { step: 2 })
+        );
+        assert!(parse("PC=XYZ").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::input::KeyReceiver;
+use crate::{Chip, Exception, FrameStats, DISP_HEIGHT};
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// 一帧的输出
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Frame {
+    /// 帧结束时的画面，见 `Chip::framebuffer_rows`
+    pub rows: [u64; DISP_HEIGHT],
+    /// 帧结束时是否在发声
+    pub tone: bool,
+    pub stats: FrameStats,
+}
+
+/// 把虚拟机包装为帧的异步流：ticker 每产生一项就运行一帧，
+/// ticker 可以是任何异步运行时的定时器流，例如每 1/60 秒一项。
+/// 虚拟机出错时产生 Err，之后流结束；ticker 结束时流也结束
+pub struct FrameStream<T> {
+    chip: Chip,
+    ticker: T,
+    instructions: u32,          // 每帧执行的指令数
+    input: Option<KeyReceiver>, // 每帧开始时应用的按键
+    done: bool,
+}
+
+impl<T: Stream + Unpin> FrameStream<T> {
+    pub fn new(chip: Chip, ticker: T, instructions: u32) -> Self {
+        Self {
+            chip,
+            ticker,
+            instructions,
+            input: None,
+            done: false,
+        }
+    }
+
+    /// 设置按键来源，其他线程或任务通过 `input::channel` 的发送端输入按键
+    pub fn set_input(&mut self, input: KeyReceiver) {
+        self.input = Some(input);
+    }
+
+    pub fn chip(&self) -> &Chip {
+        &self.chip
+    }
+
+    pub fn chip_mut(&mut self) -> &mut Chip {
+        &mut self.chip
+    }
+
+    /// 取回虚拟机
+    pub fn into_inner(self) -> Chip {
+        self.chip
+    }
+}
+
+impl<T: Stream + Unpin> Stream for FrameStream<T> {
+    type Item = Result<Frame, Exception>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.done {
+            return Poll::Ready(None);
+        }
+        match Pin::new(&mut this.ticker).poll_next(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(None) => {
+                this.done = true;
+                Poll::Ready(None)
+            }
+            Poll::Ready(Some(_)) => {
+                if let Some(input) = &mut this.input {
+                    input.apply(&mut this.chip);
+                }
+                let frame = match this.chip.run_frame(this.instructions) {
+                    Ok(stats) => Ok(Frame {
+                        rows: *this.chip.framebuffer_rows(),
+                        tone: this.chip.tone(),
+                        stats,
+                    }),
+                    Err(e) => {
+                        this.done = true;
+                        Err(e)
+                    }
+                };
+                Poll::Ready(Some(frame))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input, ENTRY_ADDR};
+    use std::task::Waker;
+
+    /// 产生 n 项后结束的定时器
+    struct Ticks(u32);
+
+    impl Stream for Ticks {
+        type Item = ();
+
+        fn poll_next(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Option<()>> {
+            if self.0 == 0 {
+                return Poll::Ready(None);
+            }
+            self.0 -= 1;
+            Poll::Ready(Some(()))
+        }
+    }
+
+    fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        let mut cx = Context::from_waker(Waker::noop());
+        match Pin::new(stream).poll_next(&mut cx) {
+            Poll::Ready(item) => item,
+            Poll::Pending => panic!("pending"),
+        }
+    }
+
+    #[test]
+    fn test_frame_stream() {
+        // 等待按键 0 后画出字符 0，然后执行非法指令
+        let rom = [
+            0x60, 0x00, // 200: LD V0, 0
+            0xE0, 0x9E, // 202: SKP V0
+            0x12, 0x02, // 204: JP 202
+            0xD0, 0x05, // 206: DRW V0, V0, 5
+            0x61, 0x00, // 208: LD V1, 0
+            0xFF, 0xFF, // 20A: 非法指令
+        ];
+        let mut chip = Chip::new(0);
+        chip.load_rom(ENTRY_ADDR, &rom).unwrap();
+        let mut stream = FrameStream::new(chip, Ticks(10), 3);
+        let (mut tx, rx) = input::channel(4);
+        stream.set_input(rx);
+
+        let frame = next(&mut stream).unwrap().unwrap();
+        assert_eq!(frame.rows[0], 0);
+        tx.send(0, true).unwrap();
+        let frame = next(&mut stream).unwrap().unwrap();
+        assert_eq!(frame.rows[0], 0xF0 << 56);
+        assert_eq!(frame.stats.draws, 1);
+        assert!(next(&mut stream).unwrap().is_err());
+        assert_eq!(next(&mut stream), None);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::Instruction;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// 符号表，把地址映射为名字
+///
+/// 文件格式为每行一个 `<地址> <名字>`，地址为十六进制，可以带 0x 前缀，
+/// `;` 之后为注释。汇编器输出的 `.sym` 文件就是这个格式
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Symbols {
+    names: BTreeMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 解析符号表文件的内容
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut symbols = Self::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.split(';').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let (Some(addr), Some(name), None) = (words.next(), words.next(), words.next()) else {
+                return Err(format!("line {}: expected `<address> <name>`", n + 1));
+            };
+            let hex = addr
+                .strip_prefix("0x")
+                .or_else(|| addr.strip_prefix("0X"))
+                .unwrap_or(addr);
+            let addr = u16::from_str_radix(hex, 16)
+                .map_err(|_| format!("line {}: invalid address {:?}", n + 1, addr))?;
+            symbols.insert(addr, name);
+        }
+        Ok(symbols)
+    }
+
+    pub fn insert(&mut self, addr: u16, name: &str) {
+        self.names.insert(addr, name.to_string());
+    }
+
+    pub fn remove(&mut self, addr: u16) {
+        self.names.remove(&addr);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// 地址对应的名字
+    pub fn name(&self, addr: u16) -> Option<&str> {
+        self.names.get(&addr).map(String::as_str)
+    }
+
+    /// 名字对应的地址
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.names
+            .iter()
+            .find(|(_, n)| n.as_str() == name)
+            .map(|(addr, _)| *addr)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &str)> {
+        self.names.iter().map(|(addr, name)| (*addr, name.as_str()))
+    }
+
+    /// 显示地址，有名字时显示名字
+    pub fn format_addr(&self, addr: u16) -> String {
+        match self.name(addr) {
+            Some(name) => name.to_string(),
+            None => format!("0x{:03X}", addr),
+        }
+    }
+
+    /// 反汇编指令，把其中的地址替换为名字
+    pub fn format(&self, ins: Instruction) -> String {
+        match ins {
+            Instruction::Jp(nnn) => format!("JP {}", self.format_addr(nnn.get())),
+            Instruction::Call(nnn) => format!("CALL {}", self.format_addr(nnn.get())),
+            Instruction::LdI(nnn) => format!("LD I, {}", self.format_addr(nnn.get())),
+            Instruction::JpV0(nnn) => format!("JP V0, {}", self.format_addr(nnn.get())),
+            _ => ins.to_string(),
+        }
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<(S, u16)> for Symbols {
+    fn from_iter<T: IntoIterator<Item = (S, u16)>>(iter: T) -> Self {
+        let mut symbols = Self::new();
+        for (name, addr) in iter {
+            symbols.insert(addr, name.as_ref());
+        }
+        symbols
+    }
+}
+
+impl fmt::Display for Symbols {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (addr, name) in self.iter() {
+            writeln!(f, "0x{:03X} {}", addr, name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Addr;
+
+    #[test]
+    fn test_symbols() {
+        let symbols = Symbols::parse("; 注释\n0x200 main\n20A draw_player ; 绘制\n\n").unwrap();
+        assert_eq!(symbols.name(0x20A), Some("draw_player"));
+        assert_eq!(symbols.address("main"), Some(0x200));
+        assert_eq!(
+            symbols.format(Instruction::Call(Addr::from_bits(0x20A))),
+            "CALL draw_player"
+        );
+        assert_eq!(
+            symbols.format(Instruction::Jp(Addr::from_bits(0x300))),
+            "JP 0x300"
+        );
+        assert_eq!(Symbols::parse(&symbols.to_string()).unwrap(), symbols);
+
+        assert!(Symbols::parse("main").is_err());
+        assert!(Symbols::parse("xyz main").is_err());
+    }
+}
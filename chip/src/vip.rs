@@ -0,0 +1,110 @@
+use crate::{Chip, Exception, FrameStats, Instruction, DISP_HEIGHT, DISP_WIDTH};
+
+/// NTSC 每帧的扫描线数
+const FRAME_LINES: u32 = 262;
+/// 中断 (帧开始) 之后到 CDP1861 开始显示的扫描线数
+const DISPLAY_START: u32 = 30;
+/// 每个 CHIP-8 像素行重复显示 4 条扫描线，共 128 条
+const LINES_PER_ROW: u32 = 4;
+/// 显示期间 DMA 占用了大部分机器周期，CPU 只剩下大约 6/14 的时间
+const DMA_SPEED: f32 = 6.0 / 14.0;
+
+/// 模拟 COSMAC VIP 由中断驱动的显示刷新
+///
+/// 每帧开始时产生中断，定时器在中断中递减；之后 CDP1861 逐行从显存取数据显示，
+/// 每一行显示的是扫描到该行时帧缓冲的内容，因此在显示期间的修改会造成画面撕裂。
+/// 与原版解释器一样，DXYN 会等待下一次中断后才执行，
+/// 所以每帧最多绘制一次，先擦除再重绘的精灵会闪烁
+#[derive(Debug, Clone)]
+pub struct VipDisplay {
+    screen: [bool; DISP_WIDTH * DISP_HEIGHT],
+}
+
+impl Default for VipDisplay {
+    fn default() -> Self {
+        Self {
+            screen: [false; DISP_WIDTH * DISP_HEIGHT],
+        }
+    }
+}
+
+impl VipDisplay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 运行一帧，instructions 为没有 DMA 时一帧可以执行的指令数
+    pub fn run_frame(
+        &mut self,
+        chip: &mut Chip,
+        instructions: u32,
+    ) -> Result<FrameStats, Exception> {
+        chip.tick_timers();
+
+        let display_end = DISPLAY_START + DISP_HEIGHT as u32 * LINES_PER_ROW;
+        let display_lines = display_end - DISPLAY_START;
+        let line_cost = instructions as f32
+            / ((FRAME_LINES - display_lines) as f32 + display_lines as f32 * DMA_SPEED);
+
+        let mut budget = 0.0;
+        let mut stats = FrameStats {
+            stack_peak: chip.stack().len(),
+            sound: chip.tone(),
+            ..FrameStats::default()
+        };
+        let mut waiting = false; // 遇到 DXYN，等待下一次中断
+        for line in 0..FRAME_LINES {
+            let displaying = (DISPLAY_START..display_end).contains(&line);
+            if displaying && (line - DISPLAY_START).is_multiple_of(LINES_PER_ROW) {
+                let row = ((line - DISPLAY_START) / LINES_PER_ROW) as usize * DISP_WIDTH;
+                self.screen[row..row + DISP_WIDTH]
+                    .copy_from_slice(&chip.framebuffer()[row..row + DISP_WIDTH]);
+            }
+
+            budget += if displaying {
+                line_cost * DMA_SPEED
+            } else {
+                line_cost
+            };
+            while !waiting && budget >= 1.0 {
+                let draw = chip
+                    .opcode_at(chip.pc())
+                    .is_some_and(|op| matches!(Instruction::decode(op), Instruction::Drw(..)));
+                if draw && stats.instructions > 0 {
+                    waiting = true;
+                    break;
+                }
+                chip.step()?;
+                stats.record_step(chip);
+                budget -= 1.0;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// 获取最近一帧显示的画面
+    pub fn screen(&self) -> &[bool] {
+        &self.screen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_draw_waits_for_interrupt() {
+        let mut chip = Chip::new(0);
+        // 200: DRW V0, V0, 1; 202: DRW V0, V0, 1; 204: JP 0x200，I 指向字体 0 的第一行
+        chip.load_rom(0x200, &[0xD0, 0x01, 0xD0, 0x01, 0x12, 0x00])
+            .unwrap();
+        let mut vip = VipDisplay::new();
+
+        // 每帧只绘制一次，画面交替显示和消失
+        assert_eq!(vip.run_frame(&mut chip, 100).unwrap().instructions, 1);
+        assert!(vip.screen()[0]);
+        assert_eq!(vip.run_frame(&mut chip, 100).unwrap().draws, 1);
+        assert!(!vip.screen()[0]);
+        assert_eq!(chip.pc(), 0x200);
+    }
+}
@@ -0,0 +1,142 @@
+use std::fmt::Write;
+
+/// XO-CHIP 音频缓冲的字节数，共 128 位
+pub const PATTERN_SIZE: usize = 16;
+const PATTERN_BITS: usize = PATTERN_SIZE * 8;
+
+/// XO-CHIP 的音高寄存器 (FX3A) 对应的播放速率 (位/秒)
+pub fn pitch_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+/// 最接近播放速率 rate 的音高，超出范围时返回 None
+pub fn rate_pitch(rate: f32) -> Option<u8> {
+    let pitch = (64.0 + 48.0 * (rate / 4000.0).log2()).round();
+    (0.0..=255.0).contains(&pitch).then_some(pitch as u8)
+}
+
+/// 把声音重采样到音高对应的速率并量化为 1 位，按 16 字节一个缓冲切分，
+/// 最后一个缓冲不足时补 0
+///
+/// samples 为单声道采样，先归一化到满幅度。量化使用一阶 sigma-delta，
+/// 保留比简单的过零判断更多的音量变化
+pub fn convert(samples: &[f32], sample_rate: u32, pitch: u8) -> Vec<[u8; PATTERN_SIZE]> {
+    let step = sample_rate as f32 / pitch_rate(pitch);
+    let bits = (samples.len() as f32 / step) as usize;
+    // 先去掉直流分量，否则一直偏向 0 或 1
+    let mean = samples.iter().sum::<f32>() / samples.len().max(1) as f32;
+    let peak = samples.iter().map(|s| (s - mean).abs()).fold(0.0, f32::max);
+    let gain = if peak > 0.0 { 1.0 / peak } else { 0.0 };
+    let mut error = 0.0;
+    let mut patterns = vec![[0; PATTERN_SIZE]; bits.div_ceil(PATTERN_BITS)];
+    for n in 0..bits {
+        let pos = n as f32 * step;
+        let (i, frac) = (pos as usize, pos.fract());
+        let next = samples.get(i + 1).unwrap_or(&samples[i]);
+        let sample = (samples[i] + (next - samples[i]) * frac - mean) * gain;
+        error += sample;
+        if error > 0.0 {
+            patterns[n / PATTERN_BITS][n % PATTERN_BITS / 8] |= 0x80 >> (n % 8);
+            error -= 1.0;
+        } else {
+            error += 1.0;
+        }
+    }
+    patterns
+}
+
+/// 用自相关估计声音的基频 (Hz)，找不到明显的周期时返回 None
+pub fn detect_frequency(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    // 只分析开头的一段，范围为 50 Hz 到 2 kHz
+    let window = &samples[..samples.len().min(sample_rate as usize / 5)];
+    let (min_lag, max_lag) = (sample_rate as usize / 2000, sample_rate as usize / 50);
+    let mean = window.iter().sum::<f32>() / window.len().max(1) as f32;
+    let correlation = |lag: usize| -> f32 {
+        window
+            .iter()
+            .zip(&window[lag..])
+            .map(|(a, b)| (a - mean) * (b - mean))
+            .sum::<f32>()
+            / (window.len() - lag) as f32
+    };
+    let energy = correlation(0);
+    if energy <= 0.0 || window.len() <= max_lag {
+        return None;
+    }
+    // 取第一个足够高的峰，避免把基频的倍数当成周期
+    let values: Vec<f32> = (min_lag.max(1)..=max_lag).map(correlation).collect();
+    let best = values.iter().cloned().fold(f32::MIN, f32::max);
+    if best < 0.5 * energy {
+        return None;
+    }
+    let mut n = values.iter().position(|v| *v >= 0.9 * best)?;
+    while n + 1 < values.len() && values[n + 1] > values[n] {
+        n += 1;
+    }
+    // 用相邻的三个点做抛物线插值，得到小数的周期
+    let offset = match (n.checked_sub(1).map(|p| values[p]), values.get(n + 1)) {
+        (Some(a), Some(&c)) if a - 2.0 * values[n] + c != 0.0 => {
+            0.5 * (a - c) / (a - 2.0 * values[n] + c)
+        }
+        _ => 0.0,
+    };
+    let lag = (n + min_lag.max(1)) as f32 + offset;
+    Some(sample_rate as f32 / lag)
+}
+
+/// 循环播放一个缓冲来发出 frequency 的音调时可用的音高：
+/// 每项为 (音高, 缓冲中的周期数, 实际频率)，按误差从小到大排列，
+/// 误差相同时优先接近默认音高 64 的
+pub fn suggest_pitches(frequency: f32) -> Vec<(u8, usize, f32)> {
+    let mut suggestions: Vec<(u8, usize, f32)> = (1..=PATTERN_BITS / 2)
+        .filter_map(|periods| {
+            let pitch = rate_pitch(frequency * PATTERN_BITS as f32 / periods as f32)?;
+            let actual = pitch_rate(pitch) * periods as f32 / PATTERN_BITS as f32;
+            Some((pitch, periods, actual))
+        })
+        .collect();
+    suggestions.sort_by(|a, b| {
+        let error = |s: &(u8, usize, f32)| (s.2 - frequency).abs();
+        error(a)
+            .total_cmp(&error(b))
+            .then((a.0 as i32 - 64).abs().cmp(&(b.0 as i32 - 64).abs()))
+    });
+    suggestions
+}
+
+/// 输出汇编器的 `DB` 行，每个缓冲一个标签
+pub fn to_asm(name: &str, patterns: &[[u8; PATTERN_SIZE]]) -> String {
+    let mut out = String::new();
+    for (n, pattern) in patterns.iter().enumerate() {
+        let bytes: Vec<String> = pattern.iter().map(|b| format!("0x{:02X}", b)).collect();
+        let _ = writeln!(out, "{}_{}:", name, n);
+        let _ = writeln!(out, "    DB {}", bytes[..8].join(", "));
+        let _ = writeln!(out, "    DB {}", bytes[8..].join(", "));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        assert_eq!(pitch_rate(64), 4000.0);
+        assert_eq!(rate_pitch(8000.0), Some(112));
+        assert_eq!(rate_pitch(1e9), None);
+
+        // 4000 Hz 采样的 500 Hz 方波，音高 64 时每 8 位一个周期
+        let samples: Vec<f32> = (0..400)
+            .map(|n| if n % 8 < 4 { 0.5 } else { -0.5 })
+            .collect();
+        let patterns = convert(&samples, 4000, 64);
+        assert_eq!(patterns.len(), 4);
+        assert_eq!(patterns[1], [0xF0; PATTERN_SIZE]);
+        assert_eq!(patterns[3][2..], [0; 14]);
+
+        let frequency = detect_frequency(&samples, 4000).unwrap();
+        assert!((frequency - 500.0).abs() < 1.0);
+        assert_eq!(suggest_pitches(500.0)[0], (64, 16, 500.0));
+    }
+}
@@ -0,0 +1,230 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem::size_of;
+use std::os::raw::c_long;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// evdev 的事件类型：按键
+const EV_KEY: u16 = 1;
+/// Esc 的按键码，用于退出
+const KEY_ESC: u16 = 1;
+
+/// Linux 帧缓冲设备 (/dev/fb0)，不需要 X 或 Wayland 就可以显示画面
+pub struct Framebuffer {
+    file: File,
+    width: usize,
+    height: usize,
+    stride: usize,          // 每行的字节数
+    bytes_per_pixel: usize, // 每个像素的字节数，支持 2 (RGB565) 和 4 (XRGB8888)
+    buffer: Vec<u8>,
+}
+
+impl Framebuffer {
+    /// 打开帧缓冲设备，分辨率和像素格式从 /sys/class/graphics 读取
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| invalid("invalid framebuffer device".to_string()))?;
+        let sys = Path::new("/sys/class/graphics").join(name);
+        let read = |file: &str| -> io::Result<String> {
+            Ok(fs::read_to_string(sys.join(file))?.trim().to_string())
+        };
+        let size = read("virtual_size")?;
+        let (width, height) = size
+            .split_once(',')
+            .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+            .ok_or_else(|| invalid(format!("invalid virtual_size {:?}", size)))?;
+        let bits: usize = read("bits_per_pixel")?
+            .parse()
+            .map_err(|_| invalid("invalid bits_per_pixel".to_string()))?;
+        if bits != 16 && bits != 32 {
+            return Err(invalid(format!("unsupported {} bits per pixel", bits)));
+        }
+        let stride = match read("stride") {
+            Ok(stride) => stride
+                .parse()
+                .map_err(|_| invalid("invalid stride".to_string()))?,
+            Err(_) => width * bits / 8,
+        };
+        let file = OpenOptions::new().write(true).open(path)?;
+        Ok(Self {
+            file,
+            width,
+            height,
+            stride,
+            bytes_per_pixel: bits / 8,
+            buffer: vec![0; stride * height],
+        })
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// 按最大的整数倍缩放画面并居中绘制，fg 和 bg 为点亮像素和背景的 RGB 颜色
+    pub fn draw(
+        &mut self,
+        screen: &[bool],
+        screen_width: usize,
+        fg: (u8, u8, u8),
+        bg: (u8, u8, u8),
+    ) -> io::Result<()> {
+        let screen_height = screen.len() / screen_width;
+        let scale = (self.width / screen_width)
+            .min(self.height / screen_height)
+            .max(1);
+        let left = self.width.saturating_sub(screen_width * scale) / 2;
+        let top = self.height.saturating_sub(screen_height * scale) / 2;
+        let fg = pack(fg, self.bytes_per_pixel);
+        let bg = pack(bg, self.bytes_per_pixel);
+
+        let bpp = self.bytes_per_pixel;
+        for y in 0..self.height {
+            let row = &mut self.buffer[y * self.stride..y * self.stride + self.width * bpp];
+            let sy = (y >= top).then(|| (y - top) / scale);
+            for (x, pixel) in row.chunks_exact_mut(bpp).enumerate() {
+                let sx = (x >= left).then(|| (x - left) / scale);
+                let on = match (sx, sy) {
+                    (Some(sx), Some(sy)) if sx < screen_width && sy < screen_height => {
+                        screen[sy * screen_width + sx]
+                    }
+                    _ => false,
+                };
+                pixel.copy_from_slice(if on { &fg[..bpp] } else { &bg[..bpp] });
+            }
+        }
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(&self.buffer)
+    }
+}
+
+/// 把 RGB 颜色转换为帧缓冲的像素格式 (小端)
+fn pack((r, g, b): (u8, u8, u8), bytes_per_pixel: usize) -> [u8; 4] {
+    if bytes_per_pixel == 2 {
+        let rgb565 = (r as u16 >> 3) << 11 | (g as u16 >> 2) << 5 | b as u16 >> 3;
+        let [lo, hi] = rgb565.to_le_bytes();
+        [lo, hi, 0, 0]
+    } else {
+        [b, g, r, 0xFF]
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// 键盘事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEvent {
+    /// CHIP-8 按键按下或松开
+    Key(u8, bool),
+    /// 按下了 Esc
+    Quit,
+}
+
+/// 从 evdev 设备 (例如 /dev/input/event0) 读取键盘，
+/// 按键布局与 SDL 前端的默认映射相同 (1234/QWER/ASDF/ZXCV)
+pub struct Keyboard {
+    events: Receiver<KeyEvent>,
+}
+
+impl Keyboard {
+    /// 打开输入设备，在后台线程中读取事件
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let (tx, events) = mpsc::channel();
+        thread::spawn(move || {
+            let mut event = [0u8; EVENT_SIZE];
+            while file.read_exact(&mut event).is_ok() {
+                if let Some(event) = parse_event(&event) {
+                    if tx.send(event).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Self { events })
+    }
+
+    /// 取出所有尚未处理的事件
+    pub fn poll(&self) -> impl Iterator<Item = KeyEvent> + '_ {
+        self.events.try_iter()
+    }
+}
+
+/// struct input_event 的大小：struct timeval、type、code 和 value
+const EVENT_SIZE: usize = 2 * size_of::<c_long>() + 8;
+
+/// 解析 struct input_event，忽略按键重复和无关的按键
+fn parse_event(event: &[u8; EVENT_SIZE]) -> Option<KeyEvent> {
+    let data = &event[EVENT_SIZE - 8..];
+    let kind = u16::from_ne_bytes([data[0], data[1]]);
+    let code = u16::from_ne_bytes([data[2], data[3]]);
+    let value = i32::from_ne_bytes([data[4], data[5], data[6], data[7]]);
+    if kind != EV_KEY || value == 2 {
+        return None;
+    }
+    if code == KEY_ESC {
+        return (value == 1).then_some(KeyEvent::Quit);
+    }
+    keypad(code).map(|key| KeyEvent::Key(key, value == 1))
+}
+
+/// Linux 按键码到 CHIP-8 按键的映射
+fn keypad(code: u16) -> Option<u8> {
+    let key = match code {
+        2 => 0x1,  // 1
+        3 => 0x2,  // 2
+        4 => 0x3,  // 3
+        5 => 0xC,  // 4
+        16 => 0x4, // Q
+        17 => 0x5, // W
+        18 => 0x6, // E
+        19 => 0xD, // R
+        30 => 0x7, // A
+        31 => 0x8, // S
+        32 => 0x9, // D
+        33 => 0xE, // F
+        44 => 0xA, // Z
+        45 => 0x0, // X
+        46 => 0xB, // C
+        47 => 0xF, // V
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: u16, code: u16, value: i32) -> [u8; EVENT_SIZE] {
+        let mut event = [0u8; EVENT_SIZE];
+        let data = &mut event[EVENT_SIZE - 8..];
+        data[..2].copy_from_slice(&kind.to_ne_bytes());
+        data[2..4].copy_from_slice(&code.to_ne_bytes());
+        data[4..].copy_from_slice(&value.to_ne_bytes());
+        event
+    }
+
+    #[test]
+    fn test_parse_event() {
+        assert_eq!(
+            parse_event(&event(EV_KEY, 16, 1)),
+            Some(KeyEvent::Key(0x4, true))
+        );
+        assert_eq!(
+            parse_event(&event(EV_KEY, 45, 0)),
+            Some(KeyEvent::Key(0x0, false))
+        );
+        assert_eq!(parse_event(&event(EV_KEY, 16, 2)), None);
+        assert_eq!(
+            parse_event(&event(EV_KEY, KEY_ESC, 1)),
+            Some(KeyEvent::Quit)
+        );
+        assert_eq!(parse_event(&event(0, 0, 0)), None);
+        assert_eq!(pack((0xFF, 0xFF, 0xFF), 2), [0xFF, 0xFF, 0, 0]);
+    }
+}
@@ -0,0 +1,137 @@
+use std::str::FromStr;
+
+/// 未指定频率时的连发频率 (每秒按下次数)
+pub const DEFAULT_RATE: f32 = 10.0;
+
+/// 一个连发按键及其频率，解析 `按键[:频率]` 形式，例如 `5` 或 `5:15`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutofireKey {
+    pub key: u8,
+    pub rate: f32,
+}
+
+impl FromStr for AutofireKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, rate) = match s.split_once(':') {
+            Some((key, rate)) => (key, Some(rate)),
+            None => (s, None),
+        };
+        let key = u8::from_str_radix(key.trim(), 16)
+            .ok()
+            .filter(|key| *key < 16)
+            .ok_or_else(|| format!("invalid CHIP-8 key {:?}", key))?;
+        let rate = match rate {
+            Some(rate) => rate
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|rate| (0.5..=30.0).contains(rate))
+                .ok_or_else(|| {
+                    format!("invalid autofire rate {:?} (0.5 to 30 per second)", rate)
+                })?,
+            None => DEFAULT_RATE,
+        };
+        Ok(Self { key, rate })
+    }
+}
+
+/// 按键连发：按住打开了连发的按键时，虚拟机看到的是按频率交替的按下和松开
+#[derive(Debug, Clone)]
+pub struct Autofire {
+    rates: [Option<f32>; 16],
+    enabled: bool,
+    held: [f32; 16], // 每个按键按住的时间 (秒，虚拟机时间)
+}
+
+impl Default for Autofire {
+    fn default() -> Self {
+        Self {
+            rates: [None; 16],
+            enabled: true,
+            held: [0.0; 16],
+        }
+    }
+}
+
+impl Autofire {
+    /// 打开按键的连发
+    pub fn set(&mut self, key: AutofireKey) {
+        self.rates[key.key as usize & 0xF] = Some(key.rate);
+    }
+
+    /// 是否设置了连发按键
+    pub fn is_configured(&self) -> bool {
+        self.rates.iter().any(Option::is_some)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 打开或关闭所有按键的连发
+    pub fn toggle(&mut self) {
+        self.enabled = !self.enabled;
+    }
+
+    /// 按键刚被按下，从按下状态开始连发
+    pub fn press(&mut self, key: u8) {
+        self.held[key as usize & 0xF] = 0.0;
+    }
+
+    /// 所有按键的按住时间前进 elapsed 秒，返回打开了连发的按键 (第 n 位为按键 n)
+    pub fn advance(&mut self, elapsed: f32) -> u16 {
+        let mut keys = 0;
+        for (key, rate) in self.rates.iter().enumerate() {
+            if rate.is_some() {
+                self.held[key] += elapsed;
+                keys |= 1 << key;
+            }
+        }
+        keys
+    }
+
+    /// 按住按键时虚拟机看到的状态，每个周期的前一半按下，后一半松开
+    pub fn output(&self, key: u8) -> bool {
+        match self.rates[key as usize & 0xF] {
+            Some(rate) if self.enabled => (self.held[key as usize & 0xF] * rate).fract() < 0.5,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_autofire() {
+        let mut autofire = Autofire::default();
+        assert!(!autofire.is_configured());
+        autofire.set("5:10".parse().unwrap());
+        assert!(autofire.is_configured());
+
+        autofire.press(5);
+        assert!(autofire.output(5));
+        assert_eq!(autofire.advance(0.06), 1 << 5);
+        assert!(!autofire.output(5));
+        autofire.advance(0.05);
+        assert!(autofire.output(5));
+        // 没有连发的按键和关闭连发时保持按下
+        assert!(autofire.output(6));
+        autofire.toggle();
+        autofire.advance(0.05);
+        assert!(autofire.output(5));
+
+        assert_eq!(
+            "a".parse::<AutofireKey>(),
+            Ok(AutofireKey {
+                key: 0xA,
+                rate: DEFAULT_RATE
+            })
+        );
+        assert!("5:100".parse::<AutofireKey>().is_err());
+        assert!("G".parse::<AutofireKey>().is_err());
+    }
+}
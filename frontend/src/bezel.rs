@@ -0,0 +1,117 @@
+use sdl2::rect::Rect;
+use std::str::FromStr;
+
+/// 画面在背景图中的位置和大小 (背景图像素)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenArea {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 解析 `x,y,宽,高` 形式的区域，例如 `120,80,640,320`
+impl FromStr for ScreenArea {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+            .filter(|v| v.len() == 4 && v[2] > 0 && v[3] > 0)
+            .ok_or_else(|| format!("invalid screen area: {} (expected x,y,width,height)", s))?;
+        Ok(ScreenArea {
+            x: values[0],
+            y: values[1],
+            width: values[2],
+            height: values[3],
+        })
+    }
+}
+
+/// 画在模拟画面后面的背景图 (机框、面板等)，画面显示在其中的 screen 区域
+#[derive(Debug, Clone)]
+pub struct Bezel {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>, // 按行排列的 RGBA 像素
+    screen: ScreenArea,
+}
+
+impl Bezel {
+    /// rgba 为按行排列的 RGBA 像素，画面默认占满整个背景图
+    pub fn new(width: u32, height: u32, rgba: Vec<u8>) -> Result<Self, String> {
+        if width == 0 || height == 0 || rgba.len() != (width * height * 4) as usize {
+            return Err("invalid bezel image size".to_string());
+        }
+        Ok(Self {
+            width,
+            height,
+            rgba,
+            screen: ScreenArea {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            },
+        })
+    }
+
+    /// 设置画面在背景图中的位置，不能超出背景图
+    pub fn set_screen(&mut self, area: ScreenArea) -> Result<(), String> {
+        if area.x + area.width > self.width || area.y + area.height > self.height {
+            return Err(format!(
+                "screen area {},{},{},{} is outside the {}x{} bezel",
+                area.x, area.y, area.width, area.height, self.width, self.height
+            ));
+        }
+        self.screen = area;
+        Ok(())
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// 背景图按比例缩放到窗口中并居中，返回背景图和画面在窗口中的位置
+    pub fn layout(&self, width: u32, height: u32) -> (Rect, Rect) {
+        let scale = (width as f32 / self.width as f32).min(height as f32 / self.height as f32);
+        let size = |n: u32| ((n as f32 * scale).round() as u32).max(1);
+        let (w, h) = (size(self.width), size(self.height));
+        let left = (width.saturating_sub(w) / 2) as i32;
+        let top = (height.saturating_sub(h) / 2) as i32;
+        let screen = Rect::new(
+            left + (self.screen.x as f32 * scale).round() as i32,
+            top + (self.screen.y as f32 * scale).round() as i32,
+            size(self.screen.width),
+            size(self.screen.height),
+        );
+        (Rect::new(left, top, w, h), screen)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bezel_layout() {
+        let mut bezel = Bezel::new(200, 100, vec![0; 200 * 100 * 4]).unwrap();
+        bezel.set_screen("40,20,128,64".parse().unwrap()).unwrap();
+        // 窗口比背景图高，背景图放大一倍后上下居中
+        let (image, screen) = bezel.layout(400, 300);
+        assert_eq!(image, Rect::new(0, 50, 400, 200));
+        assert_eq!(screen, Rect::new(80, 90, 256, 128));
+
+        assert!(bezel.set_screen("100,50,128,64".parse().unwrap()).is_err());
+        assert!("1,2,3".parse::<ScreenArea>().is_err());
+        assert!("1,2,0,4".parse::<ScreenArea>().is_err());
+        assert!(Bezel::new(2, 2, vec![0; 4]).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+use crate::KeypadSource;
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 聊天控制的参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChatConfig {
+    /// 投票窗口，每个窗口结束时按下得票最多的键，每个用户每个窗口只能投一票
+    pub vote_window: Duration,
+    /// 按键按下后保持的时间
+    pub hold_time: Duration,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        Self {
+            vote_window: Duration::from_secs(2),
+            hold_time: Duration::from_millis(200),
+        }
+    }
+}
+
+/// 一条聊天消息
+struct ChatMessage {
+    user: String,
+    text: String,
+}
+
+/// 从聊天室读取命令并转换为按键的输入源 ("Twitch Plays" 模式)
+///
+/// 消息内容为一个十六进制数字 (可以带 '!' 前缀，例如 `!5`) 时视为对该键的投票
+pub struct ChatInput {
+    rx: Receiver<ChatMessage>,
+    config: ChatConfig,
+    votes: [u32; 16],
+    voters: HashSet<String>,     // 本窗口内已投票的用户
+    window_start: Instant,       // 本窗口的开始时间
+    held: Option<(u8, Instant)>, // 当前按下的键及其松开时间
+}
+
+impl ChatInput {
+    /// 连接一个按行发送消息的 TCP 数据源，每行格式为 `<用户> <消息>`
+    pub fn connect_lines<A: ToSocketAddrs>(addr: A, config: ChatConfig) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stream).lines() {
+                let Ok(line) = line else { break };
+                let (user, text) = line.trim().split_once(' ').unwrap_or(("", line.trim()));
+                let msg = ChatMessage {
+                    user: user.to_string(),
+                    text: text.to_string(),
+                };
+                if tx.send(msg).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self::from_receiver(rx, config))
+    }
+
+    /// 连接 IRC 服务器 (例如 Twitch 的 irc.chat.twitch.tv:6667) 并加入频道
+    pub fn connect_irc<A: ToSocketAddrs>(
+        addr: A,
+        channel: &str,
+        nick: &str,
+        password: Option<&str>,
+        config: ChatConfig,
+    ) -> io::Result<Self> {
+        let mut stream = TcpStream::connect(addr)?;
+        if let Some(password) = password {
+            write!(stream, "PASS {}\r\n", password)?;
+        }
+        write!(stream, "NICK {}\r\n", nick)?;
+        let channel = if channel.starts_with('#') {
+            channel.to_string()
+        } else {
+            format!("#{}", channel)
+        };
+        write!(stream, "JOIN {}\r\n", channel)?;
+
+        let (tx, rx) = mpsc::channel();
+        let reader = BufReader::new(stream.try_clone()?);
+        thread::spawn(move || irc_loop(reader, stream, tx));
+
+        Ok(Self::from_receiver(rx, config))
+    }
+
+    fn from_receiver(rx: Receiver<ChatMessage>, config: ChatConfig) -> Self {
+        Self {
+            rx,
+            config,
+            votes: [0; 16],
+            voters: HashSet::new(),
+            window_start: Instant::now(),
+            held: None,
+        }
+    }
+
+    fn poll_at(&mut self, now: Instant) -> Vec<(u8, bool)> {
+        while let Ok(msg) = self.rx.try_recv() {
+            if let Some(key) = parse_vote(&msg.text) {
+                if self.voters.insert(msg.user) {
+                    self.votes[key as usize] += 1;
+                }
+            }
+        }
+
+        let mut events = Vec::new();
+        if let Some((key, release)) = self.held {
+            if now >= release {
+                events.push((key, false));
+                self.held = None;
+            }
+        }
+
+        if now.duration_since(self.window_start) >= self.config.vote_window {
+            // 得票相同时取编号较小的键
+            let winner = (0..16u8)
+                .filter(|key| self.votes[*key as usize] > 0)
+                .max_by_key(|key| (self.votes[*key as usize], std::cmp::Reverse(*key)));
+            if let Some(key) = winner {
+                if let Some((held, _)) = self.held.take() {
+                    events.push((held, false));
+                }
+                events.push((key, true));
+                self.held = Some((key, now + self.config.hold_time));
+            }
+            self.votes = [0; 16];
+            self.voters.clear();
+            self.window_start = now;
+        }
+
+        events
+    }
+}
+
+impl KeypadSource for ChatInput {
+    fn poll(&mut self) -> Vec<(u8, bool)> {
+        self.poll_at(Instant::now())
+    }
+}
+
+/// 读取 IRC 消息，回应 PING 并转发频道消息
+fn irc_loop(reader: BufReader<TcpStream>, mut stream: TcpStream, tx: Sender<ChatMessage>) {
+    for line in reader.lines() {
+        let Ok(line) = line else { break };
+        if let Some(server) = line.strip_prefix("PING") {
+            if write!(stream, "PONG{}\r\n", server).is_err() {
+                break;
+            }
+            continue;
+        }
+        // :nick!user@host PRIVMSG #channel :message
+        let Some(rest) = line.strip_prefix(':') else {
+            continue;
+        };
+        let Some((prefix, rest)) = rest.split_once(' ') else {
+            continue;
+        };
+        let Some(rest) = rest.strip_prefix("PRIVMSG ") else {
+            continue;
+        };
+        let Some((_, text)) = rest.split_once(" :") else {
+            continue;
+        };
+        let user = prefix.split('!').next().unwrap_or(prefix);
+        let msg = ChatMessage {
+            user: user.to_string(),
+            text: text.to_string(),
+        };
+        if tx.send(msg).is_err() {
+            break;
+        }
+    }
+}
+
+/// 解析投票命令，返回对应的 CHIP-8 按键
+fn parse_vote(text: &str) -> Option<u8> {
+    let text = text.trim();
+    let text = text.strip_prefix('!').unwrap_or(text);
+    if text.len() != 1 {
+        return None;
+    }
+    u8::from_str_radix(text, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vote_window() {
+        let (tx, rx) = mpsc::channel();
+        let config = ChatConfig {
+            vote_window: Duration::from_secs(1),
+            hold_time: Duration::from_millis(100),
+        };
+        let mut chat = ChatInput::from_receiver(rx, config);
+        let start = chat.window_start;
+        let send = |user: &str, text: &str| {
+            tx.send(ChatMessage {
+                user: user.to_string(),
+                text: text.to_string(),
+            })
+            .unwrap()
+        };
+
+        send("alice", "!5");
+        send("alice", "!5"); // 同一用户在窗口内只算一票
+        send("bob", "a");
+        send("carol", "A");
+        send("dave", "hello");
+        assert!(chat.poll_at(start + Duration::from_millis(500)).is_empty());
+        assert_eq!(chat.poll_at(start + Duration::from_secs(1)), [(0xA, true)]);
+        assert_eq!(
+            chat.poll_at(start + Duration::from_millis(1100)),
+            [(0xA, false)]
+        );
+
+        // 没有投票的窗口不会按键
+        assert!(chat.poll_at(start + Duration::from_secs(2)).is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+use sdl2::keyboard::Keycode;
+use std::str::FromStr;
+
+/// 宏的一步：同时按住的 CHIP-8 按键 (第 n 位为按键 n) 和按住的时间
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Step {
+    keys: u16,
+    seconds: f32,
+}
+
+/// 解析 `按键 毫秒` 形式的一步，多个按键用 '+' 连接，'-' 表示不按任何键，例如 `5+6 100`
+impl FromStr for Step {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || {
+            format!(
+                "invalid macro step {:?} (expected keys and milliseconds)",
+                s
+            )
+        };
+        let (keys, ms) = s.trim().split_once(' ').ok_or_else(error)?;
+        let ms: u32 = ms.trim().parse().map_err(|_| error())?;
+        let mut mask = 0;
+        if keys != "-" {
+            for key in keys.split('+') {
+                let key = u8::from_str_radix(key, 16)
+                    .ok()
+                    .filter(|key| *key < 16)
+                    .ok_or_else(|| format!("invalid CHIP-8 key {:?} in macro", key))?;
+                mask |= 1 << key;
+            }
+        }
+        Ok(Step {
+            keys: mask,
+            seconds: ms as f32 / 1000.0,
+        })
+    }
+}
+
+/// 输入宏：按下一个主机按键时按顺序按下和松开一串 CHIP-8 按键，例如开始游戏的组合键
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputMacro {
+    key: Keycode,
+    steps: Vec<Step>,
+}
+
+impl InputMacro {
+    /// key 为触发宏的 SDL 按键名，sequence 为逗号分隔的步骤，
+    /// 例如 `"5 100, - 500, 5+6 100"`：按住 5 100 毫秒，松开 500 毫秒，再同时按住 5 和 6
+    pub fn new(key: &str, sequence: &str) -> Result<Self, String> {
+        let key = Keycode::from_name(key).ok_or_else(|| format!("unknown key name: {}", key))?;
+        let steps = sequence
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<Step>, _>>()?;
+        Ok(Self { key, steps })
+    }
+
+    pub fn key(&self) -> Keycode {
+        self.key
+    }
+
+    /// 开始播放 elapsed 秒 (虚拟机时间) 后应按住的按键，播放结束后返回 None
+    pub fn keys_at(&self, elapsed: f32) -> Option<u16> {
+        let mut end = 0.0;
+        for step in &self.steps {
+            end += step.seconds;
+            if elapsed < end {
+                return Some(step.keys);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_macro() {
+        let m = InputMacro::new("F9", "5 100, - 50, 5+A 200").unwrap();
+        assert_eq!(m.key(), Keycode::F9);
+        assert_eq!(m.keys_at(0.0), Some(1 << 5));
+        assert_eq!(m.keys_at(0.12), Some(0));
+        assert_eq!(m.keys_at(0.2), Some(1 << 5 | 1 << 0xA));
+        assert_eq!(m.keys_at(0.4), None);
+
+        assert!(InputMacro::new("F9", "5").is_err());
+        assert!(InputMacro::new("F9", "G 100").is_err());
+        assert!(InputMacro::new("NoSuchKey", "5 100").is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use crate::font;
+use chip::expr::Expr;
+use chip::symbols::Symbols;
+use chip::{Addr, Reg};
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::VideoSubsystem;
+
+/// 调试窗口的字体缩放
+const SCALE: u32 = 2;
+/// 调试窗口每行可显示的字符数
+const COLUMNS: u32 = 40;
+/// 调试窗口的行数
+const ROWS: u32 = 30;
+/// 反汇编窗口显示 PC 前后各多少条指令
+const DISASM_CONTEXT: u16 = 7;
+
+const LINE_HEIGHT: u32 = (font::GLYPH_HEIGHT + 2) * SCALE;
+const CHAR_WIDTH: u32 = (font::GLYPH_WIDTH + 1) * SCALE;
+const PADDING: u32 = 4 * SCALE;
+
+/// 调试窗口中可以修改的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Pc,
+    I,
+    Delay,
+    Sound,
+    V(Reg),
+    /// 反汇编中某个地址处的指令
+    Word(u16),
+}
+
+impl Field {
+    /// 显示的十六进制位数
+    fn digits(self) -> usize {
+        match self {
+            Field::Pc | Field::I | Field::Word(_) => 4,
+            Field::Delay | Field::Sound | Field::V(_) => 2,
+        }
+    }
+
+    fn apply(self, chip: &mut chip::Chip, value: u16) -> Result<(), chip::Exception> {
+        match self {
+            Field::Pc => chip.set_pc(Addr::try_from(value)?),
+            Field::I => {
+                chip.set_i(Addr::try_from(value)?);
+                Ok(())
+            }
+            Field::Delay => {
+                chip.set_delay_timer(value as u8);
+                Ok(())
+            }
+            Field::Sound => {
+                chip.set_sound_timer(value as u8);
+                Ok(())
+            }
+            Field::V(x) => {
+                chip.set_register(x, value as u8);
+                Ok(())
+            }
+            Field::Word(addr) => chip.load_region(addr, &value.to_be_bytes()),
+        }
+    }
+}
+
+/// 调试窗口，显示寄存器、栈、监视表达式以及 PC 附近的反汇编。
+/// 双击寄存器、定时器或指令后输入十六进制数，回车修改，Esc 取消
+pub(crate) struct Inspector {
+    canvas: Canvas<Window>,
+    fields: Vec<(usize, usize, Field)>, // 上一次绘制时各字段所在的行和列
+    editing: Option<(Field, String)>,   // 正在修改的字段和已输入的数字
+}
+
+impl Inspector {
+    /// 窗口创建失败时返回错误信息
+    pub fn new(video: &VideoSubsystem) -> Result<Self, String> {
+        let window = video
+            .window(
+                "CHIP-8 Inspector",
+                COLUMNS * (font::GLYPH_WIDTH + 1) * SCALE + 2 * PADDING,
+                ROWS * LINE_HEIGHT + 2 * PADDING,
+            )
+            .build()
+            .map_err(|e| e.to_string())?;
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            canvas,
+            fields: Vec::new(),
+            editing: None,
+        })
+    }
+
+    pub fn window_id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    /// 是否正在修改字段，修改时虚拟机暂停运行
+    pub fn editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    /// 双击窗口中的位置，开始修改那里的字段
+    pub fn double_click(&mut self, x: i32, y: i32) {
+        let column = (x - PADDING as i32).div_euclid(CHAR_WIDTH as i32);
+        let row = (y - PADDING as i32).div_euclid(LINE_HEIGHT as i32);
+        self.editing = self
+            .fields
+            .iter()
+            .find(|(r, c, field)| {
+                row == *r as i32 && (*c as i32..(*c + field.digits()) as i32).contains(&column)
+            })
+            .map(|(_, _, field)| (*field, String::new()));
+    }
+
+    /// 修改字段时的按键输入
+    pub fn key(&mut self, chip: &mut chip::Chip, key: Keycode) {
+        let Some((field, text)) = self.editing.as_mut() else {
+            return;
+        };
+        match key {
+            Keycode::Escape => self.editing = None,
+            Keycode::Backspace => {
+                text.pop();
+            }
+            Keycode::Return | Keycode::KpEnter => {
+                if let Ok(value) = u16::from_str_radix(text, 16) {
+                    if let Err(e) = field.apply(chip, value) {
+                        println!("Couldn't apply the edit: {}", e);
+                    }
+                }
+                self.editing = None;
+            }
+            _ => {
+                let name = key.name();
+                let digit = name.chars().next().filter(|c| c.is_ascii_hexdigit());
+                if let (Some(digit), 1) = (digit, name.len()) {
+                    if text.len() < field.digits() {
+                        text.push(digit.to_ascii_uppercase());
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn draw(&mut self, chip: &chip::Chip, symbols: &Symbols, watches: &[Expr]) {
+        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+        self.canvas.clear();
+
+        let mut lines = Vec::new();
+        self.fields.clear();
+        self.fields.push((0, 3, Field::Pc));
+        self.fields.push((0, 11, Field::I));
+        self.fields.push((1, 3, Field::Delay));
+        self.fields.push((1, 12, Field::Sound));
+        let v = chip.registers();
+        lines.push((
+            format!(
+                "PC {:04X}  I {:04X}  OP {:04X}",
+                chip.pc(),
+                chip.i(),
+                chip.opcode()
+            ),
+            false,
+        ));
+        lines.push((
+            format!(
+                "DT {:02X}    ST {:02X}  SP {:X}",
+                chip.delay_timer(),
+                chip.sound_timer(),
+                chip.stack().len()
+            ),
+            false,
+        ));
+        for (n, regs) in v.chunks(4).enumerate() {
+            for i in 0..regs.len() {
+                let x = Reg::from_nibble((n * 4 + i) as u8);
+                self.fields.push((lines.len(), 3 + 7 * i, Field::V(x)));
+            }
+            let text = regs
+                .iter()
+                .enumerate()
+                .map(|(i, r)| format!("V{:X} {:02X}", n * 4 + i, r))
+                .collect::<Vec<_>>()
+                .join("  ");
+            lines.push((text, false));
+        }
+
+        lines.push((String::new(), false));
+        lines.push((format!("STACK  PEAK {}", chip.stack_peak()), false));
+        let stack = chip.stack();
+        if stack.is_empty() {
+            lines.push(("-".to_string(), false));
+        }
+        for addrs in stack.chunks(4) {
+            let text = addrs
+                .iter()
+                .map(|addr| format!("{:04X}", addr))
+                .collect::<Vec<_>>()
+                .join(" ");
+            lines.push((text, false));
+        }
+
+        if !watches.is_empty() {
+            lines.push((String::new(), false));
+            lines.push(("WATCH".to_string(), false));
+        }
+        for expr in watches {
+            let text = match expr.eval(chip) {
+                Ok(value) => format!("{} = {:X}", expr, value),
+                Err(_) => format!("{} = ?", expr),
+            };
+            lines.push((text, false));
+        }
+
+        lines.push((String::new(), false));
+        lines.push(("DISASSEMBLY".to_string(), false));
+        let mem = chip.memory();
+        let pc = chip.pc();
+        let start = pc.saturating_sub(2 * DISASM_CONTEXT);
+        for addr in (start..=pc + 2 * DISASM_CONTEXT).step_by(2) {
+            if addr as usize + 1 >= mem.len() {
+                break;
+            }
+            let op = (mem[addr as usize] as u16) << 8 | mem[addr as usize + 1] as u16;
+            let marker = if addr == pc { ">" } else { " " };
+            if let Some(name) = symbols.name(addr) {
+                lines.push((format!("{}:", name), false));
+            }
+            self.fields.push((lines.len(), 6, Field::Word(addr)));
+            lines.push((
+                format!(
+                    "{}{:04X} {:04X} {}",
+                    marker,
+                    addr,
+                    op,
+                    symbols.format(chip::Instruction::decode_variant(op, chip.variant()))
+                ),
+                addr == pc,
+            ));
+        }
+
+        for (n, (line, current)) in lines.iter().take(ROWS as usize).enumerate() {
+            let color = if *current {
+                Color::RGB(255, 255, 0)
+            } else {
+                Color::RGB(0, 255, 0)
+            };
+            font::draw_text(
+                &mut self.canvas,
+                PADDING as i32,
+                (PADDING + n as u32 * LINE_HEIGHT) as i32,
+                SCALE,
+                color,
+                line,
+            );
+        }
+
+        // 正在修改的字段显示已输入的数字
+        let editing = self.editing.as_ref().and_then(|(field, text)| {
+            let (row, column, _) = self.fields.iter().find(|(_, _, f)| f == field)?;
+            Some((*row, *column, field.digits(), text))
+        });
+        if let Some((row, column, digits, text)) = editing {
+            let x = (PADDING + column as u32 * CHAR_WIDTH) as i32;
+            let y = (PADDING + row as u32 * LINE_HEIGHT) as i32;
+            self.canvas.set_draw_color(Color::RGB(0, 0, 160));
+            let _ = self.canvas.fill_rect(Rect::new(
+                x - SCALE as i32,
+                y - SCALE as i32,
+                digits as u32 * CHAR_WIDTH + SCALE,
+                LINE_HEIGHT,
+            ));
+            let text = format!("{:_<width$}", text, width = digits);
+            font::draw_text(
+                &mut self.canvas,
+                x,
+                y,
+                SCALE,
+                Color::RGB(255, 255, 255),
+                &text,
+            );
+        }
+        self.canvas.present();
+    }
+}
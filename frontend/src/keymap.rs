@@ -0,0 +1,134 @@
+use sdl2::keyboard::Keycode;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// CHIP-8 键盘 (0 ~ F) 到主机按键的映射
+///
+/// 映射文件每行一个按键，格式为 `CHIP-8 按键 = SDL 按键名`，例如 `A = Z`，
+/// '#' 开头的行为注释
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    keys: [Keycode; 16],
+}
+
+impl Default for Keymap {
+    /// 默认映射，对应 COSMAC VIP 键盘的布局
+    ///
+    /// ```text
+    /// 1 2 3 C      1 2 3 4
+    /// 4 5 6 D  ->  Q W E R
+    /// 7 8 9 E      A S D F
+    /// A 0 B F      Z X C V
+    /// ```
+    fn default() -> Self {
+        Self {
+            keys: [
+                Keycode::X,
+                Keycode::Num1,
+                Keycode::Num2,
+                Keycode::Num3,
+                Keycode::Q,
+                Keycode::W,
+                Keycode::E,
+                Keycode::A,
+                Keycode::S,
+                Keycode::D,
+                Keycode::Z,
+                Keycode::C,
+                Keycode::Num4,
+                Keycode::R,
+                Keycode::F,
+                Keycode::V,
+            ],
+        }
+    }
+}
+
+impl Keymap {
+    /// 解析映射文件内容，未出现的按键保持默认映射
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut keymap = Self::default();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, name) = line
+                .split_once('=')
+                .ok_or_else(|| format!("line {}: expected `key = name`", n + 1))?;
+            let key = u8::from_str_radix(key.trim(), 16)
+                .ok()
+                .filter(|key| *key < 16)
+                .ok_or_else(|| format!("line {}: invalid CHIP-8 key {:?}", n + 1, key.trim()))?;
+            let keycode = Keycode::from_name(name.trim())
+                .ok_or_else(|| format!("line {}: unknown key name {:?}", n + 1, name.trim()))?;
+            keymap.keys[key as usize] = keycode;
+        }
+        Ok(keymap)
+    }
+
+    /// 从文件读取映射
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// 将映射保存到文件
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// 获取主机按键对应的 CHIP-8 按键
+    pub fn keypad(&self, keycode: Keycode) -> Option<u8> {
+        self.keys
+            .iter()
+            .position(|k| *k == keycode)
+            .map(|key| key as u8)
+    }
+
+    /// 获取 CHIP-8 按键对应的主机按键
+    pub fn keycode(&self, key: u8) -> Keycode {
+        self.keys[key as usize & 0xF]
+    }
+
+    /// 修改 CHIP-8 按键对应的主机按键，原先使用该主机按键的 CHIP-8 按键会与之交换
+    pub fn set(&mut self, key: u8, keycode: Keycode) {
+        let key = key as usize & 0xF;
+        if let Some(other) = self.keys.iter().position(|k| *k == keycode) {
+            self.keys[other] = self.keys[key];
+        }
+        self.keys[key] = keycode;
+    }
+}
+
+impl fmt::Display for Keymap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# CHIP-8 key = SDL key name")?;
+        for (key, keycode) in self.keys.iter().enumerate() {
+            writeln!(f, "{:X} = {}", key, keycode.name())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap_roundtrip() {
+        let mut keymap = Keymap::parse("# comment\nA = Space\n").unwrap();
+        assert_eq!(keymap.keypad(Keycode::Space), Some(0xA));
+        assert_eq!(keymap.keypad(Keycode::Z), None);
+
+        // 交换已被占用的按键
+        keymap.set(0x1, Keycode::Space);
+        assert_eq!(keymap.keycode(0xA), Keycode::Num1);
+        assert_eq!(Keymap::parse(&keymap.to_string()).unwrap(), keymap);
+
+        assert!(Keymap::parse("G = X").is_err());
+        assert!(Keymap::parse("1 = NoSuchKey").is_err());
+    }
+}
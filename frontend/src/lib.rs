@@ -1,163 +1,1396 @@
-use chip;
-use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+mod autofire;
+mod bezel;
+mod chat;
+mod font;
+mod input_macro;
+mod inspector;
+mod keymap;
+mod palette;
+mod pixel_style;
+mod remote;
+mod rotation;
+mod scan;
+mod sdl_audio;
+mod slow_draw;
+mod sprite_viewer;
+
+pub use audio::{AudioConfig, AudioOutput, Waveform};
+pub use autofire::AutofireKey;
+pub use bezel::{Bezel, ScreenArea};
+pub use chat::{ChatConfig, ChatInput};
+pub use input_macro::InputMacro;
+pub use keymap::Keymap;
+pub use palette::Palette;
+pub use pixel_style::PixelStyle;
+pub use remote::RemoteKeypad;
+pub use rotation::Rotation;
+pub use scan::Scanner;
+
+use autofire::Autofire;
+use inspector::Inspector;
+use sdl_audio::SdlOutput;
+use slow_draw::SlowDraw;
+use sprite_viewer::SpriteViewer;
+
+use chip::expr::Expr;
+use chip::symbols::Symbols;
+use chip::vip::VipDisplay;
+use chip::FrameStats;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::messagebox::{
+    show_message_box, ButtonData, ClickedButton, MessageBoxButtonFlag, MessageBoxFlag,
+};
+use sdl2::mouse::MouseButton;
 use sdl2::pixels::Color;
+use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect::Rect;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
+use sdl2::render::{BlendMode, Canvas, Texture};
+use sdl2::video::{FullscreenType, Window};
+use sdl2::VideoSubsystem;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// 窗口标题的默认文字
+const TITLE: &str = "CHIP-8 Emulator";
+/// 窗口标题中帧率统计的刷新间隔
+const STATS_INTERVAL: Duration = Duration::from_secs(1);
 
-struct SquareWave {
-    phase_inc: f32,
-    phase: f32,
-    volume: f32,
+/// 延迟定时器和声音定时器的递减周期 (60Hz)
+const TIMER_PERIOD: f32 = 1.0 / 60.0;
+/// 用 F5 打开慢速绘图模式时每秒显示的精灵像素数
+const DEFAULT_SLOW_DRAW_RATE: u32 = 32;
+/// 音量热键每次调整的幅度
+const VOLUME_STEP: f32 = 0.05;
+/// 调整音量等操作后屏幕右上角显示提示的时间
+const NOTICE_TIME: Duration = Duration::from_millis(1500);
+/// 垂直同步模式下单帧时间的上限，避免窗口被拖动等长时间阻塞后一次执行过多指令
+const MAX_FRAME_TIME: f32 = 0.1;
+
+/// 创建显示窗口时的错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitError {
+    /// SDL 初始化失败
+    Sdl(String),
+    /// 视频子系统初始化失败，例如没有可用的显示
+    Video(String),
+    Window(String),
+    Renderer(String),
+    EventPump(String),
 }
 
-impl AudioCallback for SquareWave {
-    type Channel = f32;
+impl fmt::Display for InitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InitError::Sdl(e) => write!(f, "SDL initialization failed: {}", e),
+            InitError::Video(e) => write!(f, "video initialization failed: {}", e),
+            InitError::Window(e) => write!(f, "window creation failed: {}", e),
+            InitError::Renderer(e) => write!(f, "renderer creation failed: {}", e),
+            InitError::EventPump(e) => write!(f, "event pump unavailable: {}", e),
+        }
+    }
+}
 
-    fn callback(&mut self, out: &mut [Self::Channel]) {
-        for x in out.iter_mut() {
-            *x = if self.phase <= 0.5 {
-                self.volume
-            } else {
-                -self.volume
-            };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
+impl std::error::Error for InitError {}
+
+/// 显示窗口配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayConfig {
+    /// 每个 CHIP-8 像素在窗口中的边长
+    pub pixel_scale: u32,
+    /// 是否由垂直同步驱动主循环。打开时 update 会阻塞到下一次垂直同步，
+    /// 关闭时由调用者控制调用 update 的频率
+    pub vsync: bool,
+    /// 每秒执行的指令数
+    pub ips: u32,
+    /// 是否全屏显示
+    pub fullscreen: bool,
+    /// 是否模拟 COSMAC VIP 的显示刷新，包括逐行取显存和 DXYN 等待中断造成的闪烁
+    pub vip_display: bool,
+    /// 慢速绘图模式每秒显示的精灵像素数，None 表示关闭。打开时 DXYN 逐像素播放
+    pub slow_draw: Option<u32>,
+    pub palette: Palette,
+    pub audio: AudioConfig,
+    /// 是否打开声音输出，关闭时或没有可用的音频设备时没有声音
+    pub audio_output: bool,
+    /// 窗口的位置和大小，None 表示按 pixel_scale 决定大小并居中
+    pub window: Option<WindowGeometry>,
+    /// 画面顺时针旋转的角度
+    pub rotation: Rotation,
+    /// 按键是否跟随画面旋转
+    pub rotate_input: bool,
+    pub pixel_style: PixelStyle,
+    /// 点阵模式下相邻圆点之间的空隙占像素边长的比例
+    pub dot_gap: f32,
+    /// 游戏速度的倍数，同时作用于指令和定时器，小于 1 时整个游戏变慢
+    pub game_speed: f32,
+    /// 是否在画面左下角显示键盘和按下的按键
+    pub show_keypad: bool,
+}
+
+/// 窗口的位置和大小 (屏幕像素)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            pixel_scale: 16,
+            vsync: false,
+            ips: 700,
+            fullscreen: false,
+            vip_display: false,
+            slow_draw: None,
+            palette: Palette::default(),
+            audio: AudioConfig::default(),
+            audio_output: true,
+            window: None,
+            rotation: Rotation::R0,
+            rotate_input: false,
+            pixel_style: PixelStyle::Square,
+            dot_gap: 0.2,
+            game_speed: 1.0,
+            show_keypad: false,
         }
     }
 }
 
+/// 虚拟机出错后用户选择的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    /// 复位并重新运行
+    Reset,
+    /// 打开宽松模式继续运行，只对非法指令提供
+    Continue,
+    /// 退出模拟器
+    Quit,
+}
+
+/// 外部按键输入源，例如远程键盘或聊天室
+pub trait KeypadSource {
+    /// 取出所有尚未处理的按键事件 (按键, 是否按下)，按键为 0 ~ 15，超出范围的按键会被忽略
+    fn poll(&mut self) -> Vec<(u8, bool)>;
+}
+
+/// 其他线程通过无锁队列发送的按键
+impl KeypadSource for chip::input::KeyReceiver {
+    fn poll(&mut self) -> Vec<(u8, bool)> {
+        self.try_iter().collect()
+    }
+}
+
+/// SDL 可用的音频输出设备名
+pub fn audio_devices() -> Result<Vec<String>, String> {
+    let audio = sdl2::init()?.audio()?;
+    Ok(SdlOutput::devices(&audio))
+}
+
+/// 窗口是否与某个显示器有重叠
+fn on_screen(video: &VideoSubsystem, geometry: &WindowGeometry) -> bool {
+    let rect = Rect::new(geometry.x, geometry.y, geometry.width, geometry.height);
+    (0..video.num_video_displays().unwrap_or(0))
+        .filter_map(|i| video.display_bounds(i).ok())
+        .any(|bounds| bounds.has_intersection(rect))
+}
+
+/// 窗口缩放热键对应的倍数
+fn scale_hotkey(keycode: Keycode) -> Option<u32> {
+    const KEYS: [Keycode; 8] = [
+        Keycode::Num1,
+        Keycode::Num2,
+        Keycode::Num3,
+        Keycode::Num4,
+        Keycode::Num5,
+        Keycode::Num6,
+        Keycode::Num7,
+        Keycode::Num8,
+    ];
+    KEYS.iter()
+        .position(|&k| k == keycode)
+        .map(|i| i as u32 + 1)
+}
+
+/// 创建点阵模式下的白色圆点纹理，绘制时用颜色调制为前景色
+fn dot_texture(canvas: &Canvas<Window>, size: u32, gap: f32) -> Texture {
+    let pixels: Vec<u8> = pixel_style::dot_alpha(size, gap)
+        .into_iter()
+        .flat_map(|alpha| [0xFF, 0xFF, 0xFF, alpha])
+        .collect();
+    let mut texture = canvas
+        .texture_creator()
+        .create_texture_static(PixelFormatEnum::RGBA32, size, size)
+        .unwrap();
+    texture.update(None, &pixels, size as usize * 4).unwrap();
+    texture.set_blend_mode(BlendMode::Blend);
+    texture
+}
+
+/// 暂停菜单的状态
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Menu {
+    Closed,
+    Main,
+    /// 正在为第 n 个 CHIP-8 按键选择主机按键，以及尚未生效的新映射
+    Remap(u8, Keymap),
+}
+
 pub struct Display {
     canvas: Canvas<Window>,
-    audio: AudioDevice<SquareWave>,
+    audio: Option<SdlOutput>,
+    volume: f32,                       // 音量 0.0 ~ 1.0，静音时保持不变
+    muted: bool,                       // 是否静音
+    notice: Option<(String, Instant)>, // 右上角的提示和开始显示的时间
     event_pump: sdl2::EventPump,
     pixel_scale: u32,
+    rom_name: Option<String>, // 当前装载的 ROM 名称
+    frames: u32,              // 统计周期内绘制的帧数
+    instructions: u32,        // 统计周期内执行的指令数
+    stats_since: Instant,     // 统计周期的开始时间
+    overlay: bool,            // 是否显示调试信息
+    show_keypad: bool,        // 是否显示键盘
+    video: VideoSubsystem,
+    inspector: Option<Inspector>,        // 调试窗口
+    symbols: Symbols,                    // 调试窗口反汇编使用的符号表
+    watches: Vec<Expr>,                  // 调试窗口显示的监视表达式
+    sprite_viewer: Option<SpriteViewer>, // 精灵查看窗口
+    pause_on_focus_loss: bool,           // 窗口失去焦点时是否暂停
+    focused: bool,                       // 模拟器的窗口是否拥有焦点
+    palette: Palette,
+    bezel: Option<(Bezel, Texture)>, // 背景图和它的纹理
+    pixel_style: PixelStyle,
+    dot_gap: f32,
+    dot: Option<(u32, Texture)>, // 点阵模式下圆点的纹理和它的边长
+    rotation: Rotation,
+    rotate_input: bool,        // 按键是否跟随画面旋转
+    vip: Option<VipDisplay>,   // 模拟 VIP 显示刷新时的显示状态
+    slow_draw: bool,           // 是否打开慢速绘图模式
+    slow_draw_rate: u32,       // 慢速绘图每秒显示的像素数
+    drawing: Option<SlowDraw>, // 正在慢速播放的 DXYN，播放时虚拟机暂停
+    frame_stats: FrameStats,   // 上一次 update 的执行统计
+    ips: u32,
+    game_speed: f32,          // 游戏速度的倍数
+    scanner: Option<Scanner>, // 单开关扫描输入
+    autofire: Autofire,
+    macros: Vec<InputMacro>,
+    macro_playing: Option<(usize, f32)>, // 正在播放的宏和已播放的时间 (秒)
+    macro_keys: u16,                     // 宏按住的按键
+    last_frame: Instant,                 // 上一帧的时间
+    frame_time: f32,                     // 平滑后的单帧时间 (秒)
+    budget: f32,                         // 尚未执行的指令数 (小数部分累积到下一帧)
+    timer_acc: f32,                      // 尚未用于递减定时器的时间 (秒)
+    next_rom: bool,                      // 是否按下了切换 ROM 的热键
+    dropped_rom: Option<PathBuf>,        // 拖放到窗口上、还没有装载的文件
+    keymap: Keymap,
+    keymap_path: Option<PathBuf>, // 重新映射按键后保存到的文件
+    menu: Menu,
+    local_keys: [bool; 16],              // 本地键盘的按键状态
+    sources: Vec<Box<dyn KeypadSource>>, // 外部按键输入源
+    remote_keys: [u32; 16],              // 每个按键被多少个外部输入按下
 }
 
 impl Display {
-    pub fn new(pixel_scale: u32) -> Self {
-        let sdl_context = sdl2::init().unwrap();
-        let video_subsystem = sdl_context.video().unwrap();
-        let audio_subsystem = sdl_context.audio().unwrap();
-        let audio = audio_subsystem
-            .open_playback(
-                None,
-                &AudioSpecDesired {
-                    freq: Some(44100),
-                    channels: Some(1),
-                    samples: None,
-                },
-                |spec| SquareWave {
-                    phase_inc: 440.0 / spec.freq as f32,
-                    phase: 0.0,
-                    volume: 0.25,
-                },
-            )
-            .unwrap();
+    pub fn new(pixel_scale: u32) -> Result<Self, InitError> {
+        Self::with_audio(pixel_scale, AudioConfig::default())
+    }
 
-        let window = video_subsystem
-            .window(
-                "CHIP-8 Emulator",
-                chip::DISP_WIDTH as u32 * pixel_scale,
-                chip::DISP_HEIGHT as u32 * pixel_scale,
-            )
-            .position_centered()
+    /// 使用指定的音频配置创建显示窗口
+    pub fn with_audio(pixel_scale: u32, audio_config: AudioConfig) -> Result<Self, InitError> {
+        Self::with_config(DisplayConfig {
+            pixel_scale,
+            audio: audio_config,
+            ..DisplayConfig::default()
+        })
+    }
+
+    /// 使用指定的配置创建显示窗口。视频初始化失败时返回错误，音频初始化失败时没有声音
+    pub fn with_config(config: DisplayConfig) -> Result<Self, InitError> {
+        let DisplayConfig {
+            pixel_scale,
+            audio: audio_config,
+            ..
+        } = config;
+        let sdl_context = sdl2::init().map_err(InitError::Sdl)?;
+        let video_subsystem = sdl_context.video().map_err(InitError::Video)?;
+        // 没有可用的音频驱动时仍然可以运行，只是没有声音
+        let audio = match config.audio_output.then(|| sdl_context.audio()) {
+            Some(Ok(subsystem)) => Some(SdlOutput::open(subsystem, &audio_config)),
+            Some(Err(e)) => {
+                println!("Couldn't open audio output: {}", e);
+                None
+            }
+            None => None,
+        };
+
+        // 上次保存的位置已经不在任何显示器上时 (例如拔掉了显示器) 仍然居中
+        let geometry = config
+            .window
+            .filter(|g| g.width > 0 && g.height > 0 && on_screen(&video_subsystem, g));
+        let mut window = match geometry {
+            Some(g) => {
+                let mut window = video_subsystem.window(TITLE, g.width, g.height);
+                window.position(g.x, g.y);
+                window
+            }
+            None => {
+                let (cols, rows) = config.rotation.size();
+                let mut window = video_subsystem.window(
+                    TITLE,
+                    cols as u32 * pixel_scale,
+                    rows as u32 * pixel_scale,
+                );
+                window.position_centered();
+                window
+            }
+        };
+        window.resizable();
+        if config.fullscreen {
+            window.fullscreen_desktop();
+        }
+        let window = window
             .build()
-            .unwrap();
+            .map_err(|e| InitError::Window(e.to_string()))?;
 
-        let canvas = window.into_canvas().build().unwrap();
+        let mut canvas = window.into_canvas();
+        if config.vsync {
+            canvas = canvas.present_vsync();
+        }
+        let canvas = canvas
+            .build()
+            .map_err(|e| InitError::Renderer(e.to_string()))?;
 
-        let event_pump = sdl_context.event_pump().unwrap();
+        // 初始帧时间取显示器标称的刷新率，之后根据实际测得的帧间隔修正
+        let refresh_rate = canvas
+            .window()
+            .display_mode()
+            .map(|mode| mode.refresh_rate)
+            .unwrap_or(60);
+        let refresh_rate = if refresh_rate > 0 { refresh_rate } else { 60 };
 
-        Self {
+        let event_pump = sdl_context.event_pump().map_err(InitError::EventPump)?;
+
+        Ok(Self {
             canvas,
             audio,
+            volume: audio_config.volume.clamp(0.0, 1.0),
+            muted: false,
+            notice: None,
             event_pump,
             pixel_scale,
+            rom_name: None,
+            frames: 0,
+            instructions: 0,
+            stats_since: Instant::now(),
+            overlay: false,
+            video: video_subsystem,
+            inspector: None,
+            symbols: Symbols::new(),
+            watches: Vec::new(),
+            sprite_viewer: None,
+            pause_on_focus_loss: true,
+            focused: true,
+            palette: config.palette,
+            bezel: None,
+            pixel_style: config.pixel_style,
+            dot_gap: config.dot_gap,
+            dot: None,
+            rotation: config.rotation,
+            rotate_input: config.rotate_input,
+            show_keypad: config.show_keypad,
+            vip: config.vip_display.then(VipDisplay::new),
+            slow_draw: config.slow_draw.is_some(),
+            slow_draw_rate: config.slow_draw.unwrap_or(DEFAULT_SLOW_DRAW_RATE),
+            drawing: None,
+            frame_stats: FrameStats::default(),
+            ips: config.ips,
+            game_speed: config.game_speed.max(0.01),
+            scanner: None,
+            autofire: Autofire::default(),
+            macros: Vec::new(),
+            macro_playing: None,
+            macro_keys: 0,
+            last_frame: Instant::now(),
+            frame_time: 1.0 / refresh_rate as f32,
+            budget: 0.0,
+            timer_acc: 0.0,
+            next_rom: false,
+            dropped_rom: None,
+            keymap: Keymap::default(),
+            keymap_path: None,
+            menu: Menu::Closed,
+            local_keys: [false; 16],
+            sources: Vec::new(),
+            remote_keys: [0; 16],
+        })
+    }
+
+    /// 设置窗口失去焦点时是否暂停虚拟机并静音，默认打开
+    pub fn set_pause_on_focus_loss(&mut self, pause: bool) {
+        self.pause_on_focus_loss = pause;
+    }
+
+    /// 当前音量 0.0 ~ 1.0，静音时为静音前的音量
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn muted(&self) -> bool {
+        self.muted
+    }
+
+    /// 设置音量 0.0 ~ 1.0 并在屏幕上显示
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.apply_volume();
+    }
+
+    /// 打开或关闭静音并在屏幕上显示
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+        self.apply_volume();
+    }
+
+    fn apply_volume(&mut self) {
+        let volume = if self.muted { 0.0 } else { self.volume };
+        if let Some(audio) = self.audio.as_mut() {
+            audio.set_volume(volume);
         }
+        let text = if self.muted {
+            "MUTED".to_string()
+        } else {
+            format!("VOLUME {}%", (self.volume * 100.0).round())
+        };
+        self.show_notice(text);
+    }
+
+    /// 在屏幕右上角短暂显示提示
+    fn show_notice(&mut self, text: String) {
+        self.notice = Some((text, Instant::now()));
+    }
+
+    /// 当前窗口的位置和大小，用于下次启动时恢复。全屏时返回 None
+    pub fn window_geometry(&self) -> Option<WindowGeometry> {
+        let window = self.canvas.window();
+        if window.fullscreen_state() != FullscreenType::Off {
+            return None;
+        }
+        let (x, y) = window.position();
+        let (width, height) = window.size();
+        Some(WindowGeometry {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    /// 查询并清除切换到下一个 ROM 的请求 (Tab 键)
+    pub fn take_next_rom_request(&mut self) -> bool {
+        std::mem::take(&mut self.next_rom)
+    }
+
+    /// 查询并清除拖放到窗口上的文件，由调用者像打开其他 ROM 一样装载 (包括容器)
+    pub fn take_dropped_rom(&mut self) -> Option<PathBuf> {
+        self.dropped_rom.take()
+    }
+
+    /// 设置每秒执行的指令数
+    pub fn set_ips(&mut self, ips: u32) {
+        self.ips = ips;
+    }
+
+    /// 设置按键映射
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    /// 设置在暂停菜单中重新映射按键后，新映射保存到的文件
+    pub fn set_keymap_path(&mut self, path: &Path) {
+        self.keymap_path = Some(path.to_path_buf());
+    }
+
+    /// 添加外部按键输入源，外部按键与本地按键合并后输入虚拟机
+    pub fn add_keypad_source(&mut self, source: impl KeypadSource + 'static) {
+        self.sources.push(Box::new(source));
+    }
+
+    /// 主机按键对应的 CHIP-8 按键，打开了按键旋转时随画面旋转
+    fn keypad(&self, keycode: Keycode) -> Option<u8> {
+        let key = self.keymap.keypad(keycode)?;
+        Some(if self.rotate_input {
+            self.rotation.key(key)
+        } else {
+            key
+        })
+    }
+
+    /// 打开单开关扫描输入
+    pub fn set_scanner(&mut self, scanner: Scanner) {
+        self.scanner = Some(scanner);
+    }
+
+    /// 打开按键的连发，F7 打开或关闭所有按键的连发
+    pub fn set_autofire(&mut self, key: AutofireKey) {
+        self.autofire.set(key);
+    }
+
+    /// 本地按下了 CHIP-8 按键，按键重复事件不重新开始连发
+    fn press_local(&mut self, chip: &mut chip::Chip, key: u8) {
+        if !self.local_keys[key as usize] {
+            self.autofire.press(key);
+        }
+        self.local_keys[key as usize] = true;
+        self.sync_key(chip, key);
+    }
+
+    /// 添加输入宏，按下宏的按键时播放。宏的按键优先于按键映射
+    pub fn add_macro(&mut self, input_macro: InputMacro) {
+        self.macros.push(input_macro);
+    }
+
+    /// 从头播放第 index 个宏，替换正在播放的宏
+    fn play_macro(&mut self, chip: &mut chip::Chip, index: usize) {
+        self.macro_playing = Some((index, 0.0));
+        self.advance_macro(chip, 0.0);
+    }
+
+    /// 宏的播放时间前进 elapsed 秒，按下和松开对应的按键
+    fn advance_macro(&mut self, chip: &mut chip::Chip, elapsed: f32) {
+        let keys = match self.macro_playing.as_mut() {
+            Some((index, time)) => {
+                *time += elapsed;
+                self.macros[*index].keys_at(*time)
+            }
+            None => None,
+        };
+        if keys.is_none() {
+            self.macro_playing = None;
+        }
+        let keys = keys.unwrap_or(0);
+        let changed = keys ^ self.macro_keys;
+        self.macro_keys = keys;
+        for key in (0..16).filter(|key| changed & 1 << key != 0) {
+            self.sync_key(chip, key);
+        }
+    }
+
+    /// 设置画在画面后面的背景图
+    pub fn set_bezel(&mut self, bezel: Bezel) -> Result<(), String> {
+        let (width, height) = bezel.size();
+        let mut texture = self
+            .canvas
+            .texture_creator()
+            .create_texture_static(PixelFormatEnum::RGBA32, width, height)
+            .map_err(|e| e.to_string())?;
+        texture
+            .update(None, bezel.rgba(), width as usize * 4)
+            .map_err(|e| e.to_string())?;
+        texture.set_blend_mode(BlendMode::Blend);
+        self.bezel = Some((bezel, texture));
+        Ok(())
+    }
+
+    /// 把窗口调整为画面的整数倍大小，全屏时先退出全屏
+    pub fn set_pixel_scale(&mut self, pixel_scale: u32) {
+        self.pixel_scale = pixel_scale.max(1);
+        let (cols, rows) = self.rotation.size();
+        let window = self.canvas.window_mut();
+        let _ = window.set_fullscreen(FullscreenType::Off);
+        let _ = window.set_size(
+            cols as u32 * self.pixel_scale,
+            rows as u32 * self.pixel_scale,
+        );
+    }
+
+    /// 把画面再顺时针旋转 90 度 (F6)，窗口模式下交换窗口的宽和高
+    fn rotate(&mut self, chip: &mut chip::Chip) {
+        // 旋转后松开按键对应的 CHIP-8 按键不同，先松开所有按键
+        self.local_keys.fill(false);
+        for key in 0..16 {
+            self.sync_key(chip, key);
+        }
+        self.rotation = self.rotation.next();
+        let window = self.canvas.window_mut();
+        if window.fullscreen_state() == FullscreenType::Off {
+            let (width, height) = window.size();
+            let _ = window.set_size(height, width);
+        }
+    }
+
+    /// 合并本地和远程的按键状态并更新虚拟机键盘
+    fn sync_key(&self, chip: &mut chip::Chip, key: u8) {
+        let pressed = (self.local_keys[key as usize] && self.autofire.output(key))
+            || self.remote_keys[key as usize] > 0
+            || self.macro_keys & 1 << key != 0;
+        chip.set_keypad(key, pressed);
+    }
+
+    /// 设置调试窗口反汇编时用来替换地址的符号表
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+
+    /// 设置调试窗口中显示的监视表达式
+    pub fn set_watches(&mut self, watches: Vec<Expr>) {
+        self.watches = watches;
+    }
+
+    /// 设置窗口标题中显示的 ROM 名称
+    pub fn set_rom_name(&mut self, name: &str) {
+        self.rom_name = Some(name.to_string());
+        self.update_title(None);
+    }
+
+    fn update_title(&mut self, stats: Option<(f32, f32)>) {
+        let mut title = TITLE.to_string();
+        if let Some(name) = &self.rom_name {
+            title = format!("{} - {}", title, name);
+        }
+        if let Some((fps, ips)) = stats {
+            title = format!("{} | {:.0} FPS | {:.0} IPS", title, fps, ips);
+        }
+        // 标题中不会出现 '\0'，这里可以忽略错误
+        let _ = self.canvas.window_mut().set_title(&title);
+    }
+
+    /// 每个统计周期更新一次窗口标题中的帧率和指令速度
+    fn update_stats(&mut self) {
+        let elapsed = self.stats_since.elapsed();
+        if elapsed < STATS_INTERVAL {
+            return;
+        }
+        let secs = elapsed.as_secs_f32();
+        let stats = (self.frames as f32 / secs, self.instructions as f32 / secs);
+        self.update_title(Some(stats));
+        self.frames = 0;
+        self.instructions = 0;
+        self.stats_since = Instant::now();
     }
 
     fn draw(&mut self, chip: &chip::Chip) {
-        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
-        self.canvas.clear();
-
-        let fb = chip.framebuffer();
-        self.canvas.set_draw_color(Color::RGB(255, 255, 255));
-        for (i, pixel) in fb.iter().enumerate() {
-            if *pixel {
-                let rect = Rect::new(
-                    (i % chip::DISP_WIDTH) as i32 * self.pixel_scale as i32,
-                    (i / chip::DISP_WIDTH) as i32 * self.pixel_scale as i32,
-                    self.pixel_scale,
-                    self.pixel_scale,
-                );
-                self.canvas.fill_rect(rect).unwrap();
+        // 有背景图时画面显示在背景图中的指定区域，否则占满窗口
+        let (width, height) = self.canvas.output_size().unwrap();
+        let area = match &self.bezel {
+            Some((bezel, texture)) => {
+                let (image, screen) = bezel.layout(width, height);
+                self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+                self.canvas.clear();
+                self.canvas.copy(texture, None, image).unwrap();
+                self.canvas.set_draw_color(self.palette.background);
+                self.canvas.fill_rect(screen).unwrap();
+                screen
+            }
+            None => {
+                self.canvas.set_draw_color(self.palette.background);
+                self.canvas.clear();
+                Rect::new(0, 0, width, height)
+            }
+        };
+
+        // 按区域大小取整数倍缩放并居中显示，全屏时窗口大小与创建时不同
+        let (cols, rows) = self.rotation.size();
+        let scale = (area.width() / cols as u32)
+            .min(area.height() / rows as u32)
+            .max(1);
+        let left = area.x() + (area.width().saturating_sub(scale * cols as u32) / 2) as i32;
+        let top = area.y() + (area.height().saturating_sub(scale * rows as u32) / 2) as i32;
+        let rotation = self.rotation;
+        let cell = |index: usize| {
+            let (x, y) = rotation.pixel(index % chip::DISP_WIDTH, index / chip::DISP_WIDTH);
+            Rect::new(
+                left + x as i32 * scale as i32,
+                top + y as i32 * scale as i32,
+                scale,
+                scale,
+            )
+        };
+
+        let slow_screen = self
+            .drawing
+            .as_ref()
+            .map(|drawing| drawing.screen(self.slow_draw_rate));
+        let framebuffer = chip.framebuffer();
+        let fb = match (&slow_screen, &self.vip) {
+            (Some(screen), _) => screen,
+            (None, Some(vip)) => vip.screen(),
+            (None, None) => &framebuffer[..],
+        };
+        match self.pixel_style {
+            PixelStyle::Square => {
+                self.canvas.set_draw_color(self.palette.foreground);
+                for (i, pixel) in fb.iter().enumerate() {
+                    if *pixel {
+                        self.canvas.fill_rect(cell(i)).unwrap();
+                    }
+                }
             }
+            PixelStyle::Dot => {
+                // 缩放改变时重新生成圆点的纹理
+                if self.dot.as_ref().is_none_or(|(size, _)| *size != scale) {
+                    if let Some((_, old)) = self.dot.take() {
+                        // SAFETY: 纹理由仍然存在的 canvas 创建，之后不再使用
+                        unsafe { old.destroy() };
+                    }
+                    let texture = dot_texture(&self.canvas, scale, self.dot_gap);
+                    self.dot = Some((scale, texture));
+                }
+                let (_, texture) = self.dot.as_mut().unwrap();
+                let fg = self.palette.foreground;
+                texture.set_color_mod(fg.r, fg.g, fg.b);
+                // 先画暗淡的熄灭像素，再画点亮的像素
+                for lit in [false, true] {
+                    texture.set_alpha_mod(if lit { 255 } else { pixel_style::UNLIT_ALPHA });
+                    for (i, _) in fb.iter().enumerate().filter(|(_, p)| **p == lit) {
+                        self.canvas.copy(texture, None, cell(i)).unwrap();
+                    }
+                }
+            }
+        }
+        if let Some(drawing) = &self.drawing {
+            let text_scale = (self.pixel_scale / 4).max(1);
+            let vf = chip.registers()[0xF];
+            drawing.draw_highlights(&mut self.canvas, self.slow_draw_rate, &cell, text_scale, vf);
+        }
+        if self.overlay {
+            self.draw_overlay(chip);
+        }
+        self.draw_notice();
+        self.draw_keypad(chip);
+        if self.menu != Menu::Closed {
+            self.draw_menu();
         }
         self.canvas.present();
+        self.frames += 1;
+    }
+
+    /// 在画面左上角绘制寄存器、定时器和最近执行的指令
+    fn draw_overlay(&mut self, chip: &chip::Chip) {
+        let v = chip.registers();
+        let hex = |regs: &[u8]| {
+            regs.iter()
+                .map(|r| format!("{:02X}", r))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let lines = [
+            format!(
+                "PC:{:04X} I:{:04X} OP:{:04X}",
+                chip.pc(),
+                chip.i(),
+                chip.opcode()
+            ),
+            format!(
+                "DT:{:02X} ST:{:02X} SP:{:X}",
+                chip.delay_timer(),
+                chip.sound_timer(),
+                chip.stack().len()
+            ),
+            format!("V0-7: {}", hex(&v[..8])),
+            format!("V8-F: {}", hex(&v[8..])),
+            format!(
+                "DRW:{} COL:{} KEY:{}",
+                self.frame_stats.draws, self.frame_stats.collisions, self.frame_stats.keys_sampled
+            ),
+        ];
+
+        let scale = (self.pixel_scale / 4).max(1);
+        let padding = 2 * scale;
+        let line_height = (font::GLYPH_HEIGHT + 2) * scale;
+        let width = lines
+            .iter()
+            .map(|line| font::text_width(line, scale))
+            .max()
+            .unwrap_or(0);
+
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 192));
+        self.canvas
+            .fill_rect(Rect::new(
+                0,
+                0,
+                width + 2 * padding,
+                lines.len() as u32 * line_height + padding,
+            ))
+            .unwrap();
+        self.canvas.set_blend_mode(BlendMode::None);
+
+        for (n, line) in lines.iter().enumerate() {
+            font::draw_text(
+                &mut self.canvas,
+                padding as i32,
+                (padding + n as u32 * line_height) as i32,
+                scale,
+                Color::RGB(0, 255, 0),
+                line,
+            );
+        }
     }
 
+    /// 在画面右上角显示提示，超过显示时间后清除
+    fn draw_notice(&mut self) {
+        if self
+            .notice
+            .as_ref()
+            .is_none_or(|(_, shown)| shown.elapsed() >= NOTICE_TIME)
+        {
+            self.notice = None;
+            return;
+        }
+        let Some((text, _)) = &self.notice else {
+            return;
+        };
+        let scale = (self.pixel_scale / 4).max(1);
+        let padding = 2 * scale;
+        let width = font::text_width(text, scale) + 2 * padding;
+        let (canvas_width, _) = self.canvas.output_size().unwrap();
+        let left = canvas_width.saturating_sub(width) as i32;
+
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 192));
+        self.canvas
+            .fill_rect(Rect::new(
+                left,
+                0,
+                width,
+                font::GLYPH_HEIGHT * scale + 2 * padding,
+            ))
+            .unwrap();
+        self.canvas.set_blend_mode(BlendMode::None);
+        font::draw_text(
+            &mut self.canvas,
+            left + padding as i32,
+            padding as i32,
+            scale,
+            Color::RGB(255, 255, 255),
+            text,
+        );
+    }
+
+    /// 在画面左下角绘制键盘，按下的按键为绿色。单开关扫描输入时黄色为当前扫描到的按键，
+    /// 不参与扫描的按键变暗
+    fn draw_keypad(&mut self, chip: &chip::Chip) {
+        if self.scanner.is_none() && !self.show_keypad {
+            return;
+        }
+        let pressed = chip.keypad();
+        let scale = (self.pixel_scale / 4).max(1);
+        let cell = (font::GLYPH_HEIGHT + 4) * scale;
+        let (_, height) = self.canvas.output_size().unwrap();
+        let top = height.saturating_sub(4 * cell) as i32;
+        for (row, keys) in rotation::KEYPAD.iter().enumerate() {
+            for (col, &key) in keys.iter().enumerate() {
+                let (background, text) = match &self.scanner {
+                    _ if pressed[key as usize] => (Color::RGB(0, 192, 0), Color::RGB(0, 0, 0)),
+                    Some(scanner)
+                        if scanner.pressed().is_none() && scanner.highlighted() == key =>
+                    {
+                        (Color::RGB(255, 224, 0), Color::RGB(0, 0, 0))
+                    }
+                    Some(scanner) if !scanner.keys().contains(&key) => {
+                        (Color::RGB(16, 16, 16), Color::RGB(96, 96, 96))
+                    }
+                    _ => (Color::RGB(48, 48, 48), Color::RGB(255, 255, 255)),
+                };
+                let x = col as i32 * cell as i32;
+                let y = top + row as i32 * cell as i32;
+                self.canvas.set_draw_color(background);
+                self.canvas
+                    .fill_rect(Rect::new(x, y, cell - scale, cell - scale))
+                    .unwrap();
+                font::draw_text(
+                    &mut self.canvas,
+                    x + ((cell - scale - font::GLYPH_WIDTH * scale) / 2) as i32,
+                    y + ((cell - scale - font::GLYPH_HEIGHT * scale) / 2) as i32,
+                    scale,
+                    text,
+                    &format!("{:X}", key),
+                );
+            }
+        }
+    }
+
+    /// 在画面中央绘制暂停菜单
+    fn draw_menu(&mut self) {
+        let lines = match &self.menu {
+            Menu::Closed => return,
+            Menu::Main => vec![
+                "PAUSED".to_string(),
+                String::new(),
+                "K: REMAP KEYS".to_string(),
+                "F4: RESUME".to_string(),
+            ],
+            Menu::Remap(key, keymap) => vec![
+                "REMAP KEYS".to_string(),
+                String::new(),
+                format!("PRESS KEY FOR CHIP-8 KEY {:X}", key),
+                format!("CURRENT: {}", keymap.keycode(*key).name()),
+                String::new(),
+                "ESC: CANCEL".to_string(),
+            ],
+        };
+
+        let (width, height) = self.canvas.output_size().unwrap();
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 192));
+        self.canvas.fill_rect(None).unwrap();
+        self.canvas.set_blend_mode(BlendMode::None);
+
+        let scale = (self.pixel_scale / 4).max(1);
+        let line_height = (font::GLYPH_HEIGHT + 2) * scale;
+        let top = height.saturating_sub(lines.len() as u32 * line_height) / 2;
+        for (n, line) in lines.iter().enumerate() {
+            let x = width.saturating_sub(font::text_width(line, scale)) / 2;
+            font::draw_text(
+                &mut self.canvas,
+                x as i32,
+                (top + n as u32 * line_height) as i32,
+                scale,
+                Color::RGB(255, 255, 255),
+                line,
+            );
+        }
+    }
+
+    /// 处理暂停菜单打开时的按键
+    fn menu_input(&mut self, keycode: Keycode) {
+        self.menu = match std::mem::replace(&mut self.menu, Menu::Closed) {
+            Menu::Closed => Menu::Closed,
+            Menu::Main => match keycode {
+                Keycode::F4 | Keycode::Escape => Menu::Closed,
+                Keycode::K => Menu::Remap(0, self.keymap.clone()),
+                _ => Menu::Main,
+            },
+            Menu::Remap(_, _) if keycode == Keycode::Escape => Menu::Main,
+            Menu::Remap(key, mut keymap) => {
+                keymap.set(key, keycode);
+                if key < 0xF {
+                    Menu::Remap(key + 1, keymap)
+                } else {
+                    if let Some(path) = &self.keymap_path {
+                        if let Err(e) = keymap.save(path) {
+                            println!("Couldn't save keymap {:?}: {}", path, e);
+                        }
+                    }
+                    self.keymap = keymap;
+                    Menu::Main
+                }
+            }
+        };
+    }
+
+    /// 处理窗口事件，运行一帧虚拟机并刷新画面
+    ///
+    /// 每帧执行的指令数由距上一帧的时间和 ips 计算。垂直同步模式下会阻塞到下一次垂直同步，
+    /// 否则由调用者控制调用频率 (通常为 60Hz)
     pub fn update(&mut self, chip: &mut chip::Chip) -> Result<(), chip::Exception> {
-        match self.event_pump.poll_event() {
-            Some(event) => match event {
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        for event in events {
+            match event {
                 Event::Quit { .. } => return Err(chip::Exception::Halt(0)),
-                Event::AppTerminating { timestamp } => return Err(chip::Exception::Halt(0)),
+                Event::Window {
+                    window_id,
+                    win_event: WindowEvent::Close,
+                    ..
+                } => {
+                    // 关闭调试窗口不影响模拟器运行
+                    if window_id == self.canvas.window().id() {
+                        return Err(chip::Exception::Halt(0));
+                    }
+                    if let Some(inspector) = &self.inspector {
+                        if inspector.window_id() == window_id {
+                            self.inspector = None;
+                        }
+                    }
+                    if let Some(viewer) = &self.sprite_viewer {
+                        if viewer.window_id() == window_id {
+                            self.sprite_viewer = None;
+                        }
+                    }
+                }
+                // 焦点在模拟器的几个窗口之间切换时，会先收到 FocusLost 再收到 FocusGained
+                Event::Window {
+                    win_event: WindowEvent::FocusGained,
+                    ..
+                } => self.focused = true,
+                Event::Window {
+                    win_event: WindowEvent::FocusLost,
+                    ..
+                } => self.focused = false,
+                Event::MouseWheel { window_id, y, .. } => {
+                    if let Some(viewer) = self.sprite_viewer.as_mut() {
+                        if viewer.window_id() == window_id {
+                            viewer.scroll(-y, chip.memory().len());
+                        }
+                    }
+                }
+                Event::AppTerminating { .. } => return Err(chip::Exception::Halt(0)),
+                Event::MouseButtonDown {
+                    window_id,
+                    mouse_btn: MouseButton::Left,
+                    clicks: 2,
+                    x,
+                    y,
+                    ..
+                } => {
+                    if let Some(inspector) = self.inspector.as_mut() {
+                        if inspector.window_id() == window_id {
+                            inspector.double_click(x, y);
+                        }
+                    }
+                }
+                Event::KeyDown {
+                    window_id,
+                    keycode: Some(k),
+                    ..
+                } if self
+                    .inspector
+                    .as_ref()
+                    .is_some_and(|i| i.window_id() == window_id && i.editing()) =>
+                {
+                    if let Some(inspector) = self.inspector.as_mut() {
+                        inspector.key(chip, k);
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(k), ..
+                } if self.menu != Menu::Closed => self.menu_input(k),
+                // Alt+1 ~ Alt+8 把窗口调整为 1 ~ 8 倍的画面大小
+                Event::KeyDown {
+                    keycode: Some(k),
+                    keymod,
+                    ..
+                } if keymod.intersects(Mod::LALTMOD | Mod::RALTMOD)
+                    && scale_hotkey(k).is_some() =>
+                {
+                    self.set_pixel_scale(scale_hotkey(k).unwrap_or(1));
+                }
+                Event::KeyDown {
+                    keycode: Some(k),
+                    repeat,
+                    ..
+                } if self.macros.iter().any(|m| m.key() == k) => {
+                    if let Some(index) = self.macros.iter().position(|m| m.key() == k) {
+                        if !repeat {
+                            self.play_macro(chip, index);
+                        }
+                    }
+                }
                 Event::KeyDown {
                     keycode: Some(k), ..
                 } => match k {
                     Keycode::Escape => return Err(chip::Exception::Halt(0)),
+                    Keycode::F1 => self.overlay = !self.overlay,
+                    Keycode::F2 => self.toggle_inspector(),
+                    Keycode::F3 => self.toggle_sprite_viewer(),
+                    Keycode::F5 => {
+                        self.slow_draw = !self.slow_draw;
+                        if !self.slow_draw {
+                            self.drawing = None;
+                        }
+                    }
+                    Keycode::Tab => self.next_rom = true,
+                    Keycode::F4 => {
+                        // 打开菜单时松开所有按键，避免按键一直保持按下状态
+                        self.local_keys.fill(false);
+                        self.macro_playing = None;
+                        self.advance_macro(chip, 0.0);
+                        for key in 0..16 {
+                            self.sync_key(chip, key);
+                        }
+                        self.menu = Menu::Main;
+                    }
+                    // 按键映射中用到的键仍然作为 CHIP-8 按键
+                    Keycode::Equals | Keycode::Plus | Keycode::KpPlus
+                        if self.keymap.keypad(k).is_none() =>
+                    {
+                        self.set_muted(false);
+                        self.set_volume(self.volume + VOLUME_STEP);
+                    }
+                    Keycode::Minus | Keycode::KpMinus if self.keymap.keypad(k).is_none() => {
+                        self.set_muted(false);
+                        self.set_volume(self.volume - VOLUME_STEP);
+                    }
+                    Keycode::M if self.keymap.keypad(k).is_none() => self.set_muted(!self.muted),
+                    Keycode::F6 => self.rotate(chip),
+                    Keycode::F8 => self.show_keypad = !self.show_keypad,
+                    Keycode::F7 if self.autofire.is_configured() => {
+                        self.autofire.toggle();
+                        let state = if self.autofire.enabled() { "ON" } else { "OFF" };
+                        self.show_notice(format!("AUTOFIRE {}", state));
+                        for key in 0..16 {
+                            self.sync_key(chip, key);
+                        }
+                    }
+                    Keycode::PageUp | Keycode::PageDown => {
+                        if let Some(viewer) = self.sprite_viewer.as_mut() {
+                            let columns = if k == Keycode::PageUp { -1 } else { 1 };
+                            viewer.scroll(columns, chip.memory().len());
+                        }
+                    }
+                    _ if self.scanner.as_ref().is_some_and(|s| s.switch() == k) => {
+                        // 按住开关时的重复按下事件不再按下其他按键
+                        if let Some(scanner) = self.scanner.as_mut() {
+                            if scanner.pressed().is_none() {
+                                let key = scanner.press();
+                                self.press_local(chip, key);
+                            }
+                        }
+                    }
                     _ => {
-                        if let Some(key) = Self::keycode_to_keypad(k) {
+                        if let Some(key) = self.keypad(k) {
                             // println!("Key pressed: {}", key);
-                            chip.set_keypad(key, true);
+                            self.press_local(chip, key);
                         }
                     }
                 },
+                Event::KeyUp {
+                    keycode: Some(k), ..
+                } if self.scanner.as_ref().is_some_and(|s| s.switch() == k) => {
+                    if let Some(key) = self.scanner.as_mut().and_then(|s| s.release()) {
+                        self.local_keys[key as usize] = false;
+                        self.sync_key(chip, key);
+                    }
+                }
                 Event::KeyUp {
                     keycode: Some(k), ..
                 } => {
-                    if let Some(key) = Self::keycode_to_keypad(k) {
+                    if let Some(key) = self.keypad(k) {
                         // println!("Key released: {}", key);
-                        chip.set_keypad(key, false);
+                        self.local_keys[key as usize] = false;
+                        self.sync_key(chip, key);
                     }
                 }
+                Event::DropFile { filename, .. } => {
+                    self.dropped_rom = Some(PathBuf::from(filename))
+                }
+                Event::AudioDeviceAdded {
+                    iscapture: false, ..
+                } => self.audio.iter_mut().for_each(|a| a.device_changed(true)),
+                Event::AudioDeviceRemoved {
+                    iscapture: false, ..
+                } => self.audio.iter_mut().for_each(|a| a.device_changed(false)),
                 _ => (),
-            },
-            None => (),
+            }
         }
 
-        chip.tick()?;
+        let remote_events: Vec<(u8, bool)> = self
+            .sources
+            .iter_mut()
+            .flat_map(|source| source.poll())
+            // 与 Chip::set_keypad 一样忽略超出范围的按键
+            .filter(|&(key, _)| key < 16)
+            .collect();
+        for (key, pressed) in remote_events {
+            let count = &mut self.remote_keys[key as usize];
+            *count = if pressed {
+                *count + 1
+            } else {
+                count.saturating_sub(1)
+            };
+            self.sync_key(chip, key);
+        }
 
-        if chip.tone() {
-            self.audio.resume();
-        } else {
-            self.audio.pause();
+        // 在调试窗口中修改字段时也暂停
+        let editing = self.inspector.as_ref().is_some_and(|i| i.editing());
+        let paused =
+            (self.pause_on_focus_loss && !self.focused) || self.menu != Menu::Closed || editing;
+        self.run_frame(chip, paused)?;
+        if let Some(scanner) = self.scanner.as_mut() {
+            scanner.advance(Instant::now(), paused);
+        }
+        if !paused {
+            let elapsed = self.frame_time * self.game_speed;
+            self.advance_macro(chip, elapsed);
+            let keys = self.autofire.advance(elapsed);
+            for key in (0..16).filter(|key| keys & 1 << key != 0) {
+                self.sync_key(chip, key);
+            }
+        }
+
+        // 音频设备始终运行，由包络控制声音的开关
+        if let Some(audio) = self.audio.as_mut() {
+            audio.set_tone(chip.tone() && !paused);
         }
         self.draw(chip);
+        if let Some(inspector) = self.inspector.as_mut() {
+            inspector.draw(chip, &self.symbols, &self.watches);
+        }
+        if let Some(viewer) = self.sprite_viewer.as_mut() {
+            viewer.draw(chip);
+        }
+        self.update_stats();
+
+        Ok(())
+    }
+
+    /// 运行一帧：按测得的帧时间执行指令，并以 60Hz 递减定时器
+    fn run_frame(&mut self, chip: &mut chip::Chip, paused: bool) -> Result<(), chip::Exception> {
+        let now = Instant::now();
+        let elapsed = now
+            .duration_since(self.last_frame)
+            .as_secs_f32()
+            .min(MAX_FRAME_TIME);
+        self.last_frame = now;
+        self.frame_time += (elapsed - self.frame_time) * 0.1;
+        self.frame_stats = FrameStats {
+            stack_peak: chip.stack().len(),
+            sound: chip.tone(),
+            ..FrameStats::default()
+        };
+        if paused {
+            return Ok(());
+        }
+        // 虚拟机经过的时间，游戏速度低于 1 时指令和定时器一起变慢
+        let emulated = self.frame_time * self.game_speed;
+
+        // VIP 显示模式下以帧为单位运行，定时器由 VipDisplay 在每帧开始时递减
+        if let Some(vip) = &mut self.vip {
+            self.timer_acc += emulated;
+            while self.timer_acc >= TIMER_PERIOD {
+                let stats = vip.run_frame(chip, self.ips / 60)?;
+                self.instructions += stats.instructions;
+                self.frame_stats.merge(&stats);
+                self.timer_acc -= TIMER_PERIOD;
+            }
+            return Ok(());
+        }
+
+        // 慢速播放 DXYN 时虚拟机和定时器都暂停
+        if let Some(drawing) = self.drawing.as_mut() {
+            if drawing.advance(self.frame_time, self.slow_draw_rate) {
+                return Ok(());
+            }
+            self.drawing = None;
+        }
+
+        self.budget += self.ips as f32 * emulated;
+        while self.budget >= 1.0 {
+            let drawing = self
+                .slow_draw
+                .then(|| SlowDraw::before_step(chip))
+                .flatten();
+            chip.step()?;
+            self.frame_stats.record_step(chip);
+            self.instructions += 1;
+            self.budget -= 1.0;
+            if drawing.is_some() {
+                self.drawing = drawing;
+                self.budget = 0.0;
+                break;
+            }
+        }
+
+        self.timer_acc += emulated;
+        while self.timer_acc >= TIMER_PERIOD {
+            chip.tick_timers();
+            self.timer_acc -= TIMER_PERIOD;
+        }
 
         Ok(())
     }
 
-    fn keycode_to_keypad(keycode: Keycode) -> Option<u8> {
-        match keycode {
-            Keycode::Num1 => Some(1u8),
-            Keycode::Num2 => Some(2u8),
-            Keycode::Num3 => Some(3u8),
-            Keycode::Num4 => Some(0xCu8),
-            Keycode::Q => Some(4u8),
-            Keycode::W => Some(5u8),
-            Keycode::E => Some(6u8),
-            Keycode::R => Some(0xDu8),
-            Keycode::A => Some(7u8),
-            Keycode::S => Some(8u8),
-            Keycode::D => Some(9u8),
-            Keycode::F => Some(0xEu8),
-            Keycode::Z => Some(0xAu8),
-            Keycode::X => Some(0u8),
-            Keycode::C => Some(0xBu8),
-            Keycode::V => Some(0xFu8),
-            _ => None,
+    /// 获取上一次 update 的执行统计
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    /// 弹出对话框显示虚拟机的错误，并询问用户如何处理
+    ///
+    /// 无法显示对话框时 (例如没有图形界面) 在终端打印错误并返回 Quit
+    pub fn show_error(&mut self, chip: &chip::Chip, error: &chip::Exception) -> ErrorAction {
+        if let Some(audio) = self.audio.as_mut() {
+            audio.set_tone(false);
+        }
+
+        // 出错时 PC 已经指向下一条指令，显示出错指令的地址
+        let details = format!(
+            "{}\n\nPC: 0x{:04X}    Opcode: 0x{:04X}",
+            error,
+            chip.opcode_addr(),
+            chip.opcode()
+        );
+        // 宽松模式只能跳过非法指令，其他错误继续运行也会再次出错
+        let skippable = matches!(error, chip::Exception::IllegalOpcode(_));
+        let message = format!(
+            "The program stopped because of an error:\n\n{}\n\nReset restarts the program.{}",
+            details,
+            if skippable {
+                " Continue skips illegal instructions from now on."
+            } else {
+                ""
+            }
+        );
+        let mut buttons = vec![ButtonData {
+            flags: MessageBoxButtonFlag::RETURNKEY_DEFAULT,
+            button_id: 0,
+            text: "Reset",
+        }];
+        if skippable {
+            buttons.push(ButtonData {
+                flags: MessageBoxButtonFlag::NOTHING,
+                button_id: 1,
+                text: "Continue",
+            });
+        }
+        buttons.push(ButtonData {
+            flags: MessageBoxButtonFlag::ESCAPEKEY_DEFAULT,
+            button_id: 2,
+            text: "Quit",
+        });
+
+        match show_message_box(
+            MessageBoxFlag::ERROR,
+            &buttons,
+            TITLE,
+            &message,
+            Some(self.canvas.window()),
+            None,
+        ) {
+            Ok(ClickedButton::CustomButton(button)) => match button.button_id {
+                0 => ErrorAction::Reset,
+                1 => ErrorAction::Continue,
+                _ => ErrorAction::Quit,
+            },
+            Ok(ClickedButton::CloseButton) => ErrorAction::Quit,
+            Err(_) => {
+                println!("Error occured: {}", details);
+                ErrorAction::Quit
+            }
+        }
+    }
+
+    /// 打开或关闭调试窗口
+    fn toggle_inspector(&mut self) {
+        if self.inspector.take().is_some() {
+            return;
+        }
+        // 打不开窗口时只提示，不影响游戏运行
+        match Inspector::new(&self.video) {
+            Ok(inspector) => self.inspector = Some(inspector),
+            Err(e) => {
+                println!("Couldn't open the inspector: {}", e);
+                self.show_notice("NO INSPECTOR".to_string());
+            }
+        }
+    }
+
+    /// 打开或关闭精灵查看窗口
+    fn toggle_sprite_viewer(&mut self) {
+        if self.sprite_viewer.take().is_some() {
+            return;
+        }
+        match SpriteViewer::new(&self.video) {
+            Ok(viewer) => self.sprite_viewer = Some(viewer),
+            Err(e) => {
+                println!("Couldn't open the sprite viewer: {}", e);
+                self.show_notice("NO SPRITE VIEWER".to_string());
+            }
         }
     }
 }
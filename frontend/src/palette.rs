@@ -0,0 +1,131 @@
+use sdl2::pixels::Color;
+use std::fmt;
+use std::str::FromStr;
+
+/// 显示配色，foreground 为点亮像素的颜色，background 为背景色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub foreground: Color,
+    pub background: Color,
+}
+
+/// 内置配色
+const PRESETS: [(&str, Palette); 5] = [
+    (
+        "classic",
+        Palette {
+            foreground: Color::RGB(0xFF, 0xFF, 0xFF),
+            background: Color::RGB(0x00, 0x00, 0x00),
+        },
+    ),
+    (
+        "green",
+        Palette {
+            foreground: Color::RGB(0x33, 0xFF, 0x33),
+            background: Color::RGB(0x00, 0x1A, 0x00),
+        },
+    ),
+    (
+        "amber",
+        Palette {
+            foreground: Color::RGB(0xFF, 0xB0, 0x00),
+            background: Color::RGB(0x1A, 0x0F, 0x00),
+        },
+    ),
+    (
+        "lcd",
+        Palette {
+            foreground: Color::RGB(0x0F, 0x38, 0x0F),
+            background: Color::RGB(0x9B, 0xBC, 0x0F),
+        },
+    ),
+    (
+        "inverted",
+        Palette {
+            foreground: Color::RGB(0x00, 0x00, 0x00),
+            background: Color::RGB(0xFF, 0xFF, 0xFF),
+        },
+    ),
+];
+
+impl Default for Palette {
+    fn default() -> Self {
+        PRESETS[0].1
+    }
+}
+
+impl Palette {
+    /// 内置配色的名称
+    pub fn presets() -> impl Iterator<Item = &'static str> {
+        PRESETS.iter().map(|(name, _)| *name)
+    }
+}
+
+/// 解析 `#RRGGBB` 或 `RRGGBB` 格式的颜色
+fn parse_color(s: &str) -> Result<Color, String> {
+    let hex = s.trim().trim_start_matches('#');
+    let value = u32::from_str_radix(hex, 16)
+        .ok()
+        .filter(|_| hex.len() == 6)
+        .ok_or_else(|| format!("invalid color: {}", s))?;
+    Ok(Color::RGB(
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8,
+    ))
+}
+
+/// 支持内置配色名称，或者 `<前景色>,<背景色>` 形式的自定义配色，例如 `#FFB000,#000000`
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, palette)) = PRESETS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s.trim()))
+        {
+            return Ok(*palette);
+        }
+        let (fg, bg) = s.split_once(',').ok_or_else(|| {
+            format!(
+                "unknown palette: {} (expected one of {} or <fg>,<bg>)",
+                s,
+                Palette::presets().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        Ok(Palette {
+            foreground: parse_color(fg)?,
+            background: parse_color(bg)?,
+        })
+    }
+}
+
+/// 内置配色输出名称，其他配色输出 `#RRGGBB,#RRGGBB`，可以再解析回来
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((name, _)) = PRESETS.iter().find(|(_, palette)| palette == self) {
+            return f.write_str(name);
+        }
+        let hex = |c: Color| format!("#{:02X}{:02X}{:02X}", c.r, c.g, c.b);
+        write!(f, "{},{}", hex(self.foreground), hex(self.background))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_palette() {
+        assert_eq!("Amber".parse::<Palette>().unwrap(), PRESETS[2].1);
+        let palette: Palette = "#FFB000,101010".parse().unwrap();
+        assert_eq!(palette.foreground, Color::RGB(0xFF, 0xB0, 0x00));
+        assert_eq!(palette.background, Color::RGB(0x10, 0x10, 0x10));
+        assert!("#FFB000".parse::<Palette>().is_err());
+        assert!("#FFB0,#000000".parse::<Palette>().is_err());
+
+        assert_eq!(PRESETS[2].1.to_string(), "amber");
+        assert_eq!(palette.to_string(), "#FFB000,#101010");
+        assert_eq!(palette.to_string().parse::<Palette>().unwrap(), palette);
+    }
+}
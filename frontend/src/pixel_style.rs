@@ -0,0 +1,76 @@
+use std::str::FromStr;
+
+/// 熄灭的像素在点阵模式下的不透明度，模拟 LED 点阵中未点亮的灯
+pub(crate) const UNLIT_ALPHA: u8 = 24;
+/// 点阵模式下点周围光晕的最大不透明度 (0.0 ~ 1.0)
+const GLOW_ALPHA: f32 = 0.35;
+
+/// 像素的绘制方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelStyle {
+    /// 实心方块
+    #[default]
+    Square,
+    /// 带光晕的圆点，模拟 LED 点阵和早期显示器
+    Dot,
+}
+
+impl FromStr for PixelStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "square" => Ok(PixelStyle::Square),
+            "dot" => Ok(PixelStyle::Dot),
+            _ => Err(format!(
+                "unknown pixel style: {} (expected square or dot)",
+                s
+            )),
+        }
+    }
+}
+
+/// 生成边长为 size 的圆点的不透明度，按行排列。gap 为相邻圆点之间的空隙占像素边长的比例，
+/// 空隙中是从圆点边缘向外衰减的光晕
+pub(crate) fn dot_alpha(size: u32, gap: f32) -> Vec<u8> {
+    let half = size as f32 / 2.0;
+    let radius = half * (1.0 - gap.clamp(0.0, 0.9));
+    let mut alpha = Vec::with_capacity((size * size) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let dx = x as f32 + 0.5 - half;
+            let dy = y as f32 + 0.5 - half;
+            let d = (dx * dx + dy * dy).sqrt();
+            // 边缘用一个像素的过渡做抗锯齿，之外是二次衰减的光晕
+            let core = (radius + 0.5 - d).clamp(0.0, 1.0);
+            let glow = if d > radius && half > radius {
+                GLOW_ALPHA * (1.0 - ((d - radius) / (half - radius)).min(1.0)).powi(2)
+            } else {
+                0.0
+            };
+            alpha.push((core.max(glow) * 255.0).round() as u8);
+        }
+    }
+    alpha
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_alpha() {
+        let alpha = dot_alpha(16, 0.25);
+        let at = |x: usize, y: usize| alpha[y * 16 + x];
+        assert_eq!(alpha.len(), 256);
+        assert_eq!(at(8, 8), 255);
+        // 空隙中只有较暗的光晕，角落完全透明
+        assert!(at(0, 8) > 0 && at(0, 8) < 128);
+        assert_eq!(at(0, 0), 0);
+        // 没有空隙时圆点内切于像素
+        assert_eq!(dot_alpha(16, 0.0)[8 * 16 + 1], 255);
+
+        assert_eq!("Dot".parse::<PixelStyle>(), Ok(PixelStyle::Dot));
+        assert!("round".parse::<PixelStyle>().is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use crate::KeypadSource;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// 通过 TCP 接收远程按键事件
+///
+/// 客户端每行发送一条命令：`down <key>` 或 `up <key>`，key 为 0 ~ F 的十六进制数字，
+/// 例如 `down a`。可以同时连接多个客户端，连接断开时该客户端按下的键会自动松开
+pub struct RemoteKeypad {
+    addr: SocketAddr,
+    rx: Receiver<(u8, bool)>,
+}
+
+impl RemoteKeypad {
+    /// 在指定地址上监听远程按键连接
+    pub fn listen<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                thread::spawn(move || serve(stream, tx));
+            }
+        });
+
+        Ok(Self { addr, rx })
+    }
+
+    /// 获取实际监听的地址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl KeypadSource for RemoteKeypad {
+    fn poll(&mut self) -> Vec<(u8, bool)> {
+        self.rx.try_iter().collect()
+    }
+}
+
+/// 处理一个客户端连接
+fn serve(stream: TcpStream, tx: Sender<(u8, bool)>) {
+    let mut held = [false; 16];
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_command(&line) {
+            Ok((key, pressed)) => {
+                if held[key as usize] != pressed {
+                    held[key as usize] = pressed;
+                    if tx.send((key, pressed)).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(writer, "error: {}", e);
+            }
+        }
+    }
+
+    // 连接断开，松开该客户端按下的所有按键
+    for (key, pressed) in held.iter().enumerate() {
+        if *pressed {
+            let _ = tx.send((key as u8, false));
+        }
+    }
+}
+
+/// 解析一条按键命令
+fn parse_command(line: &str) -> Result<(u8, bool), String> {
+    let mut parts = line.split_whitespace();
+    let pressed = match parts.next() {
+        Some(cmd) if cmd.eq_ignore_ascii_case("down") => true,
+        Some(cmd) if cmd.eq_ignore_ascii_case("up") => false,
+        _ => return Err(format!("unknown command {:?}", line.trim())),
+    };
+    let key = parts
+        .next()
+        .and_then(|key| u8::from_str_radix(key, 16).ok())
+        .filter(|key| *key < 16)
+        .ok_or_else(|| format!("invalid key in {:?}", line.trim()))?;
+    if parts.next().is_some() {
+        return Err(format!("unexpected argument in {:?}", line.trim()));
+    }
+    Ok((key, pressed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("down a"), Ok((0xA, true)));
+        assert_eq!(parse_command("UP 0"), Ok((0x0, false)));
+        assert!(parse_command("down 10").is_err());
+        assert!(parse_command("press 1").is_err());
+        assert!(parse_command("up").is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// COSMAC VIP 键盘的布局，旋转按键时以它为准
+pub(crate) const KEYPAD: [[u8; 4]; 4] = [
+    [0x1, 0x2, 0x3, 0xC],
+    [0x4, 0x5, 0x6, 0xD],
+    [0x7, 0x8, 0x9, 0xE],
+    [0xA, 0x0, 0xB, 0xF],
+];
+
+/// 画面顺时针旋转的角度，用于竖放的显示器和掌机
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    R0,
+    R90,
+    R180,
+    R270,
+}
+
+impl Rotation {
+    /// 再顺时针旋转 90 度
+    pub fn next(self) -> Self {
+        match self {
+            Rotation::R0 => Rotation::R90,
+            Rotation::R90 => Rotation::R180,
+            Rotation::R180 => Rotation::R270,
+            Rotation::R270 => Rotation::R0,
+        }
+    }
+
+    /// 画面是否变为竖向
+    pub fn is_portrait(self) -> bool {
+        matches!(self, Rotation::R90 | Rotation::R270)
+    }
+
+    /// 旋转后画面的宽和高 (CHIP-8 像素)
+    pub fn size(self) -> (usize, usize) {
+        if self.is_portrait() {
+            (chip::DISP_HEIGHT, chip::DISP_WIDTH)
+        } else {
+            (chip::DISP_WIDTH, chip::DISP_HEIGHT)
+        }
+    }
+
+    /// 像素 (x, y) 在旋转后画面上的位置
+    pub fn pixel(self, x: usize, y: usize) -> (usize, usize) {
+        let (w, h) = (chip::DISP_WIDTH, chip::DISP_HEIGHT);
+        match self {
+            Rotation::R0 => (x, y),
+            Rotation::R90 => (h - 1 - y, x),
+            Rotation::R180 => (w - 1 - x, h - 1 - y),
+            Rotation::R270 => (y, w - 1 - x),
+        }
+    }
+
+    /// 让键盘跟随画面旋转：按下键盘上某个位置的按键，得到旋转前画面中处于该位置的按键
+    pub fn key(self, key: u8) -> u8 {
+        let (row, col) = (0..16)
+            .map(|i| (i / 4, i % 4))
+            .find(|&(r, c)| KEYPAD[r][c] == key & 0xF)
+            .unwrap();
+        let (r, c) = match self {
+            Rotation::R0 => (row, col),
+            Rotation::R90 => (3 - col, row),
+            Rotation::R180 => (3 - row, 3 - col),
+            Rotation::R270 => (col, 3 - row),
+        };
+        KEYPAD[r][c]
+    }
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let degrees = match self {
+            Rotation::R0 => 0,
+            Rotation::R90 => 90,
+            Rotation::R180 => 180,
+            Rotation::R270 => 270,
+        };
+        write!(f, "{}", degrees)
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "0" => Ok(Rotation::R0),
+            "90" => Ok(Rotation::R90),
+            "180" => Ok(Rotation::R180),
+            "270" => Ok(Rotation::R270),
+            _ => Err(format!(
+                "invalid rotation: {} (expected 0, 90, 180 or 270)",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        // 左上角的像素顺时针转到右上角
+        assert_eq!(Rotation::R90.pixel(0, 0), (31, 0));
+        assert_eq!(Rotation::R180.pixel(0, 0), (63, 31));
+        assert_eq!(Rotation::R270.pixel(0, 0), (0, 63));
+        assert_eq!(Rotation::R90.size(), (32, 64));
+
+        // 画面顺时针旋转后，原来在左边的按键 7 位于顶部
+        assert_eq!(Rotation::R90.key(0x2), 0x7);
+        assert_eq!(Rotation::R180.key(0x1), 0xF);
+        for key in 0..16 {
+            assert_eq!(Rotation::R270.key(Rotation::R90.key(key)), key);
+            assert_eq!(Rotation::R0.key(key), key);
+        }
+
+        let rotation: Rotation = "270".parse().unwrap();
+        assert_eq!(rotation.next(), Rotation::R0);
+        assert_eq!(rotation.to_string(), "270");
+        assert!("45".parse::<Rotation>().is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use sdl2::keyboard::Keycode;
+use std::time::{Duration, Instant};
+
+/// 单开关扫描输入：依次高亮 CHIP-8 按键，按下开关时按下当前高亮的按键，
+/// 让只能操作一个按键的玩家也能玩
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    keys: Vec<u8>, // 依次扫描的 CHIP-8 按键
+    interval: Duration,
+    switch: Keycode,
+    index: usize,        // 当前高亮的按键
+    since: Instant,      // 开始高亮当前按键的时间
+    pressed: Option<u8>, // 开关按下时按下的按键，按住开关时暂停扫描
+}
+
+impl Scanner {
+    /// keys 为依次扫描的按键，interval 为每个按键的高亮时间，switch 为开关的 SDL 按键名
+    pub fn new(keys: Vec<u8>, interval: Duration, switch: &str) -> Result<Self, String> {
+        if keys.is_empty() || keys.iter().any(|&key| key > 0xF) {
+            return Err("scan keys must be 1 to 16 keys from 0 to F".to_string());
+        }
+        if interval.is_zero() {
+            return Err("scan interval must be positive".to_string());
+        }
+        let switch =
+            Keycode::from_name(switch).ok_or_else(|| format!("unknown key name: {}", switch))?;
+        Ok(Self {
+            keys,
+            interval,
+            switch,
+            index: 0,
+            since: Instant::now(),
+            pressed: None,
+        })
+    }
+
+    pub fn switch(&self) -> Keycode {
+        self.switch
+    }
+
+    /// 当前高亮的按键
+    pub fn highlighted(&self) -> u8 {
+        self.keys[self.index]
+    }
+
+    /// 开关按下时按下的按键
+    pub fn pressed(&self) -> Option<u8> {
+        self.pressed
+    }
+
+    pub fn keys(&self) -> &[u8] {
+        &self.keys
+    }
+
+    /// 按时间移动高亮，按住开关或 hold 为真 (例如暂停时) 时不移动
+    pub fn advance(&mut self, now: Instant, hold: bool) {
+        if hold || self.pressed.is_some() {
+            self.since = now;
+            return;
+        }
+        while now.duration_since(self.since) >= self.interval {
+            self.since += self.interval;
+            self.index = (self.index + 1) % self.keys.len();
+        }
+    }
+
+    /// 按下开关，返回要按下的 CHIP-8 按键
+    pub fn press(&mut self) -> u8 {
+        let key = self.highlighted();
+        self.pressed = Some(key);
+        key
+    }
+
+    /// 松开开关，返回要松开的 CHIP-8 按键
+    pub fn release(&mut self) -> Option<u8> {
+        self.pressed.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scanner() {
+        let interval = Duration::from_millis(500);
+        let mut scanner = Scanner::new(vec![4, 6, 5], interval, "Space").unwrap();
+        let start = scanner.since;
+        assert_eq!(scanner.highlighted(), 4);
+        scanner.advance(start + interval * 5, false);
+        assert_eq!(scanner.highlighted(), 5);
+
+        // 按住开关时停在当前按键，松开后重新计时
+        assert_eq!(scanner.press(), 5);
+        scanner.advance(start + interval * 10, false);
+        assert_eq!(scanner.highlighted(), 5);
+        assert_eq!(scanner.release(), Some(5));
+        assert_eq!(scanner.release(), None);
+        scanner.advance(start + interval * 11, false);
+        assert_eq!(scanner.highlighted(), 4);
+        scanner.advance(start + interval * 20, true);
+        assert_eq!(scanner.highlighted(), 4);
+
+        assert!(Scanner::new(vec![], interval, "Space").is_err());
+        assert!(Scanner::new(vec![0x10], interval, "Space").is_err());
+        assert!(Scanner::new(vec![1], interval, "NoSuchKey").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+use audio::{AudioConfig, AudioOutput, Oscillator};
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired, AudioStatus};
+use sdl2::AudioSubsystem;
+
+/// SDL 音频回调，从共享的振荡器拉取采样
+struct Callback(Oscillator);
+
+impl AudioCallback for Callback {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [Self::Channel]) {
+        self.0.fill(out);
+    }
+}
+
+/// 通过 SDL 输出声音，音频设备始终运行，由包络控制声音的开关。
+/// 设备被拔出后改用默认设备，指定的设备重新插入后切换回去
+pub struct SdlOutput {
+    subsystem: AudioSubsystem,
+    config: AudioConfig,
+    device: Option<AudioDevice<Callback>>, // 无法打开任何设备时为 None，不输出声音
+    preferred: bool,                       // 是否打开了指定的设备
+    tone: bool,
+}
+
+impl SdlOutput {
+    /// 打开配置中的输出设备，不存在时使用默认设备，都无法打开时不输出声音
+    pub fn open(subsystem: AudioSubsystem, config: &AudioConfig) -> Self {
+        let mut output = Self {
+            subsystem,
+            config: config.clone(),
+            device: None,
+            preferred: false,
+            tone: false,
+        };
+        output.reopen();
+        output
+    }
+
+    /// 可用的输出设备名
+    pub fn devices(subsystem: &AudioSubsystem) -> Vec<String> {
+        let count = subsystem.num_audio_playback_devices().unwrap_or(0);
+        (0..count)
+            .filter_map(|i| subsystem.audio_playback_device_name(i).ok())
+            .collect()
+    }
+
+    /// 处理输出设备的插入和拔出
+    pub fn device_changed(&mut self, added: bool) {
+        let lost = self
+            .device
+            .as_ref()
+            .is_none_or(|device| device.status() != AudioStatus::Playing);
+        // SDL 初始化时会为每个已有的设备发送一次插入事件，只有指定的设备出现时才切换
+        let returned = added
+            && !self.preferred
+            && self
+                .config
+                .device
+                .as_ref()
+                .is_some_and(|name| Self::devices(&self.subsystem).contains(name));
+        if lost || returned {
+            self.reopen();
+        }
+    }
+
+    /// 设置音量 0.0 ~ 1.0，重新打开设备后仍然有效
+    pub fn set_volume(&mut self, volume: f32) {
+        self.config.volume = volume;
+        if let Some(device) = self.device.as_mut() {
+            device.lock().0.set_volume(volume);
+        }
+    }
+
+    fn reopen(&mut self) {
+        // 先关闭旧设备，有的后端不允许重复打开同一个设备
+        self.device = None;
+        let name = self.config.device.clone();
+        if let Some(name) = &name {
+            match self.open_device(Some(name)) {
+                Ok(device) => {
+                    self.device = Some(device);
+                    self.preferred = true;
+                }
+                Err(e) => println!("Couldn't open audio device {:?}: {}", name, e),
+            }
+        }
+        if self.device.is_none() {
+            match self.open_device(None) {
+                Ok(device) => {
+                    self.device = Some(device);
+                    self.preferred = name.is_none();
+                }
+                Err(e) => println!("Couldn't open audio output: {}", e),
+            }
+        }
+        self.set_tone(self.tone);
+    }
+
+    fn open_device(&self, name: Option<&str>) -> Result<AudioDevice<Callback>, String> {
+        let device = self.subsystem.open_playback(
+            name,
+            &AudioSpecDesired {
+                freq: Some(44100),
+                channels: Some(1),
+                samples: self.config.buffer_size,
+            },
+            |spec| Callback(Oscillator::new(&self.config, spec.freq as u32)),
+        )?;
+        device.resume();
+        Ok(device)
+    }
+}
+
+impl AudioOutput for SdlOutput {
+    fn set_tone(&mut self, on: bool) {
+        self.tone = on;
+        if let Some(device) = self.device.as_mut() {
+            device.lock().0.set_gate(on);
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use crate::font;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::video::Window;
+
+/// 画完所有像素后继续显示 VF 结果的时间 (秒)
+const HOLD_TIME: f32 = 1.0;
+
+/// 慢速绘图模式中正在逐像素播放的一次 DXYN
+///
+/// 执行 DXYN 前记录画面和精灵，执行后按时间逐个显示精灵的每一位，
+/// 同时高亮正在读取的精灵字节、当前像素和发生碰撞的像素，画完后显示 VF
+pub(crate) struct SlowDraw {
+    before: Vec<bool>, // 执行前的画面
+    opcode: u16,
+    addr: u16,     // 精灵数据的地址 (I)
+    rows: Vec<u8>, // 精灵数据
+    x: usize,
+    y: usize,
+    elapsed: f32, // 已经播放的时间 (秒)
+}
+
+impl SlowDraw {
+    /// 下一条指令是 DXYN 时在执行前调用，记录执行前的状态
+    pub fn before_step(chip: &chip::Chip) -> Option<Self> {
+        let opcode = chip.opcode_at(chip.pc())?;
+        if opcode & 0xF000 != 0xD000 {
+            return None;
+        }
+        let v = chip.registers();
+        let addr = chip.i();
+        let n = (opcode & 0xF) as usize;
+        let rows = chip
+            .memory()
+            .get(addr as usize..addr as usize + n)?
+            .to_vec();
+        Some(Self {
+            before: chip.framebuffer().to_vec(),
+            opcode,
+            addr,
+            rows,
+            x: v[(opcode >> 8 & 0xF) as usize] as usize,
+            y: v[(opcode >> 4 & 0xF) as usize] as usize,
+            elapsed: 0.0,
+        })
+    }
+
+    /// 播放 dt 秒，播放结束后返回 false
+    pub fn advance(&mut self, dt: f32, rate: u32) -> bool {
+        self.elapsed += dt;
+        self.shown(rate) < self.rows.len() * 8 || self.elapsed < self.duration(rate)
+    }
+
+    fn duration(&self, rate: u32) -> f32 {
+        (self.rows.len() * 8) as f32 / rate.max(1) as f32 + HOLD_TIME
+    }
+
+    /// 已经显示的精灵位数
+    fn shown(&self, rate: u32) -> usize {
+        ((self.elapsed * rate as f32) as usize).min(self.rows.len() * 8)
+    }
+
+    /// 第 k 个精灵位在画面上的下标，与 DXYN 一样在边缘回绕
+    fn index(&self, k: usize) -> usize {
+        let (row, col) = (k / 8, k % 8);
+        (self.x + col) % chip::DISP_WIDTH + (self.y + row) % chip::DISP_HEIGHT * chip::DISP_WIDTH
+    }
+
+    fn bit(&self, k: usize) -> bool {
+        self.rows[k / 8] & (0x80 >> (k % 8)) != 0
+    }
+
+    /// 当前应显示的画面：已显示的位与执行前的画面异或
+    pub fn screen(&self, rate: u32) -> Vec<bool> {
+        let mut screen = self.before.clone();
+        for k in 0..self.shown(rate) {
+            screen[self.index(k)] ^= self.bit(k);
+        }
+        screen
+    }
+
+    /// 在画面上绘制高亮和说明，cell 给出画面上第 index 个像素所在的矩形
+    pub fn draw_highlights(
+        &self,
+        canvas: &mut Canvas<Window>,
+        rate: u32,
+        cell: &dyn Fn(usize) -> Rect,
+        text_scale: u32,
+        vf: u8,
+    ) {
+        let shown = self.shown(rate);
+
+        // 擦除了已点亮像素的位
+        canvas.set_draw_color(Color::RGB(255, 0, 0));
+        for k in (0..shown).filter(|k| self.bit(*k) && self.before[self.index(*k)]) {
+            let _ = canvas.fill_rect(cell(self.index(k)));
+        }
+        // 精灵覆盖的区域和正在处理的像素
+        canvas.set_draw_color(Color::RGB(0, 96, 255));
+        for k in 0..self.rows.len() * 8 {
+            let _ = canvas.draw_rect(cell(self.index(k)));
+        }
+        if shown < self.rows.len() * 8 {
+            canvas.set_draw_color(Color::RGB(255, 255, 0));
+            let _ = canvas.draw_rect(cell(self.index(shown)));
+        }
+
+        let current = shown / 8;
+        let mut lines = vec![format!(
+            "{:04X} DRW X={:02X} Y={:02X} I={:03X}",
+            self.opcode, self.x, self.y, self.addr
+        )];
+        for (n, byte) in self.rows.iter().enumerate() {
+            let marker = if n == current { '>' } else { ' ' };
+            lines.push(format!(
+                "{}{:03X}: {:08b}",
+                marker,
+                self.addr as usize + n,
+                byte
+            ));
+        }
+        if shown == self.rows.len() * 8 {
+            let result = if vf == 1 { "COLLISION" } else { "NO COLLISION" };
+            lines.push(format!("VF = {}  {}", vf, result));
+        }
+
+        let padding = 2 * text_scale;
+        let line_height = (font::GLYPH_HEIGHT + 2) * text_scale;
+        let width = lines
+            .iter()
+            .map(|line| font::text_width(line, text_scale))
+            .max()
+            .unwrap_or(0)
+            + 2 * padding;
+        let height = lines.len() as u32 * line_height + padding;
+        let (canvas_width, canvas_height) = canvas.output_size().unwrap_or((width, height));
+        let x = canvas_width.saturating_sub(width) as i32;
+        let y = canvas_height.saturating_sub(height) as i32;
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 192));
+        let _ = canvas.fill_rect(Rect::new(x, y, width, height));
+        canvas.set_blend_mode(BlendMode::None);
+        for (n, line) in lines.iter().enumerate() {
+            let color = if n == current + 1 {
+                Color::RGB(255, 255, 0)
+            } else {
+                Color::RGB(0, 255, 0)
+            };
+            font::draw_text(
+                canvas,
+                x + padding as i32,
+                y + (padding + n as u32 * line_height) as i32,
+                text_scale,
+                color,
+                line,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_draw() {
+        let mut chip = chip::Chip::new(0);
+        // 200: LD I, 206; 202: LD V0, 3E; 204: DRW V0, V1, 1; 206: 0xFF
+        chip.load_rom(0x200, &[0xA2, 0x06, 0x60, 0x3E, 0xD0, 0x11, 0xFF])
+            .unwrap();
+        chip.step().unwrap();
+        assert!(SlowDraw::before_step(&chip).is_none());
+        chip.step().unwrap();
+
+        let mut drawing = SlowDraw::before_step(&chip).unwrap();
+        chip.step().unwrap();
+        // 每秒 8 个像素，0.5 秒后画了 4 个，其中 2 个在右边缘回绕到左边
+        assert!(drawing.advance(0.5, 8));
+        let screen = drawing.screen(8);
+        assert_eq!(screen[0x3E..0x40], [true, true]);
+        assert_eq!(screen[0..3], [true, true, false]);
+        assert!(drawing.advance(0.5, 8));
+        assert_eq!(drawing.screen(8), chip.framebuffer());
+        assert!(!drawing.advance(HOLD_TIME, 8));
+    }
+}
@@ -0,0 +1,102 @@
+use crate::font;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::VideoSubsystem;
+
+/// 每个内存字节显示为一行 8 个像素，每个像素的边长
+const PIXEL: u32 = 4;
+/// 每列显示的字节数
+const COLUMN_BYTES: usize = 64;
+/// 列数
+const COLUMNS: usize = 16;
+/// 列之间的间隔
+const GAP: u32 = 4;
+/// 每页显示的字节数
+const PAGE_BYTES: usize = COLUMN_BYTES * COLUMNS;
+/// DXYN 最多绘制 15 行，以 I 开始的这些字节会被高亮
+const SPRITE_MAX_ROWS: usize = 15;
+
+const TEXT_SCALE: u32 = 2;
+const HEADER_HEIGHT: u32 = (font::GLYPH_HEIGHT + 4) * TEXT_SCALE;
+
+/// 把内存按 8 x N 精灵的方式显示出来的工具窗口
+pub(crate) struct SpriteViewer {
+    canvas: Canvas<Window>,
+    offset: usize, // 当前页的起始地址
+}
+
+impl SpriteViewer {
+    /// 窗口创建失败时返回错误信息
+    pub fn new(video: &VideoSubsystem) -> Result<Self, String> {
+        let window = video
+            .window(
+                "CHIP-8 Sprite Viewer",
+                COLUMNS as u32 * (8 * PIXEL + GAP) + GAP,
+                HEADER_HEIGHT + COLUMN_BYTES as u32 * PIXEL + GAP,
+            )
+            .build()
+            .map_err(|e| e.to_string())?;
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            canvas,
+            offset: chip::ENTRY_ADDR as usize,
+        })
+    }
+
+    pub fn window_id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    /// 按列滚动显示的内存，正数向高地址滚动
+    pub fn scroll(&mut self, columns: i32, mem_size: usize) {
+        let delta = columns as isize * COLUMN_BYTES as isize;
+        let max = mem_size.saturating_sub(PAGE_BYTES) as isize;
+        self.offset = (self.offset as isize + delta).clamp(0, max) as usize;
+    }
+
+    pub fn draw(&mut self, chip: &chip::Chip) {
+        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+        self.canvas.clear();
+
+        let mem = chip.memory();
+        let i = chip.i() as usize;
+        let end = (self.offset + PAGE_BYTES).min(mem.len());
+        font::draw_text(
+            &mut self.canvas,
+            GAP as i32,
+            (2 * TEXT_SCALE) as i32,
+            TEXT_SCALE,
+            Color::RGB(0, 255, 0),
+            &format!("ADDR {:04X}-{:04X}  I {:04X}", self.offset, end - 1, i),
+        );
+
+        for (n, byte) in mem[self.offset..end].iter().enumerate() {
+            let addr = self.offset + n;
+            let x = GAP + (n / COLUMN_BYTES) as u32 * (8 * PIXEL + GAP);
+            let y = HEADER_HEIGHT + (n % COLUMN_BYTES) as u32 * PIXEL;
+            let highlighted = (i..i + SPRITE_MAX_ROWS).contains(&addr);
+
+            if highlighted {
+                self.canvas.set_draw_color(Color::RGB(64, 64, 0));
+                self.canvas
+                    .fill_rect(Rect::new(x as i32, y as i32, 8 * PIXEL, PIXEL))
+                    .unwrap();
+            }
+            self.canvas.set_draw_color(if highlighted {
+                Color::RGB(255, 255, 0)
+            } else {
+                Color::RGB(255, 255, 255)
+            });
+            for bit in 0..8 {
+                if byte & (0x80 >> bit) != 0 {
+                    let rect = Rect::new((x + bit * PIXEL) as i32, y as i32, PIXEL, PIXEL);
+                    self.canvas.fill_rect(rect).unwrap();
+                }
+            }
+        }
+        self.canvas.present();
+    }
+}
@@ -0,0 +1,130 @@
+//! 编译时把 ROM 嵌入程序的 `include_ch8!` 宏，用于没有文件系统的嵌入式和 WASM 构建
+
+use chip::{analysis, Chip, Instruction, Variant};
+use proc_macro::TokenStream;
+use quote::quote;
+use std::path::PathBuf;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, Ident, LitStr, Token};
+
+/// 宏参数：ROM 路径和可选的 `variant = "chip8e"`
+struct Args {
+    path: LitStr,
+    variant: Option<Variant>,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let path = input.parse()?;
+        let mut variant = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            let key: Ident = input.parse()?;
+            if key != "variant" {
+                return Err(syn::Error::new(key.span(), "expected `variant = \"...\"`"));
+            }
+            input.parse::<Token![=]>()?;
+            let lit: LitStr = input.parse()?;
+            let parsed = lit
+                .value()
+                .parse()
+                .map_err(|e: String| syn::Error::new(lit.span(), e))?;
+            variant = Some(parsed);
+            input.parse::<Option<Token![,]>>()?;
+        }
+        Ok(Self { path, variant })
+    }
+}
+
+/// 检查 ROM 能否装入内存，指定变体时还检查从入口可以到达的指令都属于该变体
+fn validate(rom: &[u8], variant: Option<Variant>) -> Result<(), String> {
+    if rom.is_empty() {
+        return Err("rom is empty".to_string());
+    }
+    Chip::with_rom(0, rom).map_err(|_| {
+        format!(
+            "rom is {} bytes and doesn't fit in memory after 0x{:03X}",
+            rom.len(),
+            chip::ENTRY_ADDR
+        )
+    })?;
+    let Some(variant) = variant else {
+        return Ok(());
+    };
+    let start = chip::ENTRY_ADDR as usize;
+    let illegal: Vec<String> = analysis::analyze(rom)
+        .code
+        .iter()
+        .filter_map(|&pc| {
+            let at = pc as usize - start;
+            let opcode = u16::from_be_bytes([rom[at], rom[at + 1]]);
+            matches!(
+                Instruction::decode_variant(opcode, variant),
+                Instruction::Unknown(_)
+            )
+            .then(|| format!("{:04X} at 0x{:03X}", opcode, pc))
+        })
+        .collect();
+    match illegal.as_slice() {
+        [] => Ok(()),
+        [first, ..] => Err(format!(
+            "{} opcode(s) are not {} instructions, first {}",
+            illegal.len(),
+            variant,
+            first
+        )),
+    }
+}
+
+/// 在编译时读取 ROM 并展开为 `&'static [u8]`，路径相对于调用者的 Cargo.toml 所在目录
+///
+/// ROM 为空或放不进内存时编译失败；给出 `variant = "chip8"` 或 `"chip8e"` 时，
+/// 从入口可以到达的指令 (见 `chip::analysis`) 中有该变体不支持的操作码也会编译失败：
+///
+/// ```ignore
+/// static PONG: &[u8] = chip_macros::include_ch8!("roms/pong.ch8", variant = "chip8");
+/// ```
+#[proc_macro]
+pub fn include_ch8(input: TokenStream) -> TokenStream {
+    let Args { path, variant } = parse_macro_input!(input as Args);
+    let full = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join(path.value());
+    let rom = match std::fs::read(&full) {
+        Ok(rom) => rom,
+        Err(e) => {
+            let message = format!("couldn't read {}: {}", full.display(), e);
+            return syn::Error::new(path.span(), message)
+                .to_compile_error()
+                .into();
+        }
+    };
+    if let Err(e) = validate(&rom, variant) {
+        let message = format!("{}: {}", path.value(), e);
+        return syn::Error::new(path.span(), message)
+            .to_compile_error()
+            .into();
+    }
+    // 通过 include_bytes! 嵌入，ROM 修改后会重新编译
+    let full = full.to_string_lossy();
+    quote!((include_bytes!(#full) as &'static [u8])).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let pong = include_bytes!("../../roms/pong.ch8");
+        assert_eq!(validate(pong, Some(Variant::Chip8)), Ok(()));
+        assert!(validate(&[], None).is_err());
+        assert!(validate(&[0; 4000], None).is_err());
+        // 00ED 是 CHIP-8E 的停机指令
+        assert_eq!(validate(&[0x00, 0xED], Some(Variant::Chip8E)), Ok(()));
+        assert_eq!(
+            validate(&[0x00, 0xED], Some(Variant::Chip8)),
+            Err("1 opcode(s) are not chip8 instructions, first 00ED at 0x200".to_string())
+        );
+    }
+}
@@ -0,0 +1,95 @@
+; 检查 8XYN 指令设置的 VF
+; 每一项检查通过时在对应位置显示 1，失败时显示 0，全部通过时屏幕上是一排 1
+;
+; 构建：cargo run --bin chip8-asm -- roms/tests/flags.asm
+
+        LD V5, 1                ; 显示位置
+        LD V6, 1
+
+        ; ADD 进位
+        LD V0, 0xFF
+        LD V1, 1
+        ADD V0, V1
+        LD V2, VF
+        LD V4, 1
+        CALL check
+        ; ADD 结果
+        LD V2, V0
+        LD V4, 0
+        CALL check
+        ; ADD 没有进位
+        LD V0, 1
+        ADD V0, V1
+        LD V2, VF
+        LD V4, 0
+        CALL check
+
+        ; SUB 没有借位
+        LD V0, 5
+        LD V1, 3
+        SUB V0, V1
+        LD V2, VF
+        LD V4, 1
+        CALL check
+        ; SUB 借位
+        LD V0, 3
+        LD V1, 5
+        SUB V0, V1
+        LD V2, VF
+        LD V4, 0
+        CALL check
+        ; SUB 结果
+        LD V2, V0
+        LD V4, 0xFE
+        CALL check
+
+        ; SUBN 没有借位
+        LD V0, 3
+        LD V1, 5
+        SUBN V0, V1
+        LD V2, VF
+        LD V4, 1
+        CALL check
+        ; SUBN 借位
+        LD V0, 5
+        LD V1, 3
+        SUBN V0, V1
+        LD V2, VF
+        LD V4, 0
+        CALL check
+
+        ; SHR 移出的位
+        LD V0, 3
+        SHR V0
+        LD V2, VF
+        LD V4, 1
+        CALL check
+        ; SHL 移出的位
+        LD V0, 0x40
+        SHL V0
+        LD V2, VF
+        LD V4, 0
+        CALL check
+
+        ; VF 作为操作数时，标志位覆盖结果
+        LD VF, 0xFF
+        LD V1, 1
+        ADD VF, V1
+        LD V2, VF
+        LD V4, 1
+        CALL check
+
+end:    JP end
+
+; 比较 V2 和 V4，在 (V5, V6) 显示 1 或 0，然后移到下一个位置
+check:  LD V3, 0
+        SNE V2, V4
+        LD V3, 1
+        LD F, V3
+        DRW V5, V6, 5
+        ADD V5, 5
+        SE V5, 61
+        RET
+        LD V5, 1
+        ADD V6, 6
+        RET
@@ -0,0 +1,151 @@
+use chip::analysis::{self, Analysis, Extension};
+use chip::memmap::{self, Accesses, Region};
+use serde_json::json;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// 生成内存分布时每帧执行的指令数
+const MAP_IPS: u32 = 700;
+
+/// 内存分布的选项
+pub struct MapOptions {
+    /// 是否输出内存分布
+    pub enabled: bool,
+    /// 以 JSON 输出内存分布，不输出其他分析结果
+    pub json: bool,
+    /// 记录内存访问时运行的帧数
+    pub frames: u32,
+}
+
+/// `chip8 analyze`：输出 ROM 的静态分析结果，以及运行一段时间后得到的内存分布
+pub fn run(path: &Path, map: MapOptions) -> ExitCode {
+    let rom = match crate::container::read(path) {
+        Ok((rom, _)) => rom,
+        Err(e) => {
+            println!("Couldn't open {:?}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let analysis = analysis::analyze(&rom);
+    if map.json {
+        let regions: Vec<_> = memory_map(&rom, &analysis, map.frames)
+            .iter()
+            .map(|r| {
+                json!({
+                    "start": r.start, "end": r.end, "bytes": r.end - r.start,
+                    "kind": r.kind.to_string(),
+                })
+            })
+            .collect();
+        println!("{}", json!({ "size": rom.len(), "regions": regions }));
+        return ExitCode::SUCCESS;
+    }
+
+    println!("Size:            {} bytes", analysis.size);
+    println!(
+        "Reachable code:  {} instructions ({} bytes)",
+        analysis.code.len(),
+        analysis.code.len() * 2
+    );
+    println!("Data/unreached:  {} bytes", analysis.data_bytes);
+    let sprites: Vec<_> = analysis
+        .sprites
+        .iter()
+        .map(|addr| format!("{:03X}", addr))
+        .collect();
+    if sprites.is_empty() {
+        println!("Sprites:         0");
+    } else {
+        println!(
+            "Sprites:         {} at {}",
+            sprites.len(),
+            sprites.join(" ")
+        );
+    }
+    let mnemonics: Vec<_> = analysis
+        .mnemonics
+        .iter()
+        .map(|(name, count)| format!("{} x{}", name, count))
+        .collect();
+    println!("Opcodes used:    {}", mnemonics.join(", "));
+    match analysis.max_call_depth {
+        Some(depth) if depth > 16 => println!("Max call depth:  {} (overflows the stack)", depth),
+        Some(depth) => println!("Max call depth:  {}", depth),
+        None => println!("Max call depth:  unbounded (recursive calls)"),
+    }
+
+    let requires = analysis.requires();
+    let platform = if requires.contains(&Extension::XoChip) {
+        "XO-CHIP"
+    } else if requires.contains(&Extension::SuperChip) {
+        "SUPER-CHIP"
+    } else {
+        "CHIP-8"
+    };
+    println!("Platform:        {}", platform);
+    for (addr, op, ext) in &analysis.extensions {
+        let ext = match ext {
+            Extension::SuperChip => "SUPER-CHIP",
+            Extension::XoChip => "XO-CHIP",
+            Extension::DebugPrint => "debug print (--debug-print)",
+            Extension::UnitTest => "unit test (chip8 unit-test)",
+        };
+        println!("  {:03X}: {:04X} needs {}", addr, op, ext);
+    }
+    for (addr, op) in &analysis.illegal {
+        println!("  {:03X}: {:04X} is not a valid opcode", addr, op);
+    }
+    for addr in &analysis.self_modifying {
+        println!("  {:03X}: writes to code (self-modifying)", addr);
+    }
+    for addr in &analysis.indirect_jumps {
+        println!(
+            "  {:03X}: indirect jump, code after it may not be found",
+            addr
+        );
+    }
+
+    if map.enabled {
+        println!();
+        println!("Memory map after {} frames:", map.frames);
+        for region in memory_map(&rom, &analysis, map.frames) {
+            println!(
+                "  {:03X}-{:03X}  {:<9} {:>5} bytes",
+                region.start,
+                region.end - 1,
+                region.kind.to_string(),
+                region.end - region.start
+            );
+        }
+    }
+
+    if requires.is_empty() {
+        println!("This rom can run on this emulator.");
+        ExitCode::SUCCESS
+    } else {
+        println!(
+            "This rom needs {} which this emulator doesn't support.",
+            platform
+        );
+        ExitCode::FAILURE
+    }
+}
+
+/// 不接收输入运行 ROM 指定的帧数，记录内存访问后与静态分析合并为内存分布。
+/// 运行出错时使用出错前的记录
+fn memory_map(rom: &[u8], analysis: &Analysis, frames: u32) -> Vec<Region> {
+    let mut chip = chip::Chip::new(0);
+    let mut accesses = Accesses::new();
+    if chip.load_rom(chip::ENTRY_ADDR, rom).is_ok() {
+        'run: for _ in 0..frames {
+            for _ in 0..MAP_IPS / 60 {
+                if chip.step().is_err() {
+                    break 'run;
+                }
+                accesses.record(&chip);
+            }
+            chip.tick_timers();
+        }
+    }
+    memmap::memory_map(rom.len(), analysis, &accesses)
+}
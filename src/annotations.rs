@@ -0,0 +1,101 @@
+use chip::symbols::Symbols;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 用户为某个 ROM 添加的标签和注释，以 ROM 的 SHA-1 为文件名保存为 JSON
+///
+/// ```json
+/// { "labels": { "0x200": "main" }, "comments": { "0x2A0": "draw the paddle" } }
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Annotations {
+    #[serde(with = "hex_keys")]
+    pub labels: BTreeMap<u16, String>,
+    #[serde(with = "hex_keys")]
+    pub comments: BTreeMap<u16, String>,
+}
+
+impl Annotations {
+    /// ROM 的注释文件路径，例如 Linux 下为 `~/.config/chip8/annotations/<sha1>.json`
+    pub fn path(rom: &[u8]) -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| {
+            dir.join("chip8")
+                .join("annotations")
+                .join(format!("{}.json", crate::info::sha1(rom)))
+        })
+    }
+
+    /// 读取注释文件，文件不存在时返回空的注释
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(io::Error::from),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    /// 把标签加入符号表，与符号表冲突时以标签为准
+    pub fn apply(&self, symbols: &mut Symbols) {
+        for (addr, name) in &self.labels {
+            symbols.insert(*addr, name);
+        }
+    }
+}
+
+/// 以 "0x200" 形式的十六进制字符串作为地址的键
+mod hex_keys {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(map: &BTreeMap<u16, String>, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_map(map.iter().map(|(addr, v)| (format!("0x{:03X}", addr), v)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<BTreeMap<u16, String>, D::Error> {
+        BTreeMap::<String, String>::deserialize(d)?
+            .into_iter()
+            .map(|(key, v)| {
+                let hex = key.trim_start_matches("0x").trim_start_matches("0X");
+                u16::from_str_radix(hex, 16)
+                    .map(|addr| (addr, v))
+                    .map_err(|_| D::Error::custom(format!("invalid address {:?}", key)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotations() {
+        let mut annotations = Annotations::default();
+        annotations.labels.insert(0x200, "main".to_string());
+        annotations
+            .comments
+            .insert(0x2A0, "draw the paddle".to_string());
+        let json = serde_json::to_string(&annotations).unwrap();
+        assert_eq!(
+            json,
+            r#"{"labels":{"0x200":"main"},"comments":{"0x2A0":"draw the paddle"}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Annotations>(&json).unwrap(),
+            annotations
+        );
+        assert!(serde_json::from_str::<Annotations>(r#"{"labels":{"main":"x"}}"#).is_err());
+    }
+}
@@ -0,0 +1,147 @@
+use chip::asm;
+use chip::source_map::SourceMap;
+use chip::symbols::Symbols;
+use clap::Parser;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// CHIP-8 汇编器的命令行参数
+#[derive(Parser)]
+#[command(
+    name = "chip8-asm",
+    version,
+    about = "Assemble CHIP-8 source into a .ch8 rom",
+    after_help = "The syntax is the one printed by the disassembler (Cowgod's mnemonics), \
+with `label:` definitions, `;` comments and DB/DW data directives.
+
+Directives for multi-file projects:
+  INCLUDE \"file\"        assemble another file here (relative to the including file)
+  NAME EQU value        define a constant, shared by all files
+  ALIGN n               pad with zeros to a multiple of n
+  IF value / IFDEF NAME / IFNDEF NAME, ELSE, ENDIF
+                        conditional assembly, combine with -D"
+)]
+struct Cli {
+    /// Assembly source file
+    source: PathBuf,
+    /// Output rom (default: the source path with a .ch8 extension)
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Also write the labels as a symbol file for the debugger
+    #[arg(long, value_name = "FILE")]
+    sym: Option<PathBuf>,
+    /// Also write a source map from addresses to source lines for source-level debugging
+    #[arg(long, value_name = "FILE")]
+    map: Option<PathBuf>,
+    /// Define a constant before assembling, e.g. -D DEBUG or -D LIVES=5 (may be repeated)
+    #[arg(short = 'D', value_name = "NAME[=VALUE]", value_parser = parse_define)]
+    define: Vec<(String, u16)>,
+}
+
+/// 解析 -D 参数，省略值时为 1
+fn parse_define(text: &str) -> Result<(String, u16), String> {
+    let (name, value) = text.split_once('=').unwrap_or((text, "1"));
+    let value = match value.strip_prefix("0x") {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    value
+        .map(|value| (name.to_string(), value))
+        .map_err(|e| format!("invalid value: {}", e))
+}
+
+/// 源文件相对于映射文件所在目录的路径，无法表示为相对路径时使用绝对路径
+fn source_path(source: &Path, map: &Path) -> String {
+    let source = source.canonicalize().unwrap_or(source.to_path_buf());
+    let dir = map
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let relative = dir
+        .canonicalize()
+        .ok()
+        .and_then(|dir| source.strip_prefix(dir).ok().map(Path::to_path_buf));
+    relative.unwrap_or(source).to_string_lossy().into_owned()
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let source = match fs::read_to_string(&cli.source) {
+        Ok(source) => source,
+        Err(e) => {
+            println!("Couldn't open {:?}: {}", cli.source, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // 被包含的文件相对于包含它的文件，保留读取的内容用于显示出错的行
+    let mut included: Vec<(String, String)> = Vec::new();
+    let mut load = |from: Option<&str>, path: &str| {
+        let from = from.map_or(cli.source.as_path(), Path::new);
+        let path = from.parent().unwrap_or(Path::new("")).join(path);
+        let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        let name = path.to_string_lossy().into_owned();
+        included.push((name.clone(), text.clone()));
+        Ok((name, text))
+    };
+    let assembly = match asm::assemble_with(&source, &cli.define, &mut load) {
+        Ok(assembly) => assembly,
+        Err(e) => {
+            // 输出出错的行并标出列位置
+            let text = match &e.file {
+                Some(file) => {
+                    println!("{}", e);
+                    included
+                        .iter()
+                        .find(|(name, _)| name == file)
+                        .map(|(_, text)| text)
+                }
+                None => {
+                    println!("{}:{}", cli.source.display(), e);
+                    Some(&source)
+                }
+            };
+            if let Some(line) = text.and_then(|text| text.lines().nth(e.line - 1)) {
+                println!("    {}", line);
+                println!("    {}^", " ".repeat(e.column - 1));
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let output = cli
+        .output
+        .unwrap_or_else(|| cli.source.with_extension("ch8"));
+    if let Err(e) = fs::write(&output, &assembly.code) {
+        println!("Couldn't write {:?}: {}", output, e);
+        return ExitCode::FAILURE;
+    }
+    if let Some(path) = &cli.sym {
+        let symbols: Symbols = assembly
+            .labels
+            .iter()
+            .map(|(name, addr)| (name, *addr))
+            .collect();
+        if let Err(e) = fs::write(path, symbols.to_string()) {
+            println!("Couldn't write {:?}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(path) = &cli.map {
+        let mut map = SourceMap::new(Some(&source_path(&cli.source, path)));
+        for (addr, line) in &assembly.lines {
+            map.insert(*addr, *line);
+        }
+        if let Err(e) = fs::write(path, map.to_string()) {
+            println!("Couldn't write {:?}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    println!(
+        "Wrote {} bytes to {}",
+        assembly.code.len(),
+        output.display()
+    );
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,93 @@
+use chip::calls::CallTree;
+use chip::symbols::Symbols;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// 报告中列出的最热地址数
+const HOT_ADDRESSES: usize = 20;
+
+/// 把调用树和按子程序汇总的统计写入文本文件
+pub fn write_report(path: &Path, tree: &CallTree, symbols: &Symbols) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(
+        out,
+        "Call tree (calls, own instructions, total instructions):"
+    )?;
+    write_node(&mut out, tree, symbols, 0, 0)?;
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{:<24} {:>10} {:>12} {:>12}",
+        "Subroutine", "Calls", "Own", "Total"
+    )?;
+    for sub in tree.flat() {
+        writeln!(
+            out,
+            "{:<24} {:>10} {:>12} {:>12}",
+            name(symbols, sub.addr),
+            sub.calls,
+            sub.instructions,
+            sub.total
+        )?;
+    }
+
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{:<24} {:>12} {:>7}",
+        "Hot address", "Instructions", "%"
+    )?;
+    let total = tree.total(0).max(1);
+    for (addr, count) in tree.hot_addresses().into_iter().take(HOT_ADDRESSES) {
+        writeln!(
+            out,
+            "{:<24} {:>12} {:>6.1}%",
+            name(symbols, addr),
+            count,
+            count as f64 * 100.0 / total as f64
+        )?;
+    }
+    out.flush()
+}
+
+/// 把调用树写成 flamegraph.pl、inferno 等工具使用的折叠栈文件
+pub fn write_folded(path: &Path, tree: &CallTree, symbols: &Symbols) -> io::Result<()> {
+    let folded = tree.folded(|addr| match symbols.name(addr) {
+        Some(name) => name.replace([';', ' '], "_"),
+        None => format!("sub_{:03X}", addr),
+    });
+    std::fs::write(path, folded)
+}
+
+fn write_node(
+    out: &mut impl Write,
+    tree: &CallTree,
+    symbols: &Symbols,
+    index: usize,
+    depth: usize,
+) -> io::Result<()> {
+    let node = &tree.nodes()[index];
+    writeln!(
+        out,
+        "{}{}  {} {} {}",
+        "  ".repeat(depth),
+        name(symbols, node.addr),
+        node.calls,
+        node.instructions,
+        tree.total(index)
+    )?;
+    for child in &node.children {
+        write_node(out, tree, symbols, *child, depth + 1)?;
+    }
+    Ok(())
+}
+
+/// 子程序的名字，没有符号时显示地址
+fn name(symbols: &Symbols, addr: u16) -> String {
+    match symbols.name(addr) {
+        Some(name) => format!("{:03X} {}", addr, name),
+        None => format!("{:03X}", addr),
+    }
+}
@@ -0,0 +1,127 @@
+use crate::screen;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// 兼容性测试清单，例如
+///
+/// ```toml
+/// [[test]]
+/// name = "flags"
+/// rom = "flags.ch8"
+/// frames = 60
+/// screen = "flags.png"
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    test: Vec<TestCase>,
+}
+
+/// 一个测试：运行 ROM 指定的帧数后，屏幕应与参考图片一致。路径以清单所在目录为基准
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TestCase {
+    name: String,
+    rom: String,
+    frames: u32,
+    screen: String,
+    /// 每秒执行的指令数，默认为 700
+    speed: Option<u32>,
+}
+
+/// 测试结果
+enum Verdict {
+    Pass,
+    /// 与参考图片不同的像素数
+    Fail(usize),
+    Error(String),
+    /// 已写入新的参考图片
+    Blessed,
+}
+
+/// `chip8 test`：运行清单中的测试 ROM 并输出结果表。
+/// bless 为 true 时把当前的屏幕保存为参考图片
+pub fn run(manifest: &Path, bless: bool) -> ExitCode {
+    let manifest_text = match fs::read_to_string(manifest) {
+        Ok(text) => text,
+        Err(e) => {
+            println!("Couldn't open {:?}: {}", manifest, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let tests = match toml::from_str::<Manifest>(&manifest_text) {
+        Ok(manifest) => manifest.test,
+        Err(e) => {
+            println!("Couldn't parse {:?}: {}", manifest, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let base = manifest.parent().unwrap_or(Path::new("."));
+
+    let width = tests.iter().map(|t| t.name.len()).max().unwrap_or(0).max(4);
+    println!("{:<width$}  RESULT", "TEST");
+    let mut failures = 0;
+    for test in &tests {
+        let verdict = run_test(test, base, bless);
+        let result = match &verdict {
+            Verdict::Pass => "PASS".to_string(),
+            Verdict::Fail(n) => format!("FAIL ({} pixels differ)", n),
+            Verdict::Error(e) => format!("ERROR ({})", e),
+            Verdict::Blessed => "BLESSED".to_string(),
+        };
+        if matches!(verdict, Verdict::Fail(_) | Verdict::Error(_)) {
+            failures += 1;
+        }
+        println!("{:<width$}  {}", test.name, result);
+    }
+    println!("{} passed, {} failed", tests.len() - failures, failures);
+
+    if failures == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn run_test(test: &TestCase, base: &Path, bless: bool) -> Verdict {
+    let rom = match fs::read(base.join(&test.rom)) {
+        Ok(rom) => rom,
+        Err(e) => return Verdict::Error(format!("{}: {}", test.rom, e)),
+    };
+    // 固定种子，让使用随机数的测试结果可以重现
+    let mut cpu = match chip::Chip::with_rom(0, &rom) {
+        Ok(cpu) => cpu,
+        Err(e) => return Verdict::Error(e.to_string()),
+    };
+    let per_frame = test.speed.unwrap_or(700) / 60;
+    for _ in 0..test.frames {
+        if let Err(e) = cpu.run_frame(per_frame) {
+            return Verdict::Error(e.to_string());
+        }
+    }
+
+    let path = base.join(&test.screen);
+    if bless {
+        return match screen::save(&path, &cpu.framebuffer()) {
+            Ok(()) => Verdict::Blessed,
+            Err(e) => Verdict::Error(format!("{}: {}", test.screen, e)),
+        };
+    }
+    match screen::load(&path) {
+        Ok(expected) => {
+            let diff = expected
+                .iter()
+                .zip(cpu.framebuffer().iter())
+                .filter(|(a, b)| a != b)
+                .count();
+            if diff == 0 {
+                Verdict::Pass
+            } else {
+                Verdict::Fail(diff)
+            }
+        }
+        Err(e) => Verdict::Error(format!("{}: {}", test.screen, e)),
+    }
+}
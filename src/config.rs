@@ -0,0 +1,109 @@
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 配置文件，未设置的项使用命令行参数或默认值，命令行参数优先
+///
+/// ```toml
+/// rom_dir = "roms"
+/// keymap = "keymap.txt"
+/// speed = 1000
+/// scale = 12
+/// palette = "amber"
+/// autofire = ["5", "6:15"]
+///
+/// [audio]
+/// wave = "triangle"
+/// volume = 0.1
+/// device = "USB Audio"
+///
+/// [[macros]]
+/// key = "F9"
+/// sequence = "5 100, - 500, 5 100"
+/// ```
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub rom_dir: Option<PathBuf>,
+    pub keymap: Option<PathBuf>,
+    pub speed: Option<u32>,
+    pub scale: Option<u32>,
+    pub palette: Option<String>,
+    pub vsync: Option<bool>,
+    pub fullscreen: Option<bool>,
+    pub focus_pause: Option<bool>,
+    pub bezel: Option<PathBuf>,
+    pub bezel_screen: Option<String>,
+    pub autofire: Vec<String>,
+    pub audio: AudioSection,
+    pub macros: Vec<MacroSection>,
+}
+
+/// 配置文件中的 `[audio]` 部分
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioSection {
+    pub wave: Option<String>,
+    pub freq: Option<f32>,
+    pub volume: Option<f32>,
+    pub buffer: Option<u16>,
+    pub device: Option<String>,
+}
+
+/// 配置文件中的 `[[macros]]`：按下 key 时播放的 CHIP-8 按键序列，格式见 `frontend::InputMacro`
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MacroSection {
+    pub key: String,
+    pub sequence: String,
+}
+
+impl Config {
+    /// 默认的配置文件路径，例如 Linux 下为 `~/.config/chip8/config.toml`
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("chip8").join("config.toml"))
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        toml::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// 读取配置文件，文件中的相对路径以配置文件所在目录为基准
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let mut config =
+            Self::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        for path in [&mut config.rom_dir, &mut config.keymap, &mut config.bezel]
+            .into_iter()
+            .flatten()
+        {
+            *path = base.join(&*path);
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config =
+            Config::parse("speed = 1000\npalette = \"amber\"\n[audio]\nvolume = 0.5\n").unwrap();
+        assert_eq!(config.speed, Some(1000));
+        assert_eq!(config.palette.as_deref(), Some("amber"));
+        assert_eq!(config.audio.volume, Some(0.5));
+        assert_eq!(config.scale, None);
+
+        let config =
+            Config::parse("[[macros]]\nkey = \"F9\"\nsequence = \"5 100, - 50\"\n").unwrap();
+        assert_eq!(config.macros[0].key, "F9");
+        assert_eq!(config.macros[0].sequence, "5 100, - 50");
+
+        assert!(Config::parse("sped = 1000").is_err());
+        assert!(Config::parse("speed = \"fast\"").is_err());
+    }
+}
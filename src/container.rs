@@ -0,0 +1,229 @@
+use frontend::Keymap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
+/// 容器文件开头的标识
+const MAGIC: &[u8; 4] = b"CH8C";
+/// 容器格式版本
+const VERSION: u8 = 1;
+
+/// ROM 文件的扩展名，包括容器和压缩包
+pub const ROM_EXTENSIONS: &[&str] = &["ch8", "sc8", "xo8", "c8c", "zip"];
+
+/// ROM 针对的平台
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Platform {
+    #[serde(rename = "chip-8")]
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Platform {
+    /// 由文件扩展名判断平台
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "ch8" => Some(Platform::Chip8),
+            "sc8" => Some(Platform::SuperChip),
+            "xo8" => Some(Platform::XoChip),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Platform::Chip8 => "CHIP-8",
+            Platform::SuperChip => "SUPER-CHIP",
+            Platform::XoChip => "XO-CHIP",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// ROM 容器中的元数据
+///
+/// 容器文件 (.c8c) 的格式为 `CH8C`、1 字节版本号、4 字节大端的元数据长度、
+/// UTF-8 的 TOML 元数据，之后是 ROM 本身。元数据例如：
+///
+/// ```toml
+/// title = "Pong"
+/// author = "Paul Vervalin"
+/// description = "Two player pong"
+/// platform = "chip-8"
+/// speed = 700
+/// quirks = ["shift"]
+///
+/// [keymap]
+/// 1 = "Q"
+/// 4 = "A"
+/// ```
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Metadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    /// 目标平台：chip-8、super-chip 或 xo-chip，没有指定时由文件扩展名判断
+    pub platform: Option<Platform>,
+    /// 推荐的每秒指令数
+    pub speed: Option<u32>,
+    /// 推荐的兼容选项，目前只用于显示
+    pub quirks: Vec<String>,
+    /// CHIP-8 按键到 SDL 按键名的映射，未列出的按键使用默认映射
+    pub keymap: BTreeMap<String, String>,
+}
+
+impl Metadata {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let metadata: Self = toml::from_str(text).map_err(|e| e.to_string())?;
+        metadata.keymap()?;
+        Ok(metadata)
+    }
+
+    /// 推荐的按键映射，没有指定时返回 None
+    pub fn keymap(&self) -> Result<Option<Keymap>, String> {
+        if self.keymap.is_empty() {
+            return Ok(None);
+        }
+        let text: String = self
+            .keymap
+            .iter()
+            .map(|(key, name)| format!("{} = {}\n", key, name))
+            .collect();
+        Keymap::parse(&text).map(Some)
+    }
+}
+
+/// 把 ROM 和元数据打包为容器
+pub fn pack(rom: &[u8], metadata: &Metadata) -> Vec<u8> {
+    let text = toml::to_string(metadata).unwrap();
+    let mut data = Vec::with_capacity(MAGIC.len() + 5 + text.len() + rom.len());
+    data.extend_from_slice(MAGIC);
+    data.push(VERSION);
+    data.extend_from_slice(&(text.len() as u32).to_be_bytes());
+    data.extend_from_slice(text.as_bytes());
+    data.extend_from_slice(rom);
+    data
+}
+
+/// 解析文件内容，是容器时返回其中的 ROM 和元数据，否则整个文件就是 ROM
+pub fn unpack(data: Vec<u8>) -> Result<(Vec<u8>, Metadata), String> {
+    let Some(rest) = data.strip_prefix(MAGIC) else {
+        return Ok((data, Metadata::default()));
+    };
+    let (version, rest) = rest.split_first().ok_or("truncated header")?;
+    if *version != VERSION {
+        return Err(format!("unsupported container version {}", version));
+    }
+    let (len, rest) = rest.split_at_checked(4).ok_or("truncated header")?;
+    let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+    let (text, rom) = rest.split_at_checked(len).ok_or("truncated metadata")?;
+    let text = std::str::from_utf8(text).map_err(|_| "metadata is not UTF-8")?;
+    Ok((rom.to_vec(), Metadata::parse(text)?))
+}
+
+/// 读取 ROM 文件、容器或只包含一个 ROM 的 zip 压缩包
+pub fn read(path: &Path) -> io::Result<(Vec<u8>, Metadata)> {
+    let is_zip = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+    let (name, data) = if is_zip {
+        read_zip(path)?
+    } else {
+        (path.to_string_lossy().into_owned(), fs::read(path)?)
+    };
+    let (rom, mut metadata) =
+        unpack(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    metadata.platform = metadata.platform.or(Platform::from_path(Path::new(&name)));
+    Ok((rom, metadata))
+}
+
+/// 读取压缩包中唯一的 ROM，返回其文件名和内容
+fn read_zip(path: &Path) -> io::Result<(String, Vec<u8>)> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let mut archive =
+        zip::ZipArchive::new(File::open(path)?).map_err(|e| invalid(e.to_string()))?;
+    let roms: Vec<String> = archive
+        .file_names()
+        .filter(|name| {
+            Path::new(name).extension().is_some_and(|ext| {
+                ROM_EXTENSIONS
+                    .iter()
+                    .any(|rom| *rom != "zip" && ext.eq_ignore_ascii_case(rom))
+            })
+        })
+        .map(str::to_string)
+        .collect();
+    let name = match roms.as_slice() {
+        [name] => name.clone(),
+        [] => return Err(invalid("no rom in archive".to_string())),
+        _ => {
+            return Err(invalid(format!(
+                "archive contains several roms: {}",
+                roms.join(", ")
+            )))
+        }
+    };
+    let file = archive.by_name(&name).map_err(|e| invalid(e.to_string()))?;
+    // 不信任压缩包中记录的大小，最多读取比内存多一个字节，损坏或恶意的压缩包不会占用大量内存
+    let mut data = Vec::new();
+    file.take(chip::MEM_SIZE as u64 + 1)
+        .read_to_end(&mut data)?;
+    if data.len() > chip::MEM_SIZE {
+        return Err(invalid(format!("{} is larger than memory", name)));
+    }
+    Ok((name, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container() {
+        let metadata = Metadata::parse(
+            "title = \"Pong\"\nspeed = 1000\nquirks = [\"shift\"]\n[keymap]\nA = \"Z\"\n",
+        )
+        .unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Pong"));
+        assert!(metadata.keymap().unwrap().is_some());
+
+        let rom = [0x12, 0x00];
+        let data = pack(&rom, &metadata);
+        assert_eq!(unpack(data).unwrap(), (rom.to_vec(), metadata));
+        // 普通的 ROM 没有元数据
+        assert_eq!(
+            unpack(rom.to_vec()).unwrap(),
+            (rom.to_vec(), Metadata::default())
+        );
+        assert!(unpack(b"CH8C\x01\x00\x00\x00\x10ab".to_vec()).is_err());
+        assert!(Metadata::parse("[keymap]\nG = \"Z\"").is_err());
+        assert_eq!(
+            Metadata::parse("platform = \"super-chip\"")
+                .unwrap()
+                .platform,
+            Some(Platform::SuperChip)
+        );
+    }
+
+    #[test]
+    fn test_zip_too_large() {
+        let path = std::env::temp_dir().join(format!("chip8-zip-{}.zip", std::process::id()));
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        zip.start_file("big.ch8", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        io::Write::write_all(&mut zip, &[0; chip::MEM_SIZE + 1]).unwrap();
+        zip.finish().unwrap();
+        let result = read_zip(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use chip::symbols::Symbols;
+use chip::{Chip, Exception, Instruction};
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// 出错指令前后各反汇编的指令数
+const CONTEXT: u16 = 8;
+
+/// 把出错时的虚拟机状态写入崩溃转储文件，便于附在问题报告中
+pub fn write(
+    path: &Path,
+    cpu: &Chip,
+    error: &Exception,
+    rom: &Path,
+    symbols: &Symbols,
+) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, format(cpu, error, rom, symbols))
+}
+
+/// 生成文本格式的转储：错误、寄存器、最近执行的指令、PC 附近的反汇编、画面和全部内存
+pub fn format(cpu: &Chip, error: &Exception, rom: &Path, symbols: &Symbols) -> String {
+    let mut out = String::new();
+    let time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let _ = writeln!(out, "CHIP-8 crash dump");
+    let _ = writeln!(out, "Error:   {}", error);
+    let _ = writeln!(out, "Rom:     {}", rom.display());
+    let _ = writeln!(out, "Variant: {}", cpu.variant());
+    let _ = writeln!(out, "Time:    {} (seconds since the Unix epoch)", time);
+
+    let _ = writeln!(out, "\n== State ==");
+    let _ = writeln!(out, "{}", cpu);
+    let keys: Vec<String> = (0..16)
+        .filter(|&k| cpu.keypad()[k])
+        .map(|k| format!("{:X}", k))
+        .collect();
+    let _ = writeln!(out, "Keys down: {}", keys.join(" "));
+
+    let _ = writeln!(out, "\n== Recent instructions (oldest first) ==");
+    for (addr, op) in cpu.history() {
+        let _ = writeln!(out, "{}", line(cpu, symbols, addr, op));
+    }
+
+    let _ = writeln!(out, "\n== Disassembly around {:03X} ==", cpu.opcode_addr());
+    let mem = cpu.memory();
+    let start = cpu.opcode_addr().saturating_sub(2 * CONTEXT);
+    for addr in (start..=cpu.opcode_addr() + 2 * CONTEXT).step_by(2) {
+        let (Some(&hi), Some(&lo)) = (mem.get(addr as usize), mem.get(addr as usize + 1)) else {
+            break;
+        };
+        let op = (hi as u16) << 8 | lo as u16;
+        let _ = writeln!(out, "{}", line(cpu, symbols, addr, op));
+    }
+
+    let _ = writeln!(out, "\n== Screen ==");
+    for row in cpu.framebuffer().chunks(chip::DISP_WIDTH) {
+        let text: String = row.iter().map(|&on| if on { '#' } else { '.' }).collect();
+        let _ = writeln!(out, "{}", text);
+    }
+
+    // 全为 0 的行省略
+    let _ = writeln!(out, "\n== Memory ==");
+    for (n, row) in mem.chunks(16).enumerate() {
+        if row.iter().all(|&b| b == 0) {
+            continue;
+        }
+        let bytes: Vec<String> = row.iter().map(|b| format!("{:02X}", b)).collect();
+        let _ = writeln!(out, "{:03X}: {}", n * 16, bytes.join(" "));
+    }
+    out
+}
+
+/// 一行反汇编，出错的指令用 '>' 标出
+fn line(cpu: &Chip, symbols: &Symbols, addr: u16, op: u16) -> String {
+    let marker = if addr == cpu.opcode_addr() { '>' } else { ' ' };
+    let ins = Instruction::decode_variant(op, cpu.variant());
+    format!(
+        "{}{:03X}: {:04X}  {}",
+        marker,
+        addr,
+        op,
+        symbols.format(ins)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_dump() {
+        let mut cpu = Chip::new(0);
+        cpu.load_rom(chip::ENTRY_ADDR, &[0x60, 0x05, 0xD0, 0x05, 0xFF, 0xFF])
+            .unwrap();
+        let error = loop {
+            if let Err(e) = cpu.step() {
+                break e;
+            }
+        };
+        let dump = format(&cpu, &error, Path::new("bad.ch8"), &Symbols::new());
+        assert!(dump.contains("Error:   illegal opcode 0xFFFF"), "{}", dump);
+        assert!(dump.contains("\n>204: FFFF"));
+        assert!(dump.contains(" 202: D005  DRW V0, V0, 5"));
+        // 字符 0 画在 (5, 5)
+        assert!(dump.contains("\n.....####......."));
+        assert!(dump.contains("\n200: 60 05 D0 05 FF FF 00"));
+    }
+}
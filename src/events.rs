@@ -0,0 +1,104 @@
+use chip::events::{Event, EventTracker, Timer};
+use chip::Chip;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// 把高层事件写成 JSON Lines，每行一个事件，供可视化前端读取
+///
+/// 每个事件都有 `frame` (所在的帧)、`step` (之前执行的指令数) 和 `type` 字段，
+/// 其余字段随类型不同，地址和数值均为整数
+pub struct EventLog {
+    out: BufWriter<File>,
+    error: Option<io::Error>, // 第一次写入错误，在 finish 时返回
+    tracker: EventTracker,
+    steps: u64,
+    frames: u64,
+}
+
+impl EventLog {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            out: BufWriter::new(File::create(path)?),
+            error: None,
+            tracker: EventTracker::new(),
+            steps: 0,
+            frames: 0,
+        })
+    }
+
+    /// 在每条指令执行后调用
+    pub fn step(&mut self, chip: &Chip) {
+        self.steps += 1;
+        for event in self.tracker.step(chip) {
+            self.write(event);
+        }
+    }
+
+    /// 在每帧递减定时器后调用
+    pub fn tick(&mut self, chip: &Chip) {
+        for event in self.tracker.tick(chip) {
+            self.write(event);
+        }
+        self.frames += 1;
+        // 按实际时间运行时让读取端及时收到这一帧的事件
+        if let Err(e) = self.out.flush() {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    fn write(&mut self, event: Event) {
+        let mut value = to_json(event);
+        value["frame"] = json!(self.frames);
+        value["step"] = json!(self.steps);
+        if let Err(e) = writeln!(self.out, "{}", value) {
+            self.error.get_or_insert(e);
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.out.flush(),
+        }
+    }
+}
+
+fn timer_name(timer: Timer) -> &'static str {
+    match timer {
+        Timer::Delay => "delay",
+        Timer::Sound => "sound",
+    }
+}
+
+fn to_json(event: Event) -> Value {
+    match event {
+        Event::Push { ret, target, depth } => {
+            json!({ "type": "push", "ret": ret, "target": target, "depth": depth })
+        }
+        Event::Pop { ret, depth } => json!({ "type": "pop", "ret": ret, "depth": depth }),
+        Event::TimerLoad { timer, value } => {
+            json!({ "type": "timer_load", "timer": timer_name(timer), "value": value })
+        }
+        Event::TimerExpire { timer } => {
+            json!({ "type": "timer_expire", "timer": timer_name(timer) })
+        }
+        Event::KeyWaitBegin { addr, reg } => {
+            json!({ "type": "key_wait_begin", "addr": addr, "reg": reg })
+        }
+        Event::KeyWaitEnd { addr, reg, value } => {
+            json!({ "type": "key_wait_end", "addr": addr, "reg": reg, "value": value })
+        }
+        Event::Draw {
+            x,
+            y,
+            height,
+            addr,
+            collision,
+        } => json!({
+            "type": "draw", "x": x, "y": y, "height": height, "addr": addr, "collision": collision,
+        }),
+        Event::Clear => json!({ "type": "clear" }),
+    }
+}
@@ -0,0 +1,141 @@
+use chip::analysis::{self, Extension};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// CHIP-8 程序数据库 (https://github.com/chip-8/chip-8-database) 中的一个程序，只读取用到的字段
+#[derive(Debug, Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    release: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    roms: HashMap<String, RomInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RomInfo {
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    tickrate: Option<u32>,
+}
+
+/// 在数据库目录中查找 SHA-1 对应的程序。
+/// 目录中应有数据库的 `sha1-hashes.json` 和 `programs.json`
+fn lookup(database: &Path, sha1: &str) -> io::Result<Option<Program>> {
+    let invalid = |e: serde_json::Error| io::Error::new(io::ErrorKind::InvalidData, e);
+    let hashes: HashMap<String, usize> =
+        serde_json::from_str(&fs::read_to_string(database.join("sha1-hashes.json"))?)
+            .map_err(invalid)?;
+    let Some(index) = hashes.get(sha1) else {
+        return Ok(None);
+    };
+    let mut programs: Vec<Program> =
+        serde_json::from_str(&fs::read_to_string(database.join("programs.json"))?)
+            .map_err(invalid)?;
+    Ok((*index < programs.len()).then(|| programs.swap_remove(*index)))
+}
+
+/// `chip8 info`：输出 ROM 的大小、哈希、平台和数据库中的信息
+/// ROM 的 SHA-1，小写十六进制
+pub fn sha1(rom: &[u8]) -> String {
+    Sha1::digest(rom)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn run(path: &Path, database: Option<&Path>) -> ExitCode {
+    let (rom, metadata) = match crate::container::read(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            println!("Couldn't open {:?}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let sha1 = sha1(&rom);
+
+    println!("File:      {}", path.display());
+    println!("Size:      {} bytes", rom.len());
+    println!("SHA-1:     {}", sha1);
+    println!("CRC32:     {:08x}", crc32fast::hash(&rom));
+    // 容器中的元数据
+    for (label, value) in [
+        ("Title:", &metadata.title),
+        ("Author:", &metadata.author),
+        ("About:", &metadata.description),
+    ] {
+        if let Some(value) = value {
+            println!("{:<10} {}", label, value);
+        }
+    }
+    if let Some(speed) = metadata.speed {
+        println!("Speed:     {} instructions per second", speed);
+    }
+    if !metadata.quirks.is_empty() {
+        println!("Quirks:    {}", metadata.quirks.join(", "));
+    }
+
+    let requires = analysis::analyze(&rom).requires();
+    let platform = if requires.contains(&Extension::XoChip) {
+        "XO-CHIP"
+    } else if requires.contains(&Extension::SuperChip) {
+        "SUPER-CHIP"
+    } else {
+        "CHIP-8"
+    };
+    match metadata.platform {
+        Some(declared) => println!(
+            "Platform:  {} (declared), {} (detected from opcodes)",
+            declared, platform
+        ),
+        None => println!("Platform:  {} (detected from opcodes)", platform),
+    }
+
+    let Some(database) = database else {
+        println!("No program database configured, see --database");
+        return ExitCode::SUCCESS;
+    };
+    match lookup(database, &sha1) {
+        Ok(Some(program)) => {
+            println!("Title:     {}", program.title);
+            if !program.authors.is_empty() {
+                println!("Authors:   {}", program.authors.join(", "));
+            }
+            if let Some(release) = &program.release {
+                println!("Release:   {}", release);
+            }
+            if let Some(rom) = program.roms.get(&sha1) {
+                if !rom.platforms.is_empty() {
+                    println!("Platforms: {}", rom.platforms.join(", "));
+                }
+                if let Some(tickrate) = rom.tickrate {
+                    println!(
+                        "Speed:     {} instructions per frame (--speed {})",
+                        tickrate,
+                        tickrate * 60
+                    );
+                }
+            }
+            if let Some(description) = &program.description {
+                println!();
+                println!("{}", description.trim());
+            }
+        }
+        Ok(None) => println!("Not found in the program database"),
+        Err(e) => {
+            println!("Couldn't read program database {:?}: {}", database, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -1,2 +1,2 @@
-use chip;
-use frontend;
+pub use chip;
+pub use frontend;
@@ -0,0 +1,1725 @@
+mod analyze;
+mod annotations;
+mod calls;
+mod compat;
+mod config;
+mod container;
+mod crash;
+mod events;
+mod info;
+mod monitor;
+mod new;
+mod pipe;
+mod romtest;
+mod screen;
+mod settings;
+mod spectator;
+mod steplog;
+mod trace;
+mod wav;
+
+use annotations::Annotations;
+use chip::calls::CallTree;
+use chip::source_map::SourceMap;
+use chip::symbols::Symbols;
+use clap::{Parser, Subcommand};
+use config::Config;
+use container::{Metadata, Platform};
+use events::EventLog;
+use frontend::{
+    AudioConfig, AudioOutput, ChatConfig, ChatInput, DisplayConfig, ErrorAction, Keymap, Palette,
+    PixelStyle, RemoteKeypad, Rotation, ScreenArea, Waveform,
+};
+use notify::{RecursiveMode, Watcher};
+use pipe::PipeOutput;
+use settings::Settings;
+use spectator::Spectator;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc;
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime};
+use steplog::StepLog;
+use trace::ChromeTrace;
+
+/// 未指定 ROM 时浏览的默认目录
+const DEFAULT_ROM_DIR: &str = "roms";
+
+/// CHIP-8 模拟器的命令行参数
+#[derive(Parser)]
+#[command(
+    name = "chip8",
+    version,
+    args_conflicts_with_subcommands = true,
+    about = "A CHIP-8 emulator",
+    after_help = "Without a rom path, a menu of the roms in the rom directory is shown.\n\
+With several roms, they are played in turn as a playlist.\n\
+Defaults for most options can be set in a TOML config file, see --config.\n\
+The window size and position, last rom, palette, volume and speed are saved on exit\n\
+to chip8/settings.toml in the user config directory and restored on the next launch.\n\n\
+Hotkeys:\n    \
+F1               Toggle the debug overlay\n    \
+F2               Toggle the inspector window\n    \
+F3               Toggle the sprite viewer window\n    \
+PageUp/PageDown  Scroll the sprite viewer\n    \
++/-              Raise or lower the volume\n    \
+M                Mute\n    \
+Tab              Switch to the next rom in the playlist\n    \
+F4               Pause menu (remap keys)\n    \
+F5               Toggle slow sprite drawing\n    \
+F6               Rotate the screen by 90 degrees\n    \
+F7               Toggle autofire (with --autofire)\n    \
+F8               Toggle the keypad display\n    \
+Alt+1..Alt+8     Resize the window to 1 to 8 times 64x32\n    \
+Esc              Quit"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Rom files to play
+    roms: Vec<PathBuf>,
+    /// Config file (default: chip8/config.toml in the user config directory)
+    #[arg(long, env = "CHIP8_CONFIG", value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Directory to browse for roms (default: roms)
+    #[arg(long, env = "CHIP8_ROM_DIR")]
+    rom_dir: Option<PathBuf>,
+    /// Append the roms listed in a file, one per line
+    #[arg(long, value_name = "FILE")]
+    playlist: Option<PathBuf>,
+    /// Switch to the next rom after the given number of seconds
+    #[arg(long, value_name = "SECONDS")]
+    advance: Option<f32>,
+
+    /// Instructions per second (default: 700)
+    #[arg(short, long)]
+    speed: Option<u32>,
+    /// Size of a CHIP-8 pixel in window pixels (default: 16)
+    #[arg(long)]
+    scale: Option<u32>,
+    /// Color palette: classic, green, amber, lcd, inverted, or <fg>,<bg> such as #FFB000,#000000
+    #[arg(long)]
+    palette: Option<Palette>,
+    /// Random number seed (default: current time)
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Maximum call depth; some nonstandard interpreters allow more than 16
+    #[arg(long, value_name = "N", default_value_t = chip::STACK_SIZE)]
+    stack_depth: usize,
+    /// Enable the nonstandard debug opcodes for homebrew: 0FX0 prints VX and 0F01 prints the
+    /// zero-terminated string at I to stdout. Without this they are illegal opcodes
+    #[arg(long)]
+    debug_print: bool,
+    /// Interpreter variant: chip8, or chip8e for the CHIP-8E extended instructions
+    #[arg(long, default_value_t = chip::Variant::Chip8)]
+    variant: chip::Variant,
+    /// Start in fullscreen
+    #[arg(long, overrides_with = "windowed")]
+    fullscreen: bool,
+    /// Start in a window, overriding `fullscreen = true` in the config
+    #[arg(long, overrides_with = "fullscreen")]
+    windowed: bool,
+    /// Rotate the screen clockwise by 0, 90, 180 or 270 degrees, e.g. for a vertical monitor
+    #[arg(long, value_name = "DEGREES", default_value_t = Rotation::R0)]
+    rotate: Rotation,
+    /// Rotate the keypad layout along with the screen
+    #[arg(long)]
+    rotate_input: bool,
+    /// Show the keypad with the pressed keys in the corner (toggle with F8)
+    #[arg(long)]
+    show_keypad: bool,
+    /// How pixels are drawn: square, or dot for round LED-matrix dots with a faint glow
+    #[arg(long, value_name = "STYLE", default_value = "square")]
+    pixel_style: PixelStyle,
+    /// With --pixel-style dot, the gap between dots as a fraction of a pixel
+    #[arg(long, value_name = "FRACTION", default_value_t = 0.2)]
+    dot_gap: f32,
+    /// Accessibility: run the whole game, instructions and timers, at this percentage of
+    /// its normal speed
+    #[arg(long, value_name = "PERCENT", default_value_t = 100,
+          value_parser = clap::value_parser!(u32).range(10..=100))]
+    game_speed: u32,
+    /// Accessibility: single-switch input. The given CHIP-8 keys (hex digits, e.g. 456) are
+    /// highlighted in turn and the switch key presses the highlighted one
+    #[arg(long, value_name = "KEYS", value_parser = parse_scan_keys)]
+    scan_keys: Option<ScanKeys>,
+    /// Seconds each key stays highlighted with --scan-keys
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 1.0,
+        requires = "scan_keys"
+    )]
+    scan_interval: f32,
+    /// SDL name of the switch key for --scan-keys
+    #[arg(
+        long,
+        value_name = "KEY",
+        default_value = "Space",
+        requires = "scan_keys"
+    )]
+    scan_switch: String,
+    /// Autofire: holding the CHIP-8 key (hex) presses and releases it RATE times per second
+    /// (default 10). Can be repeated
+    #[arg(long, value_name = "KEY[:RATE]")]
+    autofire: Vec<frontend::AutofireKey>,
+    /// 配置文件中定义的输入宏
+    #[arg(skip)]
+    macros: Vec<frontend::InputMacro>,
+    /// PNG image drawn behind the screen, such as a cabinet bezel or a VIP-style overlay
+    #[arg(long, value_name = "FILE")]
+    bezel: Option<PathBuf>,
+    /// Where the screen sits in the bezel image, in image pixels (default: the whole image)
+    #[arg(long, value_name = "X,Y,W,H")]
+    bezel_screen: Option<ScreenArea>,
+    /// Run without a window or audio until the rom halts
+    #[arg(long)]
+    headless: bool,
+    /// In headless mode, stop after this many frames (60 per second) instead of running in real time
+    #[arg(long, value_name = "N", requires = "headless")]
+    frames: Option<u32>,
+    /// Run the rom headlessly as fast as possible for the given time and report the throughput
+    #[arg(long, value_name = "SECONDS", conflicts_with = "headless")]
+    bench: Option<f32>,
+    /// In headless mode, record instructions, draws and timers as a Chrome trace_event JSON file
+    /// for Perfetto or chrome://tracing
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    trace: Option<PathBuf>,
+    /// In headless mode, write the registers before every instruction, one line per step,
+    /// for comparison with `chip8 diff`
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    step_log: Option<PathBuf>,
+    /// In headless mode, write high-level events (stack push/pop, timer load/expire, key wait,
+    /// draw with collision) as JSON lines for visualization frontends; a FIFO streams them live
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    events: Option<PathBuf>,
+    /// In headless mode, record the dynamic call tree and write it with per-subroutine
+    /// instruction counts as a text report
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    call_tree: Option<PathBuf>,
+    /// In headless mode, count the instructions executed at each address and write them
+    /// with their call stacks as folded stacks for flamegraph tools
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    profile: Option<PathBuf>,
+    /// In headless mode, save the final screen as PNG, or PGM for a .pgm path
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    dump_screen: Option<PathBuf>,
+    /// When the rom faults, write a crash dump with the machine state, the last instructions,
+    /// the disassembly around PC, the screen and the memory to this file
+    #[arg(long, value_name = "FILE")]
+    crash_dump: Option<PathBuf>,
+    /// In headless mode, write every frame as raw rgb24 pixels at 60 fps to a file or named
+    /// pipe for ffmpeg; --scale sets the pixel size (default 1)
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    pipe_video: Option<PathBuf>,
+    /// With --pipe-video, also write the sound as raw mono s16le samples at 44100 Hz
+    #[arg(long, value_name = "FILE", requires = "pipe_video")]
+    pipe_audio: Option<PathBuf>,
+    /// Load a file into memory at a hexadecimal address after the rom, e.g. 300:save.bin
+    #[arg(long, value_name = "ADDR:FILE")]
+    load_memory: Option<String>,
+    /// Save the whole 4 KB of memory to a file on exit
+    #[arg(long, value_name = "FILE")]
+    dump_memory: Option<PathBuf>,
+    /// Symbol file with `<address> <name>` lines, used by the inspector and traces
+    /// (default: the rom path with a .sym extension, if it exists)
+    #[arg(long, value_name = "FILE")]
+    symbols: Option<PathBuf>,
+    /// Show an expression such as `v3 + i` or `mem[i+1]` in the inspector (repeatable)
+    #[arg(long, value_name = "EXPR")]
+    watch: Vec<chip::expr::Expr>,
+    /// Draw directly to a Linux framebuffer device such as /dev/fb0 instead of opening a window
+    #[arg(long, value_name = "DEVICE", conflicts_with = "headless")]
+    fbdev: Option<PathBuf>,
+    /// With --fbdev, read the keyboard from an evdev device such as /dev/input/event0
+    #[arg(long, value_name = "DEVICE", requires = "fbdev")]
+    evdev: Option<PathBuf>,
+    /// Pace emulation by the display refresh rate
+    #[arg(long, overrides_with = "no_vsync")]
+    vsync: bool,
+    /// Pace emulation by a timer, overriding `vsync = true` in the config
+    #[arg(long, overrides_with = "vsync")]
+    no_vsync: bool,
+    /// Emulate the COSMAC VIP's display refresh: rows are fetched as the beam passes and
+    /// DXYN waits for the next frame, reproducing the original flicker and tearing
+    #[arg(long)]
+    vip_display: bool,
+    /// Animate every DXYN pixel by pixel, showing the sprite bytes, collisions and VF
+    /// (default: 32 pixels per second, e.g. --slow-draw=64; F5 toggles)
+    #[arg(
+        long,
+        value_name = "PIXELS_PER_SEC",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "32",
+        conflicts_with = "vip_display"
+    )]
+    slow_draw: Option<u32>,
+    /// Keep running when the window loses focus
+    #[arg(long, overrides_with = "focus_pause")]
+    no_focus_pause: bool,
+    /// Pause when the window loses focus, overriding `focus_pause = false` in the config
+    #[arg(long, overrides_with = "no_focus_pause")]
+    focus_pause: bool,
+
+    /// Beeper waveform: square, triangle or sine (default: square)
+    #[arg(long)]
+    wave: Option<Waveform>,
+    /// Beeper frequency in Hz (default: 440)
+    #[arg(long)]
+    freq: Option<f32>,
+    /// Beeper volume, 0.0-1.0 (default: 0.25)
+    #[arg(long)]
+    volume: Option<f32>,
+    /// Audio buffer size in samples
+    #[arg(long, value_name = "SAMPLES")]
+    buffer: Option<u16>,
+    /// Run without sound, e.g. on machines without an audio device
+    #[arg(long)]
+    no_audio: bool,
+    /// Audio output device, falls back to the default device while it is unplugged
+    #[arg(long, value_name = "NAME")]
+    audio_device: Option<String>,
+    /// List audio output devices and exit
+    #[arg(long)]
+    list_audio_devices: bool,
+
+    /// Load key mapping from a file and save remaps to it
+    #[arg(long, value_name = "FILE")]
+    keymap: Option<PathBuf>,
+    /// Accept `down <key>`/`up <key>` lines over TCP
+    #[arg(long, value_name = "ADDR:PORT")]
+    remote_keypad: Option<String>,
+    /// Serve the screen read-only to any number of browsers over WebSocket
+    #[arg(long, value_name = "ADDR:PORT")]
+    spectate: Option<String>,
+    /// Vote keys from a `<user> <message>` line feed
+    #[arg(long, value_name = "ADDR:PORT")]
+    chat: Option<String>,
+    /// Vote keys from an IRC/Twitch channel
+    #[arg(long, value_name = "ADDR:PORT", requires = "chat_channel")]
+    chat_irc: Option<String>,
+    /// IRC channel to join
+    #[arg(long, value_name = "CHANNEL")]
+    chat_channel: Option<String>,
+    /// IRC nick (default: anonymous Twitch login)
+    #[arg(long, value_name = "NICK", default_value = "justinfan12345")]
+    chat_nick: String,
+    /// IRC password
+    #[arg(long, env = "CHIP8_CHAT_PASSWORD", hide_env_values = true)]
+    chat_password: Option<String>,
+    /// Chat vote window in seconds
+    #[arg(long, value_name = "SECONDS", default_value_t = 2.0)]
+    vote_window: f32,
+}
+
+/// 不运行模拟器的子命令
+#[derive(Subcommand)]
+enum Command {
+    /// Analyze a rom: reachable code, sprites, opcodes used and required extensions
+    Analyze {
+        /// Rom file to analyze
+        rom: PathBuf,
+        /// Also run the rom without input and map its memory into code, sprite, data,
+        /// scratch (written), untouched and free regions
+        #[arg(long)]
+        memory_map: bool,
+        /// Print only the memory map, as JSON
+        #[arg(long)]
+        json: bool,
+        /// Frames to run for the memory map
+        #[arg(long, default_value_t = 600)]
+        frames: u32,
+    },
+    /// Show a rom's size, hashes, platform and its entry in the CHIP-8 program database
+    Info {
+        /// Rom file
+        rom: PathBuf,
+        /// Directory with the program database's programs.json and sha1-hashes.json
+        /// (default: chip8/database in the user config directory)
+        #[arg(long, env = "CHIP8_DATABASE", value_name = "DIR")]
+        database: Option<PathBuf>,
+    },
+    /// Debug a rom from the command line: step, breakpoints, registers, memory and disassembly.
+    /// Commands are read from stdin, so sessions can be scripted
+    Monitor {
+        /// Rom file
+        rom: PathBuf,
+        /// Instructions per second, used to decrement the timers at 60 Hz (default: 700)
+        #[arg(short, long)]
+        speed: Option<u32>,
+        /// Random number seed
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Symbol file (default: the rom path with a .sym extension, if it exists)
+        #[arg(long, value_name = "FILE")]
+        symbols: Option<PathBuf>,
+        /// Source map written by `chip8-asm --map`, for breakpoints and stepping by source line
+        /// (default: the rom path with a .map extension, if it exists)
+        #[arg(long, value_name = "FILE")]
+        source_map: Option<PathBuf>,
+    },
+    /// Compare two step logs (from --step-log or another emulator's `PC=200 V0=00 ...` log)
+    /// and show the first step where they differ
+    Diff {
+        /// First log
+        a: PathBuf,
+        /// Second log
+        b: PathBuf,
+        /// Matching steps to show before the difference
+        #[arg(long, default_value_t = 5)]
+        context: usize,
+    },
+    /// Bundle a rom with metadata (title, author, speed, keymap...) into a .c8c container
+    Pack {
+        /// Rom file
+        rom: PathBuf,
+        /// TOML file with the metadata
+        metadata: PathBuf,
+        /// Output container (default: the rom path with a .c8c extension)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Convert a monochrome PNG into sprite data: the image is sliced into 8xN sprites,
+    /// left to right and top to bottom, and printed as assembler DB lines or Rust arrays
+    Sprite {
+        /// PNG image, its width a multiple of 8. Bright opaque pixels are on
+        image: PathBuf,
+        /// Rows per sprite, at most 15 (default: the image height)
+        #[arg(long, value_name = "N")]
+        height: Option<usize>,
+        /// Label or constant name, numbered when there are several sprites
+        /// (default: the image file name)
+        #[arg(long)]
+        name: Option<String>,
+        /// Dark pixels are on instead
+        #[arg(long)]
+        invert: bool,
+        /// Print Rust arrays instead of assembly
+        #[arg(long)]
+        rust: bool,
+        /// Output file (default: stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Convert a short WAV into XO-CHIP 1-bit audio patterns (16 bytes each, played with
+    /// F002 at the FX3A pitch), printed as assembler DB lines, with suggested pitches for
+    /// looping a single pattern as a tone
+    XoAudio {
+        /// WAV file (PCM or float, mixed down to mono)
+        wav: PathBuf,
+        /// Pitch the patterns will be played at; 64 is 4000 bits per second
+        #[arg(long, default_value_t = 64)]
+        pitch: u8,
+        /// Label prefix (default: the file name)
+        #[arg(long)]
+        name: Option<String>,
+        /// Output file (default: stdout)
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Create a project skeleton: assembly sources, a sprite image and a Makefile that
+    /// builds with chip8-asm and runs, debugs and tests the rom with chip8
+    New {
+        /// Project directory, its name is used for the rom
+        dir: PathBuf,
+    },
+    /// Run a rom's unit tests: every `test_` label is called as a subroutine and passes when
+    /// it returns; reaching the FAIL opcode (0F02) fails it. Debug prints are enabled
+    UnitTest {
+        /// Rom built with its tests
+        rom: PathBuf,
+        /// Symbol file naming the tests (default: the rom path with a .sym extension)
+        #[arg(long, value_name = "FILE")]
+        symbols: Option<PathBuf>,
+        /// Source map for showing the failing source line (default: the rom path with a
+        /// .map extension, if it exists)
+        #[arg(long, value_name = "FILE")]
+        source_map: Option<PathBuf>,
+        /// Instructions a test may run before it times out
+        #[arg(long, value_name = "N", default_value_t = chip::romtest::DEFAULT_MAX_STEPS)]
+        max_steps: u64,
+    },
+    /// Run compatibility test roms headlessly and compare their screens with reference images
+    Test {
+        /// Test manifest
+        #[arg(default_value = "roms/tests/tests.toml")]
+        manifest: PathBuf,
+        /// Save the current screens as the new reference images
+        #[arg(long)]
+        bless: bool,
+    },
+}
+
+impl Cli {
+    /// 用配置文件补全命令行中没有给出的参数
+    fn apply_config(&mut self, config: Config) -> Result<(), String> {
+        self.rom_dir = self.rom_dir.take().or(config.rom_dir);
+        self.keymap = self.keymap.take().or(config.keymap);
+        self.speed = self.speed.or(config.speed);
+        self.scale = self.scale.or(config.scale);
+        if self.palette.is_none() {
+            self.palette = config.palette.map(|s| s.parse()).transpose()?;
+        }
+        // 开关参数成对出现，命令行中后给出的一个生效，都没有给出时使用配置文件
+        let flag = |on: bool, off: bool| (on || off).then_some(on);
+        self.vsync = flag(self.vsync, self.no_vsync)
+            .or(config.vsync)
+            .unwrap_or(false);
+        self.fullscreen = flag(self.fullscreen, self.windowed)
+            .or(config.fullscreen)
+            .unwrap_or(false);
+        self.no_focus_pause = flag(self.no_focus_pause, self.focus_pause)
+            .or(config.focus_pause.map(|pause| !pause))
+            .unwrap_or(false);
+        self.bezel = self.bezel.take().or(config.bezel);
+        if self.bezel_screen.is_none() {
+            self.bezel_screen = config.bezel_screen.map(|s| s.parse()).transpose()?;
+        }
+        if self.wave.is_none() {
+            self.wave = config.audio.wave.map(|s| s.parse()).transpose()?;
+        }
+        self.freq = self.freq.or(config.audio.freq);
+        self.volume = self.volume.or(config.audio.volume);
+        self.buffer = self.buffer.or(config.audio.buffer);
+        self.audio_device = self.audio_device.take().or(config.audio.device);
+        if self.autofire.is_empty() {
+            self.autofire = config
+                .autofire
+                .iter()
+                .map(|key| key.parse())
+                .collect::<Result<_, _>>()?;
+        }
+        self.macros = config
+            .macros
+            .iter()
+            .map(|m| frontend::InputMacro::new(&m.key, &m.sequence))
+            .collect::<Result<_, _>>()?;
+        Ok(())
+    }
+
+    /// 用上次退出时保存的状态补充命令行中没有给出的参数，在配置文件之前应用，
+    /// 因此优先级为命令行、上次保存的状态、配置文件
+    fn apply_settings(&mut self, settings: &Settings) {
+        self.speed = self.speed.or(settings.speed);
+        self.volume = self.volume.or(settings.volume);
+        if self.palette.is_none() {
+            self.palette = settings.palette.as_deref().and_then(|s| s.parse().ok());
+        }
+    }
+
+    fn display_config(&self) -> DisplayConfig {
+        let default = DisplayConfig::default();
+        DisplayConfig {
+            pixel_scale: self.scale.unwrap_or(default.pixel_scale),
+            vsync: self.vsync,
+            ips: self.speed(),
+            fullscreen: self.fullscreen,
+            vip_display: self.vip_display,
+            slow_draw: self.slow_draw,
+            palette: self.palette.unwrap_or_default(),
+            audio: AudioConfig {
+                waveform: self.wave.unwrap_or_default(),
+                frequency: self.freq.unwrap_or(default.audio.frequency),
+                volume: self.volume.unwrap_or(default.audio.volume),
+                buffer_size: self.buffer,
+                device: self.audio_device.clone(),
+            },
+            audio_output: !self.no_audio,
+            window: None,
+            rotation: self.rotate,
+            rotate_input: self.rotate_input,
+            pixel_style: self.pixel_style,
+            dot_gap: self.dot_gap,
+            game_speed: self.game_speed as f32 / 100.0,
+            show_keypad: self.show_keypad,
+        }
+    }
+
+    fn speed(&self) -> u32 {
+        self.speed.unwrap_or(DisplayConfig::default().ips)
+    }
+
+    fn chat_config(&self) -> ChatConfig {
+        ChatConfig {
+            vote_window: Duration::from_secs_f32(self.vote_window),
+            ..ChatConfig::default()
+        }
+    }
+}
+
+/// 列出目录下的 ROM 文件，并在终端中让用户选择一个，直接回车选择上次运行的 ROM
+fn choose_rom(dir: &Path, last: Option<&Path>) -> Option<PathBuf> {
+    let mut roms: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension().is_some_and(|ext| {
+                    container::ROM_EXTENSIONS
+                        .iter()
+                        .any(|rom| ext.eq_ignore_ascii_case(rom))
+                })
+            })
+            .collect(),
+        Err(e) => {
+            println!("Couldn't read rom directory {:?}: {}", dir, e);
+            return None;
+        }
+    };
+    if roms.is_empty() {
+        println!("No roms found in {}", dir.display());
+        return None;
+    }
+    roms.sort();
+
+    println!("Roms in {}:", dir.display());
+    for (i, rom) in roms.iter().enumerate() {
+        let name = rom.file_stem().unwrap().to_string_lossy();
+        // 容器中有标题时一起显示
+        match container::read(rom).ok().and_then(|(_, meta)| meta.title) {
+            Some(title) => println!("  {:>2}) {} - {}", i + 1, name, title),
+            None => println!("  {:>2}) {}", i + 1, name),
+        }
+    }
+
+    loop {
+        match last {
+            Some(last) => print!(
+                "Select a rom [1-{}, Enter for {}, q to quit]: ",
+                roms.len(),
+                last.file_name().unwrap_or_default().to_string_lossy()
+            ),
+            None => print!("Select a rom [1-{}, q to quit]: ", roms.len()),
+        }
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            return None;
+        }
+        let line = line.trim();
+        if line.eq_ignore_ascii_case("q") {
+            return None;
+        }
+        if let (Some(last), "") = (last, line) {
+            return Some(last.to_path_buf());
+        }
+        match line.parse::<usize>() {
+            Ok(n) if (1..=roms.len()).contains(&n) => return Some(roms.swap_remove(n - 1)),
+            _ => println!("Invalid selection: {}", line),
+        }
+    }
+}
+
+/// 扫描输入的按键。用别名是为了让 clap 把它当作单个值而不是多个值
+type ScanKeys = Vec<u8>;
+
+/// 指定了崩溃转储文件时写入出错时的状态
+fn write_crash_dump(cli: &Cli, cpu: &chip::Chip, error: &chip::Exception, rom: &Path) {
+    let Some(path) = &cli.crash_dump else {
+        return;
+    };
+    let symbols = load_symbols(rom, cli.symbols.as_deref());
+    match crash::write(path, cpu, error, rom, &symbols) {
+        Ok(()) => println!("Crash dump written to {}", path.display()),
+        Err(e) => println!("Couldn't write crash dump {:?}: {}", path, e),
+    }
+}
+
+/// 解析扫描输入的按键，每个十六进制数字为一个 CHIP-8 按键
+fn parse_scan_keys(s: &str) -> Result<ScanKeys, String> {
+    s.chars()
+        .map(|c| {
+            c.to_digit(16)
+                .map(|d| d as u8)
+                .ok_or_else(|| format!("invalid key: {}", c))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|keys| {
+            if keys.is_empty() {
+                Err("no keys given".to_string())
+            } else {
+                Ok(keys)
+            }
+        })
+}
+
+/// 读取背景图并设置画面在其中的位置
+fn load_bezel(
+    display: &mut frontend::Display,
+    path: &Path,
+    screen: Option<ScreenArea>,
+) -> Result<(), String> {
+    let (rgba, width, height) = screen::load_rgba(path).map_err(|e| e.to_string())?;
+    let mut bezel = frontend::Bezel::new(width as u32, height as u32, rgba)?;
+    if let Some(area) = screen {
+        bezel.set_screen(area)?;
+    }
+    display.set_bezel(bezel)
+}
+
+/// 以当前时间作为随机数种子
+fn seed() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// 读取播放列表文件，每行一个 ROM 路径，忽略空行和 '#' 开头的注释。
+/// 相对路径以播放列表文件所在目录为基准
+fn read_playlist(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let base = path.parent().unwrap_or(Path::new("."));
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| base.join(line))
+        .collect())
+}
+
+/// 复位虚拟机并装载 ROM 文件或容器，返回容器中的元数据
+fn load_rom(cpu: &mut chip::Chip, path: &Path, seed: u64) -> Option<Metadata> {
+    let (bin, metadata) = match container::read(path) {
+        Ok(rom) => rom,
+        Err(e) => {
+            println!("Couldn't open {:?}: {}", path, e);
+            return None;
+        }
+    };
+    println!("Loading rom file: {}", path.display());
+    if let Some(title) = &metadata.title {
+        match &metadata.author {
+            Some(author) => println!("{} by {}", title, author),
+            None => println!("{}", title),
+        }
+    }
+    if let Some(platform @ (Platform::SuperChip | Platform::XoChip)) = metadata.platform {
+        println!(
+            "{} roms run as CHIP-8, extended instructions are not supported",
+            platform
+        );
+    }
+    if !metadata.quirks.is_empty() {
+        println!(
+            "Recommended quirks are not emulated: {}",
+            metadata.quirks.join(", ")
+        );
+    }
+
+    if let Err(e) = cpu.reset_with_rom(seed, &bin) {
+        println!("Couldn't load {:?}: {}", path, e);
+        return None;
+    }
+    Some(metadata)
+}
+
+/// 按 ROM 的元数据设置窗口标题、速度和按键映射，命令行和配置文件中的设置优先
+fn apply_metadata(
+    display: &mut frontend::Display,
+    cli: &Cli,
+    path: &Path,
+    metadata: &Metadata,
+    user_keymap: Option<&Keymap>,
+) {
+    let name = path.file_stem().unwrap().to_string_lossy();
+    display.set_rom_name(metadata.title.as_deref().unwrap_or(&name));
+    display.set_ips(cli.speed.or(metadata.speed).unwrap_or(cli.speed()));
+    let keymap = match (user_keymap, metadata.keymap()) {
+        (Some(keymap), _) => keymap.clone(),
+        (None, Ok(Some(keymap))) => keymap,
+        (None, _) => Keymap::default(),
+    };
+    display.set_keymap(keymap);
+}
+
+/// 装载 ROM 并应用它的元数据和符号表，用于切换、拖放和重新装载 ROM，返回是否成功
+fn reload(
+    cpu: &mut chip::Chip,
+    display: &mut frontend::Display,
+    cli: &Cli,
+    path: &Path,
+    user_keymap: Option<&Keymap>,
+    seed: u64,
+) -> bool {
+    let Some(metadata) = load_rom(cpu, path, seed) else {
+        return false;
+    };
+    apply_metadata(display, cli, path, &metadata, user_keymap);
+    display.set_symbols(load_symbols(path, cli.symbols.as_deref()));
+    true
+}
+
+/// `chip8 pack`：把 ROM 和元数据打包为容器
+fn pack(rom: &Path, metadata: &Path, output: &Path) -> Result<(), String> {
+    let bin = fs::read(rom).map_err(|e| format!("Couldn't open {:?}: {}", rom, e))?;
+    let text =
+        fs::read_to_string(metadata).map_err(|e| format!("Couldn't open {:?}: {}", metadata, e))?;
+    let metadata =
+        Metadata::parse(&text).map_err(|e| format!("Invalid metadata {:?}: {}", metadata, e))?;
+    fs::write(output, container::pack(&bin, &metadata))
+        .map_err(|e| format!("Couldn't write {:?}: {}", output, e))?;
+    println!("Wrote {}", output.display());
+    Ok(())
+}
+
+/// 把 `<地址>:<文件>` 形式指定的文件装载到内存
+fn load_memory(cpu: &mut chip::Chip, arg: &str) -> Result<(), String> {
+    let (addr, path) = arg
+        .split_once(':')
+        .ok_or("expected <address>:<file>".to_string())?;
+    let addr = u16::from_str_radix(addr.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("invalid address {:?}", addr))?;
+    let data = fs::read(path).map_err(|e| format!("couldn't open {:?}: {}", path, e))?;
+    cpu.load_region(addr, &data).map_err(|e| e.to_string())
+}
+
+/// 把整个内存保存到文件
+fn dump_memory(cpu: &chip::Chip, path: &Path) -> bool {
+    let mem = cpu.dump_region(0..cpu.memory().len() as u16).unwrap();
+    if let Err(e) = fs::write(path, mem) {
+        println!("Couldn't write {:?}: {}", path, e);
+        return false;
+    }
+    true
+}
+
+/// 读取符号表，未指定时使用 ROM 旁边同名的 .sym 文件 (如果存在)，
+/// 再加上用户为这个 ROM 添加的标签
+fn load_symbols(rom: &Path, path: Option<&Path>) -> Symbols {
+    let default = rom.with_extension("sym");
+    let path = match path {
+        Some(path) => Some(path),
+        None => Some(default.as_path()).filter(|path| path.exists()),
+    };
+    let mut symbols = match path {
+        Some(path) => {
+            let symbols = fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|text| Symbols::parse(&text));
+            symbols.unwrap_or_else(|e| {
+                println!("Couldn't load symbols {:?}: {}", path, e);
+                Symbols::new()
+            })
+        }
+        None => Symbols::new(),
+    };
+    if let Some((_, annotations)) = container::read(rom)
+        .ok()
+        .and_then(|(bin, _)| load_annotations(&bin))
+    {
+        annotations.apply(&mut symbols);
+    }
+    symbols
+}
+
+/// 读取 ROM 的注释文件，返回文件路径和其中的注释
+fn load_annotations(rom: &[u8]) -> Option<(PathBuf, Annotations)> {
+    let path = Annotations::path(rom)?;
+    match Annotations::load(&path) {
+        Ok(annotations) => Some((path, annotations)),
+        Err(e) => {
+            println!("Couldn't load annotations {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// 读取源代码映射和它指向的源文件，未指定时使用 ROM 旁边同名的 .map 文件 (如果存在)
+fn load_source(rom: &Path, path: Option<&Path>) -> Option<monitor::Source> {
+    let default = rom.with_extension("map");
+    let path = match path {
+        Some(path) => path,
+        None if default.exists() => &default,
+        None => return None,
+    };
+    let map = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| SourceMap::parse(&text));
+    let map = match map {
+        Ok(map) => map,
+        Err(e) => {
+            println!("Couldn't load source map {:?}: {}", path, e);
+            return None;
+        }
+    };
+    let Some(file) = &map.file else {
+        println!("Source map {:?} doesn't name a source file", path);
+        return None;
+    };
+    let source = path.parent().unwrap_or(Path::new(".")).join(file);
+    match fs::read_to_string(&source) {
+        Ok(text) => Some(monitor::Source {
+            name: file.clone(),
+            lines: text.lines().map(str::to_string).collect(),
+            map,
+        }),
+        Err(e) => {
+            println!("Couldn't open {:?}: {}", source, e);
+            None
+        }
+    }
+}
+
+/// 输出 ROM 用调试指令打印的内容
+fn print_debug_output(cpu: &mut chip::Chip) {
+    for (addr, text) in cpu.take_debug_output() {
+        println!("[{:03X}] {}", addr, text);
+    }
+}
+
+/// 无窗口运行时逐条记录指令的各种输出
+struct Recorders {
+    trace: Option<ChromeTrace>,
+    calls: Option<CallTree>,
+    log: Option<StepLog>,
+    events: Option<EventLog>,
+}
+
+impl Recorders {
+    fn is_empty(&self) -> bool {
+        self.trace.is_none() && self.calls.is_none() && self.log.is_none() && self.events.is_none()
+    }
+
+    fn step(&mut self, cpu: &mut chip::Chip) -> Result<(), chip::Exception> {
+        if let Some(log) = self.log.as_mut() {
+            log.record(cpu);
+        }
+        match self.trace.as_mut() {
+            Some(trace) => trace.step(cpu)?,
+            None => cpu.step()?,
+        }
+        if let Some(calls) = self.calls.as_mut() {
+            calls.record(cpu);
+        }
+        if let Some(events) = self.events.as_mut() {
+            events.step(cpu);
+        }
+        Ok(())
+    }
+
+    fn tick_timers(&mut self, cpu: &mut chip::Chip) {
+        match self.trace.as_mut() {
+            Some(trace) => trace.tick_timers(cpu),
+            None => cpu.tick_timers(),
+        }
+        if let Some(events) = self.events.as_mut() {
+            events.tick(cpu);
+        }
+    }
+}
+
+/// 不打开窗口运行虚拟机，直到程序停机或出错。
+/// 指定帧数时尽快运行指定的帧数后返回，否则按实际时间运行
+fn run_headless(
+    cpu: &mut chip::Chip,
+    ips: u32,
+    frames: Option<u32>,
+    recorders: &mut Recorders,
+    mut spectator: Option<&mut Spectator>,
+    mut pipe: Option<&mut PipeOutput>,
+) -> Result<(), chip::Exception> {
+    let frame = Duration::from_secs(1) / 60;
+    let mut next = Instant::now();
+    let mut count = 0;
+    while frames.is_none_or(|frames| count < frames) {
+        let result = if recorders.is_empty() {
+            cpu.run_frame(ips / 60).map(|_| ())
+        } else {
+            // 需要记录每条指令时逐条执行
+            (0..ips / 60)
+                .try_for_each(|_| recorders.step(cpu))
+                .map(|()| recorders.tick_timers(cpu))
+        };
+        print_debug_output(cpu);
+        result?;
+        if let Some(spectator) = spectator.as_deref_mut() {
+            spectator.broadcast(cpu);
+        }
+        if let Some(pipe) = pipe.as_deref_mut() {
+            // 读取端关闭管道后停止输出，虚拟机继续运行
+            if let Err(e) = pipe.write_frame(cpu) {
+                println!("Couldn't write video: {}", e);
+                return Ok(());
+            }
+        }
+        count += 1;
+        if frames.is_none() {
+            next += frame;
+            sleep(next.saturating_duration_since(Instant::now()));
+        }
+    }
+    Ok(())
+}
+
+/// 在帧缓冲设备上按实际时间运行虚拟机，直到程序停机、出错或按下 Esc
+fn run_fbdev(
+    cpu: &mut chip::Chip,
+    ips: u32,
+    palette: Palette,
+    audio: &mut dyn AudioOutput,
+    device: &Path,
+    input: Option<&Path>,
+) -> Result<(), String> {
+    let mut fb = fbdev::Framebuffer::open(device)
+        .map_err(|e| format!("Couldn't open {:?}: {}", device, e))?;
+    let keyboard = input
+        .map(fbdev::Keyboard::open)
+        .transpose()
+        .map_err(|e| format!("Couldn't open {:?}: {}", input.unwrap(), e))?;
+    let (fg, bg) = (palette.foreground, palette.background);
+    let (fg, bg) = ((fg.r, fg.g, fg.b), (bg.r, bg.g, bg.b));
+
+    let frame = Duration::from_secs(1) / 60;
+    let mut next = Instant::now();
+    loop {
+        for event in keyboard.iter().flat_map(|k| k.poll()) {
+            match event {
+                fbdev::KeyEvent::Key(key, pressed) => cpu.set_keypad(key, pressed),
+                fbdev::KeyEvent::Quit => return Ok(()),
+            }
+        }
+        let result = cpu.run_frame(ips / 60);
+        print_debug_output(cpu);
+        match result {
+            Ok(_) => (),
+            Err(chip::Exception::Halt(0)) => return Ok(()),
+            Err(e) => return Err(format!("Error occured: {}", e)),
+        }
+        audio.set_tone(cpu.tone());
+        fb.draw(&cpu.framebuffer(), chip::DISP_WIDTH, fg, bg)
+            .map_err(|e| format!("Couldn't write {:?}: {}", device, e))?;
+        next += frame;
+        sleep(next.saturating_duration_since(Instant::now()));
+    }
+}
+
+/// 打开不依赖 SDL 的音频输出
+#[cfg(feature = "cpal")]
+fn open_audio(config: &AudioConfig) -> Box<dyn AudioOutput> {
+    Box::new(audio::CpalOutput::open(config))
+}
+
+/// 没有启用 cpal 功能时不输出声音
+#[cfg(not(feature = "cpal"))]
+fn open_audio(_config: &AudioConfig) -> Box<dyn AudioOutput> {
+    Box::new(audio::Silent)
+}
+
+/// 列出可以用 --audio-device 选择的输出设备
+fn list_audio_devices() -> ExitCode {
+    let devices = match frontend::audio_devices() {
+        Ok(devices) => devices,
+        Err(e) => {
+            println!("Couldn't list audio devices: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    for name in devices {
+        println!("{}", name);
+    }
+    #[cfg(feature = "cpal")]
+    {
+        println!();
+        println!("With --fbdev:");
+        for name in audio::CpalOutput::devices() {
+            println!("{}", name);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// 尽快运行虚拟机直到指定的时间，输出每秒执行的指令数和帧数
+fn bench(cpu: &mut chip::Chip, ips: u32, duration: Duration) -> Result<(), chip::Exception> {
+    let per_frame = ips / 60;
+    let started = Instant::now();
+    let mut frames: u64 = 0;
+    while started.elapsed() < duration {
+        cpu.run_frame(per_frame)?;
+        frames += 1;
+    }
+    let secs = started.elapsed().as_secs_f64();
+    let instructions = frames * per_frame as u64;
+    println!(
+        "{} instructions, {} frames in {:.2} s ({} instructions per frame)",
+        instructions, frames, secs, per_frame
+    );
+    println!(
+        "{:.0} IPS, {:.0} FPS ({:.1}x real time)",
+        instructions as f64 / secs,
+        frames as f64 / secs,
+        frames as f64 / secs / 60.0
+    );
+    Ok(())
+}
+
+/// 用文件名作为标签名，把不能出现在名字中的字符换成 '_'
+fn file_label(path: &Path) -> String {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    stem.replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+}
+
+/// 把转换结果写入文件，没有指定文件时输出到标准输出
+fn write_output(path: Option<&Path>, text: &str) -> ExitCode {
+    match path {
+        Some(path) => {
+            if let Err(e) = fs::write(path, text) {
+                println!("Couldn't write {:?}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{}", text),
+    }
+    ExitCode::SUCCESS
+}
+
+/// `chip8 xo-audio`：以注释列出音高建议，然后是音频缓冲
+fn xo_audio(samples: &[f32], rate: u32, pitch: u8, name: &str) -> String {
+    use chip::xo_audio;
+
+    let patterns = xo_audio::convert(samples, rate, pitch);
+    let mut text = format!(
+        "; {} samples at {} Hz -> {} patterns at pitch {} ({:.0} bits/s, {:.3} s each)\n",
+        samples.len(),
+        rate,
+        patterns.len(),
+        pitch,
+        xo_audio::pitch_rate(pitch),
+        128.0 / xo_audio::pitch_rate(pitch)
+    );
+    match xo_audio::detect_frequency(samples, rate) {
+        Some(frequency) => {
+            text += &format!(
+                "; fundamental ~{:.1} Hz; to loop one pattern as a tone, fill it with\n",
+                frequency
+            );
+            for (pitch, periods, actual) in xo_audio::suggest_pitches(frequency).iter().take(5) {
+                text += &format!(
+                    ";   {} periods at pitch {} -> {:.1} Hz\n",
+                    periods, pitch, actual
+                );
+            }
+        }
+        None => text += "; no clear fundamental frequency found\n",
+    }
+    text + &xo_audio::to_asm(name, &patterns)
+}
+
+fn main() -> ExitCode {
+    let mut cli = Cli::parse();
+    match &cli.command {
+        Some(Command::Analyze {
+            rom,
+            memory_map,
+            json,
+            frames,
+        }) => {
+            let map = analyze::MapOptions {
+                enabled: *memory_map,
+                json: *json,
+                frames: *frames,
+            };
+            return analyze::run(rom, map);
+        }
+        Some(Command::Info { rom, database }) => {
+            let database = database.clone().or_else(|| {
+                dirs::config_dir()
+                    .map(|dir| dir.join("chip8").join("database"))
+                    .filter(|dir| dir.exists())
+            });
+            return info::run(rom, database.as_deref());
+        }
+        Some(Command::Monitor {
+            rom,
+            speed,
+            seed,
+            symbols,
+            source_map,
+        }) => {
+            let bin = match container::read(rom) {
+                Ok((bin, _)) => bin,
+                Err(e) => {
+                    println!("Couldn't open {:?}: {}", rom, e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let per_frame = speed.unwrap_or(DisplayConfig::default().ips) / 60;
+            let symbols = load_symbols(rom, symbols.as_deref());
+            let annotations = load_annotations(&bin);
+            match monitor::Monitor::new(bin, *seed, per_frame, symbols) {
+                Ok(mut monitor) => {
+                    if let Some((path, annotations)) = annotations {
+                        monitor.set_annotations(path, annotations);
+                    }
+                    if let Some(source) = load_source(rom, source_map.as_deref()) {
+                        monitor.set_source(source);
+                    }
+                    monitor.run();
+                }
+                Err(e) => {
+                    println!("Couldn't load {:?}: {}", rom, e);
+                    return ExitCode::FAILURE;
+                }
+            }
+            return ExitCode::SUCCESS;
+        }
+        Some(Command::Pack {
+            rom,
+            metadata,
+            output,
+        }) => {
+            let output = output.clone().unwrap_or_else(|| rom.with_extension("c8c"));
+            return match pack(rom, metadata, &output) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    println!("{}", e);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        Some(Command::Sprite {
+            image,
+            height,
+            name,
+            invert,
+            rust,
+            output,
+        }) => {
+            let name = name.clone().unwrap_or_else(|| file_label(image));
+            let sprites = screen::load_monochrome(image)
+                .map_err(|e| format!("Couldn't open {:?}: {}", image, e))
+                .and_then(|(mut pixels, width, h)| {
+                    if *invert {
+                        pixels.iter_mut().for_each(|px| *px = !*px);
+                    }
+                    chip::sprite::slice(&pixels, width, h, height.unwrap_or(h))
+                });
+            let text = match sprites {
+                Ok(sprites) if *rust => chip::sprite::to_rust(&name, &sprites),
+                Ok(sprites) => chip::sprite::to_asm(&name, &sprites),
+                Err(e) => {
+                    println!("{}", e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            return write_output(output.as_deref(), &text);
+        }
+        Some(Command::XoAudio {
+            wav,
+            pitch,
+            name,
+            output,
+        }) => {
+            let (samples, rate) = match wav::load(wav) {
+                Ok(wav) => wav,
+                Err(e) => {
+                    println!("Couldn't open {:?}: {}", wav, e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let name = name.clone().unwrap_or_else(|| file_label(wav));
+            let text = xo_audio(&samples, rate, *pitch, &name);
+            return write_output(output.as_deref(), &text);
+        }
+        Some(Command::New { dir }) => return new::run(dir),
+        Some(Command::UnitTest {
+            rom,
+            symbols,
+            source_map,
+            max_steps,
+        }) => {
+            let bin = match fs::read(rom) {
+                Ok(bin) => bin,
+                Err(e) => {
+                    println!("Couldn't open {:?}: {}", rom, e);
+                    return ExitCode::FAILURE;
+                }
+            };
+            let symbols = load_symbols(rom, symbols.as_deref());
+            let source = load_source(rom, source_map.as_deref());
+            return romtest::run(&bin, &symbols, source.as_ref(), *max_steps);
+        }
+        Some(Command::Diff { a, b, context }) => return steplog::diff(a, b, *context),
+        Some(Command::Test { manifest, bless }) => return compat::run(manifest, *bless),
+        None => (),
+    }
+    if cli.list_audio_devices {
+        return list_audio_devices();
+    }
+    // 命令行中明确给出的值。退出时保存这些值和运行中的修改，不保存配置文件中的值
+    let chosen_speed = cli.speed;
+    let chosen_palette = cli.palette;
+    let chosen_volume = cli.volume;
+    // 只有窗口模式恢复和保存上次的状态，无窗口运行的结果不受影响
+    let windowed = !cli.headless && cli.bench.is_none() && cli.fbdev.is_none();
+    let settings_path = Settings::path().filter(|_| windowed);
+    let mut settings = Settings::default();
+    if let Some(path) = &settings_path {
+        match Settings::load(path) {
+            Ok(loaded) => settings = loaded,
+            Err(e) => println!("Couldn't load settings {:?}: {}", path, e),
+        }
+    }
+    cli.apply_settings(&settings);
+    let config_path = cli.config.clone().or_else(Config::default_path);
+    if let Some(path) = &config_path {
+        // 只有明确指定的配置文件不存在时才报错
+        if cli.config.is_some() || path.exists() {
+            let config = Config::load(path).map_err(|e| e.to_string());
+            if let Err(e) = config.and_then(|config| cli.apply_config(config)) {
+                println!("Couldn't load config {:?}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let seed = || cli.seed.unwrap_or_else(seed);
+
+    let mut roms = cli.roms.clone();
+    if let Some(playlist) = &cli.playlist {
+        match read_playlist(playlist) {
+            Ok(mut list) => roms.append(&mut list),
+            Err(e) => {
+                println!("Couldn't read playlist {:?}: {}", playlist, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if roms.is_empty() {
+        let rom_dir = cli.rom_dir.as_deref().unwrap_or(Path::new(DEFAULT_ROM_DIR));
+        let last_rom = settings.last_rom.as_deref().filter(|path| path.exists());
+        match choose_rom(rom_dir, last_rom) {
+            Some(path) => roms.push(path),
+            None => return ExitCode::SUCCESS,
+        }
+    }
+    let mut playlist = Vec::new();
+    for rom in roms {
+        match rom.canonicalize() {
+            Ok(path) => playlist.push(path),
+            Err(e) => {
+                println!("Couldn't open {:?}: {}", rom, e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let mut cpu = chip::Chip::new(seed());
+    cpu.set_stack_limit(cli.stack_depth);
+    cpu.set_debug_print(cli.debug_print);
+    cpu.set_variant(cli.variant);
+    let mut current = 0;
+    let Some(metadata) = load_rom(&mut cpu, &playlist[current], seed()) else {
+        return ExitCode::FAILURE;
+    };
+    // 无窗口模式下使用第一个 ROM 推荐的速度
+    let speed = cli.speed.or(metadata.speed).unwrap_or(cli.speed());
+    if let Some(arg) = &cli.load_memory {
+        if let Err(e) = load_memory(&mut cpu, arg) {
+            println!("Couldn't load memory {:?}: {}", arg, e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Some(secs) = cli.bench {
+        if let Err(e) = bench(&mut cpu, speed, Duration::from_secs_f32(secs)) {
+            println!("Error occured: {}", e);
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+    if let Some(device) = &cli.fbdev {
+        let palette = cli.palette.unwrap_or_default();
+        let mut audio = open_audio(&cli.display_config().audio);
+        let input = cli.evdev.as_deref();
+        if let Err(e) = run_fbdev(&mut cpu, speed, palette, &mut *audio, device, input) {
+            println!("{}", e);
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+    let mut spectator = match cli.spectate.as_deref().map(Spectator::listen) {
+        Some(Ok(spectator)) => {
+            println!("Spectators can watch at http://{}/", spectator.local_addr());
+            Some(spectator)
+        }
+        Some(Err(e)) => {
+            println!(
+                "Couldn't listen on {}: {}",
+                cli.spectate.as_ref().unwrap(),
+                e
+            );
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+    if cli.headless {
+        let trace = match cli
+            .trace
+            .as_deref()
+            .map(|path| ChromeTrace::create(path, speed))
+        {
+            Some(Ok(mut trace)) => {
+                trace.set_symbols(load_symbols(&playlist[current], cli.symbols.as_deref()));
+                Some(trace)
+            }
+            Some(Err(e)) => {
+                println!("Couldn't create trace file: {}", e);
+                return ExitCode::FAILURE;
+            }
+            None => None,
+        };
+        let calls = (cli.call_tree.is_some() || cli.profile.is_some()).then(CallTree::new);
+        let log = match cli.step_log.as_deref().map(StepLog::create) {
+            Some(Ok(log)) => Some(log),
+            Some(Err(e)) => {
+                println!("Couldn't create step log: {}", e);
+                return ExitCode::FAILURE;
+            }
+            None => None,
+        };
+        let events = match cli.events.as_deref().map(EventLog::create) {
+            Some(Ok(events)) => Some(events),
+            Some(Err(e)) => {
+                println!("Couldn't create event log: {}", e);
+                return ExitCode::FAILURE;
+            }
+            None => None,
+        };
+        let mut recorders = Recorders {
+            trace,
+            calls,
+            log,
+            events,
+        };
+        let pipe = cli.pipe_video.as_deref().map(|video| {
+            let scale = cli.scale.unwrap_or(1) as usize;
+            let palette = cli.palette.unwrap_or_default();
+            let audio_config = cli.display_config().audio;
+            PipeOutput::create(
+                video,
+                cli.pipe_audio.as_deref(),
+                scale,
+                palette,
+                &audio_config,
+            )
+        });
+        let mut pipe = match pipe.transpose() {
+            Ok(pipe) => pipe,
+            Err(e) => {
+                println!("Couldn't open video output: {}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Some(pipe) = &pipe {
+            let (width, height) = pipe.size();
+            println!("Writing {}x{} rgb24 video at 60 fps", width, height);
+        }
+        let code = match run_headless(
+            &mut cpu,
+            speed,
+            cli.frames,
+            &mut recorders,
+            spectator.as_mut(),
+            pipe.as_mut(),
+        ) {
+            Ok(()) | Err(chip::Exception::Halt(0)) => ExitCode::SUCCESS,
+            Err(chip::Exception::Halt(code)) => ExitCode::from(code as u8),
+            Err(e) => {
+                println!("Error occured: {}", e);
+                write_crash_dump(&cli, &cpu, &e, &playlist[current]);
+                ExitCode::FAILURE
+            }
+        };
+        if let Some(pipe) = pipe {
+            if let Err(e) = pipe.finish() {
+                println!("Couldn't write video: {}", e);
+            }
+        }
+        // 报告最大调用深度，超过标准的 16 层时在其他解释器上可能会栈溢出
+        let peak = cpu.stack_peak();
+        if peak > chip::STACK_SIZE {
+            println!(
+                "Max call depth: {} (exceeds the standard {})",
+                peak,
+                chip::STACK_SIZE
+            );
+        } else {
+            println!("Max call depth: {}", peak);
+        }
+        if cpu.code_writes() > 0 {
+            let sites: Vec<String> = cpu
+                .code_write_sites()
+                .map(|pc| format!("{:03X}", pc))
+                .collect();
+            println!(
+                "Self-modifying code: {} writes from {}",
+                cpu.code_writes(),
+                sites.join(" ")
+            );
+        }
+        if let Some(trace) = recorders.trace {
+            if let Err(e) = trace.finish() {
+                println!("Couldn't write trace file: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+        if let Some(log) = recorders.log {
+            if let Err(e) = log.finish() {
+                println!("Couldn't write step log: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+        if let Some(events) = recorders.events {
+            if let Err(e) = events.finish() {
+                println!("Couldn't write event log: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+        if let (Some(path), Some(calls)) = (&cli.call_tree, &recorders.calls) {
+            let symbols = load_symbols(&playlist[current], cli.symbols.as_deref());
+            if let Err(e) = calls::write_report(path, calls, &symbols) {
+                println!("Couldn't write {:?}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+        if let (Some(path), Some(calls)) = (&cli.profile, &recorders.calls) {
+            let symbols = load_symbols(&playlist[current], cli.symbols.as_deref());
+            if let Err(e) = calls::write_folded(path, calls, &symbols) {
+                println!("Couldn't write {:?}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+        if let Some(path) = &cli.dump_memory {
+            if !dump_memory(&cpu, path) {
+                return ExitCode::FAILURE;
+            }
+        }
+        if let Some(path) = &cli.dump_screen {
+            if let Err(e) = screen::save(path, &cpu.framebuffer()) {
+                println!("Couldn't write {:?}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        }
+        return code;
+    }
+
+    // 监视 ROM 文件所在目录，文件被重新写入时自动复位并重新装载。
+    // 监视目录而不是文件本身，是因为很多工具会先删除再创建文件
+    let (tx, rx) = mpsc::channel();
+    // 无法监视文件时 (例如 inotify 数量达到上限) 只是不能自动重新装载
+    let mut watcher = match notify::recommended_watcher(tx) {
+        Ok(watcher) => Some(watcher),
+        Err(e) => {
+            println!("Couldn't watch roms for changes: {}", e);
+            None
+        }
+    };
+    let mut dirs: Vec<&Path> = playlist.iter().filter_map(|p| p.parent()).collect();
+    dirs.sort();
+    dirs.dedup();
+    for dir in dirs {
+        if let Some(Err(e)) = watcher
+            .as_mut()
+            .map(|w| w.watch(dir, RecursiveMode::NonRecursive))
+        {
+            println!("Couldn't watch {:?}: {}", dir, e);
+        }
+    }
+
+    let mut display_config = cli.display_config();
+    // 指定了像素大小时按像素大小创建窗口，否则恢复上次的窗口
+    if cli.scale.is_none() {
+        display_config.window = settings.window.map(Into::into);
+    }
+    let mut display = match frontend::Display::with_config(display_config) {
+        Ok(display) => display,
+        Err(e) => {
+            println!("Couldn't open the window: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let start_volume = display.volume();
+    display.set_pause_on_focus_loss(!cli.no_focus_pause);
+    if let Some(keys) = &cli.scan_keys {
+        let interval = Duration::from_secs_f32(cli.scan_interval.max(0.1));
+        match frontend::Scanner::new(keys.clone(), interval, &cli.scan_switch) {
+            Ok(scanner) => display.set_scanner(scanner),
+            Err(e) => println!("Couldn't set up scanning input: {}", e),
+        }
+    }
+    for &key in &cli.autofire {
+        display.set_autofire(key);
+    }
+    for input_macro in &cli.macros {
+        display.add_macro(input_macro.clone());
+    }
+    if let Some(path) = &cli.bezel {
+        if let Err(e) = load_bezel(&mut display, path, cli.bezel_screen) {
+            println!("Couldn't load bezel {:?}: {}", path, e);
+        }
+    }
+    let mut user_keymap = None;
+    if let Some(path) = &cli.keymap {
+        if path.exists() {
+            match Keymap::load(path) {
+                Ok(keymap) => user_keymap = Some(keymap),
+                Err(e) => println!("Couldn't load keymap {:?}: {}", path, e),
+            }
+        }
+        display.set_keymap_path(path);
+    }
+    if let Some(addr) = &cli.remote_keypad {
+        match RemoteKeypad::listen(addr.as_str()) {
+            Ok(remote) => {
+                println!("Remote keypad listening on {}", remote.local_addr());
+                display.add_keypad_source(remote);
+            }
+            Err(e) => println!("Couldn't listen on {}: {}", addr, e),
+        }
+    }
+    if let Some(addr) = &cli.chat {
+        match ChatInput::connect_lines(addr.as_str(), cli.chat_config()) {
+            Ok(chat) => display.add_keypad_source(chat),
+            Err(e) => println!("Couldn't connect to {}: {}", addr, e),
+        }
+    }
+    if let (Some(addr), Some(channel)) = (&cli.chat_irc, &cli.chat_channel) {
+        match ChatInput::connect_irc(
+            addr.as_str(),
+            channel,
+            &cli.chat_nick,
+            cli.chat_password.as_deref(),
+            cli.chat_config(),
+        ) {
+            Ok(chat) => display.add_keypad_source(chat),
+            Err(e) => println!("Couldn't connect to {}: {}", addr, e),
+        }
+    }
+    apply_metadata(
+        &mut display,
+        &cli,
+        &playlist[current],
+        &metadata,
+        user_keymap.as_ref(),
+    );
+    display.set_symbols(load_symbols(&playlist[current], cli.symbols.as_deref()));
+    display.set_watches(cli.watch.clone());
+    let advance = cli.advance.map(Duration::from_secs_f32);
+    let mut started = Instant::now();
+
+    loop {
+        let result = display.update(&mut cpu);
+        print_debug_output(&mut cpu);
+        match result {
+            Err(chip::Exception::Halt(0)) => break,
+            Err(e) => {
+                write_crash_dump(&cli, &cpu, &e, &playlist[current]);
+                match display.show_error(&cpu, &e) {
+                    ErrorAction::Reset => {
+                        load_rom(&mut cpu, &playlist[current], seed());
+                    }
+                    ErrorAction::Continue => cpu.set_permissive(true),
+                    ErrorAction::Quit => break,
+                }
+            }
+            Ok(_) => (),
+        }
+        if let Some(spectator) = spectator.as_mut() {
+            spectator.broadcast(&cpu);
+        }
+
+        let mut changed = false;
+        for event in rx.try_iter().flatten() {
+            changed |= (event.kind.is_create() || event.kind.is_modify())
+                && event.paths.iter().any(|p| p == &playlist[current]);
+        }
+        if changed {
+            let path = &playlist[current];
+            reload(
+                &mut cpu,
+                &mut display,
+                &cli,
+                path,
+                user_keymap.as_ref(),
+                seed(),
+            );
+        }
+
+        // 拖放到窗口上的文件像播放列表中的 ROM 一样装载，成功后插入到当前 ROM 之后
+        if let Some(path) = display.take_dropped_rom() {
+            if reload(
+                &mut cpu,
+                &mut display,
+                &cli,
+                &path,
+                user_keymap.as_ref(),
+                seed(),
+            ) {
+                if let (Some(dir), Some(watcher)) = (path.parent(), watcher.as_mut()) {
+                    if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                        println!("Couldn't watch {:?}: {}", dir, e);
+                    }
+                }
+                current = match playlist.iter().position(|p| p == &path) {
+                    Some(index) => index,
+                    None => {
+                        playlist.insert(current + 1, path);
+                        current + 1
+                    }
+                };
+                started = Instant::now();
+            }
+        }
+
+        // 到时间或按下热键后切换到播放列表中的下一个 ROM
+        let timeout = advance.is_some_and(|d| started.elapsed() >= d);
+        if display.take_next_rom_request() || timeout {
+            current = (current + 1) % playlist.len();
+            let path = &playlist[current];
+            reload(
+                &mut cpu,
+                &mut display,
+                &cli,
+                path,
+                user_keymap.as_ref(),
+                seed(),
+            );
+            started = Instant::now();
+        }
+
+        // 垂直同步模式下 update 会阻塞到下一帧
+        if !cli.vsync {
+            sleep(Duration::from_secs(1) / 60);
+        }
+    }
+    if let Some(path) = &settings_path {
+        let settings = Settings {
+            last_rom: Some(playlist[current].clone()),
+            // 没有在命令行中给出或运行中修改的项保留上次保存的值
+            palette: chosen_palette
+                .map(|palette| palette.to_string())
+                .or(settings.palette),
+            volume: if chosen_volume.is_some() || display.volume() != start_volume {
+                Some(display.volume())
+            } else {
+                settings.volume
+            },
+            speed: chosen_speed.or(settings.speed),
+            // 全屏时保留上次的窗口
+            window: display
+                .window_geometry()
+                .map(Into::into)
+                .or(settings.window),
+        };
+        if let Err(e) = settings.save(path) {
+            println!("Couldn't save settings {:?}: {}", path, e);
+        }
+    }
+    if let Some(path) = &cli.dump_memory {
+        if !dump_memory(&cpu, path) {
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,611 @@
+use crate::annotations::Annotations;
+use chip::debugger::{Debugger, OpcodePattern, ScreenRegion, Stop};
+use chip::expr::Expr;
+use chip::search::{Filter, MemorySearch};
+use chip::source_map::SourceMap;
+use chip::symbols::Symbols;
+use chip::{Addr, Chip, Instruction, Reg};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+/// continue 默认最多执行的指令数，避免死循环时没有响应
+const DEFAULT_LIMIT: u64 = 1_000_000;
+
+/// search 最多列出的候选地址数
+const SEARCH_SHOWN: usize = 20;
+
+/// list 显示当前行前后各多少行
+const LIST_CONTEXT: usize = 5;
+
+const HELP: &str = "\
+Commands (numbers are hexadecimal, 0x prefix optional; addresses may also be symbol names):
+    step [n]              s    Execute n instructions (default 1)
+    next [n]              n    Execute until the next source line, n times (default 1)
+    continue [limit]      c    Run until a breakpoint, an error or limit instructions
+    break <addr|:line> [if <expr>]
+                          b    Set a breakpoint at an address or a source line, optionally conditional
+    delete <addr>              Remove a breakpoint
+    opbreak <pattern>          Break before any opcode matching a pattern such as DXYN or FX0A
+    opdelete <pattern>         Remove an opcode breakpoint
+    screenbreak <x,y,w,h>      Break when a draw changes a pixel in a screen region (decimal)
+    screendelete <x,y,w,h>     Remove a screen region breakpoint
+    breaks                     List breakpoints
+    regs                  r    Show registers, timers and stack
+    mem <addr> [len]      m    Dump memory (default 0x40 bytes)
+    print <expr>          p    Evaluate an expression such as `v3 + i`, `mem[i+1]` or `dt != 0`
+    watch <expr>          w    Show an expression after every stop
+    unwatch <n>                Remove a watch
+    watches                    List watches
+    set <reg> <value>          Change V0-VF, I, PC, DT or ST
+    poke <addr> <byte>...      Write bytes to memory
+    search [filter]            Start a memory search, or keep the addresses matching a filter:
+                               changed, unchanged, increased, decreased or a decimal value
+    cheat <addr> [byte]        Freeze a byte of memory (default: its current value)
+    uncheat <addr>             Stop freezing a byte
+    cheats                     List frozen bytes
+    disasm [addr] [n]     d    Disassemble n instructions (default: 10 at PC)
+    list [line]           l    Show the source around a line (default: the current one)
+    label <addr> [name]        Name an address, or remove its name (saved for this rom)
+    comment <addr> [text]      Comment an address, or remove its comment (saved for this rom)
+    key <key> <down|up>        Press or release a keypad key
+    screen                     Print the screen
+    reset                      Reset and reload the rom
+    help                  h    Show this help
+    quit                  q    Exit
+Expressions use V0-VF, I, PC, SP, DT, ST, mem[addr] and C operators; their numbers are
+decimal unless prefixed with 0x.";
+
+/// 汇编源代码及其映射，用于源代码级调试
+pub struct Source {
+    pub name: String,
+    pub map: SourceMap,
+    pub lines: Vec<String>,
+}
+
+/// 命令行调试器，从标准输入读取命令，结果输出到标准输出，因此也可以用脚本驱动
+pub struct Monitor {
+    chip: Chip,
+    debugger: Debugger,
+    rom: Vec<u8>,
+    seed: u64,
+    symbols: Symbols,
+    source: Option<Source>,
+    annotations: Annotations,
+    annotations_path: Option<PathBuf>, // 注释文件，为空时不保存
+    watches: Vec<Expr>,                // 每次停止时显示的表达式
+    search: Option<MemorySearch>,
+}
+
+impl Monitor {
+    pub fn new(
+        rom: Vec<u8>,
+        seed: u64,
+        per_frame: u32,
+        symbols: Symbols,
+    ) -> Result<Self, chip::Exception> {
+        Ok(Self {
+            chip: Chip::with_rom(seed, &rom)?,
+            debugger: Debugger::new(per_frame),
+            rom,
+            seed,
+            symbols,
+            source: None,
+            annotations: Annotations::default(),
+            annotations_path: None,
+            watches: Vec::new(),
+            search: None,
+        })
+    }
+
+    /// 设置这个 ROM 的注释，label 和 comment 命令修改后保存到 path
+    pub fn set_annotations(&mut self, path: PathBuf, annotations: Annotations) {
+        self.annotations = annotations;
+        self.annotations_path = Some(path);
+    }
+
+    /// 设置源代码，之后可以按源代码行设置断点和单步执行
+    pub fn set_source(&mut self, source: Source) {
+        self.source = Some(source);
+    }
+
+    /// 读取并执行命令直到 quit 或输入结束
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        let interactive = stdin.is_terminal();
+        if interactive {
+            println!("CHIP-8 monitor, type `help` for commands");
+        }
+        self.print_next();
+
+        let mut lines = stdin.lock().lines();
+        loop {
+            if interactive {
+                print!("> ");
+                io::stdout().flush().unwrap();
+            }
+            let Some(Ok(line)) = lines.next() else { break };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let Some((command, args)) = words.split_first() else {
+                continue;
+            };
+            match self.execute(command, args) {
+                Ok(true) => (),
+                Ok(false) => break,
+                Err(e) => println!("error: {}", e),
+            }
+        }
+    }
+
+    /// 执行一条命令，返回 false 表示退出
+    fn execute(&mut self, command: &str, args: &[&str]) -> Result<bool, String> {
+        let arg = |n: usize| args.get(n).map(|s| parse_hex(s)).transpose();
+        // 地址必须在内存中 (0x000 ~ 0xFFF)，不能被截断
+        let addr = |n: usize| {
+            args.get(n)
+                .map(|s| {
+                    let value = match (self.symbols.address(s), s.rsplit_once(':')) {
+                        (Some(addr), _) => addr as u32,
+                        (None, Some((_, line))) => self.line_address(line)? as u32,
+                        (None, None) => parse_hex(s)?,
+                    };
+                    u16::try_from(value)
+                        .ok()
+                        .and_then(Addr::new)
+                        .map(Addr::get)
+                        .ok_or(format!("{:X} is not an address", value))
+                })
+                .transpose()
+        };
+        match command {
+            "step" | "s" => {
+                let count = arg(0)?.unwrap_or(1);
+                for _ in 0..count {
+                    if let Err(e) = self.debugger.step(&mut self.chip) {
+                        println!("Stopped: {}", e);
+                        break;
+                    }
+                    if let Some(write) = self.chip.last_code_write() {
+                        println!("Code at {:03X} modified by {:03X}", write.addr, write.pc);
+                    }
+                }
+                self.print_next();
+            }
+            "next" | "n" => {
+                let source = self.source.as_ref().ok_or("no source map loaded")?;
+                let count = arg(0)?.unwrap_or(1);
+                'lines: for _ in 0..count {
+                    let line = source.map.line(self.chip.pc());
+                    for _ in 0..DEFAULT_LIMIT {
+                        if let Err(e) = self.debugger.step(&mut self.chip) {
+                            println!("Stopped: {}", e);
+                            break 'lines;
+                        }
+                        let pc = self.chip.pc();
+                        let condition = self.debugger.condition(pc);
+                        if self.debugger.breakpoints().any(|b| b == pc)
+                            && condition.is_none_or(|c| c.test(&self.chip).unwrap_or(true))
+                        {
+                            println!("Breakpoint at {:03X}", pc);
+                            break 'lines;
+                        }
+                        let next = source.map.line(pc);
+                        if next.is_some() && next != line {
+                            continue 'lines;
+                        }
+                    }
+                    println!("Stopped after {} instructions", DEFAULT_LIMIT);
+                    break;
+                }
+                self.print_next();
+            }
+            "list" | "l" => {
+                let source = self.source.as_ref().ok_or("no source map loaded")?;
+                let current = source.map.line(self.chip.pc());
+                let center = match args.first() {
+                    Some(line) => line
+                        .parse()
+                        .map_err(|_| format!("invalid line number {:?}", line))?,
+                    None => current.ok_or("no source line at PC")?,
+                };
+                let first = center.saturating_sub(LIST_CONTEXT).max(1);
+                for (n, text) in source.lines.iter().enumerate().skip(first - 1) {
+                    let line = n + 1;
+                    if line > center + LIST_CONTEXT {
+                        break;
+                    }
+                    let marker = if Some(line) == current { '>' } else { ' ' };
+                    println!("{}{:>4}  {}", marker, line, text);
+                }
+            }
+            "label" => {
+                let addr = addr(0)?.ok_or("missing address")?;
+                match args.get(1) {
+                    Some(name) => {
+                        self.annotations.labels.insert(addr, name.to_string());
+                        self.symbols.insert(addr, name);
+                    }
+                    None => {
+                        self.annotations.labels.remove(&addr);
+                        self.symbols.remove(addr);
+                    }
+                }
+                self.save_annotations()?;
+            }
+            "comment" => {
+                let addr = addr(0)?.ok_or("missing address")?;
+                if args.len() > 1 {
+                    self.annotations.comments.insert(addr, args[1..].join(" "));
+                } else {
+                    self.annotations.comments.remove(&addr);
+                }
+                self.save_annotations()?;
+            }
+            "continue" | "c" => {
+                let limit = arg(0)?.map_or(DEFAULT_LIMIT, |n| n as u64);
+                match self.debugger.run(&mut self.chip, limit) {
+                    Stop::Breakpoint(addr) => println!("Breakpoint at {:03X}", addr),
+                    Stop::Opcode { addr, opcode } => {
+                        println!("Opcode breakpoint: {:04X} at {:03X}", opcode, addr)
+                    }
+                    Stop::Condition { addr, error } => {
+                        println!("Breakpoint at {:03X}, condition failed: {}", addr, error)
+                    }
+                    Stop::ScreenChange {
+                        addr,
+                        opcode,
+                        region,
+                    } => {
+                        println!(
+                            "Screen region {} changed by {:04X} at {:03X}",
+                            region, opcode, addr
+                        )
+                    }
+                    Stop::Exception(e) => println!("Stopped: {}", e),
+                    Stop::Limit => println!("Stopped after {} instructions", limit),
+                }
+                self.print_next();
+            }
+            "break" | "b" => {
+                let addr = addr(0)?.ok_or("missing address")?;
+                let condition = match args.get(1) {
+                    Some(&"if") => Some(args[2..].join(" ").parse::<Expr>()?),
+                    Some(word) => return Err(format!("expected `if`, found {:?}", word)),
+                    None => None,
+                };
+                if !self.debugger.add_breakpoint(addr) && condition.is_none() {
+                    println!("Breakpoint at {:03X} already set", addr);
+                }
+                self.debugger.set_condition(addr, condition);
+            }
+            "delete" => {
+                let addr = addr(0)?.ok_or("missing address")?;
+                if !self.debugger.remove_breakpoint(addr) {
+                    println!("No breakpoint at {:03X}", addr);
+                }
+            }
+            "opbreak" | "opdelete" => {
+                let pattern: OpcodePattern = args.first().ok_or("missing pattern")?.parse()?;
+                if command == "opbreak" && !self.debugger.add_opcode_breakpoint(pattern) {
+                    println!("Opcode breakpoint {} already set", pattern);
+                }
+                if command == "opdelete" && !self.debugger.remove_opcode_breakpoint(pattern) {
+                    println!("No opcode breakpoint {}", pattern);
+                }
+            }
+            "screenbreak" | "screendelete" => {
+                let region: ScreenRegion = args.first().ok_or("missing region")?.parse()?;
+                if command == "screenbreak" && !self.debugger.add_screen_watch(region) {
+                    println!("Screen region breakpoint {} already set", region);
+                }
+                if command == "screendelete" && !self.debugger.remove_screen_watch(region) {
+                    println!("No screen region breakpoint {}", region);
+                }
+            }
+            "breaks" => {
+                for addr in self.debugger.breakpoints() {
+                    let mut line = format!("{:03X}", addr);
+                    if let Some(name) = self.symbols.name(addr) {
+                        line = format!("{} {}", line, name);
+                    }
+                    if let Some(condition) = self.debugger.condition(addr) {
+                        line = format!("{} if {}", line, condition);
+                    }
+                    println!("{}", line);
+                }
+                for pattern in self.debugger.opcode_breakpoints() {
+                    println!("opcode {}", pattern);
+                }
+                for region in self.debugger.screen_watches() {
+                    println!("screen {}", region);
+                }
+            }
+            "regs" | "r" => self.print_regs(),
+            "print" | "p" => {
+                let expr: Expr = args.join(" ").parse()?;
+                let value = expr.eval(&self.chip)?;
+                println!("{} = {} ({:#X})", expr, value, value);
+            }
+            "watch" | "w" => {
+                let expr: Expr = args.join(" ").parse()?;
+                self.watches.push(expr);
+                self.print_watches();
+            }
+            "unwatch" => {
+                let n = args
+                    .first()
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .filter(|n| (1..=self.watches.len()).contains(n))
+                    .ok_or("expected a watch number, see `watches`")?;
+                self.watches.remove(n - 1);
+            }
+            "watches" => self.print_watches(),
+            "mem" | "m" => {
+                let addr = addr(0)?.ok_or("missing address")? as usize;
+                let len = arg(1)?.unwrap_or(0x40) as usize;
+                let mem = self.chip.memory();
+                let end = (addr + len).min(mem.len());
+                for row in (addr..end).step_by(16) {
+                    let bytes: Vec<String> = mem[row..(row + 16).min(end)]
+                        .iter()
+                        .map(|b| format!("{:02X}", b))
+                        .collect();
+                    println!("{:03X}: {}", row, bytes.join(" "));
+                }
+            }
+            "set" => {
+                let reg = args.first().ok_or("missing register")?.to_ascii_lowercase();
+                let value = arg(1)?.ok_or("missing value")?;
+                let byte = || u8::try_from(value).map_err(|_| format!("{:X} is not a byte", value));
+                let addr = || {
+                    u16::try_from(value)
+                        .ok()
+                        .and_then(Addr::new)
+                        .ok_or(format!("{:X} is not an address", value))
+                };
+                let result = match reg.as_str() {
+                    "i" => {
+                        self.chip.set_i(addr()?);
+                        Ok(())
+                    }
+                    "pc" => self.chip.set_pc(addr()?),
+                    "dt" => {
+                        self.chip.set_delay_timer(byte()?);
+                        Ok(())
+                    }
+                    "st" => {
+                        self.chip.set_sound_timer(byte()?);
+                        Ok(())
+                    }
+                    _ => {
+                        let x = reg
+                            .strip_prefix('v')
+                            .and_then(|x| u8::from_str_radix(x, 16).ok())
+                            .and_then(Reg::new)
+                            .ok_or(format!("unknown register {:?}", reg))?;
+                        self.chip.set_register(x, byte()?);
+                        Ok(())
+                    }
+                };
+                result.map_err(|e| e.to_string())?;
+                if reg == "pc" {
+                    self.print_next();
+                }
+            }
+            "poke" => {
+                let addr = addr(0)?.ok_or("missing address")?;
+                let bytes = args[1..]
+                    .iter()
+                    .map(|s| {
+                        let value = parse_hex(s)?;
+                        u8::try_from(value).map_err(|_| format!("{:X} is not a byte", value))
+                    })
+                    .collect::<Result<Vec<u8>, String>>()?;
+                if bytes.is_empty() {
+                    return Err("missing bytes".to_string());
+                }
+                self.chip
+                    .load_region(addr, &bytes)
+                    .map_err(|e| e.to_string())?;
+            }
+            "search" => match args.first() {
+                None => {
+                    let search = MemorySearch::new(&self.chip);
+                    println!("{} candidates", search.candidates().len());
+                    self.search = Some(search);
+                }
+                Some(filter) => {
+                    let filter: Filter = filter.parse()?;
+                    let search = self
+                        .search
+                        .as_mut()
+                        .ok_or("no search started, type `search` first")?;
+                    let count = search.filter(&self.chip, filter);
+                    println!("{} candidates", count);
+                    for &addr in search.candidates().iter().take(SEARCH_SHOWN) {
+                        let value = search.value(addr);
+                        println!("{:03X}: {:02X} ({})", addr, value, value);
+                    }
+                    if count > SEARCH_SHOWN {
+                        println!("...");
+                    }
+                }
+            },
+            "cheat" => {
+                let addr = addr(0)?.ok_or("missing address")?;
+                let value = match arg(1)? {
+                    Some(value) => {
+                        u8::try_from(value).map_err(|_| format!("{:X} is not a byte", value))?
+                    }
+                    None => *self
+                        .chip
+                        .memory()
+                        .get(addr as usize)
+                        .ok_or_else(|| format!("address {:03X} is out of memory", addr))?,
+                };
+                self.debugger
+                    .add_cheat(&mut self.chip, addr, value)
+                    .map_err(|e| e.to_string())?;
+            }
+            "uncheat" => {
+                let addr = addr(0)?.ok_or("missing address")?;
+                if !self.debugger.remove_cheat(addr) {
+                    println!("No cheat at {:03X}", addr);
+                }
+            }
+            "cheats" => {
+                for (addr, value) in self.debugger.cheats() {
+                    match self.symbols.name(addr) {
+                        Some(name) => println!("{:03X} {} = {:02X}", addr, name, value),
+                        None => println!("{:03X} = {:02X}", addr, value),
+                    }
+                }
+            }
+            "disasm" | "d" => {
+                let addr = addr(0)?.unwrap_or(self.chip.pc());
+                let count = arg(1)?.unwrap_or(10);
+                for n in 0..count as u16 {
+                    let addr = addr.wrapping_add(n * 2);
+                    let Some(op) = self.chip.opcode_at(addr) else {
+                        break;
+                    };
+                    if let Some(name) = self.symbols.name(addr) {
+                        println!("{}:", name);
+                    }
+                    println!("{}", self.format_instruction(addr, op));
+                }
+            }
+            "key" => {
+                let key = arg(0)?.filter(|k| *k < 16).ok_or("expected a key 0-F")?;
+                let pressed = match args.get(1) {
+                    Some(&"down") => true,
+                    Some(&"up") => false,
+                    _ => return Err("expected down or up".to_string()),
+                };
+                self.chip.set_keypad(key as u8, pressed);
+            }
+            "screen" => {
+                for row in self.chip.framebuffer().chunks(chip::DISP_WIDTH) {
+                    let line: String = row.iter().map(|on| if *on { '#' } else { '.' }).collect();
+                    println!("{}", line);
+                }
+            }
+            "reset" => {
+                self.chip
+                    .reset_with_rom(self.seed, &self.rom)
+                    .map_err(|e| e.to_string())?;
+                self.print_next();
+            }
+            "help" | "h" => println!("{}", HELP),
+            "quit" | "q" => return Ok(false),
+            _ => return Err(format!("unknown command {:?}, try `help`", command)),
+        }
+        Ok(true)
+    }
+
+    fn format_instruction(&self, addr: u16, op: u16) -> String {
+        let marker = if addr == self.chip.pc() { '>' } else { ' ' };
+        let bp = if self.debugger.breakpoints().any(|b| b == addr) {
+            '*'
+        } else {
+            ' '
+        };
+        let text = format!(
+            "{}{}{:03X}: {:04X}  {}",
+            bp,
+            marker,
+            addr,
+            op,
+            self.symbols
+                .format(Instruction::decode_variant(op, self.chip.variant()))
+        );
+        match self.annotations.comments.get(&addr) {
+            Some(comment) => format!("{:<32}; {}", text, comment),
+            None => text,
+        }
+    }
+
+    fn save_annotations(&self) -> Result<(), String> {
+        match &self.annotations_path {
+            Some(path) => self
+                .annotations
+                .save(path)
+                .map_err(|e| format!("couldn't save annotations {:?}: {}", path, e)),
+            None => Ok(()),
+        }
+    }
+
+    /// 源代码行号对应的地址
+    fn line_address(&self, line: &str) -> Result<u16, String> {
+        let source = self.source.as_ref().ok_or("no source map loaded")?;
+        let line = line
+            .parse()
+            .map_err(|_| format!("invalid line number {:?}", line))?;
+        source
+            .map
+            .address(line)
+            .ok_or_else(|| format!("no code at or after line {}", line))
+    }
+
+    /// 输出下一条要执行的指令
+    fn print_next(&self) {
+        let pc = self.chip.pc();
+        if let Some(source) = &self.source {
+            if let Some(line) = source.map.line(pc) {
+                let text = source.lines.get(line - 1).map_or("", |s| s.trim());
+                println!("{}:{}: {}", source.name, line, text);
+            }
+        }
+        if let Some(op) = self.chip.opcode_at(pc) {
+            println!("{}", self.format_instruction(pc, op));
+        }
+        self.print_watches();
+    }
+
+    fn print_watches(&self) {
+        for (n, expr) in self.watches.iter().enumerate() {
+            match expr.eval(&self.chip) {
+                Ok(value) => println!("{}: {} = {} ({:#X})", n + 1, expr, value, value),
+                Err(e) => println!("{}: {} = <{}>", n + 1, expr, e),
+            }
+        }
+    }
+
+    fn print_regs(&self) {
+        let regs: Vec<String> = self
+            .chip
+            .registers()
+            .iter()
+            .enumerate()
+            .map(|(n, v)| format!("V{:X}={:02X}", n, v))
+            .collect();
+        println!("{}", regs[..8].join(" "));
+        println!("{}", regs[8..].join(" "));
+        println!(
+            "PC={:03X} I={:03X} DT={:02X} ST={:02X}",
+            self.chip.pc(),
+            self.chip.i(),
+            self.chip.delay_timer(),
+            self.chip.sound_timer()
+        );
+        let stack: Vec<String> = self
+            .chip
+            .stack()
+            .iter()
+            .map(|addr| format!("{:03X}", addr))
+            .collect();
+        println!(
+            "Stack: [{}] (peak {})",
+            stack.join(" "),
+            self.chip.stack_peak()
+        );
+    }
+}
+
+/// 解析十六进制数，可以带 0x 前缀
+fn parse_hex(text: &str) -> Result<u32, String> {
+    let hex = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u32::from_str_radix(hex, 16).map_err(|_| format!("invalid number {:?}", text))
+}
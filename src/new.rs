@@ -0,0 +1,168 @@
+use crate::screen;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// 模板中的 `{name}` 替换为项目名
+const MAKEFILE: &str = "\
+# Tools, override e.g. with `make CHIP8='cargo run --release --'`
+CHIP8 ?= chip8
+CHIP8_ASM ?= chip8-asm
+
+ROM = build/{name}.ch8
+
+build: $(ROM)
+
+$(ROM): src/*.asm src/sprites.asm
+\tmkdir -p build
+\t$(CHIP8_ASM) src/main.asm -o $(ROM) --sym build/{name}.sym --map build/{name}.map
+
+# Regenerate the sprite data after editing the image
+src/sprites.asm: assets/ball.png
+\t$(CHIP8) sprite assets/ball.png --name ball -o src/sprites.asm
+
+run: $(ROM)
+\t$(CHIP8) $(ROM)
+
+debug: $(ROM)
+\t$(CHIP8) monitor $(ROM)
+
+test: $(ROM)
+\t$(CHIP8) test tests/tests.toml
+
+# Build with -D TEST, which includes src/tests.asm, and run its test_ subroutines
+unit: src/*.asm src/sprites.asm
+\tmkdir -p build
+\t$(CHIP8_ASM) -D TEST src/main.asm -o build/unit.ch8 --sym build/unit.sym --map build/unit.map
+\t$(CHIP8) unit-test build/unit.ch8
+
+# Accept the current screens as the test references
+bless: $(ROM)
+\t$(CHIP8) test --bless tests/tests.toml
+
+clean:
+\trm -rf build
+
+.PHONY: build run debug test unit bless clean
+";
+
+const MAIN_ASM: &str = "\
+; {name}: draws the ball and waits. Build with `make`, run with `make run`.
+INCLUDE \"constants.asm\"
+
+start:
+    CLS
+    LD V0, BALL_X
+    LD V1, BALL_Y
+    LD I, ball
+    DRW V0, V1, BALL_HEIGHT
+loop:
+    JP loop
+
+INCLUDE \"sprites.asm\"
+
+IFDEF TEST
+INCLUDE \"tests.asm\"
+ENDIF
+";
+
+const TESTS_ASM: &str = "\
+; Unit tests, run with `make unit`. Each test_ label is called as a subroutine and passes
+; when it returns; FAIL (usually after a skip, as an assertion) fails it. DBG Vx and
+; DBG [I] print while testing.
+test_ball_fits:
+    LD V0, BALL_Y
+    ADD V0, BALL_HEIGHT
+    SE V0, 20
+    FAIL
+    RET
+";
+
+const CONSTANTS_ASM: &str = "\
+; Shared constants. Build with `make CHIP8_ASM='chip8-asm -D DEBUG'` to test IFDEF DEBUG blocks.
+BALL_X EQU 28
+BALL_Y EQU 12
+BALL_HEIGHT EQU 8
+";
+
+const TESTS_TOML: &str = "\
+# Run with `make test`; after `make bless` the screens are compared with the saved references.
+[[test]]
+name = \"{name}\"
+rom = \"../build/{name}.ch8\"
+frames = 60
+screen = \"{name}.png\"
+";
+
+const README: &str = "\
+# {name}
+
+A CHIP-8 program.
+
+- `make` assembles `src/main.asm` into `build/{name}.ch8`, with symbols and a source map
+- `make run` runs it, `make debug` opens it in the monitor
+- `make unit` runs the `test_` subroutines in `src/tests.asm`
+- `make test` runs it headlessly and compares the screen with `tests/{name}.png`;
+  `make bless` saves the current screen as the reference
+- `make src/sprites.asm` regenerates the sprites after editing `assets/ball.png`
+";
+
+const GITIGNORE: &str = "build/\n";
+
+/// 示例精灵：8x8 的圆
+const BALL: [u8; 8] = [
+    0b00111100, 0b01111110, 0b11111111, 0b11111111, 0b11111111, 0b11111111, 0b01111110, 0b00111100,
+];
+
+/// `chip8 new`：在 dir 中创建一个使用汇编器、精灵转换器和测试的项目
+pub fn run(dir: &Path) -> ExitCode {
+    let name = crate::file_label(dir);
+    if dir.exists() && fs::read_dir(dir).map_or(true, |mut d| d.next().is_some()) {
+        println!("{:?} already exists and is not empty", dir);
+        return ExitCode::FAILURE;
+    }
+    match create(dir, &name) {
+        Ok(()) => {
+            println!("Created {}. Next:", dir.display());
+            println!("    cd {}", dir.display());
+            println!("    make run");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            println!("Couldn't create {:?}: {}", dir, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn create(dir: &Path, name: &str) -> io::Result<()> {
+    for sub in ["src", "assets", "tests"] {
+        fs::create_dir_all(dir.join(sub))?;
+    }
+    let files = [
+        ("Makefile", MAKEFILE),
+        ("README.md", README),
+        (".gitignore", GITIGNORE),
+        ("src/main.asm", MAIN_ASM),
+        ("src/constants.asm", CONSTANTS_ASM),
+        ("src/tests.asm", TESTS_ASM),
+        ("tests/tests.toml", TESTS_TOML),
+    ];
+    for (path, template) in files {
+        fs::write(dir.join(path), template.replace("{name}", name))?;
+    }
+
+    // 图片和由它生成的汇编一起写入，这样不用先运行转换器就能汇编
+    let pixels: Vec<bool> = BALL
+        .iter()
+        .flat_map(|row| (0..8).map(move |x| row & (0x80 >> x) != 0))
+        .collect();
+    screen::save_monochrome(&dir.join("assets/ball.png"), &pixels, 8, BALL.len())?;
+    let sprites = chip::sprite::slice(&pixels, 8, BALL.len(), BALL.len())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::write(
+        dir.join("src/sprites.asm"),
+        chip::sprite::to_asm("ball", &sprites),
+    )
+}
@@ -0,0 +1,137 @@
+use audio::{AudioConfig, Oscillator};
+use frontend::Palette;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// 输出声音的采样率，每帧正好 735 个采样
+pub const SAMPLE_RATE: u32 = 44100;
+const SAMPLES_PER_FRAME: usize = SAMPLE_RATE as usize / 60;
+
+/// 每帧把画面写为原始 RGB24 像素，声音写为单声道 s16le 采样，帧率固定为 60。
+/// 输出可以是文件或命名管道，由 ffmpeg 等外部工具编码，例如
+/// `ffmpeg -f rawvideo -pixel_format rgb24 -video_size 64x32 -framerate 60 -i video.fifo`
+pub struct PipeOutput {
+    video: BufWriter<File>,
+    audio: Option<(BufWriter<File>, Oscillator)>,
+    scale: usize,
+    fg: u32,
+    bg: u32,
+    pixels: Vec<u32>, // 放大后的一帧画面
+    samples: Vec<f32>,
+}
+
+impl PipeOutput {
+    /// 打开视频和声音的输出。打开命名管道时会阻塞到有程序开始读取
+    pub fn create(
+        video: &Path,
+        audio: Option<&Path>,
+        scale: usize,
+        palette: Palette,
+        audio_config: &AudioConfig,
+    ) -> io::Result<Self> {
+        let scale = scale.max(1);
+        let (fg, bg) = (palette.foreground, palette.background);
+        let audio = match audio {
+            Some(path) => Some((
+                BufWriter::new(File::create(path)?),
+                Oscillator::new(audio_config, SAMPLE_RATE),
+            )),
+            None => None,
+        };
+        Ok(Self {
+            video: BufWriter::new(File::create(video)?),
+            audio,
+            scale,
+            fg: u32::from_ne_bytes([fg.r, fg.g, fg.b, 0]),
+            bg: u32::from_ne_bytes([bg.r, bg.g, bg.b, 0]),
+            pixels: vec![0; chip::DISP_WIDTH * chip::DISP_HEIGHT * scale * scale],
+            samples: vec![0.0; SAMPLES_PER_FRAME],
+        })
+    }
+
+    /// 视频的宽和高
+    pub fn size(&self) -> (usize, usize) {
+        (
+            chip::DISP_WIDTH * self.scale,
+            chip::DISP_HEIGHT * self.scale,
+        )
+    }
+
+    /// 写入一帧的画面和这一帧的声音
+    pub fn write_frame(&mut self, cpu: &chip::Chip) -> io::Result<()> {
+        let stride = chip::DISP_WIDTH * self.scale;
+        chip::expand::expand(
+            cpu.framebuffer_rows(),
+            self.scale,
+            self.fg,
+            self.bg,
+            &mut self.pixels,
+            stride,
+        );
+        let bytes: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|px| {
+                let [r, g, b, _] = px.to_ne_bytes();
+                [r, g, b]
+            })
+            .collect();
+        self.video.write_all(&bytes)?;
+
+        if let Some((out, osc)) = self.audio.as_mut() {
+            osc.set_gate(cpu.tone());
+            osc.fill(&mut self.samples);
+            let bytes: Vec<u8> = self
+                .samples
+                .iter()
+                .flat_map(|x| ((x.clamp(-1.0, 1.0) * 32767.0) as i16).to_le_bytes())
+                .collect();
+            out.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.video.flush()?;
+        if let Some((out, _)) = self.audio.as_mut() {
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_pipe_output() {
+        let dir = std::env::temp_dir().join(format!("chip8-pipe-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (video, audio) = (dir.join("video.rgb"), dir.join("audio.raw"));
+        let palette: Palette = "#FF0000,#000080".parse().unwrap();
+        let mut pipe =
+            PipeOutput::create(&video, Some(&audio), 2, palette, &AudioConfig::default()).unwrap();
+        assert_eq!(pipe.size(), (128, 64));
+
+        // 在左上角画出字符 0 的第一行 (4 个像素)
+        let mut cpu = chip::Chip::new(0);
+        cpu.load_rom(chip::ENTRY_ADDR, &[0xD0, 0x01]).unwrap();
+        cpu.step().unwrap();
+        pipe.write_frame(&cpu).unwrap();
+        pipe.write_frame(&cpu).unwrap();
+        pipe.finish().unwrap();
+
+        let bytes = fs::read(&video).unwrap();
+        assert_eq!(bytes.len(), 2 * 128 * 64 * 3);
+        assert_eq!(bytes[..3], [0xFF, 0, 0]);
+        assert_eq!(bytes[128 * 3 + 7 * 3..128 * 3 + 8 * 3], [0xFF, 0, 0]);
+        assert_eq!(bytes[8 * 3..9 * 3], [0, 0, 0x80]);
+        let samples = fs::read(&audio).unwrap();
+        assert_eq!(samples.len(), 2 * SAMPLES_PER_FRAME * 2);
+        assert!(samples.iter().all(|&b| b == 0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,51 @@
+use crate::monitor::Source;
+use chip::romtest::{self, Outcome};
+use chip::symbols::Symbols;
+use std::process::ExitCode;
+
+/// `chip8 unit-test`：运行 ROM 中 test_ 开头的标签，输出每个测试的结果和调试输出。
+/// 有源代码映射时同时显示失败指令所在的源代码行
+pub fn run(rom: &[u8], symbols: &Symbols, source: Option<&Source>, max_steps: u64) -> ExitCode {
+    let entries = romtest::entries(symbols);
+    if entries.is_empty() {
+        println!(
+            "No tests found: label the test subroutines {}<name> and pass the symbol file",
+            romtest::TEST_PREFIX
+        );
+        return ExitCode::FAILURE;
+    }
+
+    let mut failures = 0;
+    for (name, addr) in &entries {
+        let result = romtest::run_test(rom, name, *addr, max_steps);
+        if result.outcome != Outcome::Pass {
+            failures += 1;
+        }
+        match result.outcome {
+            Outcome::Fail { addr: fail } => {
+                // 失败指令通常在测试子程序之内，否则用符号表示
+                let location = match fail.checked_sub(*addr) {
+                    Some(offset) => format!("{}+{}", name, offset),
+                    None => symbols.format_addr(fail),
+                };
+                println!("test {} ... {} ({})", name, result.outcome, location);
+                let line = source.and_then(|s| Some((s, s.map.line(fail)?)));
+                if let Some((source, n)) = line {
+                    let text = source.lines.get(n - 1).map_or("", |l| l.trim());
+                    println!("    {}:{}: {}", source.name, n, text);
+                }
+            }
+            _ => println!("test {} ... {}", name, result.outcome),
+        }
+        for (addr, text) in &result.output {
+            println!("    [{:03X}] {}", addr, text);
+        }
+    }
+    println!("{} passed, {} failed", entries.len() - failures, failures);
+
+    if failures == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+/// 把帧缓冲保存为图片，每个 CHIP-8 像素对应一个图片像素，点亮的像素为白色。
+/// 扩展名为 `.pgm` 时保存为 PGM，否则保存为 PNG
+pub fn save(path: &Path, framebuffer: &[bool]) -> io::Result<()> {
+    let pixels: Vec<u8> = framebuffer
+        .iter()
+        .map(|on| if *on { 0xFF } else { 0x00 })
+        .collect();
+    let (width, height) = (chip::DISP_WIDTH as u32, chip::DISP_HEIGHT as u32);
+
+    let pgm = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pgm"));
+    if pgm {
+        let mut data = format!("P5\n{} {}\n255\n", width, height).into_bytes();
+        data.extend_from_slice(&pixels);
+        return fs::write(path, data);
+    }
+
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    Ok(())
+}
+
+/// 把任意大小的单色图片保存为 PNG，与 `load_monochrome` 对应
+pub fn save_monochrome(
+    path: &Path,
+    pixels: &[bool],
+    width: usize,
+    height: usize,
+) -> io::Result<()> {
+    let data: Vec<u8> = pixels
+        .iter()
+        .map(|on| if *on { 0xFF } else { 0x00 })
+        .collect();
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        width as u32,
+        height as u32,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&data)?;
+    Ok(())
+}
+
+/// 读取 `save` 保存的图片，返回帧缓冲。亮度超过一半的像素视为点亮
+pub fn load(path: &Path) -> io::Result<Vec<bool>> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let (width, height) = (chip::DISP_WIDTH, chip::DISP_HEIGHT);
+
+    let pgm = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pgm"));
+    let pixels = if pgm {
+        let data = fs::read(path)?;
+        let header = format!("P5\n{} {}\n255\n", width, height);
+        data.strip_prefix(header.as_bytes())
+            .ok_or_else(|| invalid("not a 64x32 8-bit PGM image"))?
+            .to_vec()
+    } else {
+        let (w, h, channels, buf) = decode_png(path)?;
+        if w != width || h != height {
+            return Err(invalid("not a 64x32 PNG image"));
+        }
+        // 只取每个像素的第一个通道
+        buf.chunks(channels).map(|px| px[0]).collect()
+    };
+    if pixels.len() < width * height {
+        return Err(invalid("truncated image"));
+    }
+    Ok(pixels[..width * height]
+        .iter()
+        .map(|px| *px > 0x7F)
+        .collect())
+}
+
+/// 解码 PNG，返回宽、高、每像素的通道数和 8 位的像素数据
+fn decode_png(path: &Path) -> io::Result<(usize, usize, usize, Vec<u8>)> {
+    let mut decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    buf.truncate(info.buffer_size());
+    let channels = info.color_type.samples();
+    Ok((info.width as usize, info.height as usize, channels, buf))
+}
+
+/// 读取 PNG 并转换为按行排列的 RGBA 像素，返回像素、宽和高
+pub fn load_rgba(path: &Path) -> io::Result<(Vec<u8>, usize, usize)> {
+    let (width, height, channels, buf) = decode_png(path)?;
+    let pixels = buf
+        .chunks(channels)
+        .flat_map(|px| match *px {
+            [l] => [l, l, l, 0xFF],
+            [l, a] => [l, l, l, a],
+            [r, g, b] => [r, g, b, 0xFF],
+            [r, g, b, a, ..] => [r, g, b, a],
+            _ => [0; 4],
+        })
+        .collect();
+    Ok((pixels, width, height))
+}
+
+/// 读取任意大小的 PNG 作为单色图片，返回按行排列的像素、宽和高。
+/// 亮度超过一半且不透明的像素视为点亮
+pub fn load_monochrome(path: &Path) -> io::Result<(Vec<bool>, usize, usize)> {
+    let (width, height, channels, buf) = decode_png(path)?;
+    let pixels = buf
+        .chunks(channels)
+        .map(|px| {
+            let luma = match px.len() {
+                1 | 2 => px[0] as u32,
+                _ => (px[0] as u32 * 299 + px[1] as u32 * 587 + px[2] as u32 * 114) / 1000,
+            };
+            let alpha = if px.len() % 2 == 0 {
+                px[px.len() - 1]
+            } else {
+                0xFF
+            };
+            luma > 0x7F && alpha > 0x7F
+        })
+        .collect();
+    Ok((pixels, width, height))
+}
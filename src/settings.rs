@@ -0,0 +1,120 @@
+use frontend::WindowGeometry;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 退出时自动保存、下次启动时恢复的状态。与用户编写的配置文件分开保存，
+/// 优先级低于命令行参数、高于配置文件；只保存命令行中给出或运行中修改的值，
+/// 配置文件中的值不会被写入这里
+///
+/// ```toml
+/// last_rom = "/home/user/roms/pong.ch8"
+/// palette = "amber"
+/// volume = 0.1
+/// speed = 1000
+///
+/// [window]
+/// x = 100
+/// y = 80
+/// width = 1024
+/// height = 512
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub last_rom: Option<PathBuf>,
+    pub palette: Option<String>,
+    pub volume: Option<f32>,
+    pub speed: Option<u32>,
+    pub window: Option<WindowSection>,
+}
+
+/// 状态文件中的 `[window]` 部分，全屏时不保存
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WindowSection {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl From<WindowGeometry> for WindowSection {
+    fn from(g: WindowGeometry) -> Self {
+        Self {
+            x: g.x,
+            y: g.y,
+            width: g.width,
+            height: g.height,
+        }
+    }
+}
+
+impl From<WindowSection> for WindowGeometry {
+    fn from(w: WindowSection) -> Self {
+        Self {
+            x: w.x,
+            y: w.y,
+            width: w.width,
+            height: w.height,
+        }
+    }
+}
+
+impl Settings {
+    /// 状态文件路径，例如 Linux 下为 `~/.config/chip8/settings.toml`
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("chip8").join("settings.toml"))
+    }
+
+    /// 读取状态文件，文件不存在时返回空的状态
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => {
+                toml::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(path, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_round_trip() {
+        let settings = Settings {
+            last_rom: Some(PathBuf::from("/roms/pong.ch8")),
+            palette: Some("#FFB000,#000000".to_string()),
+            volume: Some(0.5),
+            speed: None,
+            window: Some(WindowSection {
+                x: -20,
+                y: 40,
+                width: 640,
+                height: 320,
+            }),
+        };
+        let text = toml::to_string(&settings).unwrap();
+        assert!(!text.contains("speed"));
+        assert_eq!(toml::from_str::<Settings>(&text).unwrap(), settings);
+        // 较新版本写入的未知项被忽略
+        assert_eq!(
+            toml::from_str::<Settings>("speed = 900\nzoom = 2\n").unwrap(),
+            Settings {
+                speed: Some(900),
+                ..Settings::default()
+            }
+        );
+    }
+}
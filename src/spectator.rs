@@ -0,0 +1,244 @@
+use sha1::{Digest, Sha1};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+
+/// WebSocket 握手时附加在客户端密钥后的固定字符串 (RFC 6455)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// 浏览器打开服务地址时返回的观看页面
+const PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head><meta charset="utf-8"><title>CHIP-8</title>
+<style>
+body { background: #111; color: #888; font: 14px sans-serif; text-align: center; }
+canvas { width: 90vw; max-width: 1024px; image-rendering: pixelated; border: 4px solid #222; }
+canvas.tone { border-color: #a80; }
+</style>
+</head>
+<body>
+<canvas id="screen" width="64" height="32"></canvas>
+<p id="status">Connecting...</p>
+<script>
+const canvas = document.getElementById("screen");
+const status = document.getElementById("status");
+const ctx = canvas.getContext("2d");
+const ws = new WebSocket("ws://" + location.host + "/");
+ws.binaryType = "arraybuffer";
+ws.onopen = () => status.textContent = "Watching";
+ws.onclose = () => status.textContent = "Disconnected";
+ws.onmessage = (msg) => {
+  const data = new Uint8Array(msg.data);
+  const [flags, width, height] = data;
+  canvas.width = width;
+  canvas.height = height;
+  canvas.classList.toggle("tone", (flags & 1) != 0);
+  const image = ctx.createImageData(width, height);
+  for (let i = 0; i < width * height; i++) {
+    const on = (data[3 + (i >> 3)] >> (7 - (i & 7))) & 1;
+    image.data.fill(on ? 255 : 0, i * 4, i * 4 + 3);
+    image.data[i * 4 + 3] = 255;
+  }
+  ctx.putImageData(image, 0, 0);
+};
+</script>
+</body>
+</html>
+"#;
+
+/// 发给一个观众的最新画面，只保留最后一帧，观众来不及接收时丢弃中间的帧
+struct Slot {
+    frame: Mutex<Option<Arc<Vec<u8>>>>,
+    ready: Condvar,
+}
+
+/// 监听线程和主线程共享的状态
+#[derive(Default)]
+struct Shared {
+    viewers: Vec<Arc<Slot>>,
+    last: Option<Arc<Vec<u8>>>, // 上一次发送的画面，新加入的观众先收到这一帧
+}
+
+/// 通过 WebSocket 向任意数量的浏览器只读地直播画面和蜂鸣器状态
+///
+/// 用浏览器打开监听地址会得到观看页面。每条二进制消息为 1 字节标志 (bit 0 为蜂鸣器)、
+/// 宽度、高度，之后是按行排列、高位在前的像素位
+pub struct Spectator {
+    addr: SocketAddr,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Spectator {
+    /// 在指定地址上监听观众连接
+    pub fn listen<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let state = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let shared = state.clone();
+                thread::spawn(move || serve(stream, shared));
+            }
+        });
+        Ok(Self { addr, shared })
+    }
+
+    /// 获取实际监听的地址
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 每帧调用一次，画面或蜂鸣器变化时发送给所有观众
+    pub fn broadcast(&mut self, chip: &chip::Chip) {
+        let frame = encode(&chip.framebuffer(), chip::DISP_WIDTH, chip.tone());
+        let mut shared = self.shared.lock().unwrap();
+        if shared.last.as_deref() == Some(&frame) {
+            return;
+        }
+        let frame = Arc::new(frame);
+        // 只剩这里持有的槽位说明观众已经断开
+        shared.viewers.retain(|slot| Arc::strong_count(slot) > 1);
+        for slot in &shared.viewers {
+            *slot.frame.lock().unwrap() = Some(frame.clone());
+            slot.ready.notify_one();
+        }
+        shared.last = Some(frame);
+    }
+}
+
+/// 把画面编码为一条消息
+fn encode(screen: &[bool], width: usize, tone: bool) -> Vec<u8> {
+    let height = screen.len() / width;
+    let mut data = vec![tone as u8, width as u8, height as u8];
+    data.extend(screen.chunks(8).map(|bits| {
+        bits.iter()
+            .enumerate()
+            .fold(0u8, |byte, (i, on)| byte | (*on as u8) << (7 - i))
+    }));
+    data
+}
+
+/// 处理一个连接：WebSocket 请求成为观众，其他请求返回观看页面
+fn serve(stream: TcpStream, shared: Arc<Mutex<Shared>>) {
+    let mut reader = BufReader::new(&stream);
+    let mut key = None;
+    let mut line = String::new();
+    while reader.read_line(&mut line).is_ok_and(|n| n > 0) {
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_string());
+            }
+        }
+        line.clear();
+    }
+    let mut stream = &stream;
+    let Some(key) = key else {
+        let _ = write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            PAGE.len(),
+            PAGE
+        );
+        return;
+    };
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    );
+    if stream.write_all(response.as_bytes()).is_err() {
+        return;
+    }
+
+    let slot = {
+        let mut shared = shared.lock().unwrap();
+        let slot = Arc::new(Slot {
+            frame: Mutex::new(shared.last.clone()),
+            ready: Condvar::new(),
+        });
+        shared.viewers.push(slot.clone());
+        slot
+    };
+    loop {
+        let frame = {
+            let mut pending = slot.frame.lock().unwrap();
+            while pending.is_none() {
+                pending = slot.ready.wait(pending).unwrap();
+            }
+            pending.take().unwrap()
+        };
+        if stream.write_all(&websocket_frame(&frame)).is_err() {
+            break;
+        }
+    }
+}
+
+/// 根据客户端的 Sec-WebSocket-Key 计算 Sec-WebSocket-Accept
+fn accept_key(key: &str) -> String {
+    let digest = Sha1::digest(format!("{}{}", key, WEBSOCKET_GUID));
+    base64(&digest)
+}
+
+/// 服务器发出的二进制 WebSocket 帧，不加掩码
+fn websocket_frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x82];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    frame
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i)) as usize & 0x3F] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_websocket() {
+        // RFC 6455 中的示例
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64(b"ab"), "YWI=");
+        assert_eq!(websocket_frame(&[0; 300])[..4], [0x82, 126, 0x01, 0x2C]);
+
+        let mut screen = vec![false; 16];
+        screen[0] = true;
+        screen[9] = true;
+        assert_eq!(encode(&screen, 8, true), vec![1, 8, 2, 0x80, 0x40]);
+    }
+}
@@ -0,0 +1,71 @@
+use chip::steplog::{self, Divergence, Step};
+use chip::Chip;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+/// 把每条指令执行前的状态逐行写入文件，供 `chip8 diff` 与其他日志比较
+pub struct StepLog {
+    out: BufWriter<File>,
+    error: Option<io::Error>, // 第一次写入错误，在 finish 时返回
+}
+
+impl StepLog {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            out: BufWriter::new(File::create(path)?),
+            error: None,
+        })
+    }
+
+    /// 在执行每条指令前调用
+    pub fn record(&mut self, chip: &Chip) {
+        if self.error.is_none() {
+            if let Err(e) = writeln!(self.out, "{}", Step::capture(chip)) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.out.flush(),
+        }
+    }
+}
+
+/// `chip8 diff`：找出两份日志第一次不同的步骤，并显示之前的 context 步
+pub fn diff(a: &Path, b: &Path, context: usize) -> ExitCode {
+    let read = |path: &Path| {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| steplog::parse(&text))
+            .map_err(|e| println!("Couldn't read {:?}: {}", path, e))
+    };
+    let (Ok(steps_a), Ok(steps_b)) = (read(a), read(b)) else {
+        return ExitCode::FAILURE;
+    };
+    let (step, fields) = match steplog::compare(&steps_a, &steps_b) {
+        None => {
+            println!("Logs match ({} steps)", steps_a.len());
+            return ExitCode::SUCCESS;
+        }
+        Some(Divergence::Step { step, fields }) => (step, fields),
+        Some(Divergence::Length { step }) => (step, Vec::new()),
+    };
+    if fields.is_empty() {
+        let ended = if steps_a.len() == step { a } else { b };
+        println!("Logs match for {} steps, then {:?} ends", step, ended);
+    } else {
+        println!("First difference at step {}: {}", step, fields.join(" "));
+    }
+    for n in step.saturating_sub(context)..=step {
+        let marker = if n == step { '>' } else { ' ' };
+        let line = |steps: &[Step]| steps.get(n).map_or("(end)".to_string(), |s| s.to_string());
+        println!("{}{:>8}  a: {}", marker, n, line(&steps_a));
+        println!("{:>9}  b: {}", "", line(&steps_b));
+    }
+    ExitCode::from(1)
+}
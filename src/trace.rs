@@ -0,0 +1,127 @@
+use chip::symbols::Symbols;
+use chip::{Chip, Exception, Instruction};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// 线程编号，在 Perfetto/chrome://tracing 中分别显示为一行
+const TID_FRAMES: u32 = 0;
+const TID_CPU: u32 = 1;
+const TID_DRAW: u32 = 2;
+
+/// 以 Chrome trace_event JSON 格式记录指令执行、绘图和定时器
+///
+/// 时间戳使用模拟的时间：每条指令耗时 1/ips 秒，每帧 1/60 秒
+pub struct ChromeTrace {
+    out: BufWriter<File>,
+    error: Option<io::Error>, // 第一次写入错误，在 finish 时返回
+    empty: bool,              // 还没有写入任何事件
+    instruction_us: f64,
+    instructions: u64,
+    frames: u64,
+    frame_start: f64,
+    symbols: Symbols, // 指令名称中用来替换地址的符号表
+}
+
+impl ChromeTrace {
+    pub fn create(path: &Path, ips: u32) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "[")?;
+        let mut trace = Self {
+            out,
+            error: None,
+            empty: true,
+            instruction_us: 1_000_000.0 / ips.max(1) as f64,
+            instructions: 0,
+            frames: 0,
+            frame_start: 0.0,
+            symbols: Symbols::new(),
+        };
+        for (tid, name) in [(TID_FRAMES, "frames"), (TID_CPU, "cpu"), (TID_DRAW, "draw")] {
+            trace.event(json!({
+                "name": "thread_name", "ph": "M", "pid": 1, "tid": tid,
+                "args": { "name": name },
+            }));
+        }
+        Ok(trace)
+    }
+
+    pub fn set_symbols(&mut self, symbols: Symbols) {
+        self.symbols = symbols;
+    }
+
+    fn now(&self) -> f64 {
+        self.instructions as f64 * self.instruction_us
+    }
+
+    fn event(&mut self, event: Value) {
+        if self.error.is_some() {
+            return;
+        }
+        let sep = if self.empty { "" } else { "," };
+        self.empty = false;
+        let result = writeln!(self.out, "{}", sep)
+            .and_then(|_| serde_json::to_writer(&mut self.out, &event).map_err(io::Error::from));
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+
+    /// 执行一条指令并记录
+    pub fn step(&mut self, cpu: &mut Chip) -> Result<(), Exception> {
+        let pc = cpu.pc();
+        let opcode = cpu.opcode_at(pc).unwrap_or(0);
+        let ins = Instruction::decode_variant(opcode, cpu.variant());
+        let ts = self.now();
+        let result = cpu.step();
+
+        self.event(json!({
+            "name": self.symbols.format(ins), "cat": "cpu", "ph": "X", "pid": 1, "tid": TID_CPU,
+            "ts": ts, "dur": self.instruction_us,
+            "args": { "pc": format!("0x{:03X}", pc), "opcode": format!("0x{:04X}", opcode) },
+        }));
+        if let (Ok(()), Some(draw)) = (&result, cpu.last_draw()) {
+            let name = if let Instruction::Cls = ins {
+                "CLS"
+            } else {
+                "DRW"
+            };
+            self.event(json!({
+                "name": name, "cat": "draw", "ph": "i", "s": "t", "pid": 1, "tid": TID_DRAW,
+                "ts": ts,
+                "args": {
+                    "x": draw.x, "y": draw.y, "width": draw.width, "height": draw.height,
+                    "collision": draw.collision,
+                },
+            }));
+        }
+        self.instructions += 1;
+        result
+    }
+
+    /// 递减定时器，并记录定时器的值和这一帧
+    pub fn tick_timers(&mut self, cpu: &mut Chip) {
+        cpu.tick_timers();
+        let now = self.now();
+        self.event(json!({
+            "name": "timers", "ph": "C", "pid": 1, "ts": now,
+            "args": { "delay": cpu.delay_timer(), "sound": cpu.sound_timer() },
+        }));
+        self.event(json!({
+            "name": format!("frame {}", self.frames), "cat": "frame", "ph": "X", "pid": 1,
+            "tid": TID_FRAMES, "ts": self.frame_start, "dur": now - self.frame_start,
+        }));
+        self.frames += 1;
+        self.frame_start = now;
+    }
+
+    /// 结束 JSON 数组并写入文件
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        writeln!(self.out, "\n]")?;
+        self.out.flush()
+    }
+}
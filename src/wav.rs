@@ -0,0 +1,54 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// 读取 PCM (8/16/24/32 位整数或 32 位浮点) 的 WAV 文件，
+/// 返回混合为单声道、范围为 -1.0 到 1.0 的采样和采样率
+pub fn load(path: &Path) -> io::Result<(Vec<f32>, u32)> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+    let data = fs::read(path)?;
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(invalid("not a WAV file"));
+    }
+
+    let mut format = None; // (格式, 声道数, 采样率, 位数)
+    let mut samples = None;
+    let mut rest = &data[12..];
+    while rest.len() >= 8 {
+        let id = &rest[..4];
+        let len = u32::from_le_bytes(rest[4..8].try_into().unwrap()) as usize;
+        let body = &rest[8..(8 + len).min(rest.len())];
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let u16_at = |n: usize| u16::from_le_bytes([body[n], body[n + 1]]);
+                let rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+                format = Some((u16_at(0), u16_at(2), rate, u16_at(14)));
+            }
+            b"data" => samples = Some(body),
+            _ => (),
+        }
+        // 块按偶数字节对齐
+        rest = rest.get(8 + len + len % 2..).unwrap_or_default();
+    }
+    let (Some((tag, channels, rate, bits)), Some(body)) = (format, samples) else {
+        return Err(invalid("missing fmt or data chunk"));
+    };
+
+    let decode: fn(&[u8]) -> f32 = match (tag, bits) {
+        (1, 8) => |b| (b[0] as f32 - 128.0) / 128.0,
+        (1, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+        (1, 24) => |b| i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2147483648.0,
+        (1, 32) => |b| i32::from_le_bytes(b.try_into().unwrap()) as f32 / 2147483648.0,
+        (3, 32) => |b| f32::from_le_bytes(b.try_into().unwrap()),
+        // WAVE_FORMAT_EXTENSIBLE 不检查子格式，按整数处理
+        (0xFFFE, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+        _ => return Err(invalid("unsupported sample format")),
+    };
+    let width = bits as usize / 8;
+    let frame = width * channels.max(1) as usize;
+    let mono = body
+        .chunks_exact(frame)
+        .map(|frame| frame.chunks(width).map(decode).sum::<f32>() / channels.max(1) as f32)
+        .collect();
+    Ok((mono, rate))
+}
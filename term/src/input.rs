@@ -0,0 +1,114 @@
+use std::io::{self, Read};
+use std::thread;
+use std::time::Duration;
+
+/// 从标准输入读取按键。终端需要处于非规范模式 (stty -icanon -echo)，
+/// 否则要按回车后才能读到输入
+pub struct Input {
+    #[cfg(not(target_os = "wasi"))]
+    bytes: std::sync::mpsc::Receiver<Vec<u8>>,
+    eof: bool, // 标准输入已经关闭，之后只等待超时
+}
+
+#[cfg(not(target_os = "wasi"))]
+impl Input {
+    /// 在后台线程中读取标准输入
+    pub fn open() -> Self {
+        let (tx, bytes) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let mut buf = [0u8; 64];
+            while let Ok(n @ 1..) = io::stdin().read(&mut buf) {
+                if tx.send(buf[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { bytes, eof: false }
+    }
+
+    /// 等待输入或超时，返回读到的所有字节
+    pub fn poll(&mut self, timeout: Duration) -> Vec<u8> {
+        use std::sync::mpsc::RecvTimeoutError;
+
+        if self.eof {
+            thread::sleep(timeout);
+            return Vec::new();
+        }
+        let mut bytes = match self.bytes.recv_timeout(timeout) {
+            Ok(bytes) => bytes,
+            Err(RecvTimeoutError::Timeout) => Vec::new(),
+            Err(RecvTimeoutError::Disconnected) => {
+                self.eof = true;
+                Vec::new()
+            }
+        };
+        for more in self.bytes.try_iter() {
+            bytes.extend(more);
+        }
+        bytes
+    }
+}
+
+/// WASI 没有线程，用 poll_oneoff 同时等待标准输入和超时
+#[cfg(target_os = "wasi")]
+impl Input {
+    pub fn open() -> Self {
+        Self { eof: false }
+    }
+
+    /// 等待输入或超时，返回读到的所有字节
+    pub fn poll(&mut self, timeout: Duration) -> Vec<u8> {
+        if self.eof {
+            thread::sleep(timeout);
+            return Vec::new();
+        }
+        let subscriptions = [
+            wasi::Subscription {
+                userdata: 0,
+                u: wasi::SubscriptionU {
+                    tag: wasi::EVENTTYPE_FD_READ.raw(),
+                    u: wasi::SubscriptionUU {
+                        fd_read: wasi::SubscriptionFdReadwrite { file_descriptor: 0 },
+                    },
+                },
+            },
+            wasi::Subscription {
+                userdata: 1,
+                u: wasi::SubscriptionU {
+                    tag: wasi::EVENTTYPE_CLOCK.raw(),
+                    u: wasi::SubscriptionUU {
+                        clock: wasi::SubscriptionClock {
+                            id: wasi::CLOCKID_MONOTONIC,
+                            timeout: timeout.as_nanos() as u64,
+                            precision: 0,
+                            flags: 0,
+                        },
+                    },
+                },
+            },
+        ];
+        // SAFETY: Event 只包含整数字段，全零是合法的值
+        let mut events: [wasi::Event; 2] = unsafe { std::mem::zeroed() };
+        // SAFETY: 两个数组的长度都与传入的数量一致
+        let n = unsafe {
+            wasi::poll_oneoff(
+                subscriptions.as_ptr(),
+                events.as_mut_ptr(),
+                subscriptions.len(),
+            )
+        }
+        .unwrap_or(0);
+        let ready = events[..n]
+            .iter()
+            .any(|e| e.userdata == 0 && e.error == wasi::ERRNO_SUCCESS);
+        if !ready {
+            return Vec::new();
+        }
+        // 缓冲区不小于标准输入自身的缓冲区时直接读取，不会有字节留在缓冲区中而错过下一次 poll
+        let mut bytes = vec![0u8; 8 * 1024];
+        let n = io::stdin().read(&mut bytes).unwrap_or(0);
+        self.eof = n == 0;
+        bytes.truncate(n);
+        bytes
+    }
+}
@@ -0,0 +1,131 @@
+mod input;
+
+pub use input::Input;
+
+use std::fmt::Write;
+
+/// 终端不会报告按键松开，按下的按键保持的帧数，应略长于终端的按键重复间隔
+const HOLD_FRAMES: u8 = 10;
+
+/// 用 ANSI 转义序列把画面绘制到终端，每个字符 '▀' 显示上下两个像素。
+/// fg 和 bg 为点亮像素和背景的 RGB 颜色
+pub fn render(screen: &[bool], width: usize, fg: (u8, u8, u8), bg: (u8, u8, u8)) -> String {
+    let height = screen.len() / width;
+    let mut out = String::from("\x1b[H");
+    for y in (0..height).step_by(2) {
+        let mut colors = None;
+        for x in 0..width {
+            let top = screen[y * width + x];
+            let bottom = y + 1 < height && screen[(y + 1) * width + x];
+            // 只在颜色变化时输出转义序列
+            if colors != Some((top, bottom)) {
+                let (t, b) = (if top { fg } else { bg }, if bottom { fg } else { bg });
+                let _ = write!(
+                    out,
+                    "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                    t.0, t.1, t.2, b.0, b.1, b.2
+                );
+                colors = Some((top, bottom));
+            }
+            out.push('▀');
+        }
+        out.push_str("\x1b[0m\r\n");
+    }
+    out
+}
+
+/// 字符到 CHIP-8 按键的映射，布局与 SDL 前端的默认映射相同 (1234/QWER/ASDF/ZXCV)
+pub fn keypad(c: u8) -> Option<u8> {
+    let key = match c.to_ascii_lowercase() {
+        b'1' => 0x1,
+        b'2' => 0x2,
+        b'3' => 0x3,
+        b'4' => 0xC,
+        b'q' => 0x4,
+        b'w' => 0x5,
+        b'e' => 0x6,
+        b'r' => 0xD,
+        b'a' => 0x7,
+        b's' => 0x8,
+        b'd' => 0x9,
+        b'f' => 0xE,
+        b'z' => 0xA,
+        b'x' => 0x0,
+        b'c' => 0xB,
+        b'v' => 0xF,
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// 终端输入的事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEvent {
+    Key(u8),
+    /// 按下了 Esc
+    Quit,
+}
+
+/// 解析从终端读到的字节，方向键等转义序列被忽略
+pub fn parse_input(bytes: &[u8]) -> Vec<KeyEvent> {
+    let mut events = Vec::new();
+    let mut iter = bytes.iter().copied().peekable();
+    while let Some(byte) = iter.next() {
+        if byte == 0x1B {
+            match iter.peek() {
+                Some(b'[' | b'O') => {
+                    // 跳过 CSI/SS3 序列，直到结束字符
+                    iter.next();
+                    for b in iter.by_ref() {
+                        if (0x40..=0x7E).contains(&b) {
+                            break;
+                        }
+                    }
+                }
+                _ => events.push(KeyEvent::Quit),
+            }
+        } else if let Some(key) = keypad(byte) {
+            events.push(KeyEvent::Key(key));
+        }
+    }
+    events
+}
+
+/// 按键状态，按键在最后一次按下后保持 HOLD_FRAMES 帧
+#[derive(Debug, Default)]
+pub struct Keys {
+    held: [u8; 16], // 每个按键剩余的保持帧数
+}
+
+impl Keys {
+    pub fn press(&mut self, key: u8) {
+        self.held[key as usize & 0xF] = HOLD_FRAMES;
+    }
+
+    /// 更新虚拟机的按键状态并进入下一帧
+    pub fn apply(&mut self, chip: &mut chip::Chip) {
+        for (key, held) in self.held.iter_mut().enumerate() {
+            chip.set_keypad(key as u8, *held > 0);
+            *held = held.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input() {
+        assert_eq!(
+            parse_input(b"q\x1b[AX"),
+            vec![KeyEvent::Key(0x4), KeyEvent::Key(0x0)]
+        );
+        assert_eq!(parse_input(b"\x1b"), vec![KeyEvent::Quit]);
+
+        let screen = [true, false, false, true];
+        let out = render(&screen, 2, (255, 255, 255), (0, 0, 0));
+        assert_eq!(out.matches('▀').count(), 2);
+        assert_eq!(out.matches("\x1b[38").count(), 2);
+    }
+}
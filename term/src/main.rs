@@ -0,0 +1,151 @@
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::time::{Duration, Instant, SystemTime};
+use term::{Input, KeyEvent, Keys};
+
+/// 默认每秒执行的指令数
+const DEFAULT_IPS: u32 = 700;
+/// 点亮像素和背景的颜色
+const FOREGROUND: (u8, u8, u8) = (0xFF, 0xFF, 0xFF);
+const BACKGROUND: (u8, u8, u8) = (0x00, 0x00, 0x00);
+
+const USAGE: &str = "Usage: chip8-term [--speed IPS] ROM";
+
+/// 把终端切换到非规范、不回显的模式，退出时恢复。WASI 下无法执行 stty，需要在启动前设置
+struct RawMode {
+    #[cfg(not(target_os = "wasi"))]
+    saved: Option<String>,
+}
+
+impl RawMode {
+    #[cfg(not(target_os = "wasi"))]
+    fn enter() -> Self {
+        use std::process::{Command, Stdio};
+
+        let stty = |args: &[&str]| {
+            Command::new("stty")
+                .args(args)
+                .stdin(Stdio::inherit())
+                .output()
+                .ok()
+                .filter(|out| out.status.success())
+                .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+        };
+        let saved = stty(&["-g"]);
+        if saved.is_some() {
+            stty(&["-icanon", "-echo", "min", "1"]);
+        }
+        Self { saved }
+    }
+
+    #[cfg(target_os = "wasi")]
+    fn enter() -> Self {
+        Self {}
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(b"\x1b[0m\x1b[?25h\r\n");
+        let _ = stdout.flush();
+        #[cfg(not(target_os = "wasi"))]
+        if let Some(saved) = &self.saved {
+            let _ = std::process::Command::new("stty")
+                .arg(saved)
+                .stdin(std::process::Stdio::inherit())
+                .status();
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut args = env::args().skip(1);
+    let mut ips = DEFAULT_IPS;
+    let mut rom = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--speed" => match args.next().and_then(|s| s.parse().ok()) {
+                Some(speed) => ips = speed,
+                None => {
+                    println!("{}", USAGE);
+                    return ExitCode::FAILURE;
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                println!("Keys: 1234/QWER/ASDF/ZXCV, Esc quits");
+                return ExitCode::SUCCESS;
+            }
+            _ => rom = Some(arg),
+        }
+    }
+    let Some(rom) = rom else {
+        println!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    let bin = match fs::read(&rom) {
+        Ok(bin) => bin,
+        Err(e) => {
+            println!("Couldn't open {:?}: {}", rom, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let seed = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let mut cpu = match chip::Chip::with_rom(seed, &bin) {
+        Ok(cpu) => cpu,
+        Err(e) => {
+            println!("Couldn't load {:?}: {}", rom, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let _raw = RawMode::enter();
+    match run(&mut cpu, ips) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            print!("\x1b[0m\r\nError occured: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cpu: &mut chip::Chip, ips: u32) -> Result<(), chip::Exception> {
+    let mut input = Input::open();
+    let mut keys = Keys::default();
+    let mut stdout = io::stdout();
+    let mut tone = false;
+    // 清屏并隐藏光标
+    print!("\x1b[2J\x1b[?25l");
+
+    let frame = Duration::from_secs(1) / 60;
+    let mut next = Instant::now();
+    loop {
+        next += frame;
+        let bytes = input.poll(next.saturating_duration_since(Instant::now()));
+        for event in term::parse_input(&bytes) {
+            match event {
+                KeyEvent::Key(key) => keys.press(key),
+                KeyEvent::Quit => return Ok(()),
+            }
+        }
+        keys.apply(cpu);
+        match cpu.run_frame(ips / 60) {
+            Ok(_) => (),
+            Err(chip::Exception::Halt(0)) => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        let mut out = term::render(&cpu.framebuffer(), chip::DISP_WIDTH, FOREGROUND, BACKGROUND);
+        // 声音开始时响铃
+        if cpu.tone() && !tone {
+            out.push('\x07');
+        }
+        tone = cpu.tone();
+        let _ = stdout.write_all(out.as_bytes());
+        let _ = stdout.flush();
+    }
+}